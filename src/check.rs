@@ -0,0 +1,726 @@
+use crate::env::Env;
+use crate::eval::{call_function, clear_error_trace, eval, is_interruption};
+use crate::testing;
+use crate::value::{Function, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Property-based testing in the style of test.check. A generator turns a
+// stream of random choices into a value. Every choice a failing run made is
+// recorded, and shrinking replays simpler edits of that record (fewer
+// choices, smaller choices) until the property stops failing, so combinators
+// shrink through whatever they wrap without any shrinking code of their own.
+
+/// Characters `gen/char` and `gen/string` draw from, simplest first. The
+/// tail holds the ones readers and printers tend to get wrong.
+const PRINTABLE: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~\n\té\u{3bb}\u{4e2d}\u{1f600}";
+const ALPHANUMERIC: usize = 62;
+const ASCII: usize = 95;
+const NAME_REST: &str = "abcdefghijklmnopqrstuvwxyz0123456789-?!*";
+
+// A collection grows while a draw from 0..=GROW isn't 0
+const GROW: u64 = 4;
+const SUCH_THAT_TRIES: usize = 100;
+const MAX_DRAWS: usize = 100_000;
+const MAX_SHRINKS: usize = 10_000;
+
+pub type Native = fn(&[Value]) -> Result<Value, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alphabet {
+    Printable,
+    Ascii,
+    Alphanumeric,
+}
+
+impl Alphabet {
+    fn chars(self) -> Vec<char> {
+        let all = PRINTABLE.chars();
+        match self {
+            Alphabet::Printable => all.collect(),
+            Alphabet::Ascii => all.take(ASCII).collect(),
+            Alphabet::Alphanumeric => all.take(ALPHANUMERIC).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collection {
+    List,
+    Vector,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Generator {
+    Return(Value),
+    /// An integer between -size and size
+    Int,
+    /// An integer between 0 and size
+    Nat,
+    Choose(i64, i64),
+    Double,
+    Boolean,
+    Char(Alphabet),
+    Str(Alphabet),
+    Keyword,
+    Symbol,
+    Elements(Vec<Value>),
+    OneOf(Vec<Generator>),
+    Frequency(Vec<(u64, Generator)>),
+    Collection { kind: Collection, element: Box<Generator>, min: usize, max: Option<usize> },
+    Map(Box<Generator>, Box<Generator>),
+    Tuple(Vec<Generator>),
+    Fmap(Value, Box<Generator>),
+    Bind(Box<Generator>, Value),
+    SuchThat(Value, Box<Generator>),
+    /// Any scalar: nil, a boolean, number, string, keyword or symbol
+    SimpleType,
+    /// A scalar, or a list, vector or map of further values
+    Any,
+    /// What `prop/for-all` makes: generates the argument vector for `function`
+    Property { generators: Vec<Generator>, function: Value },
+}
+
+impl Generator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Generator::Return(_) => "return",
+            Generator::Int => "int",
+            Generator::Nat => "nat",
+            Generator::Choose(..) => "choose",
+            Generator::Double => "double",
+            Generator::Boolean => "boolean",
+            Generator::Char(_) => "char",
+            Generator::Str(_) => "string",
+            Generator::Keyword => "keyword",
+            Generator::Symbol => "symbol",
+            Generator::Elements(_) => "elements",
+            Generator::OneOf(_) => "one-of",
+            Generator::Frequency(_) => "frequency",
+            Generator::Collection { kind: Collection::List, .. } => "list",
+            Generator::Collection { kind: Collection::Vector, .. } => "vector",
+            Generator::Map(..) => "map",
+            Generator::Tuple(_) => "tuple",
+            Generator::Fmap(..) => "fmap",
+            Generator::Bind(..) => "bind",
+            Generator::SuchThat(..) => "such-that",
+            Generator::SimpleType => "simple-type",
+            Generator::Any => "any",
+            Generator::Property { .. } => "property",
+        }
+    }
+}
+
+/// SplitMix64: small, fast and the same everywhere for a given seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Where a generator's choices come from: a random stream, or a recorded
+/// sequence being replayed, which reads as zeros once it runs out.
+struct Source {
+    replay: Vec<u64>,
+    rng: Option<Rng>,
+    used: Vec<u64>,
+}
+
+impl Source {
+    fn random(seed: u64) -> Source {
+        Source { replay: Vec::new(), rng: Some(Rng(seed)), used: Vec::new() }
+    }
+
+    fn replay(choices: Vec<u64>) -> Source {
+        Source { replay: choices, rng: None, used: Vec::new() }
+    }
+
+    /// A choice between 0 and `max` inclusive. Smaller choices must make
+    /// simpler values, since shrinking works by making choices smaller.
+    fn draw(&mut self, max: u64) -> Result<u64, String> {
+        if self.used.len() >= MAX_DRAWS {
+            return Err("Generator made too many choices; is it recursive without bound?".to_string());
+        }
+        let choice = match (self.replay.get(self.used.len()), &mut self.rng) {
+            (Some(&choice), _) => choice.min(max),
+            (None, Some(rng)) if max == u64::MAX => rng.next(),
+            (None, Some(rng)) => rng.next() % (max + 1),
+            (None, None) => 0,
+        };
+        self.used.push(choice);
+        Ok(choice)
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Bool(false) | Value::Nil)
+}
+
+fn map_key(key: Value) -> Result<String, String> {
+    match key {
+        Value::Keyword(k) | Value::Str(k) => Ok(k),
+        other => Err(format!("gen/map keys must be keywords or strings, got {}", other)),
+    }
+}
+
+fn generate(generator: &Generator, source: &mut Source, size: u64) -> Result<Value, String> {
+    match generator {
+        Generator::Return(value) => Ok(value.clone()),
+        Generator::Int => {
+            let magnitude = source.draw(size)? as f64;
+            let negative = magnitude > 0.0 && source.draw(1)? == 1;
+            Ok(Value::Number(if negative { -magnitude } else { magnitude }))
+        }
+        Generator::Nat => Ok(Value::Number(source.draw(size)? as f64)),
+        Generator::Choose(low, high) => Ok(Value::Number((*low + source.draw((high - low) as u64)? as i64) as f64)),
+        Generator::Double => {
+            let whole = source.draw(size)? as f64;
+            let fraction = source.draw(99)? as f64 / 100.0;
+            let negative = whole + fraction > 0.0 && source.draw(1)? == 1;
+            Ok(Value::Number(if negative { -(whole + fraction) } else { whole + fraction }))
+        }
+        Generator::Boolean => Ok(Value::Bool(source.draw(1)? == 1)),
+        Generator::Char(alphabet) => {
+            let chars = alphabet.chars();
+            Ok(Value::Str(chars[source.draw(chars.len() as u64 - 1)? as usize].to_string()))
+        }
+        Generator::Str(alphabet) => {
+            let chars = alphabet.chars();
+            let mut s = String::new();
+            while (s.chars().count() as u64) < size && source.draw(GROW)? != 0 {
+                s.push(chars[source.draw(chars.len() as u64 - 1)? as usize]);
+            }
+            Ok(Value::Str(s))
+        }
+        Generator::Keyword => Ok(Value::Keyword(generate_name(source, size)?)),
+        Generator::Symbol => Ok(Value::Symbol(generate_name(source, size)?)),
+        Generator::Elements(values) => Ok(values[source.draw(values.len() as u64 - 1)? as usize].clone()),
+        Generator::OneOf(generators) => {
+            let index = source.draw(generators.len() as u64 - 1)? as usize;
+            generate(&generators[index], source, size)
+        }
+        Generator::Frequency(weighted) => {
+            let total: u64 = weighted.iter().map(|(weight, _)| weight).sum();
+            let mut pick = source.draw(total - 1)?;
+            for (weight, generator) in weighted {
+                if pick < *weight {
+                    return generate(generator, source, size);
+                }
+                pick -= weight;
+            }
+            unreachable!("pick is below the total weight")
+        }
+        Generator::Collection { kind, element, min, max } => {
+            let mut items = Vec::new();
+            let limit = max.unwrap_or((size as usize).max(*min));
+            while items.len() < *min {
+                items.push(generate(element, source, size)?);
+            }
+            while items.len() < limit && source.draw(GROW)? != 0 {
+                items.push(generate(element, source, size)?);
+            }
+            Ok(match kind {
+                Collection::List => Value::List(items),
+                Collection::Vector => Value::Vector(items),
+            })
+        }
+        Generator::Map(key, value) => {
+            let mut map = HashMap::new();
+            while (map.len() as u64) < size && source.draw(GROW)? != 0 {
+                let k = map_key(generate(key, source, size)?)?;
+                map.insert(k, generate(value, source, size)?);
+            }
+            Ok(Value::Map(map))
+        }
+        Generator::Tuple(generators) => {
+            Ok(Value::Vector(generators.iter().map(|g| generate(g, source, size)).collect::<Result<_, _>>()?))
+        }
+        Generator::Fmap(f, generator) => call_function(f, &[generate(generator, source, size)?]),
+        Generator::Bind(generator, f) => match call_function(f, &[generate(generator, source, size)?])? {
+            Value::Generator(next) => generate(&next, source, size),
+            other => Err(format!("gen/bind function must return a generator, got {}", other)),
+        },
+        Generator::SuchThat(pred, generator) => {
+            // Each retry grows the size, so a value too small to pass can't stick
+            for tries in 0..SUCH_THAT_TRIES as u64 {
+                let value = generate(generator, source, size + tries)?;
+                if is_truthy(&call_function(pred, std::slice::from_ref(&value))?) {
+                    return Ok(value);
+                }
+            }
+            Err(format!("gen/such-that couldn't satisfy its predicate after {} tries", SUCH_THAT_TRIES))
+        }
+        Generator::SimpleType => {
+            let scalars = [
+                Generator::Return(Value::Nil),
+                Generator::Boolean,
+                Generator::Int,
+                Generator::Double,
+                Generator::Str(Alphabet::Printable),
+                Generator::Keyword,
+                Generator::Symbol,
+            ];
+            generate(&scalars[source.draw(scalars.len() as u64 - 1)? as usize], source, size)
+        }
+        Generator::Any => {
+            // Collections only while there's size left, halving it each level
+            if size == 0 || source.draw(2)? != 2 {
+                return generate(&Generator::SimpleType, source, size);
+            }
+            let nested = size / 2;
+            let any = Box::new(Generator::Any);
+            let collection = match source.draw(2)? {
+                0 => Generator::Collection { kind: Collection::Vector, element: any, min: 0, max: None },
+                1 => Generator::Collection { kind: Collection::List, element: any, min: 0, max: None },
+                _ => Generator::Map(Box::new(Generator::Keyword), any),
+            };
+            generate(&collection, source, nested)
+        }
+        Generator::Property { generators, .. } => generate(&Generator::Tuple(generators.clone()), source, size),
+    }
+}
+
+/// A keyword or symbol name: a letter, then letters, digits and -?!*.
+fn generate_name(source: &mut Source, size: u64) -> Result<String, String> {
+    let letters: Vec<char> = PRINTABLE.chars().take(52).collect();
+    let rest: Vec<char> = NAME_REST.chars().collect();
+    let mut name = letters[source.draw(51)? as usize].to_string();
+    while (name.len() as u64) < size.max(1) && source.draw(GROW)? != 0 {
+        name.push(rest[source.draw(rest.len() as u64 - 1)? as usize]);
+    }
+    Ok(name)
+}
+
+// === RUNNING PROPERTIES ===
+
+#[allow(clippy::large_enum_variant)]
+enum Outcome {
+    Pass,
+    /// The property returned this falsy value or failed with this error
+    Fail(Value),
+}
+
+/// Generate arguments from `source` and apply the property to them.
+fn trial(generators: &[Generator], function: &Value, source: &mut Source, size: u64) -> Result<(Value, Outcome), String> {
+    let args = generate(&Generator::Tuple(generators.to_vec()), source, size)?;
+    let items = match &args {
+        Value::Vector(items) => items.clone(),
+        _ => unreachable!("a tuple generates a vector"),
+    };
+    let outcome = match call_function(function, &items) {
+        Ok(value) if is_truthy(&value) => Outcome::Pass,
+        Ok(value) => Outcome::Fail(value),
+        Err(e) if is_interruption(&e) => return Err(e),
+        Err(e) => {
+            clear_error_trace();
+            Outcome::Fail(Value::Str(e))
+        }
+    };
+    Ok((args, outcome))
+}
+
+/// Simpler edits of a choice sequence to try, most drastic first: drop runs
+/// of choices, then zero, halve or decrement each one.
+fn candidates(choices: &[u64]) -> Vec<Vec<u64>> {
+    let mut out = Vec::new();
+    for run in [8, 4, 2, 1] {
+        for start in 0..choices.len().saturating_sub(run - 1) {
+            out.push([&choices[..start], &choices[start + run..]].concat());
+        }
+    }
+    for i in 0..choices.len() {
+        if choices[i] == 0 {
+            continue;
+        }
+        for smaller in [0, choices[i] / 2, choices[i] - 1] {
+            if smaller < choices[i] {
+                let mut candidate = choices.to_vec();
+                candidate[i] = smaller;
+                if !out.contains(&candidate) {
+                    out.push(candidate);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Shorter sequences are simpler, then smaller choices from the left.
+fn simpler(a: &[u64], b: &[u64]) -> bool {
+    (a.len(), a) < (b.len(), b)
+}
+
+struct Shrunk {
+    args: Value,
+    result: Value,
+    visited: usize,
+    depth: usize,
+}
+
+fn shrink(generators: &[Generator], function: &Value, choices: Vec<u64>, args: Value, result: Value, size: u64) -> Result<Shrunk, String> {
+    let mut best = Shrunk { args, result, visited: 0, depth: 0 };
+    let mut best_choices = choices;
+    'improving: while best.visited < MAX_SHRINKS {
+        for candidate in candidates(&best_choices) {
+            if best.visited >= MAX_SHRINKS {
+                break 'improving;
+            }
+            best.visited += 1;
+            let mut source = Source::replay(candidate);
+            // Edits that no longer generate a value are simply not simpler
+            let (args, outcome) = match trial(generators, function, &mut source, size) {
+                Ok(run) => run,
+                Err(e) if is_interruption(&e) => return Err(e),
+                Err(_) => {
+                    clear_error_trace();
+                    continue;
+                }
+            };
+            if let Outcome::Fail(result) = outcome {
+                if simpler(&source.used, &best_choices) {
+                    best_choices = source.used;
+                    best.args = args;
+                    best.result = result;
+                    best.depth += 1;
+                    continue 'improving;
+                }
+            }
+        }
+        break;
+    }
+    Ok(best)
+}
+
+fn entry(key: &str, value: Value) -> (String, Value) {
+    (key.to_string(), value)
+}
+
+fn new_seed() -> u64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    // Seeds travel as numbers, which are exact up to 2^53
+    (nanos as u64) & ((1 << 53) - 1)
+}
+
+/// Run a property on `num_tests` generated inputs, shrinking the first that
+/// fails, and return a test.check style result map.
+fn quick_check(num_tests: usize, property: &Value, opts: Option<&Value>) -> Result<Value, String> {
+    let (generators, function) = match property {
+        Value::Generator(generator) => match &**generator {
+            Generator::Property { generators, function } => (generators, function),
+            other => return Err(format!("quick-check requires a property from prop/for-all, got a {} generator", other.name())),
+        },
+        other => return Err(format!("quick-check requires a property from prop/for-all, got {}", other)),
+    };
+    let mut seed = new_seed();
+    let mut max_size = 200;
+    if let Some(Value::Map(opts)) = opts {
+        for (key, value) in opts {
+            match (key.as_str(), value) {
+                ("seed", Value::Number(n)) if *n >= 0.0 => seed = *n as u64,
+                ("max-size", Value::Number(n)) if *n >= 0.0 => max_size = *n as u64,
+                ("seed", _) | ("max-size", _) => return Err(format!("quick-check :{} must be a non-negative number", key)),
+                _ => {}
+            }
+        }
+    } else if let Some(other) = opts.filter(|o| !matches!(o, Value::Nil)) {
+        return Err(format!("quick-check options must be a map, got {}", other));
+    }
+
+    let mut rng = Rng(seed);
+    for run in 0..num_tests {
+        // Sizes cycle from 0 up to max-size so early runs try small values
+        let size = if max_size == 0 { 0 } else { run as u64 % (max_size + 1) };
+        let mut source = Source::random(rng.next());
+        let (args, outcome) = trial(generators, function, &mut source, size)?;
+        if let Outcome::Fail(result) = outcome {
+            let shrunk = shrink(generators, function, source.used, args.clone(), result.clone(), size)?;
+            let shrunk = HashMap::from([
+                entry("smallest", shrunk.args),
+                entry("result", shrunk.result),
+                entry("total-nodes-visited", Value::Number(shrunk.visited as f64)),
+                entry("depth", Value::Number(shrunk.depth as f64)),
+            ]);
+            return Ok(Value::Map(HashMap::from([
+                entry("pass?", Value::Bool(false)),
+                entry("result", result),
+                entry("num-tests", Value::Number((run + 1) as f64)),
+                entry("seed", Value::Number(seed as f64)),
+                entry("fail", args),
+                entry("failing-size", Value::Number(size as f64)),
+                entry("shrunk", Value::Map(shrunk)),
+            ])));
+        }
+    }
+    Ok(Value::Map(HashMap::from([
+        entry("pass?", Value::Bool(true)),
+        entry("result", Value::Bool(true)),
+        entry("num-tests", Value::Number(num_tests as f64)),
+        entry("seed", Value::Number(seed as f64)),
+    ])))
+}
+
+// === SPECIAL FORMS ===
+
+/// (prop/for-all [x gen-x y gen-y] body) is a property: body should hold
+/// for any x and y the generators make.
+pub fn eval_for_all(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let bindings = match list.get(1) {
+        Some(Value::Vector(bindings)) if bindings.len() % 2 == 0 => bindings,
+        _ => return Err("prop/for-all requires a vector of name and generator pairs".to_string()),
+    };
+    if list.len() != 3 {
+        return Err("prop/for-all requires bindings and a body".to_string());
+    }
+    let mut params = Vec::new();
+    let mut generators = Vec::new();
+    for pair in bindings.chunks(2) {
+        params.push(pair[0].clone());
+        match eval(&pair[1], env)? {
+            Value::Generator(generator) => generators.push(*generator),
+            other => return Err(format!("prop/for-all requires generators, got {}", other)),
+        }
+    }
+    let function = eval(&Value::List(vec![Value::Symbol("fn".to_string()), Value::Vector(params), list[2].clone()]), env)?;
+    Ok(Value::Generator(Box::new(Generator::Property { generators, function })))
+}
+
+/// (defspec name prop), (defspec name num-tests prop) or
+/// (defspec name {:num-tests n :seed s :max-size m} prop) defines a test that
+/// checks the property and reports the smallest failing input.
+pub fn eval_defspec(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let name = match list.get(1) {
+        Some(Value::Symbol(name)) => name,
+        _ => return Err("defspec requires a name".to_string()),
+    };
+    let (opts, property) = match &list[2..] {
+        [property] => (Value::Nil, property),
+        [opts, property] => (opts.clone(), property),
+        _ => return Err("defspec requires a property, optionally after a test count or options".to_string()),
+    };
+    let quote = |form: &Value| Value::List(vec![Value::Symbol("quote".to_string()), form.clone()]);
+    let body = Value::List(vec![
+        Value::Function(Function::Native(check_spec)),
+        quote(property),
+        property.clone(),
+        opts,
+    ]);
+    testing::define_test(name, body, list, env)
+}
+
+/// Run a defspec's property and count the outcome as an assertion.
+fn check_spec(args: &[Value]) -> Result<Value, String> {
+    let [form, property, opts] = args else {
+        return Err("defspec check requires a form, property and options".to_string());
+    };
+    let (num_tests, opts) = match opts {
+        Value::Number(n) => (*n as usize, None),
+        Value::Map(map) => match map.get("num-tests") {
+            Some(Value::Number(n)) => (*n as usize, Some(opts)),
+            _ => (100, Some(opts)),
+        },
+        _ => (100, None),
+    };
+    let result = quick_check(num_tests, property, opts)?;
+    let field = |map: &Value, key: &str| match map {
+        Value::Map(map) => map.get(key).cloned().unwrap_or(Value::Nil),
+        _ => Value::Nil,
+    };
+    if field(&result, "pass?") == Value::Bool(true) {
+        testing::record_pass();
+    } else {
+        let shrunk = field(&result, "shrunk");
+        let actual = Value::Map(HashMap::from([
+            entry("smallest", field(&shrunk, "smallest")),
+            entry("result", field(&shrunk, "result")),
+            entry("fail", field(&result, "fail")),
+        ]));
+        let message = format!(
+            "Falsified after {} of {} tests with seed {}",
+            field(&result, "num-tests"),
+            num_tests,
+            field(&result, "seed")
+        );
+        testing::report_fail(form, Value::Bool(true), actual, message);
+    }
+    Ok(result)
+}
+
+// === NATIVES ===
+
+fn generator_arg(value: &Value, fn_name: &str) -> Result<Generator, String> {
+    match value {
+        Value::Generator(generator) => Ok((**generator).clone()),
+        other => Err(format!("{} requires a generator, got {}", fn_name, other)),
+    }
+}
+
+fn count_arg(value: &Value, fn_name: &str) -> Result<usize, String> {
+    match value {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        other => Err(format!("{} requires a non-negative whole number, got {}", fn_name, other)),
+    }
+}
+
+fn wrap(generator: Generator) -> Value {
+    Value::Generator(Box::new(generator))
+}
+
+fn collection(kind: Collection, args: &[Value], fn_name: &str) -> Result<Value, String> {
+    let element = Box::new(match args.first() {
+        Some(arg) => generator_arg(arg, fn_name)?,
+        None => return Err(format!("{} requires a generator", fn_name)),
+    });
+    let (min, max) = match &args[1..] {
+        [] => (0, None),
+        [n] => (count_arg(n, fn_name)?, Some(count_arg(n, fn_name)?)),
+        [min, max] => (count_arg(min, fn_name)?, Some(count_arg(max, fn_name)?)),
+        _ => return Err(format!("{} takes a generator and an optional size or size range", fn_name)),
+    };
+    if max.is_some_and(|max| max < min) {
+        return Err(format!("{} requires min <= max", fn_name));
+    }
+    Ok(wrap(Generator::Collection { kind, element, min, max }))
+}
+
+fn items_of(value: &Value, fn_name: &str) -> Result<Vec<Value>, String> {
+    match value {
+        Value::List(items) | Value::Vector(items) => Ok(items.clone()),
+        Value::LazySeq(seq) => seq.to_vec(),
+        other => Err(format!("{} requires a collection, got {}", fn_name, other)),
+    }
+}
+
+/// The generators that need no arguments, bound as values the way
+/// test.check binds `gen/int`.
+pub fn generators() -> Vec<(&'static str, Value)> {
+    vec![
+        ("gen/int", wrap(Generator::Int)),
+        ("gen/nat", wrap(Generator::Nat)),
+        ("gen/double", wrap(Generator::Double)),
+        ("gen/boolean", wrap(Generator::Boolean)),
+        ("gen/char", wrap(Generator::Char(Alphabet::Printable))),
+        ("gen/char-alphanumeric", wrap(Generator::Char(Alphabet::Alphanumeric))),
+        ("gen/string", wrap(Generator::Str(Alphabet::Printable))),
+        ("gen/string-ascii", wrap(Generator::Str(Alphabet::Ascii))),
+        ("gen/string-alphanumeric", wrap(Generator::Str(Alphabet::Alphanumeric))),
+        ("gen/keyword", wrap(Generator::Keyword)),
+        ("gen/symbol", wrap(Generator::Symbol)),
+        ("gen/simple-type", wrap(Generator::SimpleType)),
+        ("gen/any", wrap(Generator::Any)),
+    ]
+}
+
+/// The gen/ and check/ functions, to bind in the default environment.
+pub fn natives() -> Vec<(&'static str, Native)> {
+    vec![
+        ("gen/return", |args| match args {
+            [value] => Ok(wrap(Generator::Return(value.clone()))),
+            _ => Err("gen/return requires exactly 1 argument".to_string()),
+        }),
+        ("gen/choose", |args| match args {
+            [Value::Number(low), Value::Number(high)] if low.fract() == 0.0 && high.fract() == 0.0 && low <= high => {
+                Ok(wrap(Generator::Choose(*low as i64, *high as i64)))
+            }
+            _ => Err("gen/choose requires two whole numbers, low <= high".to_string()),
+        }),
+        ("gen/elements", |args| match args {
+            [coll] => match items_of(coll, "gen/elements")? {
+                items if items.is_empty() => Err("gen/elements requires a non-empty collection".to_string()),
+                items => Ok(wrap(Generator::Elements(items))),
+            },
+            _ => Err("gen/elements requires a collection".to_string()),
+        }),
+        ("gen/one-of", |args| match args {
+            [coll] => {
+                let generators = items_of(coll, "gen/one-of")?
+                    .iter()
+                    .map(|g| generator_arg(g, "gen/one-of"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if generators.is_empty() {
+                    return Err("gen/one-of requires at least one generator".to_string());
+                }
+                Ok(wrap(Generator::OneOf(generators)))
+            }
+            _ => Err("gen/one-of requires a collection of generators".to_string()),
+        }),
+        ("gen/frequency", |args| match args {
+            [coll] => {
+                let mut weighted = Vec::new();
+                for pair in items_of(coll, "gen/frequency")? {
+                    match items_of(&pair, "gen/frequency")?.as_slice() {
+                        [Value::Number(weight), generator] if *weight >= 0.0 => {
+                            weighted.push((*weight as u64, generator_arg(generator, "gen/frequency")?))
+                        }
+                        _ => return Err("gen/frequency requires [weight generator] pairs".to_string()),
+                    }
+                }
+                if weighted.iter().all(|(weight, _)| *weight == 0) {
+                    return Err("gen/frequency requires a positive weight".to_string());
+                }
+                Ok(wrap(Generator::Frequency(weighted)))
+            }
+            _ => Err("gen/frequency requires a collection of [weight generator] pairs".to_string()),
+        }),
+        ("gen/list", |args| collection(Collection::List, args, "gen/list")),
+        ("gen/vector", |args| collection(Collection::Vector, args, "gen/vector")),
+        ("gen/map", |args| match args {
+            [key, value] => Ok(wrap(Generator::Map(
+                Box::new(generator_arg(key, "gen/map")?),
+                Box::new(generator_arg(value, "gen/map")?),
+            ))),
+            _ => Err("gen/map requires a key generator and a value generator".to_string()),
+        }),
+        ("gen/tuple", |args| {
+            Ok(wrap(Generator::Tuple(args.iter().map(|g| generator_arg(g, "gen/tuple")).collect::<Result<_, _>>()?)))
+        }),
+        ("gen/fmap", |args| match args {
+            [f @ Value::Function(_), generator] => Ok(wrap(Generator::Fmap(f.clone(), Box::new(generator_arg(generator, "gen/fmap")?)))),
+            _ => Err("gen/fmap requires a function and a generator".to_string()),
+        }),
+        ("gen/bind", |args| match args {
+            [generator, f @ Value::Function(_)] => Ok(wrap(Generator::Bind(Box::new(generator_arg(generator, "gen/bind")?), f.clone()))),
+            _ => Err("gen/bind requires a generator and a function returning a generator".to_string()),
+        }),
+        ("gen/such-that", |args| match args {
+            [pred @ Value::Function(_), generator] => {
+                Ok(wrap(Generator::SuchThat(pred.clone(), Box::new(generator_arg(generator, "gen/such-that")?))))
+            }
+            _ => Err("gen/such-that requires a predicate and a generator".to_string()),
+        }),
+        // (gen/sample g) or (gen/sample g n): n values at sizes 0, 1, 2...
+        ("gen/sample", |args| {
+            let (generator, n) = match args {
+                [g] => (generator_arg(g, "gen/sample")?, 10),
+                [g, n] => (generator_arg(g, "gen/sample")?, count_arg(n, "gen/sample")?),
+                _ => return Err("gen/sample requires a generator and an optional count".to_string()),
+            };
+            let mut rng = Rng(new_seed());
+            let values = (0..n)
+                .map(|size| generate(&generator, &mut Source::random(rng.next()), size as u64))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Value::List(values))
+        }),
+        // (gen/generate g), (gen/generate g size) or (gen/generate g size seed)
+        ("gen/generate", |args| {
+            let generator = match args.first() {
+                Some(g) => generator_arg(g, "gen/generate")?,
+                None => return Err("gen/generate requires a generator".to_string()),
+            };
+            let size = args.get(1).map(|n| count_arg(n, "gen/generate")).transpose()?.unwrap_or(30);
+            let seed = args.get(2).map(|n| count_arg(n, "gen/generate")).transpose()?.map(|n| n as u64).unwrap_or_else(new_seed);
+            generate(&generator, &mut Source::random(Rng(seed).next()), size as u64)
+        }),
+        // (check/quick-check num-tests property) or with {:seed s :max-size m}
+        ("check/quick-check", |args| match args {
+            [n, property] => quick_check(count_arg(n, "check/quick-check")?, property, None),
+            [n, property, opts] => quick_check(count_arg(n, "check/quick-check")?, property, Some(opts)),
+            _ => Err("check/quick-check requires a test count, a property and optional options".to_string()),
+        }),
+    ]
+}
@@ -0,0 +1,338 @@
+use crate::eval::eval;
+use crate::env::Env;
+use crate::reader::{Pos, SourceMap};
+use crate::value::Value;
+use serde_json::json;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+// Coverage for `cortado test --coverage`. Forms loaded from files are
+// rewritten before they're evaluated: each branch of an `if` or `cond` and
+// each function body is wrapped in `(cortado.coverage/hit id form)`, which
+// counts the point `id` and evaluates `form`. Points carry the reader's
+// source positions so the counts can be reported as lcov.
+
+/// The special form the rewritten code calls; not one users write.
+pub const HIT: &str = "cortado.coverage/hit";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// A top-level form of the file
+    Form,
+    /// One way through the `if` or `cond` that starts at `block`
+    Branch { block: Pos, index: usize },
+    /// The body of the function with this name
+    Function(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub file: String,
+    pub namespace: String,
+    pub pos: Pos,
+    pub kind: Kind,
+    pub hits: u64,
+}
+
+impl Point {
+    pub fn to_json(&self) -> serde_json::Value {
+        let (kind, name, block, index) = match &self.kind {
+            Kind::Form => ("form", None, None, None),
+            Kind::Branch { block, index } => ("branch", None, Some([block.line, block.column]), Some(*index)),
+            Kind::Function(name) => ("function", Some(name), None, None),
+        };
+        json!({
+            "file": self.file,
+            "namespace": self.namespace,
+            "pos": [self.pos.line, self.pos.column],
+            "kind": kind,
+            "name": name,
+            "block": block,
+            "index": index,
+            "hits": self.hits,
+        })
+    }
+
+    pub fn from_json(json: &serde_json::Value) -> Option<Point> {
+        let pos = |value: &serde_json::Value| {
+            Some(Pos { line: value[0].as_u64()? as usize, column: value[1].as_u64()? as usize })
+        };
+        let kind = match json["kind"].as_str()? {
+            "form" => Kind::Form,
+            "branch" => Kind::Branch { block: pos(&json["block"])?, index: json["index"].as_u64()? as usize },
+            "function" => Kind::Function(json["name"].as_str()?.to_string()),
+            _ => return None,
+        };
+        Some(Point {
+            file: json["file"].as_str()?.to_string(),
+            namespace: json["namespace"].as_str()?.to_string(),
+            pos: pos(&json["pos"])?,
+            kind,
+            hits: json["hits"].as_u64()?,
+        })
+    }
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    /// Files loaded without instrumenting, such as the test file being run
+    static EXCLUDED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static POINTS: RefCell<Vec<Point>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Instrument the files loaded from now on, apart from those in `excluded`.
+pub fn enable(excluded: Vec<String>) {
+    ENABLED.with(|enabled| enabled.set(true));
+    EXCLUDED.with(|files| *files.borrow_mut() = excluded);
+}
+
+/// Every point instrumented so far, with how often it ran.
+pub fn points() -> Vec<Point> {
+    POINTS.with(|points| points.borrow().clone())
+}
+
+/// Count a run of the point `id`.
+pub fn hit(id: usize) {
+    POINTS.with(|points| {
+        if let Some(point) = points.borrow_mut().get_mut(id) {
+            point.hits += 1;
+        }
+    });
+}
+
+/// (cortado.coverage/hit id form) counts `id`, then evaluates `form`.
+pub fn eval_hit(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    match list {
+        [_, Value::Number(id), form] => {
+            hit(*id as usize);
+            eval(form, env)
+        }
+        _ => Err(format!("{} requires a point and a form", HIT)),
+    }
+}
+
+fn register(file: &str, namespace: &str, pos: Pos, kind: Kind) -> usize {
+    POINTS.with(|points| {
+        let mut points = points.borrow_mut();
+        points.push(Point { file: file.to_string(), namespace: namespace.to_string(), pos, kind, hits: 0 });
+        points.len() - 1
+    })
+}
+
+/// The forms read from `file`, instrumented when coverage is on, each with
+/// the point to count when its loader evaluates it. `namespace` is the one
+/// the file starts in; an `(ns ...)` form moves the forms after it.
+pub fn instrument(file: &str, forms: Vec<(Value, SourceMap)>, namespace: &str) -> Vec<(Value, Option<usize>)> {
+    let excluded = EXCLUDED.with(|files| files.borrow().iter().any(|f| f == file));
+    if !ENABLED.with(Cell::get) || excluded {
+        return forms.into_iter().map(|(form, _)| (form, None)).collect();
+    }
+    let mut namespace = namespace.to_string();
+    forms
+        .into_iter()
+        .map(|(form, map)| {
+            if let Value::List(items) = &form {
+                if let [Value::Symbol(head), Value::Symbol(name)] = items.as_slice() {
+                    if head == "ns" {
+                        namespace = name.clone();
+                    }
+                }
+            }
+            let point = register(file, &namespace, map.span.start, Kind::Form);
+            let instrumenter = Instrumenter { file, namespace: &namespace };
+            (instrumenter.walk(&form, &map), Some(point))
+        })
+        .collect()
+}
+
+struct Instrumenter<'a> {
+    file: &'a str,
+    namespace: &'a str,
+}
+
+impl Instrumenter<'_> {
+    /// `form` wrapped so that evaluating it counts a new point.
+    fn wrap(&self, form: Value, pos: Pos, kind: Kind) -> Value {
+        let id = register(self.file, self.namespace, pos, kind);
+        Value::List(vec![Value::Symbol(HIT.to_string()), Value::Number(id as f64), form])
+    }
+
+    /// Rewrite the branches and function bodies inside `form`. Quoted code
+    /// is data and macro bodies run at expansion time, so both are left as
+    /// they are; so is anything the source map doesn't line up with, such
+    /// as a map, whose entries the reader doesn't keep in order.
+    fn walk(&self, form: &Value, map: &SourceMap) -> Value {
+        let (items, children) = match form {
+            Value::List(items) | Value::Vector(items) if items.len() == map.children.len() => (items, &map.children),
+            _ => return form.clone(),
+        };
+        let head = match (form, items.first()) {
+            (Value::List(_), Some(Value::Symbol(head))) => head.as_str(),
+            _ => "",
+        };
+        if matches!(head, "quote" | "quasiquote" | "defmacro") {
+            return form.clone();
+        }
+        let mut walked: Vec<Value> = items.iter().zip(children).map(|(item, child)| self.walk(item, child)).collect();
+        let start = map.span.start;
+        let branch = |index| Kind::Branch { block: start, index };
+        match head {
+            "if" if walked.len() == 4 => {
+                for (index, i) in [2, 3].into_iter().enumerate() {
+                    walked[i] = self.wrap(walked[i].clone(), children[i].span.start, branch(index));
+                }
+            }
+            // (cond test result test result ... default), also when written
+            // with its namespace, e.g. core.control/cond. An odd number of
+            // clauses ends in a default, which is a branch of its own.
+            _ if head.rsplit('/').next() == Some("cond") => {
+                let default = walked.len().is_multiple_of(2).then(|| walked.len() - 1);
+                let results = (2..walked.len()).step_by(2).chain(default);
+                for (index, i) in results.enumerate() {
+                    walked[i] = self.wrap(walked[i].clone(), children[i].span.start, branch(index));
+                }
+            }
+            "fn" if walked.len() == 3 => {
+                let name = format!("fn@{}:{}", start.line + 1, start.column + 1);
+                walked[2] = self.wrap(walked[2].clone(), start, Kind::Function(name));
+            }
+            // (defn name [params] body), maybe with a docstring after the name
+            "defn" if walked.len() >= 4 => {
+                if let Value::Symbol(name) = &walked[1] {
+                    let name = format!("{}/{}", self.namespace, name);
+                    let last = walked.len() - 1;
+                    walked[last] = self.wrap(walked[last].clone(), start, Kind::Function(name));
+                }
+            }
+            _ => {}
+        }
+        match form {
+            Value::Vector(_) => Value::Vector(walked),
+            _ => Value::List(walked),
+        }
+    }
+}
+
+// === REPORTS ===
+
+/// Add up the points from several runs, such as one per test file, that
+/// instrumented the same source.
+pub fn merge(points: impl IntoIterator<Item = Point>) -> Vec<Point> {
+    let mut merged: BTreeMap<(String, Pos, Kind), Point> = BTreeMap::new();
+    for point in points {
+        let key = (point.file.clone(), point.pos, point.kind.clone());
+        match merged.get_mut(&key) {
+            Some(existing) => existing.hits += point.hits,
+            None => {
+                merged.insert(key, point);
+            }
+        }
+    }
+    merged.into_values().collect()
+}
+
+fn by_file(points: &[Point]) -> BTreeMap<&str, Vec<&Point>> {
+    let mut files: BTreeMap<&str, Vec<&Point>> = BTreeMap::new();
+    for point in points {
+        files.entry(&point.file).or_default().push(point);
+    }
+    files
+}
+
+/// The lines with points on them and the most any of them ran.
+fn lines<'a>(points: impl IntoIterator<Item = &'a Point>) -> BTreeMap<(&'a str, usize), u64> {
+    let mut lines = BTreeMap::new();
+    for point in points {
+        let hits = lines.entry((point.file.as_str(), point.pos.line + 1)).or_insert(0);
+        *hits = (*hits).max(point.hits);
+    }
+    lines
+}
+
+/// Merged points as an lcov tracefile, one record per source file.
+pub fn lcov(points: &[Point]) -> String {
+    let mut out = String::new();
+    for (file, points) in by_file(points) {
+        out.push_str(&format!("TN:\nSF:{}\n", file));
+
+        let functions: Vec<(&str, &Point)> = points
+            .iter()
+            .filter_map(|p| match &p.kind {
+                Kind::Function(name) => Some((name.as_str(), *p)),
+                _ => None,
+            })
+            .collect();
+        for (name, point) in &functions {
+            out.push_str(&format!("FN:{},{}\n", point.pos.line + 1, name));
+        }
+        for (name, point) in &functions {
+            out.push_str(&format!("FNDA:{},{}\n", point.hits, name));
+        }
+        out.push_str(&format!("FNF:{}\nFNH:{}\n", functions.len(), functions.iter().filter(|(_, p)| p.hits > 0).count()));
+
+        let mut blocks: BTreeMap<Pos, Vec<(usize, u64)>> = BTreeMap::new();
+        for point in &points {
+            if let Kind::Branch { block, index } = point.kind {
+                blocks.entry(block).or_default().push((index, point.hits));
+            }
+        }
+        let (mut found, mut hit) = (0, 0);
+        for (number, (block, mut branches)) in blocks.into_iter().enumerate() {
+            branches.sort();
+            // lcov writes "-" for the branches of a block that never ran
+            let reached = branches.iter().any(|(_, hits)| *hits > 0);
+            for (index, hits) in branches {
+                let taken = if reached { hits.to_string() } else { "-".to_string() };
+                out.push_str(&format!("BRDA:{},{},{},{}\n", block.line + 1, number, index, taken));
+                found += 1;
+                hit += usize::from(hits > 0);
+            }
+        }
+        out.push_str(&format!("BRF:{}\nBRH:{}\n", found, hit));
+
+        let lines = lines(points.iter().copied());
+        for ((_, line), hits) in &lines {
+            out.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        out.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), lines.values().filter(|h| **h > 0).count()));
+    }
+    out
+}
+
+fn ratio(hit: usize, found: usize) -> String {
+    if found == 0 {
+        return "-".to_string();
+    }
+    format!("{}/{} {:.1}%", hit, found, 100.0 * hit as f64 / found as f64)
+}
+
+/// A table of line, branch and function coverage for each namespace.
+pub fn summary(points: &[Point]) -> String {
+    let mut namespaces: BTreeMap<&str, Vec<&Point>> = BTreeMap::new();
+    for point in points {
+        namespaces.entry(&point.namespace).or_default().push(point);
+    }
+    let mut rows = vec![["Namespace".to_string(), "Lines".to_string(), "Branches".to_string(), "Functions".to_string()]];
+    for (namespace, points) in &namespaces {
+        let lines = lines(points.iter().copied());
+        let count = |of_kind: fn(&Kind) -> bool| {
+            let all: Vec<&&Point> = points.iter().filter(|p| of_kind(&p.kind)).collect();
+            (all.iter().filter(|p| p.hits > 0).count(), all.len())
+        };
+        let (branches_hit, branches) = count(|kind| matches!(kind, Kind::Branch { .. }));
+        let (functions_hit, functions) = count(|kind| matches!(kind, Kind::Function(_)));
+        rows.push([
+            namespace.to_string(),
+            ratio(lines.values().filter(|h| **h > 0).count(), lines.len()),
+            ratio(branches_hit, branches),
+            ratio(functions_hit, functions),
+        ]);
+    }
+    let widths: Vec<usize> = (0..4).map(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0)).collect();
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+            format!("  {}\n", cells.join("  ").trim_end())
+        })
+        .collect()
+}
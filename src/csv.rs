@@ -0,0 +1,229 @@
+use crate::eval::str_of;
+use crate::lazy::seq_iter;
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// Options shared by the csv readers and writers.
+pub struct CsvOptions {
+    pub separator: char,
+    pub quote: char,
+    /// Reading: treat the first row as a header and return maps.
+    pub header: bool,
+    /// Writing maps: the keys to write, in column order.
+    pub columns: Option<Vec<String>>,
+    pub newline: &'static str,
+}
+
+pub fn options(opts: Option<&Value>, fn_name: &str) -> Result<CsvOptions, String> {
+    let mut options = CsvOptions { separator: ',', quote: '"', header: false, columns: None, newline: "\n" };
+    let map = match opts {
+        None | Some(Value::Nil) => return Ok(options),
+        Some(Value::Map(map)) => map,
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    };
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("separator", _) => options.separator = single_char(value, key, fn_name)?,
+            ("quote", _) => options.quote = single_char(value, key, fn_name)?,
+            ("header", Value::Bool(b)) => options.header = *b,
+            ("header", _) => return Err(format!("{} option :header must be a boolean", fn_name)),
+            ("columns", _) if crate::lazy::is_seqable(value) => {
+                let columns = seq_iter(value, fn_name)?
+                    .map(|column| match column? {
+                        Value::Str(name) | Value::Keyword(name) => Ok(name),
+                        other => Err(format!("{} option :columns must hold keywords or strings, got {}", fn_name, other)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                options.columns = Some(columns);
+            }
+            ("columns", _) => return Err(format!("{} option :columns must be a vector", fn_name)),
+            ("newline", Value::Keyword(k)) if k == "lf" => options.newline = "\n",
+            ("newline", Value::Keyword(k)) if k == "crlf" => options.newline = "\r\n",
+            ("newline", _) => return Err(format!("{} option :newline must be :lf or :crlf", fn_name)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    if options.separator == options.quote {
+        return Err(format!("{} :separator and :quote must differ", fn_name));
+    }
+    if matches!(options.separator, '\n' | '\r') || matches!(options.quote, '\n' | '\r') {
+        return Err(format!("{} :separator and :quote can't be line breaks", fn_name));
+    }
+    Ok(options)
+}
+
+fn single_char(value: &Value, key: &str, fn_name: &str) -> Result<char, String> {
+    if let Value::Str(s) = value {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(c);
+        }
+    }
+    Err(format!("{} option :{} must be a one-character string, got {}", fn_name, key, value))
+}
+
+/// Split CSV text into rows of fields. Quoted fields may contain the
+/// separator, line breaks and doubled quotes; blank lines are skipped.
+pub fn parse(text: &str, opts: &CsvOptions, fn_name: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    // Whether the current field began with a quote, so an empty quoted field
+    // still counts as a field on an otherwise blank line
+    let mut quoted = false;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == opts.quote && field.is_empty() && !quoted {
+            quoted = true;
+            let start_line = line;
+            loop {
+                match chars.next() {
+                    Some(q) if q == opts.quote => {
+                        if chars.peek() == Some(&opts.quote) {
+                            chars.next();
+                            field.push(q);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some(ch) => {
+                        if ch == '\n' {
+                            line += 1;
+                        }
+                        field.push(ch);
+                    }
+                    None => return Err(format!("{}: unterminated quoted field starting on line {}", fn_name, start_line)),
+                }
+            }
+            match chars.peek() {
+                None | Some('\n') | Some('\r') => {}
+                Some(&next) if next == opts.separator => {}
+                Some(&next) => {
+                    return Err(format!("{}: unexpected '{}' after closing quote on line {}", fn_name, next, line));
+                }
+            }
+        } else if c == opts.separator {
+            row.push(std::mem::take(&mut field));
+            quoted = false;
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            if !row.is_empty() || !field.is_empty() || quoted {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            quoted = false;
+            line += 1;
+        } else if c == opts.quote {
+            return Err(format!("{}: unexpected quote inside an unquoted field on line {}", fn_name, line));
+        } else {
+            field.push(c);
+        }
+    }
+    if !row.is_empty() || !field.is_empty() || quoted {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Rows as Cortado values: vectors of strings, or maps keyed by the header
+/// row when `:header` is set.
+pub fn rows_value(rows: Vec<Vec<String>>, opts: &CsvOptions, fn_name: &str) -> Result<Value, String> {
+    let to_vector = |row: Vec<String>| Value::Vector(row.into_iter().map(Value::Str).collect());
+    if !opts.header {
+        return Ok(Value::Vector(rows.into_iter().map(to_vector).collect()));
+    }
+    let mut rows = rows.into_iter();
+    let header = match rows.next() {
+        Some(header) => header,
+        None => return Ok(Value::Vector(Vec::new())),
+    };
+    let mut records = Vec::new();
+    for (i, row) in rows.enumerate() {
+        if row.len() != header.len() {
+            return Err(format!(
+                "{}: record {} has {} fields but the header has {}",
+                fn_name,
+                i + 1,
+                row.len(),
+                header.len()
+            ));
+        }
+        let record: HashMap<String, Value> = header.iter().cloned().zip(row.into_iter().map(Value::Str)).collect();
+        records.push(Value::Map(record));
+    }
+    Ok(Value::Vector(records))
+}
+
+/// Render rows as CSV. Rows are sequences of fields, or maps, in which case
+/// a header row is written first using `:columns` (or the first map's keys
+/// in sorted order).
+pub fn write(rows: &Value, opts: &CsvOptions, fn_name: &str) -> Result<String, String> {
+    let rows: Vec<Value> = seq_iter(rows, fn_name)?.collect::<Result<_, _>>()?;
+    let mut out = String::new();
+
+    let columns = match rows.first() {
+        Some(Value::Map(first)) => {
+            let columns = opts.columns.clone().unwrap_or_else(|| {
+                let mut keys: Vec<String> = first.keys().cloned().collect();
+                keys.sort();
+                keys
+            });
+            write_record(columns.iter().map(String::as_str), opts, &mut out);
+            Some(columns)
+        }
+        _ => None,
+    };
+
+    for row in &rows {
+        let fields: Vec<String> = match (row, &columns) {
+            (Value::Map(map), Some(columns)) => columns.iter().map(|c| field_str(map.get(c).unwrap_or(&Value::Nil))).collect(),
+            (Value::Map(_), None) => return Err(format!("{} can't mix maps with other rows", fn_name)),
+            (_, Some(_)) => return Err(format!("{} can't mix maps with other rows", fn_name)),
+            _ if crate::lazy::is_seqable(row) => {
+                seq_iter(row, fn_name)?.map(|field| field.map(|f| field_str(&f))).collect::<Result<_, _>>()?
+            }
+            (other, None) => return Err(format!("{} rows must be sequences or maps, got {}", fn_name, other)),
+        };
+        write_record(fields.iter().map(String::as_str), opts, &mut out);
+    }
+    Ok(out)
+}
+
+fn field_str(value: &Value) -> String {
+    match value {
+        Value::Nil => String::new(),
+        other => str_of(other),
+    }
+}
+
+fn write_record<'a>(fields: impl ExactSizeIterator<Item = &'a str>, opts: &CsvOptions, out: &mut String) {
+    // A lone empty field is quoted so the line isn't read back as blank
+    let lone = fields.len() == 1;
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(opts.separator);
+        }
+        let needs_quotes = field
+            .chars()
+            .any(|c| c == opts.separator || c == opts.quote || c == '\n' || c == '\r')
+            || (lone && field.is_empty());
+        if needs_quotes {
+            out.push(opts.quote);
+            for c in field.chars() {
+                if c == opts.quote {
+                    out.push(c);
+                }
+                out.push(c);
+            }
+            out.push(opts.quote);
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str(opts.newline);
+}
@@ -0,0 +1,315 @@
+use crate::eval::call_function;
+use crate::reader::is_symbol_char;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    // Tag readers registered with register-tag-reader!, consulted whenever
+    // code or data is read
+    static TAG_READERS: RefCell<HashMap<String, Value>> = RefCell::new(HashMap::new());
+}
+
+pub fn register_tag_reader(tag: String, f: Value) {
+    TAG_READERS.with(|readers| readers.borrow_mut().insert(tag, f));
+}
+
+/// Tag readers supplied to a single `edn/read-string` call.
+#[derive(Default)]
+pub struct ReadOptions {
+    pub readers: HashMap<String, Value>,
+    /// Called with the tag and form when no reader handles a tag
+    pub default: Option<Value>,
+}
+
+pub fn read_options(opts: Option<&Value>, fn_name: &str) -> Result<ReadOptions, String> {
+    let mut options = ReadOptions::default();
+    let map = match opts {
+        None | Some(Value::Nil) => return Ok(options),
+        Some(Value::Map(map)) => map,
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    };
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("readers", Value::Map(readers)) => {
+                for (tag, f) in readers {
+                    if !matches!(f, Value::Function(_)) {
+                        return Err(format!("{} reader for #{} must be a function, got {}", fn_name, tag, f));
+                    }
+                    options.readers.insert(tag.clone(), f.clone());
+                }
+            }
+            ("default", Value::Function(_)) => options.default = Some(value.clone()),
+            ("readers", _) => return Err(format!("{} option :readers must be a map of tags to functions", fn_name)),
+            ("default", _) => return Err(format!("{} option :default must be a function", fn_name)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    Ok(options)
+}
+
+/// Resolve a tagged literal `#tag form`: per-call readers first, then
+/// registered ones, then the built-in `inst`, `uuid` and `bytes`.
+pub fn read_tagged(tag: &str, form: Value, opts: Option<&ReadOptions>) -> Result<Value, String> {
+    if let Some(f) = opts.and_then(|o| o.readers.get(tag)) {
+        return call_function(f, &[form]);
+    }
+    if let Some(f) = TAG_READERS.with(|readers| readers.borrow().get(tag).cloned()) {
+        return call_function(&f, &[form]);
+    }
+    match (tag, &form) {
+        ("inst", Value::Str(s)) => parse_inst(s).map(Value::Inst),
+        ("uuid", Value::Str(s)) => parse_uuid(s).map(Value::Uuid),
+        ("bytes", Value::Vector(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Number(n) if n.fract() == 0.0 && (0.0..=255.0).contains(n) => Ok(*n as u8),
+                other => Err(format!("#bytes elements must be integers from 0 to 255, got {}", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Bytes),
+        ("inst", other) | ("uuid", other) => Err(format!("#{} requires a string, got {}", tag, other)),
+        ("bytes", other) => Err(format!("#bytes requires a vector, got {}", other)),
+        _ => match opts.and_then(|o| o.default.as_ref()) {
+            Some(f) => call_function(f, &[Value::Symbol(tag.to_string()), form]),
+            None => Err(format!("No reader function for tag #{}", tag)),
+        },
+    }
+}
+
+/// Print `value` so that reading the result gives back an equal value.
+/// Values with no readable form (functions, I/O resources) print as `#<...>`,
+/// which the reader rejects.
+pub fn pr_str(value: &Value) -> String {
+    let mut out = String::new();
+    write_readable(value, &mut out);
+    out
+}
+
+fn write_readable(value: &Value, out: &mut String) {
+    match value {
+        Value::Number(n) if n.is_nan() => out.push_str("##NaN"),
+        Value::Number(n) if n.is_infinite() => out.push_str(if *n > 0.0 { "##Inf" } else { "##-Inf" }),
+        Value::Str(s) => write_string(s, out),
+        Value::List(items) => write_seq("(", items, ")", out),
+        Value::Vector(items) => write_seq("[", items, "]", out),
+        Value::LazySeq(seq) => match seq.printable() {
+            Ok((items, more)) => {
+                write_seq("(", &items, ")", out);
+                if more {
+                    out.pop();
+                    out.push_str(" ...)");
+                }
+            }
+            Err(e) => out.push_str(&format!("#<lazy-seq error: {}>", e)),
+        },
+        Value::Map(map) => {
+            // Keys print in sorted order so equal maps print the same way
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                // Keyword and string keys are the same key, so a key that
+                // can't be read as a keyword prints as a string
+                if !key.is_empty() && key.chars().all(is_symbol_char) {
+                    out.push(':');
+                    out.push_str(key);
+                } else {
+                    write_string(key, out);
+                }
+                out.push(' ');
+                write_readable(value, out);
+            }
+            out.push('}');
+        }
+        Value::Reduced(inner) => {
+            out.push_str("#<reduced ");
+            write_readable(inner, out);
+            out.push('>');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+fn write_seq(open: &str, items: &[Value], close: &str, out: &mut String) {
+    out.push_str(open);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        write_readable(item, out);
+    }
+    out.push_str(close);
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Parse an RFC 3339 timestamp. As with Clojure's `#inst`, trailing parts
+/// may be left off: `"2024"`, `"2024-03"` and `"2024-03-01T12:30"` are all
+/// valid, and a missing offset means UTC.
+pub fn parse_inst(s: &str) -> Result<i64, String> {
+    let invalid = || format!("Invalid #inst \"{}\": expected yyyy-mm-ddThh:mm:ss.fff+hh:mm", s);
+    let bytes = s.as_bytes();
+    let mut pos = 0;
+    let number = |len: usize, pos: &mut usize| -> Result<i64, String> {
+        let digits = bytes.get(*pos..*pos + len).filter(|d| d.iter().all(u8::is_ascii_digit)).ok_or_else(invalid)?;
+        *pos += len;
+        Ok(digits.iter().fold(0, |n, d| n * 10 + (d - b'0') as i64))
+    };
+    let next_is = |c: u8, pos: &mut usize| -> bool {
+        if bytes.get(*pos) == Some(&c) {
+            *pos += 1;
+            true
+        } else {
+            false
+        }
+    };
+
+    let year = number(4, &mut pos)?;
+    let (mut month, mut day, mut hour, mut minute, mut second, mut millis) = (1, 1, 0, 0, 0, 0);
+    if next_is(b'-', &mut pos) {
+        month = number(2, &mut pos)?;
+        if next_is(b'-', &mut pos) {
+            day = number(2, &mut pos)?;
+            if next_is(b'T', &mut pos) {
+                hour = number(2, &mut pos)?;
+                if !next_is(b':', &mut pos) {
+                    return Err(invalid());
+                }
+                minute = number(2, &mut pos)?;
+                if next_is(b':', &mut pos) {
+                    second = number(2, &mut pos)?;
+                    if next_is(b'.', &mut pos) {
+                        // Digits past milliseconds are accepted and dropped
+                        let start = pos;
+                        while bytes.get(pos).is_some_and(u8::is_ascii_digit) {
+                            pos += 1;
+                        }
+                        if pos == start {
+                            return Err(invalid());
+                        }
+                        let fraction = format!("{:0<3}", &s[start..pos.min(start + 3)]);
+                        millis = fraction.parse::<i64>().map_err(|_| invalid())?;
+                    }
+                }
+            }
+        }
+    }
+    let offset_minutes = match bytes.get(pos) {
+        None => 0,
+        Some(b'Z') | Some(b'z') => {
+            pos += 1;
+            0
+        }
+        Some(&sign @ (b'+' | b'-')) => {
+            pos += 1;
+            let hours = number(2, &mut pos)?;
+            if !next_is(b':', &mut pos) {
+                return Err(invalid());
+            }
+            let minutes = number(2, &mut pos)?;
+            if hours > 23 || minutes > 59 {
+                return Err(invalid());
+            }
+            if sign == b'-' { -(hours * 60 + minutes) } else { hours * 60 + minutes }
+        }
+        Some(_) => return Err(invalid()),
+    };
+    if pos != bytes.len() {
+        return Err(invalid());
+    }
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 59 {
+        return Err(format!("Invalid #inst \"{}\": date or time out of range", s));
+    }
+
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second - offset_minutes * 60;
+    Ok(seconds * 1_000 + millis)
+}
+
+/// Render epoch milliseconds as an RFC 3339 UTC timestamp.
+pub fn format_inst(ms: i64) -> String {
+    let days = ms.div_euclid(86_400_000);
+    let in_day = ms.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        in_day / 3_600_000,
+        in_day / 60_000 % 60,
+        in_day / 1_000 % 60,
+        in_day % 1_000
+    )
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's
+// days_from_civil)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parse the canonical 8-4-4-4-12 hex form of a UUID.
+pub fn parse_uuid(s: &str) -> Result<u128, String> {
+    let groups: Vec<&str> = s.split('-').collect();
+    let well_formed = groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()));
+    if !well_formed {
+        return Err(format!("Invalid #uuid \"{}\": expected xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx", s));
+    }
+    u128::from_str_radix(&groups.concat(), 16).map_err(|e| format!("Invalid #uuid \"{}\": {}", s, e))
+}
+
+pub fn format_uuid(id: u128) -> String {
+    let hex = format!("{:032x}", id);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
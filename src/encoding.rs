@@ -0,0 +1,242 @@
+use std::io::{self, Read, Write};
+
+/// Text encodings understood by the `:encoding` IO option. Cortado strings
+/// are UTF-8 internally; other encodings are transcoded at the IO boundary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    /// UTF-16 with byte order mark; big endian when no BOM is present
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Result<Encoding, String> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" | "iso8859-1" => Ok(Encoding::Latin1),
+            "utf-16" | "utf16" => Ok(Encoding::Utf16),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            _ => Err(format!("Unsupported encoding: {}", name)),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, String> {
+        if *self == Encoding::Utf8 {
+            return String::from_utf8(bytes.to_vec()).map_err(|e| format!("Invalid UTF-8: {}", e));
+        }
+        let mut decoder = Decoder::new(*self);
+        let mut out = String::new();
+        decoder.feed(bytes, &mut out);
+        decoder.finish(&mut out);
+        Ok(out)
+    }
+}
+
+fn encode_into(encoding: Encoding, s: &str, out: &mut Vec<u8>) -> Result<(), String> {
+    match encoding {
+        Encoding::Utf8 => out.extend_from_slice(s.as_bytes()),
+        Encoding::Latin1 => {
+            for c in s.chars() {
+                if (c as u32) > 0xFF {
+                    return Err(format!("Character '{}' cannot be encoded as latin1", c));
+                }
+                out.push(c as u32 as u8);
+            }
+        }
+        Encoding::Utf16 | Encoding::Utf16Be => {
+            for unit in s.encode_utf16() {
+                out.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        Encoding::Utf16Le => {
+            for unit in s.encode_utf16() {
+                out.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Incremental decoder that tolerates multi-byte sequences split across reads.
+struct Decoder {
+    encoding: Encoding,
+    pending: Vec<u8>,
+    big_endian: bool,
+    saw_start: bool,
+}
+
+impl Decoder {
+    fn new(encoding: Encoding) -> Self {
+        Decoder {
+            encoding,
+            pending: Vec::new(),
+            big_endian: encoding != Encoding::Utf16Le,
+            saw_start: false,
+        }
+    }
+
+    fn feed(&mut self, bytes: &[u8], out: &mut String) {
+        self.pending.extend_from_slice(bytes);
+        match self.encoding {
+            Encoding::Utf8 => {
+                let valid_up_to = match std::str::from_utf8(&self.pending) {
+                    Ok(s) => s.len(),
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    Err(e) => e.valid_up_to() + e.error_len().unwrap_or(1),
+                };
+                out.push_str(&String::from_utf8_lossy(&self.pending[..valid_up_to]));
+                self.pending.drain(..valid_up_to);
+            }
+            Encoding::Latin1 => {
+                out.extend(self.pending.iter().map(|&b| b as char));
+                self.pending.clear();
+            }
+            Encoding::Utf16 | Encoding::Utf16Le | Encoding::Utf16Be => {
+                if !self.saw_start {
+                    if self.pending.len() < 2 {
+                        return;
+                    }
+                    self.saw_start = true;
+                    match (self.pending[0], self.pending[1]) {
+                        (0xFE, 0xFF) if self.encoding != Encoding::Utf16Le => {
+                            self.big_endian = true;
+                            self.pending.drain(..2);
+                        }
+                        (0xFF, 0xFE) if self.encoding != Encoding::Utf16Be => {
+                            self.big_endian = false;
+                            self.pending.drain(..2);
+                        }
+                        _ => {}
+                    }
+                }
+                let even = self.pending.len() - self.pending.len() % 2;
+                let mut units: Vec<u16> = self.pending[..even]
+                    .chunks(2)
+                    .map(|pair| {
+                        if self.big_endian {
+                            u16::from_be_bytes([pair[0], pair[1]])
+                        } else {
+                            u16::from_le_bytes([pair[0], pair[1]])
+                        }
+                    })
+                    .collect();
+                let mut consumed = even;
+                // Keep a trailing high surrogate until its partner arrives
+                if let Some(&last) = units.last() {
+                    if (0xD800..0xDC00).contains(&last) {
+                        units.pop();
+                        consumed -= 2;
+                    }
+                }
+                out.extend(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)));
+                self.pending.drain(..consumed);
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut String) {
+        if !self.pending.is_empty() {
+            out.push(char::REPLACEMENT_CHARACTER);
+            self.pending.clear();
+        }
+    }
+}
+
+/// Reader adapter producing UTF-8 bytes from an underlying encoded stream.
+pub struct DecodingReader<R: Read> {
+    inner: R,
+    decoder: Decoder,
+    decoded: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub fn new(inner: R, encoding: Encoding) -> Self {
+        DecodingReader {
+            inner,
+            decoder: Decoder::new(encoding),
+            decoded: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.decoded.len() && !self.eof {
+            let mut chunk = [0u8; 8192];
+            let n = self.inner.read(&mut chunk)?;
+            let mut text = String::new();
+            if n == 0 {
+                self.eof = true;
+                self.decoder.finish(&mut text);
+            } else {
+                self.decoder.feed(&chunk[..n], &mut text);
+            }
+            self.decoded = text.into_bytes();
+            self.pos = 0;
+        }
+        let available = &self.decoded[self.pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Writer adapter that transcodes UTF-8 input into the target encoding.
+pub struct EncodingWriter<W: Write> {
+    inner: W,
+    encoding: Encoding,
+    pending: Vec<u8>,
+    wrote_bom: bool,
+}
+
+impl<W: Write> EncodingWriter<W> {
+    pub fn new(inner: W, encoding: Encoding) -> Self {
+        EncodingWriter {
+            inner,
+            encoding,
+            pending: Vec::new(),
+            wrote_bom: encoding != Encoding::Utf16,
+        }
+    }
+
+    /// Appending to an existing UTF-16 file must not insert a second BOM.
+    pub fn without_bom(mut self) -> Self {
+        self.wrote_bom = true;
+        self
+    }
+}
+
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let valid_up_to = match std::str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let mut out = Vec::new();
+        if !self.wrote_bom {
+            out.extend_from_slice(&[0xFE, 0xFF]);
+            self.wrote_bom = true;
+        }
+        let text = std::str::from_utf8(&self.pending[..valid_up_to]).expect("validated above");
+        encode_into(self.encoding, text, &mut out)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.inner.write_all(&out)?;
+        self.pending.drain(..valid_up_to);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
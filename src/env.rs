@@ -1,20 +1,29 @@
 use crate::value::Value;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+// Frames are shared copy-on-write, so capturing an environment in a closure
+// or lazy-seq is cheap and only a frame that is then changed gets copied.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Env {
-    parent: Option<Box<Env>>,
-    data: HashMap<String, Value>,
+    parent: Option<Arc<Env>>,
+    data: Arc<HashMap<String, Value>>,
     current_namespace: String,
     loaded_namespaces: HashSet<String>,
     namespace_aliases: HashMap<String, String>, // alias -> full namespace mapping
 }
 
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Env {
     pub fn new() -> Self {
         Env {
             parent: None,
-            data: HashMap::new(),
+            data: Arc::default(),
             current_namespace: "user".to_string(),
             loaded_namespaces: HashSet::new(),
             namespace_aliases: HashMap::new(),
@@ -27,8 +36,8 @@ impl Env {
         let namespace_aliases = parent.namespace_aliases.clone();
         
         Env {
-            parent: Some(Box::new(parent)),
-            data: HashMap::new(),
+            parent: Some(Arc::new(parent)),
+            data: Arc::default(),
             current_namespace,
             loaded_namespaces,
             namespace_aliases,
@@ -36,7 +45,7 @@ impl Env {
     }
 
     pub fn set(&mut self, name: String, val: Value) {
-        self.data.insert(name, val);
+        Arc::make_mut(&mut self.data).insert(name, val);
     }
 
     pub fn get(&self, name: &str) -> Option<Value> {
@@ -51,10 +60,10 @@ impl Env {
 
     pub fn update(&mut self, name: &str, val: Value) -> Result<(), String> {
         if self.data.contains_key(name) {
-            self.data.insert(name.to_string(), val);
+            Arc::make_mut(&mut self.data).insert(name.to_string(), val);
             Ok(())
         } else if let Some(parent) = &mut self.parent {
-            parent.update(name, val)
+            Arc::make_mut(parent).update(name, val)
         } else {
             Err(format!("Variable {} not found for update", name))
         }
@@ -79,14 +88,18 @@ impl Env {
 
     // Set a namespaced symbol
     pub fn set_namespaced(&mut self, name: String, val: Value) {
-        let qualified_name = if name.contains('/') {
+        let qualified_name = self.qualify(&name);
+        Arc::make_mut(&mut self.data).insert(qualified_name, val);
+    }
+
+    /// The name a definition of `name` gets in the current namespace.
+    pub fn qualify(&self, name: &str) -> String {
+        if name.contains('/') {
             // Already qualified
-            name
+            name.to_string()
         } else {
-            // Qualify with current namespace
             format!("{}/{}", self.current_namespace, name)
-        };
-        self.data.insert(qualified_name, val);
+        }
     }
 
     // Get with namespace resolution
@@ -152,12 +165,74 @@ impl Env {
         self.get_with_namespaces(name)
     }
 
+    /// Every bound name, including those of parent environments.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.data.keys().cloned().collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.names());
+        }
+        names
+    }
+
+    /// Every namespace alias as (alias, namespace) pairs.
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        let mut aliases: Vec<(String, String)> =
+            self.namespace_aliases.iter().map(|(a, ns)| (a.clone(), ns.clone())).collect();
+        if let Some(parent) = &self.parent {
+            aliases.extend(parent.aliases());
+        }
+        aliases
+    }
+
+    /// Find what a symbol refers to the way evaluation would, returning the
+    /// qualified name it was found under.
+    pub fn resolve(&self, name: &str) -> Option<(String, Value)> {
+        let mut candidates = Vec::new();
+        match name.split_once('/') {
+            Some((prefix, suffix)) if !prefix.is_empty() => {
+                if let Some(ns) = self.resolve_alias(prefix) {
+                    candidates.push(format!("{}/{}", ns, suffix));
+                }
+            }
+            _ => {
+                candidates.push(format!("{}/{}", self.current_namespace, name));
+                candidates.push(format!("core/{}", name));
+                candidates.push(format!("user/{}", name));
+            }
+        }
+        candidates.push(name.to_string());
+        candidates.into_iter().find_map(|candidate| self.get(&candidate).map(|value| (candidate, value)))
+    }
+
+    /// Every name a symbol could be completed to: bindings as written in the
+    /// current namespace, fully qualified and through aliases, sorted.
+    pub fn completion_names(&self) -> Vec<String> {
+        let local_prefixes = [format!("{}/", self.current_namespace), "user/".to_string(), "core/".to_string()];
+        let aliases: Vec<(String, String)> =
+            self.aliases().into_iter().map(|(alias, ns)| (alias, format!("{}/", ns))).collect();
+        let mut names = std::collections::BTreeSet::new();
+        for name in self.names() {
+            for prefix in &local_prefixes {
+                if let Some(short) = name.strip_prefix(prefix.as_str()) {
+                    names.insert(short.to_string());
+                }
+            }
+            for (alias, ns_prefix) in &aliases {
+                if let Some(short) = name.strip_prefix(ns_prefix.as_str()) {
+                    names.insert(format!("{}/{}", alias, short));
+                }
+            }
+            names.insert(name);
+        }
+        names.into_iter().collect()
+    }
+
     // Get all functions from a specific namespace
     pub fn get_namespace_functions(&self, namespace: &str) -> Vec<(String, Value)> {
         let mut functions = Vec::new();
         let prefix = format!("{}/", namespace);
         
-        for (key, value) in &self.data {
+        for (key, value) in self.data.iter() {
             if key.starts_with(&prefix) {
                 functions.push((key.clone(), value.clone()));
             }
@@ -1,13 +1,38 @@
 use crate::env::Env;
-use crate::value::{Value, Function, IOResource};
+use crate::value::{Value, Function, IOResource, with_open};
+use crate::encoding::{Encoding, DecodingReader, EncodingWriter};
+use crate::lazy::{self, LazySeq, SeqIter, Step};
+use crate::transduce::{self, Flow, Xform};
+use crate::json;
+use crate::edn;
+use crate::csv;
+use crate::process;
+use crate::fs;
+use crate::watch;
+use crate::nrepl;
+use crate::testing;
+use crate::check;
+use crate::coverage;
+use crate::profile;
+use crate::reader;
 use std::collections::HashMap;
-use std::cell::RefCell;
-use std::sync::{Arc, Mutex};
-use std::io::{BufRead, Write, Read};
+use std::cell::{Cell, RefCell};
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Forms `eval` handles itself rather than by calling a bound function.
+pub const SPECIAL_FORMS: &[&str] = &[
+    "def", "defn", "defmacro", "if", "fn", "quote", "quasiquote", "macroexpand", "letrec", "let", "load", "do",
+    "ns", "require", "and", "or", "with-open", "with-temp", "lazy-seq", "deftest", "testing", "is", "are",
+    "thrown?", "thrown-with-msg?", "use-fixtures", "prop/for-all", "defspec", "profile", "with-out-str",
+];
 
 pub fn eval(expr: &Value, env: &mut Env) -> Result<Value, String> {
     match expr {
-        Value::Number(_) | Value::Bool(_) | Value::Nil | Value::Str(_) | Value::Keyword(_) | Value::IOResource(_) => {
+        Value::Number(_) | Value::Bool(_) | Value::Nil | Value::Str(_) | Value::Keyword(_) | Value::IOResource(_) | Value::Bytes(_) => {
             Ok(expr.clone())
         }
         Value::Uninitialized => {
@@ -54,23 +79,76 @@ pub fn eval(expr: &Value, env: &mut Env) -> Result<Value, String> {
                     "require" => eval_require(list, env),
                     "and" => eval_and(list, env),
                     "or" => eval_or(list, env),
+                    "with-open" => eval_with_open(list, env),
+                    "with-temp" => eval_with_temp(list, env),
+                    "lazy-seq" => eval_lazy_seq(list, env),
+                    "with-out-str" => eval_with_out_str(list, env),
+                    "deftest" => testing::eval_deftest(list, env),
+                    "testing" => testing::eval_testing(list, env),
+                    "is" => testing::eval_is(list, env),
+                    "are" => testing::eval_are(list, env),
+                    "thrown?" | "thrown-with-msg?" => testing::eval_thrown(list, env),
+                    "use-fixtures" => testing::eval_use_fixtures(list, env),
+                    "prop/for-all" => check::eval_for_all(list, env),
+                    "defspec" => check::eval_defspec(list, env),
+                    "profile" => profile::eval_profile(list, env),
+                    coverage::HIT => coverage::eval_hit(list, env),
                     _ => eval_call(list, env),
                 }
             } else {
                 eval_call(list, env)
             }
         }
-        Value::Function(_) => Ok(expr.clone()),
+        Value::Function(_) | Value::Transducer(_) | Value::Reduced(_) | Value::Regex(_) | Value::Inst(_) | Value::Uuid(_) | Value::Generator(_) => Ok(expr.clone()),
+        // Code built with lazy operations such as `concat` is evaluated as a list
+        Value::LazySeq(seq) => eval(&Value::List(seq.to_vec()?), env),
+    }
+}
+
+/// How a name was defined, kept for the REPL's :doc and :source.
+#[derive(Clone)]
+pub struct Definition {
+    pub form: Value,
+    pub doc: Option<String>,
+}
+
+thread_local! {
+    static DEFINITIONS: RefCell<HashMap<String, Definition>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn record_definition(qualified_name: String, form: &[Value], doc: Option<String>) {
+    let definition = Definition { form: Value::List(form.to_vec()), doc };
+    DEFINITIONS.with(|defs| defs.borrow_mut().insert(qualified_name, definition));
+}
+
+/// The definition form and docstring recorded for a qualified name.
+pub fn definition(qualified_name: &str) -> Option<Definition> {
+    DEFINITIONS.with(|defs| defs.borrow().get(qualified_name).cloned())
+}
+
+// (def name value), (defn name [params] body) and (defmacro ...) accept an
+// optional docstring after the name; this splits it off.
+fn split_docstring(list: &[Value], plain_len: usize) -> (Option<String>, Vec<Value>) {
+    match &list.get(2) {
+        Some(Value::Str(doc)) if list.len() == plain_len + 1 => {
+            let mut rest = list[..2].to_vec();
+            rest.extend_from_slice(&list[3..]);
+            (Some(doc.clone()), rest)
+        }
+        _ => (None, list.to_vec()),
     }
 }
 
 fn eval_def(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let form = list;
+    let (doc, list) = split_docstring(list, 3);
     if list.len() != 3 {
         return Err("def requires exactly 2 arguments".to_string());
     }
 
     if let Value::Symbol(name) = &list[1] {
         let value = eval(&list[2], env)?;
+        record_definition(env.qualify(name), form, doc);
         env.set_namespaced(name.clone(), value.clone());
         Ok(value)
     } else {
@@ -84,10 +162,7 @@ fn eval_if(list: &[Value], env: &mut Env) -> Result<Value, String> {
     }
 
     let condition = eval(&list[1], env)?;
-    let is_truthy = match condition {
-        Value::Bool(false) | Value::Nil => false,
-        _ => true,
-    };
+    let is_truthy = !matches!(condition, Value::Bool(false) | Value::Nil);
 
     if is_truthy {
         eval(&list[2], env)
@@ -158,7 +233,124 @@ fn eval_or(list: &[Value], env: &mut Env) -> Result<Value, String> {
     Ok(Value::Nil)
 }
 
+fn eval_with_open(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    eval_with_cleanup(
+        list,
+        env,
+        "with-open",
+        |value| match value {
+            Value::IOResource(resource) => Ok(Some(resource.clone())),
+            _ => Ok(None),
+        },
+        IOResource::close,
+    )
+}
+
+// (with-temp [dir (temp-dir) f (temp-file)] body...) binds temporary paths
+// and deletes them when the body finishes, even if it failed. Only paths
+// made by temp-file or temp-dir are accepted, so a typo can't delete real
+// files.
+fn eval_with_temp(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    eval_with_cleanup(
+        list,
+        env,
+        "with-temp",
+        |value| match value {
+            Value::Str(path) if fs::is_temp(path) => Ok(Some(path.clone())),
+            other => Err(format!("with-temp only binds paths made by temp-file or temp-dir, got {}", other)),
+        },
+        |path| fs::remove_temp(path),
+    )
+}
+
+// Shared by with-open and with-temp: bind each name in order, keeping what
+// `acquire` returns for the bound value, evaluate the body, then `release`
+// what was kept in reverse order, even when binding or the body failed.
+fn eval_with_cleanup<T>(
+    list: &[Value],
+    env: &mut Env,
+    form: &str,
+    acquire: impl Fn(&Value) -> Result<Option<T>, String>,
+    release: impl Fn(&T) -> Result<(), String>,
+) -> Result<Value, String> {
+    if list.len() < 3 {
+        return Err(format!("{} requires a binding vector and a body", form));
+    }
+
+    let bindings = match &list[1] {
+        Value::Vector(bindings) if bindings.len() % 2 == 0 => bindings,
+        Value::Vector(_) => return Err(format!("{} bindings must have an even number of elements", form)),
+        _ => return Err(format!("{} bindings must be a vector", form)),
+    };
+
+    let mut local_env = Env::with_parent(env.clone());
+    let mut acquired: Vec<T> = Vec::new();
+
+    let mut result = Ok(Value::Nil);
+    for pair in bindings.chunks(2) {
+        let name = match &pair[0] {
+            Value::Symbol(name) => name,
+            _ => {
+                result = Err("Binding names must be symbols".to_string());
+                break;
+            }
+        };
+        let bound = eval(&pair[1], &mut local_env).and_then(|value| Ok((acquire(&value)?, value)));
+        match bound {
+            Ok((kept, value)) => {
+                acquired.extend(kept);
+                local_env.set(name.clone(), value);
+            }
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+
+    if result.is_ok() {
+        for expr in &list[2..] {
+            result = eval(expr, &mut local_env);
+            if result.is_err() {
+                break;
+            }
+        }
+    }
+
+    let mut release_error = None;
+    for item in acquired.iter().rev() {
+        if let Err(e) = release(item) {
+            release_error.get_or_insert(e);
+        }
+    }
+
+    match (result, release_error) {
+        (Err(e), _) => Err(e),
+        (Ok(_), Some(e)) => Err(e),
+        (Ok(value), None) => Ok(value),
+    }
+}
+
+// (lazy-seq body...) defers evaluating body until the sequence is first
+// realized; the result is memoized.
+fn eval_lazy_seq(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let body = match list.len() {
+        1 => Value::Nil,
+        2 => list[1].clone(),
+        _ => {
+            let mut forms = vec![Value::Symbol("do".to_string())];
+            forms.extend(list[1..].iter().cloned());
+            Value::List(forms)
+        }
+    };
+    // The body becomes a closure of no arguments, capturing env as fn does
+    let thunk = eval_fn(&[Value::Symbol("fn".to_string()), Value::Vector(Vec::new()), body], env)?;
+    Ok(Value::LazySeq(LazySeq::from_fn(move || call_function(&thunk, &[]))))
+}
+
 fn eval_defn(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let form = list;
+    let (doc, list) = split_docstring(list, 4);
     if list.len() != 4 {
         return Err("defn requires exactly 3 arguments".to_string());
     }
@@ -172,6 +364,7 @@ fn eval_defn(list: &[Value], env: &mut Env) -> Result<Value, String> {
             list[3].clone(),
         ], env)?;
         
+        record_definition(env.qualify(name), form, doc);
         env.set_namespaced(name.clone(), fn_value.clone());
         Ok(fn_value)
     } else {
@@ -225,6 +418,8 @@ fn eval_quasiquote_form(form: &Value, env: &mut Env) -> Result<Value, String> {
 }
 
 fn eval_defmacro(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let form = list;
+    let (doc, list) = split_docstring(list, 4);
     if list.len() != 4 {
         return Err("defmacro requires exactly 3 arguments".to_string());
     }
@@ -254,6 +449,7 @@ fn eval_defmacro(list: &[Value], env: &mut Env) -> Result<Value, String> {
             env: captured_env,
         });
         
+        record_definition(env.qualify(name), form, doc);
         env.set_namespaced(name.clone(), macro_fn.clone());
         Ok(macro_fn)
     } else {
@@ -408,17 +604,18 @@ fn eval_load(list: &[Value], env: &mut Env) -> Result<Value, String> {
         Err(e) => return Err(format!("Failed to read file '{}': {}", filename, e)),
     };
 
-    // Parse all forms from the file using read_all_forms
-    use crate::reader::read_all_forms;
-    
-    let forms = match read_all_forms(&content) {
-        Ok(forms) => forms,
-        Err(e) => return Err(format!("Parse error in '{}': {}", filename, e)),
+    // Parse all forms from the file, keeping positions for coverage
+    let forms = match reader::read_all_forms_with_positions(&content) {
+        (_, Some(e)) => return Err(format!("Parse error in '{}': {}", filename, e.message)),
+        (forms, None) => coverage::instrument(&filename, forms, env.get_namespace()),
     };
 
     // Evaluate each form in order
     let mut last_result = Value::Nil;
-    for form in forms {
+    for (form, point) in forms {
+        if let Some(point) = point {
+            coverage::hit(point);
+        }
         match eval(&form, env) {
             Ok(result) => last_result = result,
             Err(e) => return Err(format!("Error evaluating expression in '{}': {}", filename, e)),
@@ -429,15 +626,15 @@ fn eval_load(list: &[Value], env: &mut Env) -> Result<Value, String> {
 }
 
 fn eval_call(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    check_interrupt()?;
+
     // First check if it's a macro call
     if let Value::Symbol(name) = &list[0] {
-        if let Some(value) = env.get_with_aliases(name) {
-            if let Value::Function(Function::Macro { params, body, env: macro_env }) = value {
-                // It's a macro - expand it first
-                let expanded = expand_macro(&params, &body, &list[1..], &macro_env)?;
-                // Then evaluate the expanded form
-                return eval(&expanded, env);
-            }
+        if let Some(Value::Function(Function::Macro { params, body, env: macro_env })) = env.get_with_aliases(name) {
+            // It's a macro - expand it first
+            let expanded = expand_macro(&params, &body, &list[1..], &macro_env)?;
+            // Then evaluate the expanded form
+            return eval(&expanded, env);
         }
     }
     
@@ -465,7 +662,11 @@ fn eval_call(list: &[Value], env: &mut Env) -> Result<Value, String> {
     }
     
     if let Value::Function(func) = &evaluated[0] {
-        match func {
+        let _frame = profile::enter(|| match &list[0] {
+            Value::Symbol(name) => name.clone(),
+            _ => "(fn)".to_string(),
+        });
+        let result = match func {
             Function::Native(f) => f(&evaluated[1..]),
             Function::UserDefined { params, body, env: captured_env } => {
                 if evaluated.len() - 1 != params.len() {
@@ -492,15 +693,159 @@ fn eval_call(list: &[Value], env: &mut Env) -> Result<Value, String> {
             Function::Macro { .. } => {
                 Err("Macros should be expanded before evaluation".to_string())
             }
+            Function::Composed(fns) => call_composed(fns, &evaluated[1..]),
+        };
+        if result.is_err() {
+            push_error_frame(list);
         }
+        result
     } else {
         Err(format!("Cannot call non-function: {:?}", evaluated[0]))
     }
 }
 
+// Set by an nREPL session so its client can stop a long evaluation. The
+// flag stays raised until the session starts its next evaluation.
+thread_local! {
+    static INTERRUPT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Make evaluation on this thread fail with "Interrupted" once `flag` is set.
+pub fn set_interrupt_flag(flag: Arc<AtomicBool>) {
+    INTERRUPT.with(|interrupt| *interrupt.borrow_mut() = Some(flag));
+}
+
+// Set by the test runner while a test runs; evaluation past it fails with
+// TIMED_OUT
+thread_local! {
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+pub(crate) const TIMED_OUT: &str = "Timed out";
+
+/// Set this thread's deadline, returning the one it replaces.
+pub fn set_deadline(deadline: Option<Instant>) -> Option<Instant> {
+    DEADLINE.with(|d| d.replace(deadline))
+}
+
+pub(crate) fn check_interrupt() -> Result<(), String> {
+    let interrupted = INTERRUPT.with(|interrupt| {
+        interrupt.borrow().as_ref().is_some_and(|flag| flag.load(Ordering::SeqCst))
+    });
+    if interrupted {
+        return Err("Interrupted".to_string());
+    }
+    if DEADLINE.with(|d| d.get()).is_some_and(|deadline| Instant::now() >= deadline) {
+        return Err(TIMED_OUT.to_string());
+    }
+    Ok(())
+}
+
+/// Whether an error is an interrupt or timeout, which nothing should catch.
+pub(crate) fn is_interruption(message: &str) -> bool {
+    message.ends_with("Interrupted") || message.ends_with(TIMED_OUT)
+}
+
+// Calls that failed, innermost first, recorded as an error unwinds so the
+// REPL can show where it came from
+thread_local! {
+    static ERROR_TRACE: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static LAST_ERROR: RefCell<Option<Value>> = const { RefCell::new(None) };
+}
+
+const MAX_TRACE_FRAMES: usize = 50;
+
+fn push_error_frame(call: &[Value]) {
+    ERROR_TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut();
+        if trace.len() < MAX_TRACE_FRAMES {
+            let form = Value::List(call.to_vec()).to_string();
+            let frame = if form.chars().count() > 80 {
+                format!("{}...", form.chars().take(77).collect::<String>())
+            } else {
+                form
+            };
+            trace.push(frame);
+        }
+    });
+}
+
+/// Forget the frames of any earlier error before evaluating new input.
+pub fn clear_error_trace() {
+    ERROR_TRACE.with(|trace| trace.borrow_mut().clear());
+}
+
+/// Turn an error message and the frames recorded for it into the map the
+/// REPL binds to `*e`, and remember it for `pst`.
+pub fn record_error(message: &str) -> Value {
+    let trace = ERROR_TRACE.with(|trace| std::mem::take(&mut *trace.borrow_mut()));
+    let mut error = HashMap::new();
+    error.insert("message".to_string(), Value::Str(message.to_string()));
+    error.insert("trace".to_string(), Value::Vector(trace.into_iter().map(Value::Str).collect()));
+    let error = Value::Map(error);
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error.clone()));
+    error
+}
+
+fn print_stack_trace(error: &Value) -> Result<(), String> {
+    let map = match error {
+        Value::Map(map) => map,
+        other => return Err(format!("pst requires an error map such as *e, got {}", other)),
+    };
+    match map.get("message") {
+        Some(Value::Str(message)) => write_out(&format!("Error: {}\n", message)),
+        _ => return Err("pst requires an error map with a :message".to_string()),
+    }
+    if let Some(Value::Vector(frames)) = map.get("trace") {
+        for frame in frames {
+            write_out(&format!("  in {}\n", str_of(frame)));
+        }
+    }
+    Ok(())
+}
+
+// Output from print and friends normally goes to stdout, but an nREPL
+// session collects it to send to its client instead
+thread_local! {
+    static CAPTURED_OUTPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn write_out(text: &str) {
+    let captured = CAPTURED_OUTPUT.with(|out| match out.borrow_mut().as_mut() {
+        Some(buffer) => {
+            buffer.push_str(text);
+            true
+        }
+        None => false,
+    });
+    if !captured {
+        print!("{}", text);
+    }
+}
+
+// (with-out-str body...) evaluates body and returns what it printed as a
+// string, restoring whatever was collecting output before.
+fn eval_with_out_str(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let outer = CAPTURED_OUTPUT.with(|out| out.borrow_mut().replace(String::new()));
+    let result = list[1..].iter().try_for_each(|form| eval(form, env).map(drop));
+    let printed = CAPTURED_OUTPUT.with(|out| std::mem::replace(&mut *out.borrow_mut(), outer));
+    result.map(|()| Value::Str(printed.unwrap_or_default()))
+}
+
+/// Collect this thread's printed output from now on rather than writing it
+/// to stdout.
+pub fn capture_output() {
+    CAPTURED_OUTPUT.with(|out| *out.borrow_mut() = Some(String::new()));
+}
+
+/// The output collected since the last call.
+pub fn take_captured_output() -> String {
+    CAPTURED_OUTPUT.with(|out| out.borrow_mut().as_mut().map(std::mem::take).unwrap_or_default())
+}
+
 // Thread-local recursion depth counter to prevent stack overflow
 thread_local! {
-    static RECURSION_DEPTH: RefCell<usize> = RefCell::new(0);
+    static RECURSION_DEPTH: RefCell<usize> = const { RefCell::new(0) };
 }
 
 const MAX_RECURSION_DEPTH: usize = 1000;
@@ -540,6 +885,49 @@ fn eval_user_function_with_tco(params: &[String], body: &Value, captured_env: &E
     result
 }
 
+/// Call a function value from native code, e.g. the function given to `map`.
+pub(crate) fn call_function(func: &Value, args: &[Value]) -> Result<Value, String> {
+    // Called by value, so there's no name to profile it under
+    let _frame = match func {
+        Value::Function(_) => profile::enter(|| "(fn)".to_string()),
+        _ => None,
+    };
+    match func {
+        Value::Function(Function::Native(f)) => f(args),
+        Value::Function(Function::UserDefined { params, body, env: captured_env }) => {
+            if params.len() != args.len() {
+                return Err(format!("Function expects {} arguments, got {}", params.len(), args.len()));
+            }
+            eval_user_function_with_tco(params, body, captured_env, args, captured_env, None, func)
+        }
+        Value::Function(Function::Macro { .. }) => {
+            Err("Cannot apply macro (use macroexpand instead)".to_string())
+        }
+        Value::Function(Function::Composed(fns)) => call_composed(fns, args),
+        Value::Keyword(key) => match args {
+            [Value::Map(map)] => Ok(map.get(key).cloned().unwrap_or(Value::Nil)),
+            _ => Err("Keyword as function requires a map argument".to_string()),
+        },
+        _ => Err(format!("Cannot call non-function: {}", func)),
+    }
+}
+
+// The last function takes every argument and the rest run right to left on
+// its result; with no functions, comp is identity
+fn call_composed(fns: &[Value], args: &[Value]) -> Result<Value, String> {
+    let Some((last, rest)) = fns.split_last() else {
+        return match args {
+            [arg] => Ok(arg.clone()),
+            _ => Err(format!("(comp) expects 1 argument, got {}", args.len())),
+        };
+    };
+    let mut result = call_function(last, args)?;
+    for f in rest.iter().rev() {
+        result = call_function(f, &[result])?;
+    }
+    Ok(result)
+}
+
 fn expand_macro(params: &[String], body: &Value, args: &[Value], macro_env: &Env) -> Result<Value, String> {
     if args.len() != params.len() {
         return Err(format!(
@@ -635,6 +1023,304 @@ fn substitute_symbols(expr: &Value, substitutions: &std::collections::HashMap<St
     }
 }
 
+// Remove a trailing "\n" or "\r\n" left by read_line
+fn trim_line_ending(line: &mut String) {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+}
+
+type ReaderHandle = std::sync::Arc<std::sync::Mutex<Option<Box<dyn std::io::BufRead + Send>>>>;
+
+/// Lines of a reader, read one at a time as the iterator advances.
+pub(crate) fn reader_lines(reader: &ReaderHandle) -> impl Iterator<Item = Result<Value, String>> + Send {
+    let reader = reader.clone();
+    std::iter::from_fn(move || {
+        let line = with_open(&reader, "reader", |r| {
+            let mut line = String::new();
+            match r.read_line(&mut line) {
+                Ok(0) => Ok(None),
+                Ok(_) => {
+                    trim_line_ending(&mut line);
+                    Ok(Some(Value::Str(line)))
+                }
+                Err(e) => Err(format!("Failed to read line from reader: {}", e)),
+            }
+        });
+        line.transpose()
+    })
+}
+
+fn read_utf8_char(r: &mut dyn std::io::BufRead) -> Result<Option<char>, String> {
+    let first = match r.fill_buf() {
+        Ok([]) => return Ok(None),
+        Ok(buf) => buf[0],
+        Err(e) => return Err(format!("Failed to read char from reader: {}", e)),
+    };
+    let width = match first {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Err("Invalid UTF-8 in reader".to_string()),
+    };
+    let mut bytes = [0u8; 4];
+    r.read_exact(&mut bytes[..width])
+        .map_err(|e| format!("Failed to read char from reader: {}", e))?;
+    std::str::from_utf8(&bytes[..width])
+        .map(|s| s.chars().next())
+        .map_err(|_| "Invalid UTF-8 in reader".to_string())
+}
+
+fn read_up_to(r: &mut dyn std::io::Read, n: usize) -> Result<Value, String> {
+    let mut buf = Vec::with_capacity(n.min(DEFAULT_IO_BUFFER_SIZE));
+    match Read::take(r, n as u64).read_to_end(&mut buf) {
+        Ok(0) if n > 0 => Ok(Value::Nil), // EOF
+        Ok(_) => Ok(Value::Bytes(buf)),
+        Err(e) => Err(format!("Failed to read bytes: {}", e)),
+    }
+}
+
+const DEFAULT_IO_BUFFER_SIZE: usize = 8 * 1024;
+
+// Options map accepted by reader, writer, slurp and spit
+struct IOOptions {
+    append: bool,
+    encoding: Encoding,
+    buffer_size: usize,
+    create_dirs: bool,
+}
+
+fn parse_io_options(opts: Option<&Value>, fn_name: &str) -> Result<IOOptions, String> {
+    let mut options = IOOptions {
+        append: false,
+        encoding: Encoding::Utf8,
+        buffer_size: DEFAULT_IO_BUFFER_SIZE,
+        create_dirs: false,
+    };
+
+    let map = match opts {
+        None | Some(Value::Nil) => return Ok(options),
+        Some(Value::Map(map)) => map,
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    };
+
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("append", Value::Bool(b)) => options.append = *b,
+            ("create-dirs", Value::Bool(b)) => options.create_dirs = *b,
+            ("encoding", Value::Str(name)) | ("encoding", Value::Keyword(name)) => {
+                options.encoding = Encoding::from_name(name)?;
+            }
+            ("buffer-size", Value::Number(n)) if *n >= 1.0 && n.fract() == 0.0 => {
+                options.buffer_size = *n as usize;
+            }
+            ("append", _) | ("create-dirs", _) => {
+                return Err(format!("{} option :{} must be a boolean", fn_name, key));
+            }
+            ("encoding", _) => return Err(format!("{} option :encoding must be a string", fn_name)),
+            ("buffer-size", _) => {
+                return Err(format!("{} option :buffer-size must be a positive integer", fn_name));
+            }
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn wrap_reader(source: impl std::io::Read + Send + 'static, opts: &IOOptions) -> IOResource {
+    if opts.encoding == Encoding::Utf8 {
+        IOResource::reader(std::io::BufReader::with_capacity(opts.buffer_size, source))
+    } else {
+        let decoder = DecodingReader::new(source, opts.encoding);
+        IOResource::reader(std::io::BufReader::with_capacity(opts.buffer_size, decoder))
+    }
+}
+
+fn wrap_writer(sink: impl std::io::Write + Send + 'static, opts: &IOOptions, write_bom: bool) -> IOResource {
+    if opts.encoding == Encoding::Utf8 {
+        IOResource::writer(std::io::BufWriter::with_capacity(opts.buffer_size, sink))
+    } else {
+        let encoder = EncodingWriter::new(sink, opts.encoding);
+        let encoder = if write_bom { encoder } else { encoder.without_bom() };
+        IOResource::writer(std::io::BufWriter::with_capacity(opts.buffer_size, encoder))
+    }
+}
+
+fn open_file_writer(filename: &str, opts: &IOOptions) -> Result<IOResource, String> {
+    if opts.create_dirs {
+        if let Some(parent) = std::path::Path::new(filename).parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directories for '{}': {}", filename, e))?;
+            }
+        }
+    }
+
+    // Only the start of a file gets a byte order mark
+    let existing_len = std::fs::metadata(filename).map(|m| m.len()).unwrap_or(0);
+    let write_bom = !opts.append || existing_len == 0;
+
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(opts.append)
+        .truncate(!opts.append)
+        .open(filename)
+        .map_err(|e| format!("Failed to create file '{}': {}", filename, e))?;
+
+    Ok(wrap_writer(file, opts, write_bom))
+}
+
+// The text `str` produces for a value: strings without quotes, keywords
+// without the colon, and everything else as printed.
+pub(crate) fn str_of(value: &Value) -> String {
+    match value {
+        Value::Str(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Nil => "nil".to_string(),
+        Value::Symbol(s) => s.clone(),
+        Value::Keyword(k) => k.clone(),
+        Value::List(l) => {
+            let items: Vec<String> = l.iter().map(|v| v.to_string()).collect();
+            format!("({})", items.join(" "))
+        }
+        Value::Function(f) => match f {
+            Function::Macro { .. } => "#<macro>".to_string(),
+            _ => "#<function>".to_string(),
+        },
+        Value::Vector(v) => {
+            let items: Vec<String> = v.iter().map(|val| val.to_string()).collect();
+            format!("[{}]", items.join(" "))
+        }
+        Value::Map(m) => {
+            let pairs: Vec<String> = m.iter()
+                .map(|(k, v)| format!("{} {}", k, v))
+                .collect();
+            format!("{{{}}}", pairs.join(" "))
+        }
+        Value::IOResource(resource) => format!("#<{}>", resource.kind()),
+        Value::Regex(re) => re.as_str().to_string(),
+        Value::Inst(ms) => crate::edn::format_inst(*ms),
+        Value::Uuid(id) => crate::edn::format_uuid(*id),
+        Value::Bytes(_) | Value::LazySeq(_) | Value::Transducer(_) | Value::Reduced(_) | Value::Generator(_) => value.to_string(),
+        Value::Uninitialized => "#<uninitialized>".to_string(),
+    }
+}
+
+// Argument helpers for the str namespace
+fn str_arg<'a>(args: &'a [Value], i: usize, fn_name: &str) -> Result<&'a str, String> {
+    match args.get(i) {
+        Some(Value::Str(s)) => Ok(s),
+        Some(other) => Err(format!("{} requires a string, got {}", fn_name, other)),
+        None => Err(format!("{} is missing argument {}", fn_name, i + 1)),
+    }
+}
+
+fn index_arg(value: &Value, fn_name: &str) -> Result<usize, String> {
+    match value {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        other => Err(format!("{} requires a non-negative integer index, got {}", fn_name, other)),
+    }
+}
+
+pub(crate) fn check_arity(args: &[Value], min: usize, max: usize, fn_name: &str) -> Result<(), String> {
+    if args.len() < min || args.len() > max {
+        if min == max {
+            return Err(format!("{} requires exactly {} argument(s)", fn_name, min));
+        }
+        return Err(format!("{} requires {} to {} arguments", fn_name, min, max));
+    }
+    Ok(())
+}
+
+// Positions in the str namespace count grapheme clusters (user-perceived
+// characters), so "é" written as e + combining accent is one character.
+fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+fn grapheme_index_of_byte(s: &str, byte_offset: usize) -> usize {
+    s[..byte_offset].graphemes(true).count()
+}
+
+fn byte_offset_of_grapheme(s: &str, index: usize) -> Option<usize> {
+    if index == 0 {
+        return Some(0);
+    }
+    s.grapheme_indices(true)
+        .map(|(offset, g)| offset + g.len())
+        .nth(index - 1)
+}
+
+// A regex match as re-find returns it: the matched text, or a vector of the
+// match followed by each group when the pattern has groups.
+fn match_value(caps: &regex::Captures) -> Value {
+    if caps.len() == 1 {
+        return Value::Str(caps[0].to_string());
+    }
+    Value::Vector(
+        caps.iter()
+            .map(|group| group.map_or(Value::Nil, |m| Value::Str(m.as_str().to_string())))
+            .collect(),
+    )
+}
+
+fn regex_arg<'a>(value: &'a Value, fn_name: &str) -> Result<&'a regex::Regex, String> {
+    match value {
+        Value::Regex(re) => Ok(re),
+        other => Err(format!("{} requires a regex, got {}", fn_name, other)),
+    }
+}
+
+// str/replace and str/replace-first. A regex pattern's replacement may refer
+// to groups as $1 or ${name}, or be a function called with each match.
+fn replace_str(args: &[Value], fn_name: &str, limit: usize) -> Result<Value, String> {
+    check_arity(args, 3, 3, fn_name)?;
+    let s = str_arg(args, 0, fn_name)?;
+    let re = match &args[1] {
+        Value::Regex(re) => re.clone(),
+        Value::Str(pattern) if pattern.is_empty() => return Err(format!("{} pattern must not be empty", fn_name)),
+        Value::Str(pattern) => {
+            let replacement = str_arg(args, 2, fn_name)?;
+            return Ok(Value::Str(s.replacen(pattern.as_str(), replacement, limit)));
+        }
+        other => return Err(format!("{} pattern must be a string or regex, got {}", fn_name, other)),
+    };
+    let mut result = String::new();
+    let mut last = 0;
+    for caps in re.captures_iter(s).take(limit) {
+        let whole = caps.get(0).expect("group 0 is always present");
+        result.push_str(&s[last..whole.start()]);
+        match &args[2] {
+            Value::Str(replacement) => caps.expand(replacement, &mut result),
+            f @ Value::Function(_) => result.push_str(&str_of(&call_function(f, &[match_value(&caps)])?)),
+            other => return Err(format!("{} replacement must be a string or function, got {}", fn_name, other)),
+        }
+        last = whole.end();
+    }
+    result.push_str(&s[last..]);
+    Ok(Value::Str(result))
+}
+
+fn pad_str(args: &[Value], fn_name: &str, left: bool) -> Result<Value, String> {
+    check_arity(args, 2, 3, fn_name)?;
+    let s = str_arg(args, 0, fn_name)?;
+    let width = index_arg(&args[1], fn_name)?;
+    let pad = if args.len() == 3 { str_arg(args, 2, fn_name)? } else { " " };
+    if pad.graphemes(true).count() != 1 {
+        return Err(format!("{} pad must be a single character", fn_name));
+    }
+    let missing = width.saturating_sub(s.graphemes(true).count());
+    let padding = pad.repeat(missing);
+    Ok(Value::Str(if left { padding + s } else { s.to_string() + &padding }))
+}
+
 pub fn create_default_env() -> Env {
     let mut env = Env::new();
 
@@ -764,13 +1450,8 @@ pub fn create_default_env() -> Env {
     env.set(
         "print".to_string(),
         Value::Function(Function::Native(|args| {
-            for (i, arg) in args.iter().enumerate() {
-                if i > 0 {
-                    print!(" ");
-                }
-                print!("{}", arg);
-            }
-            println!();
+            let printed: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            write_out(&format!("{}\n", printed.join(" ")));
             Ok(Value::Nil)
         })),
     );
@@ -815,6 +1496,8 @@ pub fn create_default_env() -> Env {
                 let mut new_list = vec![args[0].clone()];
                 new_list.extend(list.iter().cloned());
                 Ok(Value::List(new_list))
+            } else if let Value::LazySeq(_) = &args[1] {
+                Ok(Value::LazySeq(LazySeq::cons(args[0].clone(), args[1].clone())))
             } else if args[1] == Value::Nil {
                 Ok(Value::List(vec![args[0].clone()]))
             } else {
@@ -830,13 +1513,17 @@ pub fn create_default_env() -> Env {
                 return Err("first requires exactly 1 argument".to_string());
             }
             match &args[0] {
-                Value::List(list) => {
+                Value::List(list) | Value::Vector(list) => {
                     if list.is_empty() {
                         Ok(Value::Nil)
                     } else {
                         Ok(list[0].clone())
                     }
                 }
+                Value::LazySeq(seq) => match seq.step()? {
+                    Step::Cons(first, _) => Ok(first),
+                    Step::Empty => Ok(Value::Nil),
+                },
                 Value::Nil => Ok(Value::Nil),
                 _ => Err("first requires a list".to_string()),
             }
@@ -850,15 +1537,23 @@ pub fn create_default_env() -> Env {
                 return Err("rest requires exactly 1 argument".to_string());
             }
             match &args[0] {
-                Value::List(list) => {
-                    if list.is_empty() {
-                        Ok(Value::Nil)
-                    } else if list.len() == 1 {
-                        Ok(Value::Nil)  // rest of single-element list is nil
+                Value::List(list) | Value::Vector(list) => {
+                    if list.len() <= 1 {
+                        Ok(Value::Nil)  // rest of empty or single-element list is nil
                     } else {
                         Ok(Value::List(list[1..].to_vec()))
                     }
                 }
+                Value::LazySeq(seq) => match seq.step()? {
+                    Step::Cons(_, rest) => {
+                        if lazy::seq_iter(&rest, "rest")?.next().transpose()?.is_none() {
+                            Ok(Value::Nil)
+                        } else {
+                            Ok(rest)
+                        }
+                    }
+                    Step::Empty => Ok(Value::Nil),
+                },
                 Value::Nil => Ok(Value::Nil),
                 _ => Err("rest requires a list".to_string()),
             }
@@ -928,6 +1623,7 @@ pub fn create_default_env() -> Env {
                 Value::List(list) => Ok(Value::Bool(list.is_empty())),
                 Value::Vector(vec) => Ok(Value::Bool(vec.is_empty())),
                 Value::Str(s) => Ok(Value::Bool(s.is_empty())),
+                Value::LazySeq(seq) => Ok(Value::Bool(matches!(seq.step()?, Step::Empty))),
                 Value::Nil => Ok(Value::Bool(true)),
                 _ => Ok(Value::Bool(false))
             }
@@ -1032,146 +1728,763 @@ pub fn create_default_env() -> Env {
         Value::Function(Function::Native(|args| {
             let mut result = String::new();
             for arg in args {
-                match arg {
-                    Value::Str(s) => result.push_str(s),
-                    Value::Number(n) => result.push_str(&n.to_string()),
-                    Value::Bool(b) => result.push_str(&b.to_string()),
-                    Value::Nil => result.push_str("nil"),
-                    Value::Symbol(s) => result.push_str(s),
-                    Value::Keyword(k) => result.push_str(k),
-                    Value::List(l) => {
-                        let items: Vec<String> = l.iter().map(|v| v.to_string()).collect();
-                        result.push_str(&format!("({})", items.join(" ")));
-                    }
-                    Value::Function(f) => match f {
-                        Function::Macro { .. } => result.push_str("#<macro>"),
-                        _ => result.push_str("#<function>"),
-                    },
-                    Value::Vector(v) => {
-                        let items: Vec<String> = v.iter().map(|val| val.to_string()).collect();
-                        result.push_str(&format!("[{}]", items.join(" ")));
-                    }
-                    Value::Map(m) => {
-                        let pairs: Vec<String> = m.iter()
-                            .map(|(k, v)| format!("{} {}", k, v))
-                            .collect();
-                        result.push_str(&format!("{{{}}}", pairs.join(" ")));
-                    }
-                    Value::IOResource(resource) => match resource {
-                        IOResource::Reader(_) => result.push_str("#<reader>"),
-                        IOResource::Writer(_) => result.push_str("#<writer>"),
-                        IOResource::InputStream(_) => result.push_str("#<input-stream>"),
-                        IOResource::OutputStream(_) => result.push_str("#<output-stream>"),
-                    },
-                    Value::Uninitialized => result.push_str("#<uninitialized>"),
-                }
+                result.push_str(&str_of(arg));
             }
             Ok(Value::Str(result))
         })),
     );
 
-    // Time functions
+    // String namespace: (str/split s ","), (str/join ", " xs), ...
     env.set(
-        "now-ms".to_string(),
+        "str/split".to_string(),
         Value::Function(Function::Native(|args| {
-            if !args.is_empty() {
-                return Err("now-ms takes no arguments".to_string());
-            }
-            use std::time::{SystemTime, UNIX_EPOCH};
-            match SystemTime::now().duration_since(UNIX_EPOCH) {
-                Ok(duration) => Ok(Value::Number(duration.as_millis() as f64)),
-                Err(_) => Err("Failed to get system time".to_string()),
-            }
+            check_arity(args, 2, 3, "str/split")?;
+            let s = str_arg(args, 0, "str/split")?;
+            let limit = match args.get(2) {
+                Some(n) => index_arg(n, "str/split")?.max(1),
+                None => usize::MAX,
+            };
+            if let Value::Regex(re) = &args[1] {
+                return Ok(Value::Vector(re.splitn(s, limit).map(|part| Value::Str(part.to_string())).collect()));
+            }
+            let sep = str_arg(args, 1, "str/split")?;
+            let parts: Vec<Value> = if sep.is_empty() {
+                let chars = graphemes(s);
+                let split_at = chars.len().min(limit - 1);
+                let mut parts: Vec<Value> = chars[..split_at].iter().map(|g| Value::Str(g.to_string())).collect();
+                if split_at < chars.len() {
+                    parts.push(Value::Str(chars[split_at..].concat()));
+                }
+                parts
+            } else {
+                s.splitn(limit, sep).map(|part| Value::Str(part.to_string())).collect()
+            };
+            Ok(Value::Vector(parts))
         })),
     );
 
     env.set(
-        "sleep-ms".to_string(),
+        "str/split-lines".to_string(),
         Value::Function(Function::Native(|args| {
-            if args.len() != 1 {
-                return Err("sleep-ms requires exactly 1 argument".to_string());
-            }
-            match &args[0] {
-                Value::Number(ms) => {
-                    if *ms >= 0.0 {
-                        std::thread::sleep(std::time::Duration::from_millis(*ms as u64));
-                        Ok(Value::Nil)
-                    } else {
-                        Err("sleep-ms requires a non-negative number".to_string())
-                    }
-                }
-                _ => Err("sleep-ms requires a number".to_string()),
-            }
+            check_arity(args, 1, 1, "str/split-lines")?;
+            let s = str_arg(args, 0, "str/split-lines")?;
+            Ok(Value::Vector(s.lines().map(|line| Value::Str(line.to_string())).collect()))
         })),
     );
 
-    // Test result counters - global state for tracking
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    
-    static PASS_COUNT: AtomicUsize = AtomicUsize::new(0);
-    static FAIL_COUNT: AtomicUsize = AtomicUsize::new(0);
-    
-    // Enhanced assert-eq that tracks results
     env.set(
-        "test-assert-eq".to_string(),
+        "str/join".to_string(),
         Value::Function(Function::Native(|args| {
-            if args.len() != 2 {
-                return Err("test-assert-eq requires exactly 2 arguments".to_string());
+            let (sep, coll) = match args {
+                [coll] => ("", coll),
+                [Value::Str(sep), coll] => (sep.as_str(), coll),
+                [other, _] => return Err(format!("str/join separator must be a string, got {}", other)),
+                _ => return Err("str/join requires 1 or 2 arguments".to_string()),
+            };
+            let mut parts = Vec::new();
+            for item in lazy::seq_iter(coll, "str/join")? {
+                parts.push(str_of(&item?));
             }
-            
-            if args[0] == args[1] {
-                PASS_COUNT.fetch_add(1, Ordering::SeqCst);
-                println!("  PASS: {} == {}", args[0], args[1]);
-                Ok(Value::Bool(true))
-            } else {
-                FAIL_COUNT.fetch_add(1, Ordering::SeqCst);
-                println!("  FAIL: expected {} but got {}", args[0], args[1]);
-                Ok(Value::Bool(false))
+            Ok(Value::Str(parts.join(sep)))
+        })),
+    );
+
+    env.set(
+        "str/replace".to_string(),
+        Value::Function(Function::Native(|args| {
+            replace_str(args, "str/replace", usize::MAX)
+        })),
+    );
+
+    env.set(
+        "str/replace-first".to_string(),
+        Value::Function(Function::Native(|args| {
+            replace_str(args, "str/replace-first", 1)
+        })),
+    );
+
+    env.set(
+        "str/trim".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/trim")?;
+            Ok(Value::Str(str_arg(args, 0, "str/trim")?.trim().to_string()))
+        })),
+    );
+
+    env.set(
+        "str/triml".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/triml")?;
+            Ok(Value::Str(str_arg(args, 0, "str/triml")?.trim_start().to_string()))
+        })),
+    );
+
+    env.set(
+        "str/trimr".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/trimr")?;
+            Ok(Value::Str(str_arg(args, 0, "str/trimr")?.trim_end().to_string()))
+        })),
+    );
+
+    env.set(
+        "str/upper-case".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/upper-case")?;
+            Ok(Value::Str(str_arg(args, 0, "str/upper-case")?.to_uppercase()))
+        })),
+    );
+
+    env.set(
+        "str/lower-case".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/lower-case")?;
+            Ok(Value::Str(str_arg(args, 0, "str/lower-case")?.to_lowercase()))
+        })),
+    );
+
+    env.set(
+        "str/capitalize".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/capitalize")?;
+            let s = str_arg(args, 0, "str/capitalize")?;
+            // Upper-case the first character, lower-case the rest
+            let mut chars = s.graphemes(true);
+            let result = match chars.next() {
+                Some(first) => first.to_uppercase() + &chars.as_str().to_lowercase(),
+                None => String::new(),
+            };
+            Ok(Value::Str(result))
+        })),
+    );
+
+    env.set(
+        "str/subs".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 3, "str/subs")?;
+            let s = str_arg(args, 0, "str/subs")?;
+            let len = s.graphemes(true).count();
+            let start = index_arg(&args[1], "str/subs")?;
+            let end = match args.get(2) {
+                Some(end) => index_arg(end, "str/subs")?,
+                None => len,
+            };
+            if start > end || end > len {
+                return Err(format!("str/subs range {}..{} out of bounds for string of length {}", start, end, len));
             }
+            let from = byte_offset_of_grapheme(s, start).unwrap_or(s.len());
+            let to = byte_offset_of_grapheme(s, end).unwrap_or(s.len());
+            Ok(Value::Str(s[from..to].to_string()))
         })),
     );
 
-    // Functions to access test counters
     env.set(
-        "get-pass-count".to_string(),
-        Value::Function(Function::Native(|_args| {
-            Ok(Value::Number(PASS_COUNT.load(Ordering::SeqCst) as f64))
+        "str/index-of".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 3, "str/index-of")?;
+            let s = str_arg(args, 0, "str/index-of")?;
+            let needle = str_arg(args, 1, "str/index-of")?;
+            let from = match args.get(2) {
+                Some(from) => match byte_offset_of_grapheme(s, index_arg(from, "str/index-of")?) {
+                    Some(offset) => offset,
+                    None => return Ok(Value::Nil),
+                },
+                None => 0,
+            };
+            Ok(s[from..]
+                .find(needle)
+                .map_or(Value::Nil, |offset| Value::Number(grapheme_index_of_byte(s, from + offset) as f64)))
         })),
     );
 
     env.set(
-        "get-fail-count".to_string(),
-        Value::Function(Function::Native(|_args| {
-            Ok(Value::Number(FAIL_COUNT.load(Ordering::SeqCst) as f64))
+        "str/last-index-of".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 3, "str/last-index-of")?;
+            let s = str_arg(args, 0, "str/last-index-of")?;
+            let needle = str_arg(args, 1, "str/last-index-of")?;
+            // With a from index, only matches starting at or before it count
+            let limit = match args.get(2) {
+                Some(from) => byte_offset_of_grapheme(s, index_arg(from, "str/last-index-of")?).unwrap_or(s.len()),
+                None => s.len(),
+            };
+            // A match ends on a char boundary, so flooring the end to one
+            // keeps every match that starts by the limit, overlapping or not
+            let mut end = (limit + needle.len()).min(s.len());
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            Ok(s[..end]
+                .rfind(needle)
+                .map_or(Value::Nil, |offset| Value::Number(grapheme_index_of_byte(s, offset) as f64)))
         })),
     );
 
     env.set(
-        "reset-test-counts".to_string(),
-        Value::Function(Function::Native(|_args| {
-            PASS_COUNT.store(0, Ordering::SeqCst);
-            FAIL_COUNT.store(0, Ordering::SeqCst);
+        "str/starts-with?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "str/starts-with?")?;
+            let s = str_arg(args, 0, "str/starts-with?")?;
+            Ok(Value::Bool(s.starts_with(str_arg(args, 1, "str/starts-with?")?)))
+        })),
+    );
+
+    env.set(
+        "str/ends-with?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "str/ends-with?")?;
+            let s = str_arg(args, 0, "str/ends-with?")?;
+            Ok(Value::Bool(s.ends_with(str_arg(args, 1, "str/ends-with?")?)))
+        })),
+    );
+
+    env.set(
+        "str/includes?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "str/includes?")?;
+            let s = str_arg(args, 0, "str/includes?")?;
+            Ok(Value::Bool(s.contains(str_arg(args, 1, "str/includes?")?)))
+        })),
+    );
+
+    env.set(
+        "str/pad-left".to_string(),
+        Value::Function(Function::Native(|args| pad_str(args, "str/pad-left", true))),
+    );
+
+    env.set(
+        "str/pad-right".to_string(),
+        Value::Function(Function::Native(|args| pad_str(args, "str/pad-right", false))),
+    );
+
+    env.set(
+        "str/reverse".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "str/reverse")?;
+            let s = str_arg(args, 0, "str/reverse")?;
+            Ok(Value::Str(s.graphemes(true).rev().collect()))
+        })),
+    );
+
+    // Regular expressions: #"\d+" literals, or (re-pattern "\\d+")
+    env.set(
+        "re-pattern".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "re-pattern")?;
+            match &args[0] {
+                Value::Regex(_) => Ok(args[0].clone()),
+                Value::Str(pattern) => regex::Regex::new(pattern)
+                    .map(Value::Regex)
+                    .map_err(|e| format!("Invalid regex #\"{}\": {}", pattern, e)),
+                other => Err(format!("re-pattern requires a string, got {}", other)),
+            }
+        })),
+    );
+
+    env.set(
+        "re-find".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "re-find")?;
+            let re = regex_arg(&args[0], "re-find")?;
+            let s = str_arg(args, 1, "re-find")?;
+            Ok(re.captures(s).map_or(Value::Nil, |caps| match_value(&caps)))
+        })),
+    );
+
+    env.set(
+        "re-matches".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "re-matches")?;
+            let re = regex_arg(&args[0], "re-matches")?;
+            let s = str_arg(args, 1, "re-matches")?;
+            // Anchor the whole pattern so alternations can't settle for a prefix
+            let anchored = regex::Regex::new(&format!("^(?:{})$", re.as_str()))
+                .map_err(|e| format!("Invalid regex #\"{}\": {}", re.as_str(), e))?;
+            Ok(anchored.captures(s).map_or(Value::Nil, |caps| match_value(&caps)))
+        })),
+    );
+
+    env.set(
+        "re-seq".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "re-seq")?;
+            let re = regex_arg(&args[0], "re-seq")?.clone();
+            let s = str_arg(args, 1, "re-seq")?.to_string();
+            // Matches are found one at a time as the sequence is realized
+            let mut pos = Some(0);
+            let matches = std::iter::from_fn(move || {
+                let start = pos?;
+                let caps = match re.captures_at(&s, start) {
+                    Some(caps) => caps,
+                    None => {
+                        pos = None;
+                        return None;
+                    }
+                };
+                let whole = caps.get(0).expect("group 0 is always present");
+                pos = if whole.end() > whole.start() {
+                    Some(whole.end())
+                } else {
+                    // Step past an empty match so the search makes progress
+                    s[whole.end()..].chars().next().map(|c| whole.end() + c.len_utf8())
+                };
+                Some(Ok(match_value(&caps)))
+            });
+            Ok(Value::LazySeq(LazySeq::from_items(matches)))
+        })),
+    );
+
+    env.set(
+        "re-groups".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "re-groups")?;
+            let re = regex_arg(&args[0], "re-groups")?;
+            let s = str_arg(args, 1, "re-groups")?;
+            // Named groups of the first match, keyed by group name
+            let caps = match re.captures(s) {
+                Some(caps) => caps,
+                None => return Ok(Value::Nil),
+            };
+            let mut groups = HashMap::new();
+            for name in re.capture_names().flatten() {
+                let value = caps.name(name).map_or(Value::Nil, |m| Value::Str(m.as_str().to_string()));
+                groups.insert(name.to_string(), value);
+            }
+            Ok(Value::Map(groups))
+        })),
+    );
+
+    env.set(
+        "regex?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "regex?")?;
+            Ok(Value::Bool(matches!(args[0], Value::Regex(_))))
+        })),
+    );
+
+    // JSON, via serde_json
+    env.set(
+        "json/parse".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "json/parse")?;
+            let opts = json::parse_options(args.get(1), "json/parse")?;
+            let parsed: serde_json::Value = match &args[0] {
+                Value::Str(s) => serde_json::from_str(s).map_err(|e| format!("json/parse: {}", e))?,
+                Value::IOResource(IOResource::Reader(reader)) => with_open(reader, "reader", |r| {
+                    serde_json::from_reader(r).map_err(|e| format!("json/parse: {}", e))
+                })?,
+                other => return Err(format!("json/parse requires a string or reader, got {}", other)),
+            };
+            json::from_json(parsed, &opts, "json/parse")
+        })),
+    );
+
+    env.set(
+        "json/write-str".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "json/write-str")?;
+            let opts = json::write_options(args.get(1), "json/write-str")?;
+            json::write_string(&args[0], &opts, "json/write-str").map(Value::Str)
+        })),
+    );
+
+    env.set(
+        "json/write".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 3, "json/write")?;
+            let opts = json::write_options(args.get(2), "json/write")?;
+            let writer = match &args[0] {
+                Value::IOResource(IOResource::Writer(writer)) => writer,
+                other => return Err(format!("json/write requires a writer, got {}", other)),
+            };
+            // Encode everything first so a bad value leaves the writer untouched
+            let text = json::write_string(&args[1], &opts, "json/write")?;
+            with_open(writer, "writer", |w| {
+                w.write_all(text.as_bytes())
+                    .and_then(|_| w.flush())
+                    .map_err(|e| format!("Failed to write to writer: {}", e))
+            })?;
+            Ok(Value::Nil)
+        })),
+    );
+
+    // CSV
+    env.set(
+        "csv/read".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "csv/read")?;
+            let opts = csv::options(args.get(1), "csv/read")?;
+            let text = match &args[0] {
+                Value::Str(path) => std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read file '{}': {}", path, e))?,
+                Value::IOResource(IOResource::Reader(reader)) => with_open(reader, "reader", |r| {
+                    let mut content = String::new();
+                    r.read_to_string(&mut content)
+                        .map_err(|e| format!("Failed to read from reader: {}", e))?;
+                    Ok(content)
+                })?,
+                other => return Err(format!("csv/read requires a file path or reader, got {}", other)),
+            };
+            let rows = csv::parse(&text, &opts, "csv/read")?;
+            csv::rows_value(rows, &opts, "csv/read")
+        })),
+    );
+
+    env.set(
+        "csv/read-str".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "csv/read-str")?;
+            let text = str_arg(args, 0, "csv/read-str")?;
+            let opts = csv::options(args.get(1), "csv/read-str")?;
+            let rows = csv::parse(text, &opts, "csv/read-str")?;
+            csv::rows_value(rows, &opts, "csv/read-str")
+        })),
+    );
+
+    env.set(
+        "csv/write".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 3, "csv/write")?;
+            let opts = csv::options(args.get(2), "csv/write")?;
+            let text = csv::write(&args[1], &opts, "csv/write")?;
+            match &args[0] {
+                Value::Str(path) => std::fs::write(path, text)
+                    .map_err(|e| format!("Failed to write file '{}': {}", path, e))?,
+                Value::IOResource(IOResource::Writer(writer)) => with_open(writer, "writer", |w| {
+                    w.write_all(text.as_bytes())
+                        .and_then(|_| w.flush())
+                        .map_err(|e| format!("Failed to write to writer: {}", e))
+                })?,
+                other => return Err(format!("csv/write requires a file path or writer, got {}", other)),
+            }
             Ok(Value::Nil)
         })),
     );
 
-    // Test runner function that actually runs tests
     env.set(
-        "run-tests".to_string(),
+        "csv/write-str".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "csv/write-str")?;
+            let opts = csv::options(args.get(1), "csv/write-str")?;
+            csv::write(&args[0], &opts, "csv/write-str").map(Value::Str)
+        })),
+    );
+
+    // Subprocesses
+    env.set(
+        "sh".to_string(),
+        Value::Function(Function::Native(|args| {
+            let (argv, opts) = process::command_args(args, "sh")?;
+            let opts = process::options(opts, "sh")?;
+            process::sh(&argv, opts, "sh")
+        })),
+    );
+
+    env.set(
+        "proc/start".to_string(),
+        Value::Function(Function::Native(|args| {
+            let (argv, opts) = process::command_args(args, "proc/start")?;
+            let opts = process::options(opts, "proc/start")?;
+            process::start(&argv, opts, "proc/start")
+        })),
+    );
+
+    env.set(
+        "proc/wait".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "proc/wait")?;
+            let timeout = match args.get(1) {
+                None | Some(Value::Nil) => None,
+                Some(Value::Number(ms)) if *ms >= 0.0 => Some(std::time::Duration::from_millis(*ms as u64)),
+                Some(other) => return Err(format!("proc/wait timeout must be a number of milliseconds, got {}", other)),
+            };
+            process::wait(&args[0], timeout, "proc/wait")
+        })),
+    );
+
+    env.set(
+        "proc/kill".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "proc/kill")?;
+            process::kill(&args[0], "proc/kill")
+        })),
+    );
+
+    env.set(
+        "proc/alive?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "proc/alive?")?;
+            process::is_alive(&args[0], "proc/alive?")
+        })),
+    );
+
+    // Process environment
+    env.set("*command-line-args*".to_string(), Value::Nil);
+
+    env.set(
+        "getenv".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "getenv")?;
+            let name = str_arg(args, 0, "getenv")?;
+            Ok(std::env::var(name).map(Value::Str).unwrap_or(Value::Nil))
+        })),
+    );
+
+    env.set(
+        "setenv".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "setenv")?;
+            let name = str_arg(args, 0, "setenv")?;
+            if name.is_empty() || name.contains('=') || name.contains('\0') {
+                return Err(format!("setenv: invalid variable name \"{}\"", name));
+            }
+            match &args[1] {
+                Value::Nil => std::env::remove_var(name),
+                value => {
+                    let value = str_of(value);
+                    if value.contains('\0') {
+                        return Err("setenv: value can't contain a NUL character".to_string());
+                    }
+                    std::env::set_var(name, value)
+                }
+            }
+            Ok(Value::Nil)
+        })),
+    );
+
+    env.set(
+        "env-map".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 0, "env-map")?;
+            let vars = std::env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, Value::Str(v.into_string().ok()?))))
+                .collect();
+            Ok(Value::Map(vars))
+        })),
+    );
+
+    env.set(
+        "exit".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 1, "exit")?;
+            let code = match args.first() {
+                None => 0,
+                Some(Value::Number(n)) if n.fract() == 0.0 && (i32::MIN as f64..=i32::MAX as f64).contains(n) => *n as i32,
+                Some(other) => return Err(format!("exit requires an integer status, got {}", other)),
+            };
+            use std::io::Write;
+            profile::report_script();
+            let _ = std::io::stdout().flush();
+            let _ = std::io::stderr().flush();
+            std::process::exit(code)
+        })),
+    );
+
+    // Readable printing and EDN
+    env.set(
+        "pr-str".to_string(),
+        Value::Function(Function::Native(|args| {
+            let printed: Vec<String> = args.iter().map(edn::pr_str).collect();
+            Ok(Value::Str(printed.join(" ")))
+        })),
+    );
+
+    env.set(
+        "pr".to_string(),
+        Value::Function(Function::Native(|args| {
+            let printed: Vec<String> = args.iter().map(edn::pr_str).collect();
+            write_out(&printed.join(" "));
+            Ok(Value::Nil)
+        })),
+    );
+
+    env.set(
+        "prn".to_string(),
+        Value::Function(Function::Native(|args| {
+            let printed: Vec<String> = args.iter().map(edn::pr_str).collect();
+            write_out(&format!("{}\n", printed.join(" ")));
+            Ok(Value::Nil)
+        })),
+    );
+
+    env.set(
+        "edn/read-string".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "edn/read-string")?;
+            let opts = edn::read_options(args.get(1), "edn/read-string")?;
+            match &args[0] {
+                Value::Str(s) => reader::read_edn(s, &opts).map_err(|e| format!("edn/read-string: {}", e)),
+                Value::Nil => Ok(Value::Nil),
+                other => Err(format!("edn/read-string requires a string, got {}", other)),
+            }
+        })),
+    );
+
+    env.set(
+        "register-tag-reader!".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "register-tag-reader!")?;
+            let tag = match &args[0] {
+                Value::Symbol(tag) | Value::Keyword(tag) | Value::Str(tag) => tag.clone(),
+                other => return Err(format!("register-tag-reader! requires a symbol tag, got {}", other)),
+            };
+            if !matches!(args[1], Value::Function(_)) {
+                return Err(format!("register-tag-reader! requires a function, got {}", args[1]));
+            }
+            edn::register_tag_reader(tag, args[1].clone());
+            Ok(Value::Nil)
+        })),
+    );
+
+    env.set(
+        "inst?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "inst?")?;
+            Ok(Value::Bool(matches!(args[0], Value::Inst(_))))
+        })),
+    );
+
+    env.set(
+        "inst-ms".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "inst-ms")?;
+            match &args[0] {
+                Value::Inst(ms) => Ok(Value::Number(*ms as f64)),
+                other => Err(format!("inst-ms requires an inst, got {}", other)),
+            }
+        })),
+    );
+
+    env.set(
+        "pst".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 1, "pst")?;
+            match args.first() {
+                Some(error) => print_stack_trace(error)?,
+                None => match LAST_ERROR.with(|last| last.borrow().clone()) {
+                    Some(error) => print_stack_trace(&error)?,
+                    None => write_out("No error recorded yet\n"),
+                },
+            }
+            Ok(Value::Nil)
+        })),
+    );
+
+    env.set(
+        "type".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "type")?;
+            Ok(Value::Keyword(args[0].type_name().to_string()))
+        })),
+    );
+
+    env.set(
+        "uuid?".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "uuid?")?;
+            Ok(Value::Bool(matches!(args[0], Value::Uuid(_))))
+        })),
+    );
+
+    // Time functions
+    env.set(
+        "now-ms".to_string(),
+        Value::Function(Function::Native(|args| {
+            if !args.is_empty() {
+                return Err("now-ms takes no arguments".to_string());
+            }
+            use std::time::{SystemTime, UNIX_EPOCH};
+            match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(duration) => Ok(Value::Number(duration.as_millis() as f64)),
+                Err(_) => Err("Failed to get system time".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "sleep-ms".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("sleep-ms requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::Number(ms) => {
+                    if *ms >= 0.0 {
+                        // Sleep in slices so an interrupt doesn't wait out the whole nap
+                        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(*ms as u64);
+                        loop {
+                            check_interrupt()?;
+                            let left = deadline.saturating_duration_since(std::time::Instant::now());
+                            if left.is_zero() {
+                                break;
+                            }
+                            std::thread::sleep(left.min(std::time::Duration::from_millis(20)));
+                        }
+                        Ok(Value::Nil)
+                    } else {
+                        Err("sleep-ms requires a non-negative number".to_string())
+                    }
+                }
+                _ => Err("sleep-ms requires a number".to_string()),
+            }
+        })),
+    );
+
+    // Assertion counters shared with deftest/is; see testing.rs
+    env.set(
+        "test-assert-eq".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 2 {
+                return Err("test-assert-eq requires exactly 2 arguments".to_string());
+            }
+
+            if args[0] == args[1] {
+                testing::record_pass();
+                write_out(&format!("  PASS: {} == {}\n", args[0], args[1]));
+                Ok(Value::Bool(true))
+            } else {
+                write_out(&format!("  FAIL: expected {} but got {}\n", args[0], args[1]));
+                let expression = Value::List(vec![Value::Symbol("=".to_string()), args[0].clone(), args[1].clone()]);
+                testing::record_fail(&expression, args[0].clone(), args[1].clone());
+                Ok(Value::Bool(false))
+            }
+        })),
+    );
+
+    env.set(
+        "get-pass-count".to_string(),
+        Value::Function(Function::Native(|_args| Ok(Value::Number(testing::counts().pass as f64)))),
+    );
+
+    env.set(
+        "get-fail-count".to_string(),
+        Value::Function(Function::Native(|_args| Ok(Value::Number(testing::counts().fail as f64)))),
+    );
+
+    env.set(
+        "get-error-count".to_string(),
+        Value::Function(Function::Native(|_args| Ok(Value::Number(testing::counts().error as f64)))),
+    );
+
+    env.set(
+        "reset-test-counts".to_string(),
         Value::Function(Function::Native(|_args| {
-            // Reset counters
-            PASS_COUNT.store(0, Ordering::SeqCst);
-            FAIL_COUNT.store(0, Ordering::SeqCst);
-            
-            println!("🧪 CORTADO COMPREHENSIVE TEST SUITE");
-            println!("=====================================");
-            
-            // We need to run this in the current environment context
-            // Return a special value that the caller can interpret
-            Ok(Value::Symbol("__RUN_COMPREHENSIVE_TESTS__".to_string()))
+            testing::reset_counts();
+            Ok(Value::Nil)
         })),
     );
 
+    // (run-tests) or (run-tests 'ns ...) runs deftests and returns a result map
+    env.set("run-tests".to_string(), Value::Function(Function::Native(testing::run_tests)));
+
+    // Generators, properties and quick-check; see check.rs
+    for (name, generator) in check::generators() {
+        env.set(name.to_string(), generator);
+    }
+    for (name, f) in check::natives() {
+        env.set(name.to_string(), Value::Function(Function::Native(f)));
+    }
+
     // File I/O functions (legacy - kept for compatibility)
     env.set(
         "read-file".to_string(),
@@ -1218,24 +2531,17 @@ pub fn create_default_env() -> Env {
             }
             
             let source = &args[0];
-            let _opts = if args.len() == 2 { Some(&args[1]) } else { None };
+            let opts = parse_io_options(args.get(1), "reader")?;
             
             match source {
                 Value::Str(filename) => {
                     match std::fs::File::open(filename) {
-                        Ok(file) => {
-                            let buf_reader = std::io::BufReader::new(file);
-                            let resource = IOResource::Reader(Arc::new(Mutex::new(Box::new(buf_reader))));
-                            Ok(Value::IOResource(resource))
-                        }
+                        Ok(file) => Ok(Value::IOResource(wrap_reader(file, &opts))),
                         Err(e) => Err(format!("Failed to open file '{}': {}", filename, e)),
                     }
                 }
                 Value::Keyword(k) if k == "stdin" => {
-                    let stdin = std::io::stdin();
-                    let buf_reader = std::io::BufReader::new(stdin);
-                    let resource = IOResource::Reader(Arc::new(Mutex::new(Box::new(buf_reader))));
-                    Ok(Value::IOResource(resource))
+                    Ok(Value::IOResource(wrap_reader(std::io::stdin(), &opts)))
                 }
                 _ => Err("reader requires a string filename or :stdin".to_string()),
             }
@@ -1250,30 +2556,15 @@ pub fn create_default_env() -> Env {
             }
             
             let dest = &args[0];
-            let _opts = if args.len() == 2 { Some(&args[1]) } else { None };
+            let opts = parse_io_options(args.get(1), "writer")?;
             
             match dest {
-                Value::Str(filename) => {
-                    match std::fs::File::create(filename) {
-                        Ok(file) => {
-                            let buf_writer = std::io::BufWriter::new(file);
-                            let resource = IOResource::Writer(Arc::new(Mutex::new(Box::new(buf_writer))));
-                            Ok(Value::IOResource(resource))
-                        }
-                        Err(e) => Err(format!("Failed to create file '{}': {}", filename, e)),
-                    }
-                }
+                Value::Str(filename) => open_file_writer(filename, &opts).map(Value::IOResource),
                 Value::Keyword(k) if k == "stdout" => {
-                    let stdout = std::io::stdout();
-                    let buf_writer = std::io::BufWriter::new(stdout);
-                    let resource = IOResource::Writer(Arc::new(Mutex::new(Box::new(buf_writer))));
-                    Ok(Value::IOResource(resource))
+                    Ok(Value::IOResource(wrap_writer(std::io::stdout(), &opts, false)))
                 }
                 Value::Keyword(k) if k == "stderr" => {
-                    let stderr = std::io::stderr();
-                    let buf_writer = std::io::BufWriter::new(stderr);
-                    let resource = IOResource::Writer(Arc::new(Mutex::new(Box::new(buf_writer))));
-                    Ok(Value::IOResource(resource))
+                    Ok(Value::IOResource(wrap_writer(std::io::stderr(), &opts, false)))
                 }
                 _ => Err("writer requires a string filename or :stdout/:stderr".to_string()),
             }
@@ -1290,10 +2581,7 @@ pub fn create_default_env() -> Env {
             match &args[0] {
                 Value::Str(filename) => {
                     match std::fs::File::open(filename) {
-                        Ok(file) => {
-                            let resource = IOResource::InputStream(Arc::new(Mutex::new(Box::new(file))));
-                            Ok(Value::IOResource(resource))
-                        }
+                        Ok(file) => Ok(Value::IOResource(IOResource::input_stream(file))),
                         Err(e) => Err(format!("Failed to open file '{}': {}", filename, e)),
                     }
                 }
@@ -1312,10 +2600,7 @@ pub fn create_default_env() -> Env {
             match &args[0] {
                 Value::Str(filename) => {
                     match std::fs::File::create(filename) {
-                        Ok(file) => {
-                            let resource = IOResource::OutputStream(Arc::new(Mutex::new(Box::new(file))));
-                            Ok(Value::IOResource(resource))
-                        }
+                        Ok(file) => Ok(Value::IOResource(IOResource::output_stream(file))),
                         Err(e) => Err(format!("Failed to create file '{}': {}", filename, e)),
                     }
                 }
@@ -1333,26 +2618,25 @@ pub fn create_default_env() -> Env {
             }
             
             let source = &args[0];
-            let _opts = if args.len() == 2 { Some(&args[1]) } else { None };
+            let opts = parse_io_options(args.get(1), "slurp")?;
             
             match source {
                 Value::Str(filename) => {
-                    match std::fs::read_to_string(filename) {
-                        Ok(content) => Ok(Value::Str(content)),
+                    match std::fs::read(filename) {
+                        Ok(bytes) => opts.encoding.decode(&bytes)
+                            .map(Value::Str)
+                            .map_err(|e| format!("Failed to decode file '{}': {}", filename, e)),
                         Err(e) => Err(format!("Failed to read file '{}': {}", filename, e)),
                     }
                 }
                 Value::IOResource(IOResource::Reader(reader)) => {
-                    match reader.lock() {
-                        Ok(mut r) => {
-                            let mut content = String::new();
-                            match r.read_to_string(&mut content) {
-                                Ok(_) => Ok(Value::Str(content)),
-                                Err(e) => Err(format!("Failed to read from reader: {}", e)),
-                            }
+                    with_open(reader, "reader", |r| {
+                        let mut content = String::new();
+                        match r.read_to_string(&mut content) {
+                            Ok(_) => Ok(Value::Str(content)),
+                            Err(e) => Err(format!("Failed to read from reader: {}", e)),
                         }
-                        Err(e) => Err(format!("Failed to lock reader: {}", e)),
-                    }
+                    })
                 }
                 _ => Err("slurp requires a string filename or reader".to_string()),
             }
@@ -1368,7 +2652,7 @@ pub fn create_default_env() -> Env {
             
             let dest = &args[0];
             let content = &args[1];
-            let _opts = if args.len() == 3 { Some(&args[2]) } else { None };
+            let opts = parse_io_options(args.get(2), "spit")?;
             
             let content_str = match content {
                 Value::Str(s) => s.clone(),
@@ -1377,26 +2661,30 @@ pub fn create_default_env() -> Env {
             
             match dest {
                 Value::Str(filename) => {
-                    match std::fs::write(filename, content_str) {
-                        Ok(_) => Ok(Value::Nil),
-                        Err(e) => Err(format!("Failed to write file '{}': {}", filename, e)),
-                    }
+                    let resource = open_file_writer(filename, &opts)?;
+                    let written = match &resource {
+                        IOResource::Writer(writer) => with_open(writer, "writer", |w| {
+                            w.write_all(content_str.as_bytes())
+                                .map_err(|e| format!("Failed to write file '{}': {}", filename, e))
+                        }),
+                        _ => unreachable!("open_file_writer always returns a writer"),
+                    };
+                    let closed = resource.close()
+                        .map_err(|e| format!("Failed to write file '{}': {}", filename, e));
+                    written.and(closed).map(|_| Value::Nil)
                 }
                 Value::IOResource(IOResource::Writer(writer)) => {
-                    match writer.lock() {
-                        Ok(mut w) => {
-                            match w.write_all(content_str.as_bytes()) {
-                                Ok(_) => {
-                                    match w.flush() {
-                                        Ok(_) => Ok(Value::Nil),
-                                        Err(e) => Err(format!("Failed to flush writer: {}", e)),
-                                    }
+                    with_open(writer, "writer", |w| {
+                        match w.write_all(content_str.as_bytes()) {
+                            Ok(_) => {
+                                match w.flush() {
+                                    Ok(_) => Ok(Value::Nil),
+                                    Err(e) => Err(format!("Failed to flush writer: {}", e)),
                                 }
-                                Err(e) => Err(format!("Failed to write to writer: {}", e)),
                             }
+                            Err(e) => Err(format!("Failed to write to writer: {}", e)),
                         }
-                        Err(e) => Err(format!("Failed to lock writer: {}", e)),
-                    }
+                    })
                 }
                 _ => Err("spit requires a string filename or writer".to_string()),
             }
@@ -1404,35 +2692,83 @@ pub fn create_default_env() -> Env {
     );
 
     env.set(
-        "copy".to_string(),
+        "copy".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() < 2 || args.len() > 3 {
+                return Err("copy requires 2-3 arguments".to_string());
+            }
+            
+            let input = &args[0];
+            let output = &args[1];
+            let _opts = if args.len() == 3 { Some(&args[2]) } else { None };
+            
+            match (input, output) {
+                (Value::IOResource(IOResource::Reader(reader)), Value::IOResource(IOResource::Writer(writer))) => {
+                    with_open(reader, "reader", |r| {
+                        with_open(writer, "writer", |w| {
+                            match std::io::copy(r, w) {
+                                Ok(bytes_copied) => Ok(Value::Number(bytes_copied as f64)),
+                                Err(e) => Err(format!("Failed to copy data: {}", e)),
+                            }
+                        })
+                    })
+                }
+                (Value::IOResource(IOResource::InputStream(input)), Value::IOResource(IOResource::OutputStream(output))) => {
+                    with_open(input, "input-stream", |r| {
+                        with_open(output, "output-stream", |w| {
+                            match std::io::copy(r, w) {
+                                Ok(bytes_copied) => Ok(Value::Number(bytes_copied as f64)),
+                                Err(e) => Err(format!("Failed to copy data: {}", e)),
+                            }
+                        })
+                    })
+                }
+                (Value::Str(input_file), Value::Str(output_file)) => {
+                    match std::fs::copy(input_file, output_file) {
+                        Ok(bytes_copied) => Ok(Value::Number(bytes_copied as f64)),
+                        Err(e) => Err(format!("Failed to copy file '{}' to '{}': {}", input_file, output_file, e)),
+                    }
+                }
+                _ => Err("copy requires two strings (filenames), a reader and writer, or two streams".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "flush".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("flush requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::IOResource(resource) => resource.flush().map(|_| Value::Nil),
+                _ => Err("flush requires a reader, writer or stream".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "close".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("close requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::IOResource(resource) => resource.close().map(|_| Value::Nil),
+                _ => Err("close requires a reader, writer or stream".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "closed?".to_string(),
         Value::Function(Function::Native(|args| {
-            if args.len() < 2 || args.len() > 3 {
-                return Err("copy requires 2-3 arguments".to_string());
+            if args.len() != 1 {
+                return Err("closed? requires exactly 1 argument".to_string());
             }
-            
-            let input = &args[0];
-            let output = &args[1];
-            let _opts = if args.len() == 3 { Some(&args[2]) } else { None };
-            
-            match (input, output) {
-                (Value::IOResource(IOResource::Reader(reader)), Value::IOResource(IOResource::Writer(writer))) => {
-                    match (reader.lock(), writer.lock()) {
-                        (Ok(mut r), Ok(mut w)) => {
-                            match std::io::copy(&mut *r, &mut *w) {
-                                Ok(bytes_copied) => Ok(Value::Number(bytes_copied as f64)),
-                                Err(e) => Err(format!("Failed to copy data: {}", e)),
-                            }
-                        }
-                        _ => Err("Failed to lock reader or writer".to_string()),
-                    }
-                }
-                (Value::Str(input_file), Value::Str(output_file)) => {
-                    match std::fs::copy(input_file, output_file) {
-                        Ok(bytes_copied) => Ok(Value::Number(bytes_copied as f64)),
-                        Err(e) => Err(format!("Failed to copy file '{}' to '{}': {}", input_file, output_file, e)),
-                    }
-                }
-                _ => Err("copy requires two strings (filenames) or a reader and writer".to_string()),
+            match &args[0] {
+                Value::IOResource(resource) => Ok(Value::Bool(resource.is_closed())),
+                _ => Err("closed? requires a reader, writer or stream".to_string()),
             }
         })),
     );
@@ -1567,98 +2903,461 @@ pub fn create_default_env() -> Env {
                         Err(e) => Err(format!("Failed to read directory '{}': {}", path, e)),
                     }
                 }
-                _ => Err("list-dir requires a string path".to_string()),
+                _ => Err("list-dir requires a string path".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "create-dir".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "create-dir")?;
+            // Missing parent directories are created unless {:parents false}
+            let parents = match args.get(1) {
+                None | Some(Value::Nil) => true,
+                Some(Value::Map(opts)) => match (opts.get("parents"), opts.len()) {
+                    (Some(Value::Bool(b)), 1) => *b,
+                    (None, 0) => true,
+                    _ => return Err("create-dir only supports the option {:parents bool}".to_string()),
+                },
+                Some(_) => return Err("create-dir options must be a map".to_string()),
+            };
+            match &args[0] {
+                Value::Str(path) => {
+                    let created = if parents { std::fs::create_dir_all(path) } else { std::fs::create_dir(path) };
+                    match created {
+                        Ok(_) => Ok(Value::Nil),
+                        Err(e) => Err(format!("Failed to create directory '{}': {}", path, e)),
+                    }
+                }
+                _ => Err("create-dir requires a string path".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "delete-dir".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("delete-dir requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::Str(path) => {
+                    match std::fs::remove_dir_all(path) {
+                        Ok(_) => Ok(Value::Nil),
+                        Err(e) => Err(format!("Failed to delete directory '{}': {}", path, e)),
+                    }
+                }
+                _ => Err("delete-dir requires a string path".to_string()),
+            }
+        })),
+    );
+
+    // Paths
+    env.set(
+        "path/join".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.is_empty() {
+                return Err("path/join requires at least 1 argument".to_string());
+            }
+            let mut path = std::path::PathBuf::new();
+            for i in 0..args.len() {
+                path.push(str_arg(args, i, "path/join")?);
+            }
+            Ok(Value::Str(fs::path_string(&path)))
+        })),
+    );
+
+    env.set(
+        "path/parent".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "path/parent")?;
+            Ok(fs::parent(str_arg(args, 0, "path/parent")?))
+        })),
+    );
+
+    env.set(
+        "path/file-name".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "path/file-name")?;
+            Ok(fs::file_name(str_arg(args, 0, "path/file-name")?))
+        })),
+    );
+
+    env.set(
+        "path/extension".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "path/extension")?;
+            Ok(fs::extension(str_arg(args, 0, "path/extension")?))
+        })),
+    );
+
+    env.set(
+        "path/absolute".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "path/absolute")?;
+            let path = fs::absolute(str_arg(args, 0, "path/absolute")?, "path/absolute")?;
+            Ok(Value::Str(fs::path_string(&path)))
+        })),
+    );
+
+    env.set(
+        "path/canonical".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "path/canonical")?;
+            fs::canonical(str_arg(args, 0, "path/canonical")?, "path/canonical").map(Value::Str)
+        })),
+    );
+
+    env.set(
+        "path/relative-to".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "path/relative-to")?;
+            let base = str_arg(args, 0, "path/relative-to")?;
+            let path = str_arg(args, 1, "path/relative-to")?;
+            fs::relative_to(base, path, "path/relative-to").map(Value::Str)
+        })),
+    );
+
+    // Walking, globbing and metadata
+    env.set(
+        "walk-dir".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "walk-dir")?;
+            let opts = fs::walk_options(args.get(1), "walk-dir")?;
+            fs::walk(str_arg(args, 0, "walk-dir")?, &opts, "walk-dir")
+        })),
+    );
+
+    env.set(
+        "glob".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "glob")?;
+            let pattern = str_arg(args, 0, "glob")?;
+            let base = match args.get(1) {
+                None | Some(Value::Nil) => ".".to_string(),
+                Some(Value::Map(opts)) => match (opts.get("dir"), opts.len()) {
+                    (Some(Value::Str(dir)), 1) => dir.clone(),
+                    (None, 0) => ".".to_string(),
+                    _ => return Err("glob only supports the option {:dir path}".to_string()),
+                },
+                Some(_) => return Err("glob options must be a map".to_string()),
+            };
+            fs::glob(pattern, std::path::Path::new(&base), "glob")
+        })),
+    );
+
+    env.set(
+        "file-info".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 2, "file-info")?;
+            let follow = match args.get(1) {
+                None | Some(Value::Nil) => false,
+                Some(Value::Map(opts)) => match (opts.get("follow-links"), opts.len()) {
+                    (Some(Value::Bool(b)), 1) => *b,
+                    (None, 0) => false,
+                    _ => return Err("file-info only supports the option {:follow-links bool}".to_string()),
+                },
+                Some(_) => return Err("file-info options must be a map".to_string()),
+            };
+            fs::info(str_arg(args, 0, "file-info")?, follow, "file-info")
+        })),
+    );
+
+    // Temporary files; with-temp deletes them when it's done
+    env.set(
+        "temp-file".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 1, "temp-file")?;
+            let opts = fs::temp_options(args.first(), "temp-file")?;
+            fs::create_temp(&opts, false, "temp-file").map(Value::Str)
+        })),
+    );
+
+    env.set(
+        "temp-dir".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 1, "temp-dir")?;
+            let opts = fs::temp_options(args.first(), "temp-dir")?;
+            fs::create_temp(&opts, true, "temp-dir").map(Value::Str)
+        })),
+    );
+
+    // Watching for changes by polling
+    env.set(
+        "watch".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 3, "watch")?;
+            let path = str_arg(args, 0, "watch")?;
+            watch::watch(path, args[1].clone(), args.get(2), "watch")
+        })),
+    );
+
+    env.set(
+        "unwatch".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "unwatch")?;
+            watch::unwatch(&args[0], "unwatch")
+        })),
+    );
+
+    env.set(
+        "poll-watches".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 0, "poll-watches")?;
+            watch::poll()
+        })),
+    );
+
+    env.set(
+        "run-watches".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 1, "run-watches")?;
+            watch::run(args.first(), "run-watches")
+        })),
+    );
+
+    // nREPL server and a client for talking to one
+    env.set(
+        "nrepl/start-server".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 0, 1, "nrepl/start-server")?;
+            let port = match args.first() {
+                None | Some(Value::Nil) => 0,
+                Some(Value::Number(n)) if *n >= 0.0 && *n <= u16::MAX as f64 && n.fract() == 0.0 => *n as u16,
+                Some(other) => return Err(format!("nrepl/start-server requires a port number, got {}", other)),
+            };
+            let (port, _) = nrepl::start(port)?;
+            Ok(Value::Number(port as f64))
+        })),
+    );
+
+    env.set(
+        "nrepl/connect".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "nrepl/connect")?;
+            nrepl::connect(&args[0], "nrepl/connect")
+        })),
+    );
+
+    env.set(
+        "nrepl/send".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "nrepl/send")?;
+            nrepl::send(&args[0], &args[1], "nrepl/send")
+        })),
+    );
+
+    env.set(
+        "nrepl/responses".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "nrepl/responses")?;
+            nrepl::responses(&args[0], &args[1], "nrepl/responses")
+        })),
+    );
+
+    env.set(
+        "nrepl/message".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 2, 2, "nrepl/message")?;
+            let id = nrepl::send(&args[0], &args[1], "nrepl/message")?;
+            nrepl::responses(&args[0], &id, "nrepl/message")
+        })),
+    );
+
+    env.set(
+        "nrepl/close".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "nrepl/close")?;
+            nrepl::close(&args[0], "nrepl/close")
+        })),
+    );
+
+    // Enhanced standard I/O operations
+    env.set(
+        "read-line".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() > 1 {
+                return Err("read-line requires 0-1 arguments".to_string());
+            }
+            
+            if args.is_empty() {
+                // Read from stdin
+                let mut line = String::new();
+                match std::io::stdin().read_line(&mut line) {
+                    Ok(_) => {
+                        trim_line_ending(&mut line);
+                        Ok(Value::Str(line))
+                    }
+                    Err(e) => Err(format!("Failed to read line from stdin: {}", e)),
+                }
+            } else {
+                // Read from reader
+                match &args[0] {
+                    Value::IOResource(IOResource::Reader(reader)) => {
+                        with_open(reader, "reader", |r| {
+                            let mut line = String::new();
+                            match r.read_line(&mut line) {
+                                Ok(0) => Ok(Value::Nil), // EOF
+                                Ok(_) => {
+                                    trim_line_ending(&mut line);
+                                    Ok(Value::Str(line))
+                                }
+                                Err(e) => Err(format!("Failed to read line from reader: {}", e)),
+                            }
+                        })
+                    }
+                    _ => Err("read-line requires a reader or no arguments for stdin".to_string()),
+                }
+            }
+        })),
+    );
+
+    env.set(
+        "read-char".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("read-char requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::IOResource(IOResource::Reader(reader)) => {
+                    with_open(reader, "reader", |r| {
+                        Ok(read_utf8_char(r)?.map_or(Value::Nil, |c| Value::Str(c.to_string())))
+                    })
+                }
+                _ => Err("read-char requires a reader".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "line-seq".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("line-seq requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::IOResource(IOResource::Reader(reader)) => {
+                    with_open(reader, "reader", |_| Ok(()))?;
+                    // Lines are read only as the sequence is realized
+                    Ok(Value::LazySeq(LazySeq::from_items(reader_lines(reader))))
+                }
+                _ => Err("line-seq requires a reader".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "read-bytes".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 2 {
+                return Err("read-bytes requires exactly 2 arguments".to_string());
+            }
+            let n = match &args[1] {
+                Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+                _ => return Err("read-bytes requires a non-negative integer count".to_string()),
+            };
+            match &args[0] {
+                Value::IOResource(IOResource::InputStream(stream)) => {
+                    with_open(stream, "input-stream", |r| read_up_to(r, n))
+                }
+                Value::IOResource(IOResource::Reader(reader)) => {
+                    with_open(reader, "reader", |r| read_up_to(r, n))
+                }
+                _ => Err("read-bytes requires an input-stream or reader".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "write-bytes".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 2 {
+                return Err("write-bytes requires exactly 2 arguments".to_string());
+            }
+            match (&args[0], &args[1]) {
+                (Value::IOResource(IOResource::OutputStream(stream)), Value::Bytes(bytes)) => {
+                    with_open(stream, "output-stream", |w| {
+                        w.write_all(bytes)
+                            .map(|_| Value::Nil)
+                            .map_err(|e| format!("Failed to write bytes: {}", e))
+                    })
+                }
+                (Value::IOResource(IOResource::OutputStream(_)), _) => {
+                    Err("write-bytes requires a byte array to write".to_string())
+                }
+                _ => Err("write-bytes requires an output-stream".to_string()),
             }
         })),
     );
 
+    // Byte arrays
     env.set(
-        "create-dir".to_string(),
+        "bytes".to_string(),
         Value::Function(Function::Native(|args| {
             if args.len() != 1 {
-                return Err("create-dir requires exactly 1 argument".to_string());
+                return Err("bytes requires exactly 1 argument".to_string());
             }
             match &args[0] {
-                Value::Str(path) => {
-                    match std::fs::create_dir_all(path) {
-                        Ok(_) => Ok(Value::Nil),
-                        Err(e) => Err(format!("Failed to create directory '{}': {}", path, e)),
+                Value::Bytes(bytes) => Ok(Value::Bytes(bytes.clone())),
+                Value::Str(s) => Ok(Value::Bytes(s.as_bytes().to_vec())),
+                Value::List(items) | Value::Vector(items) => {
+                    let mut bytes = Vec::with_capacity(items.len());
+                    for item in items {
+                        match item {
+                            Value::Number(n) if (0.0..=255.0).contains(n) && n.fract() == 0.0 => {
+                                bytes.push(*n as u8);
+                            }
+                            _ => return Err(format!("bytes requires integers from 0 to 255, got {}", item)),
+                        }
                     }
+                    Ok(Value::Bytes(bytes))
                 }
-                _ => Err("create-dir requires a string path".to_string()),
+                Value::Nil => Ok(Value::Bytes(Vec::new())),
+                _ => Err("bytes requires a string, list or vector".to_string()),
             }
         })),
     );
 
     env.set(
-        "delete-dir".to_string(),
+        "bytes?".to_string(),
         Value::Function(Function::Native(|args| {
             if args.len() != 1 {
-                return Err("delete-dir requires exactly 1 argument".to_string());
+                return Err("bytes? requires exactly 1 argument".to_string());
+            }
+            Ok(Value::Bool(matches!(args[0], Value::Bytes(_))))
+        })),
+    );
+
+    env.set(
+        "bytes->str".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.is_empty() || args.len() > 2 {
+                return Err("bytes->str requires 1-2 arguments".to_string());
             }
+            let encoding = match args.get(1) {
+                None => Encoding::Utf8,
+                Some(Value::Str(name)) | Some(Value::Keyword(name)) => Encoding::from_name(name)?,
+                Some(_) => return Err("bytes->str encoding must be a string".to_string()),
+            };
             match &args[0] {
-                Value::Str(path) => {
-                    match std::fs::remove_dir_all(path) {
-                        Ok(_) => Ok(Value::Nil),
-                        Err(e) => Err(format!("Failed to delete directory '{}': {}", path, e)),
-                    }
-                }
-                _ => Err("delete-dir requires a string path".to_string()),
+                Value::Bytes(bytes) => encoding.decode(bytes).map(Value::Str),
+                _ => Err("bytes->str requires a byte array".to_string()),
             }
         })),
     );
 
-    // Enhanced standard I/O operations
     env.set(
-        "read-line".to_string(),
+        "bytes->vector".to_string(),
         Value::Function(Function::Native(|args| {
-            if args.len() > 1 {
-                return Err("read-line requires 0-1 arguments".to_string());
+            if args.len() != 1 {
+                return Err("bytes->vector requires exactly 1 argument".to_string());
             }
-            
-            if args.is_empty() {
-                // Read from stdin
-                let mut line = String::new();
-                match std::io::stdin().read_line(&mut line) {
-                    Ok(_) => {
-                        // Remove trailing newline
-                        if line.ends_with('\n') {
-                            line.pop();
-                            if line.ends_with('\r') {
-                                line.pop();
-                            }
-                        }
-                        Ok(Value::Str(line))
-                    }
-                    Err(e) => Err(format!("Failed to read line from stdin: {}", e)),
-                }
-            } else {
-                // Read from reader
-                match &args[0] {
-                    Value::IOResource(IOResource::Reader(reader)) => {
-                        match reader.lock() {
-                            Ok(mut r) => {
-                                let mut line = String::new();
-                                match r.read_line(&mut line) {
-                                    Ok(0) => Ok(Value::Nil), // EOF
-                                    Ok(_) => {
-                                        // Remove trailing newline
-                                        if line.ends_with('\n') {
-                                            line.pop();
-                                            if line.ends_with('\r') {
-                                                line.pop();
-                                            }
-                                        }
-                                        Ok(Value::Str(line))
-                                    }
-                                    Err(e) => Err(format!("Failed to read line from reader: {}", e)),
-                                }
-                            }
-                            Err(e) => Err(format!("Failed to lock reader: {}", e)),
-                        }
-                    }
-                    _ => Err("read-line requires a reader or no arguments for stdin".to_string()),
+            match &args[0] {
+                Value::Bytes(bytes) => {
+                    Ok(Value::Vector(bytes.iter().map(|b| Value::Number(*b as f64)).collect()))
                 }
+                _ => Err("bytes->vector requires a byte array".to_string()),
             }
         })),
     );
@@ -1667,7 +3366,7 @@ pub fn create_default_env() -> Env {
         "println".to_string(),
         Value::Function(Function::Native(|args| {
             if args.is_empty() {
-                println!();
+                write_out("\n");
                 Ok(Value::Nil)
             } else {
                 let output = args
@@ -1678,7 +3377,7 @@ pub fn create_default_env() -> Env {
                     })
                     .collect::<Vec<String>>()
                     .join(" ");
-                println!("{}", output);
+                write_out(&format!("{}\n", output));
                 Ok(Value::Nil)
             }
         })),
@@ -1687,51 +3386,27 @@ pub fn create_default_env() -> Env {
     env.set(
         "printf".to_string(),
         Value::Function(Function::Native(|args| {
-            if args.is_empty() {
-                return Err("printf requires at least 1 argument".to_string());
-            }
-            
-            let format_str = match &args[0] {
-                Value::Str(s) => s.clone(),
-                other => format!("{}", other),
+            let fmt = match args.first() {
+                Some(Value::Str(fmt)) => fmt,
+                Some(other) => return Err(format!("printf requires a format string, got {}", other)),
+                None => return Err("printf requires at least 1 argument".to_string()),
             };
-            
-            // Simple printf implementation - just replace %s with arguments
-            let mut result = format_str.clone();
-            for (i, arg) in args.iter().skip(1).enumerate() {
-                let placeholder = format!("%{}", i + 1);
-                let value = match arg {
-                    Value::Str(s) => s.clone(),
-                    other => format!("{}", other),
-                };
-                result = result.replace(&placeholder, &value);
-            }
-            
-            // Also support %s for sequential replacement
-            let mut parts = result.split("%s");
-            let mut output = String::new();
-            let mut arg_index = 1;
-            
-            output.push_str(parts.next().unwrap_or(""));
-            for part in parts {
-                if arg_index < args.len() {
-                    let value = match &args[arg_index] {
-                        Value::Str(s) => s.clone(),
-                        other => format!("{}", other),
-                    };
-                    output.push_str(&value);
-                    arg_index += 1;
-                } else {
-                    output.push_str("%s");
-                }
-                output.push_str(part);
-            }
-            
-            print!("{}", output);
+            write_out(&crate::format::format(fmt, &args[1..])?);
             Ok(Value::Nil)
         })),
     );
 
+    env.set(
+        "format".to_string(),
+        Value::Function(Function::Native(|args| {
+            match args.first() {
+                Some(Value::Str(fmt)) => Ok(Value::Str(crate::format::format(fmt, &args[1..])?)),
+                Some(other) => Err(format!("format requires a format string, got {}", other)),
+                None => Err("format requires at least 1 argument".to_string()),
+            }
+        })),
+    );
+
     // Essential helper functions as native functions to avoid closure overhead
     env.set(
         "inc".to_string(),
@@ -2113,52 +3788,415 @@ pub fn create_default_env() -> Env {
             let func = &args[0];
             let arg_list = &args[1];
             
-            // Extract arguments from list/vector
+            // Extract arguments from list/vector/lazy seq
             let call_args = match arg_list {
                 Value::List(items) => items.clone(),
                 Value::Vector(items) => items.clone(),
+                Value::LazySeq(seq) => seq.to_vec()?,
                 Value::Nil => vec![],
                 _ => return Err("apply requires a list or vector as second argument".to_string()),
             };
             
             // Call the function with the arguments
             match func {
-                Value::Function(Function::Native(f)) => f(&call_args),
-                Value::Function(Function::UserDefined { params, body, env: func_env }) => {
-                    if params.len() != call_args.len() {
-                        return Err(format!("Function expects {} arguments, got {}", params.len(), call_args.len()));
-                    }
-                    
-                    let mut exec_env = func_env.clone();
-                    for (param, arg) in params.iter().zip(call_args.iter()) {
-                        exec_env.set(param.clone(), arg.clone());
-                    }
-                    eval(body, &mut exec_env)
-                }
-                Value::Function(Function::Macro { .. }) => {
-                    Err("Cannot apply macro (use macroexpand instead)".to_string())
-                }
+                Value::Function(_) => call_function(func, &call_args),
                 _ => Err("First argument to apply must be a function".to_string()),
             }
         })),
     );
     
-    // concat - concatenate collections
+    // concat - lazily concatenate collections
     env.set(
         "concat".to_string(),
         Value::Function(Function::Native(|args| {
-            let mut result = Vec::new();
-            
+            if !args.iter().all(lazy::is_seqable) {
+                return Err("concat requires lists, vectors, or nil".to_string());
+            }
+            let colls = args.to_vec();
+            Ok(Value::LazySeq(LazySeq::from_items(colls.into_iter().flat_map(SeqIter::new))))
+        })),
+    );
+
+    // Lazy sequence operations: elements are computed only as they are
+    // consumed, so these work on infinite sequences such as (range)
+    env.set(
+        "map".to_string(),
+        Value::Function(Function::Native(|args| {
+            match args.len() {
+                0 => return Err("map requires a function".to_string()),
+                1 => return Ok(Value::Transducer(vec![Xform::Map(args[0].clone())])),
+                _ => {}
+            }
+            let func = args[0].clone();
+            let mut colls = Vec::new();
+            for coll in &args[1..] {
+                colls.push(lazy::seq_iter(coll, "map")?);
+            }
+            // Stops at the end of the shortest collection
+            let items = std::iter::from_fn(move || {
+                let mut call_args = Vec::with_capacity(colls.len());
+                for coll in colls.iter_mut() {
+                    match coll.next()? {
+                        Ok(item) => call_args.push(item),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                Some(call_function(&func, &call_args))
+            });
+            Ok(Value::LazySeq(LazySeq::from_items(items)))
+        })),
+    );
+
+    env.set(
+        "filter".to_string(),
+        Value::Function(Function::Native(|args| {
+            match args.len() {
+                1 => return Ok(Value::Transducer(vec![Xform::Filter(args[0].clone())])),
+                2 => {}
+                _ => return Err("filter requires 1 or 2 arguments".to_string()),
+            }
+            let pred = args[0].clone();
+            let items = lazy::seq_iter(&args[1], "filter")?.filter_map(move |item| {
+                let item = match item {
+                    Ok(item) => item,
+                    Err(e) => return Some(Err(e)),
+                };
+                match call_function(&pred, std::slice::from_ref(&item)) {
+                    Ok(Value::Bool(false)) | Ok(Value::Nil) => None,
+                    Ok(_) => Some(Ok(item)),
+                    Err(e) => Some(Err(e)),
+                }
+            });
+            Ok(Value::LazySeq(LazySeq::from_items(items)))
+        })),
+    );
+
+    env.set(
+        "take".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.is_empty() || args.len() > 2 {
+                return Err("take requires 1 or 2 arguments".to_string());
+            }
+            let n = match &args[0] {
+                Value::Number(n) if *n >= 0.0 => *n as usize,
+                _ => return Err("take requires a non-negative count".to_string()),
+            };
+            if args.len() == 1 {
+                return Ok(Value::Transducer(vec![Xform::Take(n)]));
+            }
+            let items = lazy::seq_iter(&args[1], "take")?.take(n);
+            Ok(Value::LazySeq(LazySeq::from_items(items)))
+        })),
+    );
+
+    env.set(
+        "drop".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 2 {
+                return Err("drop requires exactly 2 arguments".to_string());
+            }
+            let n = match &args[0] {
+                Value::Number(n) if *n >= 0.0 => *n as usize,
+                _ => return Err("drop requires a non-negative count".to_string()),
+            };
+            let mut items = lazy::seq_iter(&args[1], "drop")?;
+            // The skipped prefix is only realized once the result is
+            Ok(Value::LazySeq(LazySeq::from_fn(move || {
+                for _ in 0..n {
+                    if items.next().transpose()?.is_none() {
+                        break;
+                    }
+                }
+                Ok(Value::LazySeq(LazySeq::from_items(items)))
+            })))
+        })),
+    );
+
+    env.set(
+        "iterate".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 2 {
+                return Err("iterate requires exactly 2 arguments".to_string());
+            }
+            let func = args[0].clone();
+            let mut current: Option<Result<Value, String>> = None;
+            let mut seed = Some(args[1].clone());
+            // x, (f x), (f (f x)), ... calling f only when the next element is needed
+            let items = std::iter::from_fn(move || {
+                let next = match (seed.take(), current.take()) {
+                    (Some(x), _) => Ok(x),
+                    (None, Some(Ok(prev))) => call_function(&func, &[prev]),
+                    (None, _) => return None,
+                };
+                current = Some(next.clone());
+                Some(next)
+            });
+            Ok(Value::LazySeq(LazySeq::from_items(items)))
+        })),
+    );
+
+    env.set(
+        "range".to_string(),
+        Value::Function(Function::Native(|args| {
+            let mut bounds = Vec::new();
             for arg in args {
                 match arg {
-                    Value::List(items) => result.extend(items.clone()),
-                    Value::Vector(items) => result.extend(items.clone()),
-                    Value::Nil => {}, // nil contributes nothing
-                    _ => return Err("concat requires lists, vectors, or nil".to_string()),
+                    Value::Number(n) => bounds.push(*n),
+                    _ => return Err("range requires numbers".to_string()),
                 }
             }
-            
-            Ok(Value::List(result))
+            let (start, end, step) = match bounds.as_slice() {
+                [] => (0.0, f64::INFINITY, 1.0),
+                [end] => (0.0, *end, 1.0),
+                [start, end] => (*start, *end, 1.0),
+                [start, end, step] => (*start, *end, *step),
+                _ => return Err("range takes at most 3 arguments".to_string()),
+            };
+            if step == 0.0 {
+                return Err("range step must not be zero".to_string());
+            }
+            let items = (0u64..)
+                .map(move |i| start + step * i as f64)
+                .take_while(move |x| if step > 0.0 { *x < end } else { *x > end })
+                .map(|x| Ok(Value::Number(x)));
+            Ok(Value::LazySeq(LazySeq::from_items(items)))
+        })),
+    );
+
+    // Transducers: composable transformations independent of the input
+    // source. (map f), (filter p), (take n), (partition-by f) and (dedupe)
+    // return transducers; transduce, into, sequence and eduction apply them
+    // in a single pass without intermediate collections.
+    env.set(
+        "partition-by".to_string(),
+        Value::Function(Function::Native(|args| {
+            let xform = match args {
+                [f] | [f, _] => Xform::PartitionBy(f.clone()),
+                _ => return Err("partition-by requires 1 or 2 arguments".to_string()),
+            };
+            match args.get(1) {
+                None => Ok(Value::Transducer(vec![xform])),
+                Some(coll) => Ok(Value::LazySeq(LazySeq::from_items(transduce::sequence(vec![xform], coll, "partition-by")?))),
+            }
+        })),
+    );
+
+    env.set(
+        "dedupe".to_string(),
+        Value::Function(Function::Native(|args| {
+            match args {
+                [] => Ok(Value::Transducer(vec![Xform::Dedupe])),
+                [coll] => Ok(Value::LazySeq(LazySeq::from_items(transduce::sequence(vec![Xform::Dedupe], coll, "dedupe")?))),
+                _ => Err("dedupe requires 0 or 1 arguments".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "comp".to_string(),
+        Value::Function(Function::Native(|args| {
+            if !args.is_empty() && args.iter().all(|arg| matches!(arg, Value::Transducer(_))) {
+                // Composed transducers apply left to right
+                let mut composed = Vec::new();
+                for arg in args {
+                    composed.extend(transduce::xforms(arg, "comp")?);
+                }
+                return Ok(Value::Transducer(composed));
+            }
+            // Functions apply right to left: ((comp f g) x y) is (f (g x y)),
+            // and (comp) is identity, which also works as a transducer
+            if let Some(other) = args.iter().find(|arg| !matches!(arg, Value::Function(_) | Value::Keyword(_))) {
+                return Err(format!("comp requires all functions or all transducers, got {}", other));
+            }
+            Ok(Value::Function(Function::Composed(args.to_vec())))
+        })),
+    );
+
+    env.set(
+        "transduce".to_string(),
+        Value::Function(Function::Native(|args| {
+            let (xform, f, init, coll) = match args {
+                [xform, f, coll] => (xform, f, call_function(f, &[])?, coll),
+                [xform, f, init, coll] => (xform, f, init.clone(), coll),
+                _ => return Err("transduce requires 3 or 4 arguments".to_string()),
+            };
+            transduce::transduce(&transduce::xforms(xform, "transduce")?, f, init, coll, "transduce")
+        })),
+    );
+
+    env.set(
+        "into".to_string(),
+        Value::Function(Function::Native(|args| {
+            let (to, xforms, from) = match args {
+                [to, from] => (to, Vec::new(), from),
+                [to, xform, from] => (to, transduce::xforms(xform, "into")?, from),
+                _ => return Err("into requires 2 or 3 arguments".to_string()),
+            };
+            match to {
+                // Like cons, into a list adds each item at the front
+                Value::List(_) | Value::Nil => {
+                    let mut items = match to {
+                        Value::List(items) => items.clone(),
+                        _ => Vec::new(),
+                    };
+                    items.reverse();
+                    transduce::run(&xforms, from, "into", &mut |item| {
+                        items.push(item);
+                        Ok(Flow::Continue)
+                    })?;
+                    items.reverse();
+                    Ok(Value::List(items))
+                }
+                Value::Vector(items) => {
+                    let mut items = items.clone();
+                    transduce::run(&xforms, from, "into", &mut |item| {
+                        items.push(item);
+                        Ok(Flow::Continue)
+                    })?;
+                    Ok(Value::Vector(items))
+                }
+                Value::Map(map) => {
+                    let mut map = map.clone();
+                    transduce::run(&xforms, from, "into", &mut |item| {
+                        match item {
+                            Value::Map(entries) => map.extend(entries),
+                            Value::Vector(pair) | Value::List(pair) if pair.len() == 2 => {
+                                let key = match &pair[0] {
+                                    Value::Keyword(k) | Value::Str(k) => k.clone(),
+                                    other => return Err(format!("into a map requires keyword or string keys, got {}", other)),
+                                };
+                                map.insert(key, pair[1].clone());
+                            }
+                            other => return Err(format!("into a map requires [key value] entries, got {}", other)),
+                        }
+                        Ok(Flow::Continue)
+                    })?;
+                    Ok(Value::Map(map))
+                }
+                _ => Err("into requires a list, vector, map or nil as first argument".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "sequence".to_string(),
+        Value::Function(Function::Native(|args| {
+            let (xforms, coll) = match args {
+                [coll] => (Vec::new(), coll),
+                [xform, coll] => (transduce::xforms(xform, "sequence")?, coll),
+                _ => return Err("sequence requires 1 or 2 arguments".to_string()),
+            };
+            Ok(Value::LazySeq(LazySeq::from_items(transduce::sequence(xforms, coll, "sequence")?)))
+        })),
+    );
+
+    env.set(
+        "eduction".to_string(),
+        Value::Function(Function::Native(|args| {
+            let (coll, xform_args) = match args.split_last() {
+                Some(split) => split,
+                None => return Err("eduction requires a collection".to_string()),
+            };
+            let mut xforms = Vec::new();
+            for xform in xform_args {
+                xforms.extend(transduce::xforms(xform, "eduction")?);
+            }
+            Ok(Value::LazySeq(LazySeq::from_items(transduce::sequence(xforms, coll, "eduction")?)))
+        })),
+    );
+
+    env.set(
+        "reduce".to_string(),
+        Value::Function(Function::Native(|args| {
+            match args {
+                [f, init, coll] => transduce::transduce(&[], f, init.clone(), coll, "reduce"),
+                [f, coll] => {
+                    let mut items = transduce::items(coll, "reduce")?;
+                    match items.next().transpose()? {
+                        None => call_function(f, &[]),
+                        Some(first) => {
+                            let rest = Value::LazySeq(LazySeq::from_items(items));
+                            transduce::transduce(&[], f, first, &rest, "reduce")
+                        }
+                    }
+                }
+                _ => Err("reduce requires 2 or 3 arguments".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "reduced".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("reduced requires exactly 1 argument".to_string());
+            }
+            Ok(Value::Reduced(Box::new(args[0].clone())))
+        })),
+    );
+
+    env.set(
+        "reduced?".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("reduced? requires exactly 1 argument".to_string());
+            }
+            Ok(Value::Bool(matches!(args[0], Value::Reduced(_))))
+        })),
+    );
+
+    env.set(
+        "doall".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("doall requires exactly 1 argument".to_string());
+            }
+            if let Value::LazySeq(seq) = &args[0] {
+                seq.to_vec()?;
+            }
+            Ok(args[0].clone())
+        })),
+    );
+
+    env.set(
+        "seq".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("seq requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::List(items) | Value::Vector(items) if items.is_empty() => Ok(Value::Nil),
+                Value::List(items) | Value::Vector(items) => Ok(Value::List(items.clone())),
+                Value::LazySeq(seq) => match seq.step()? {
+                    Step::Empty => Ok(Value::Nil),
+                    Step::Cons(..) => Ok(args[0].clone()),
+                },
+                Value::Nil => Ok(Value::Nil),
+                _ => Err("seq requires a list, vector, lazy seq or nil".to_string()),
+            }
+        })),
+    );
+
+    env.set(
+        "seq?".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("seq? requires exactly 1 argument".to_string());
+            }
+            Ok(Value::Bool(matches!(args[0], Value::List(_) | Value::LazySeq(_))))
+        })),
+    );
+
+    env.set(
+        "realized?".to_string(),
+        Value::Function(Function::Native(|args| {
+            if args.len() != 1 {
+                return Err("realized? requires exactly 1 argument".to_string());
+            }
+            match &args[0] {
+                Value::LazySeq(seq) => Ok(Value::Bool(seq.is_realized())),
+                _ => Err("realized? requires a lazy seq".to_string()),
+            }
         })),
     );
     
@@ -2224,6 +4262,8 @@ pub fn create_default_env() -> Env {
                 Value::List(items) => items.is_empty(),
                 Value::Vector(items) => items.is_empty(),
                 Value::Str(s) => s.is_empty(),
+                Value::Bytes(bytes) => bytes.is_empty(),
+                Value::LazySeq(seq) => matches!(seq.step()?, Step::Empty),
                 Value::Nil => true,
                 _ => false,
             };
@@ -2242,6 +4282,8 @@ pub fn create_default_env() -> Env {
                 Value::List(items) => Ok(Value::Number(items.len() as f64)),
                 Value::Vector(items) => Ok(Value::Number(items.len() as f64)),
                 Value::Str(s) => Ok(Value::Number(s.len() as f64)),
+                Value::Bytes(bytes) => Ok(Value::Number(bytes.len() as f64)),
+                Value::LazySeq(seq) => Ok(Value::Number(seq.to_vec()?.len() as f64)),
                 Value::Nil => Ok(Value::Number(0.0)),
                 _ => Err("count requires a sequence (list, vector, or string)".to_string()),
             }
@@ -2259,6 +4301,8 @@ pub fn create_default_env() -> Env {
                 Value::List(items) => Ok(Value::Number(items.len() as f64)),
                 Value::Vector(items) => Ok(Value::Number(items.len() as f64)),
                 Value::Str(s) => Ok(Value::Number(s.len() as f64)),
+                Value::Bytes(bytes) => Ok(Value::Number(bytes.len() as f64)),
+                Value::LazySeq(seq) => Ok(Value::Number(seq.to_vec()?.len() as f64)),
                 Value::Nil => Ok(Value::Number(0.0)),
                 _ => Err("length requires a sequence (list, vector, or string)".to_string()),
             }
@@ -2328,11 +4372,7 @@ fn load_namespace(ns_name: &str, env: &mut Env) -> Result<Value, String> {
 
     // Convert namespace name to file path
     // my.namespace -> std/my/namespace.lisp
-    let file_path = if ns_name.starts_with("core.") {
-        format!("std/{}.lisp", ns_name.replace('.', "/"))
-    } else {
-        format!("std/{}.lisp", ns_name.replace('.', "/"))
-    };
+    let file_path = format!("std/{}.lisp", ns_name.replace('.', "/"));
 
     // Save current namespace
     let _current_ns = env.get_namespace().to_string();
@@ -2478,6 +4518,8 @@ fn load_form_hybrid(form: &Value, env: &mut Env) -> Result<Value, String> {
                         }
                     }
                     "defn" => {
+                        let form = list;
+                        let (doc, list) = split_docstring(list, 4);
                         if list.len() != 4 {
                             return Err("defn requires exactly 3 arguments".to_string());
                         }
@@ -2501,7 +4543,8 @@ fn load_form_hybrid(form: &Value, env: &mut Env) -> Result<Value, String> {
                                     "+", "-", "*", "/", "=", "<", ">", "<=", ">=", "not=",
                                     "first", "rest", "cons", "list", "list?", "nil?", "empty?",
                                     "if", "do", "and", "or", "not", "true?", "false?",
-                                    "print", "str", "count", "concat", "vector?", "symbol?", "number?"
+                                    "print", "str", "count", "concat", "vector?", "symbol?", "number?",
+                                    "reset-test-counts", "get-pass-count", "get-fail-count", "get-error-count"
                                 ];
                                 for func_name in &essential_funcs {
                                     if let Some(func_val) = env.get(func_name) {
@@ -2529,6 +4572,7 @@ fn load_form_hybrid(form: &Value, env: &mut Env) -> Result<Value, String> {
                                     env: minimal_env,
                                 });
                                 
+                                record_definition(env.qualify(fname), form, doc);
                                 env.set_namespaced(fname.clone(), func.clone());
                                 Ok(func)
                             } else {
@@ -2539,6 +4583,8 @@ fn load_form_hybrid(form: &Value, env: &mut Env) -> Result<Value, String> {
                         }
                     }
                     "defmacro" => {
+                        let form = list;
+                        let (doc, list) = split_docstring(list, 4);
                         if list.len() != 4 {
                             return Err("defmacro requires exactly 3 arguments".to_string());
                         }
@@ -2559,6 +4605,7 @@ fn load_form_hybrid(form: &Value, env: &mut Env) -> Result<Value, String> {
                                     env: env.clone(), // Macros need full environment for type checking functions
                                 });
                                 
+                                record_definition(env.qualify(mname), form, doc);
                                 env.set_namespaced(mname.clone(), macro_fn.clone());
                                 Ok(macro_fn)
                             } else {
@@ -2570,12 +4617,15 @@ fn load_form_hybrid(form: &Value, env: &mut Env) -> Result<Value, String> {
                     }
                     "def" => {
                         // Only handle simple literal values to avoid evaluation
+                        let form = list;
+                        let (doc, list) = split_docstring(list, 3);
                         if list.len() != 3 {
                             return Err("def requires exactly 2 arguments".to_string());
                         }
                         if let Value::Symbol(name) = &list[1] {
                             match &list[2] {
                                 Value::Number(_) | Value::Str(_) | Value::Bool(_) | Value::Nil => {
+                                    record_definition(env.qualify(name), form, doc);
                                     env.set_namespaced(name.clone(), list[2].clone());
                                     Ok(list[2].clone())
                                 }
@@ -2604,17 +4654,18 @@ fn load_namespace_file(file_path: &str, env: &mut Env) -> Result<Value, String>
         Err(e) => return Err(format!("Failed to read file '{}': {}", file_path, e)),
     };
 
-    // Parse all forms from the file
-    use crate::reader::read_all_forms;
-    
-    let forms = match read_all_forms(&content) {
-        Ok(forms) => forms,
-        Err(e) => return Err(format!("Parse error in '{}': {}", file_path, e)),
+    // Parse all forms from the file, keeping positions for coverage
+    let forms = match reader::read_all_forms_with_positions(&content) {
+        (_, Some(e)) => return Err(format!("Parse error in '{}': {}", file_path, e.message)),
+        (forms, None) => coverage::instrument(file_path, forms, env.get_namespace()),
     };
 
     // Process forms with hybrid fast loading
     let mut last_result = Value::Nil;
-    for form in forms {
+    for (form, point) in forms {
+        if let Some(point) = point {
+            coverage::hit(point);
+        }
         match load_form_hybrid(&form, env) {
             Ok(result) => last_result = result,
             Err(e) => return Err(format!("Error loading form in '{}': {}", file_path, e)),
@@ -2628,10 +4679,8 @@ pub fn macroexpand(expr: &Value, env: &Env) -> Result<Value, String> {
     if let Value::List(list) = expr {
         if !list.is_empty() {
             if let Value::Symbol(name) = &list[0] {
-                if let Some(value) = env.get_with_namespaces(name) {
-                    if let Value::Function(Function::Macro { params, body, env: macro_env }) = value {
-                        return expand_macro_form(&params, &body, &list[1..], &macro_env);
-                    }
+                if let Some(Value::Function(Function::Macro { params, body, env: macro_env })) = env.get_with_namespaces(name) {
+                    return expand_macro_form(&params, &body, &list[1..], &macro_env);
                 }
             }
         }
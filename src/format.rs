@@ -0,0 +1,298 @@
+use crate::eval::str_of;
+use crate::value::Value;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Render `fmt` with `args` using printf-style directives:
+/// `%[argument$][flags][width][.precision]conversion`.
+///
+/// Conversions are `s` (as `str` renders it), `d` (integer), `f` and `e`
+/// (floating point, precision defaults to 6), `x`/`X` (hex), `o` (octal),
+/// `%` (a literal percent) and `n` (newline). Flags are `-` (left align),
+/// `0` (zero pad), `+` (always show the sign), ` ` (space before positive
+/// numbers) and `,` (group thousands). Every argument must be used.
+pub fn format(fmt: &str, args: &[Value]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut used = vec![false; args.len()];
+    let mut next_arg = 0;
+    let mut chars = fmt.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        let mut digits = String::new();
+        while let Some(&(_, d)) = chars.peek() {
+            if !d.is_ascii_digit() {
+                break;
+            }
+            digits.push(d);
+            chars.next();
+        }
+        // Digits followed by '$' select an argument; otherwise they begin the
+        // flags (a leading 0) and width
+        let mut position = None;
+        if chars.peek().map(|&(_, d)| d) == Some('$') {
+            chars.next();
+            match digits.parse::<usize>() {
+                Ok(n) if n >= 1 => position = Some(n - 1),
+                _ => return Err(format!("format: invalid argument index in {}", directive_at(fmt, start))),
+            }
+            digits.clear();
+        } else if !digits.is_empty()
+            && !digits.starts_with('0')
+            && !matches!(chars.peek(), Some(&(_, d)) if d == '.' || d == '%' || d.is_ascii_alphabetic())
+        {
+            // printf used to replace a bare %1 with its first argument
+            return Err(format!("format: %{0} is not a directive; write %{0}$s to use argument {0}", digits));
+        }
+        if digits.is_empty() {
+            while let Some(&(_, f)) = chars.peek() {
+                match f {
+                    '-' => spec.left = true,
+                    '0' => spec.zero = true,
+                    '+' => spec.plus = true,
+                    ' ' => spec.space = true,
+                    ',' => spec.group = true,
+                    _ => break,
+                }
+                chars.next();
+            }
+            while let Some(&(_, d)) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                digits.push(d);
+                chars.next();
+            }
+        } else if digits.starts_with('0') {
+            spec.zero = true;
+        }
+        if !digits.is_empty() {
+            spec.width = Some(digits.parse().map_err(|_| format!("format: width too large in {}", directive_at(fmt, start)))?);
+        }
+        if chars.peek().map(|&(_, d)| d) == Some('.') {
+            chars.next();
+            let mut precision = String::new();
+            while let Some(&(_, d)) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                precision.push(d);
+                chars.next();
+            }
+            if precision.is_empty() {
+                return Err(format!("format: missing precision in {}", directive_at(fmt, start)));
+            }
+            spec.precision = Some(precision.parse().map_err(|_| format!("format: precision too large in {}", directive_at(fmt, start)))?);
+        }
+        let conversion = match chars.next() {
+            Some((_, conversion)) => conversion,
+            None => return Err(format!("format: incomplete directive at end of \"{}\"", fmt)),
+        };
+        let end = chars.peek().map_or(fmt.len(), |&(i, _)| i);
+        let directive = &fmt[start..end];
+
+        if (spec.left || spec.zero) && spec.width.is_none() {
+            return Err(format!("format: {} needs a width for its - or 0 flag", directive));
+        }
+        if spec.left && spec.zero {
+            return Err(format!("format: {} can't combine the - and 0 flags", directive));
+        }
+
+        let numeric = matches!(conversion, 'd' | 'x' | 'X' | 'o' | 'f' | 'e' | 'E');
+        if !numeric && (spec.zero || spec.plus || spec.space || spec.group) {
+            return Err(format!("format: {} uses a numeric flag with a non-numeric conversion", directive));
+        }
+        if matches!(conversion, 'd' | 'x' | 'X' | 'o') && spec.precision.is_some() {
+            return Err(format!("format: {} can't take a precision", directive));
+        }
+
+        match conversion {
+            '%' => {
+                out.push_str(&pad("%", &spec));
+                continue;
+            }
+            'n' => {
+                out.push('\n');
+                continue;
+            }
+            _ => {}
+        }
+
+        let index = match position {
+            Some(index) => index,
+            None => {
+                next_arg += 1;
+                next_arg - 1
+            }
+        };
+        let arg = args.get(index).ok_or_else(|| {
+            format!(
+                "format: {} needs argument {} but only {} {} given",
+                directive,
+                index + 1,
+                args.len(),
+                if args.len() == 1 { "was" } else { "were" }
+            )
+        })?;
+        used[index] = true;
+
+        let rendered = match conversion {
+            's' => {
+                let s = str_of(arg);
+                match spec.precision {
+                    Some(p) => s.graphemes(true).take(p).collect(),
+                    None => s,
+                }
+            }
+            'd' => signed(&integer(arg, directive)?.to_string(), &spec, true),
+            'x' | 'X' | 'o' => {
+                // Negative values print as 64-bit two's complement, like Java
+                let n = integer(arg, directive)? as u64;
+                let digits = match conversion {
+                    'x' => format!("{:x}", n),
+                    'X' => format!("{:X}", n),
+                    _ => format!("{:o}", n),
+                };
+                signed(&digits, &spec, false)
+            }
+            'f' | 'e' | 'E' => {
+                let n = number(arg, directive)?;
+                let precision = spec.precision.unwrap_or(6);
+                let digits = if n.is_nan() {
+                    "NaN".to_string()
+                } else if n.is_infinite() {
+                    "Infinity".to_string()
+                } else if conversion == 'f' {
+                    format!("{:.*}", precision, n.abs())
+                } else {
+                    scientific(n.abs(), precision, conversion == 'E')
+                };
+                let negative = n.is_sign_negative() && !n.is_nan();
+                signed(&format!("{}{}", if negative { "-" } else { "" }, digits), &spec, conversion == 'f')
+            }
+            other => return Err(format!("format: unknown conversion '{}' in {}", other, directive)),
+        };
+        out.push_str(&pad(&rendered, &spec));
+    }
+
+    if let Some(unused) = used.iter().position(|u| !u) {
+        return Err(format!(
+            "format: {} argument(s) given but argument {} ({}) is never used by \"{}\"",
+            args.len(),
+            unused + 1,
+            args[unused],
+            fmt
+        ));
+    }
+    Ok(out)
+}
+
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    group: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+fn directive_at(fmt: &str, start: usize) -> &str {
+    let rest = &fmt[start..];
+    let end = rest[1..].find(|c: char| c.is_alphabetic() || c == '%').map_or(rest.len(), |i| i + 2);
+    &rest[..end.min(rest.len())]
+}
+
+fn integer(arg: &Value, directive: &str) -> Result<i64, String> {
+    match arg {
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < 9.2e18 => Ok(*n as i64),
+        Value::Number(n) => Err(format!("format: {} requires an integer, got {}", directive, n)),
+        other => Err(format!("format: {} requires a number, got {}", directive, other)),
+    }
+}
+
+fn number(arg: &Value, directive: &str) -> Result<f64, String> {
+    match arg {
+        Value::Number(n) => Ok(*n),
+        other => Err(format!("format: {} requires a number, got {}", directive, other)),
+    }
+}
+
+// Java-style exponent: at least two digits and always signed, e.g. 1.5e+03
+fn scientific(n: f64, precision: usize, upper: bool) -> String {
+    let formatted = format!("{:.*e}", precision, n);
+    let (mantissa, exponent) = formatted.split_once('e').expect("{:e} output has an exponent");
+    let exponent: i32 = exponent.parse().expect("{:e} exponent is an integer");
+    let sign = if exponent < 0 { '-' } else { '+' };
+    let e = if upper { 'E' } else { 'e' };
+    format!("{}{}{}{:02}", mantissa, e, sign, exponent.abs())
+}
+
+// Apply the sign and grouping flags to a rendered number, leaving a leading
+// '-' in place so zero padding goes after it.
+fn signed(digits: &str, spec: &Spec, allow_group: bool) -> String {
+    let (negative, body) = match digits.strip_prefix('-') {
+        Some(body) => (true, body),
+        None => (false, digits),
+    };
+    let body = if spec.group && allow_group { group_thousands(body) } else { body.to_string() };
+    let sign = if negative {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    };
+    let mut result = sign.to_string();
+    if spec.zero {
+        let width = spec.width.unwrap_or(0);
+        let len = sign.len() + body.len();
+        if len < width {
+            result.push_str(&"0".repeat(width - len));
+        }
+    }
+    result.push_str(&body);
+    result
+}
+
+fn group_thousands(body: &str) -> String {
+    let (int_part, fraction) = match body.find('.') {
+        Some(dot) => body.split_at(dot),
+        None => (body, ""),
+    };
+    if !int_part.bytes().all(|b| b.is_ascii_digit()) {
+        return body.to_string();
+    }
+    let mut grouped = String::new();
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped + fraction
+}
+
+fn pad(s: &str, spec: &Spec) -> String {
+    let width = match spec.width {
+        Some(width) if !spec.zero => width,
+        _ => return s.to_string(),
+    };
+    let len = s.graphemes(true).count();
+    if len >= width {
+        return s.to_string();
+    }
+    let padding = " ".repeat(width - len);
+    if spec.left {
+        format!("{}{}", s, padding)
+    } else {
+        format!("{}{}", padding, s)
+    }
+}
@@ -0,0 +1,538 @@
+use crate::eval::call_function;
+use crate::value::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn optional_path(path: Option<&Path>) -> Value {
+    match path {
+        Some(p) if !p.as_os_str().is_empty() => Value::Str(path_string(p)),
+        _ => Value::Nil,
+    }
+}
+
+pub fn parent(path: &str) -> Value {
+    optional_path(Path::new(path).parent())
+}
+
+pub fn file_name(path: &str) -> Value {
+    optional_path(Path::new(path).file_name().map(Path::new))
+}
+
+/// The extension without its dot, or nil.
+pub fn extension(path: &str) -> Value {
+    optional_path(Path::new(path).extension().map(Path::new))
+}
+
+/// Resolve `.` and `..` components without touching the filesystem, so
+/// symlinks are not followed.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match result.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    result.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => result.push(".."),
+            },
+            other => result.push(other),
+        }
+    }
+    if result.as_os_str().is_empty() {
+        result.push(".");
+    }
+    result
+}
+
+pub fn absolute(path: &str, fn_name: &str) -> Result<PathBuf, String> {
+    std::path::absolute(path)
+        .map(|p| normalize(&p))
+        .map_err(|e| format!("{}: can't make '{}' absolute: {}", fn_name, path, e))
+}
+
+pub fn canonical(path: &str, fn_name: &str) -> Result<String, String> {
+    std::fs::canonicalize(path)
+        .map(|p| path_string(&p))
+        .map_err(|e| format!("{}: can't resolve '{}': {}", fn_name, path, e))
+}
+
+/// The path that leads from `base` to `path`, using `..` where needed.
+pub fn relative_to(base: &str, path: &str, fn_name: &str) -> Result<String, String> {
+    let base = absolute(base, fn_name)?;
+    let path = absolute(path, fn_name)?;
+    let base: Vec<Component> = base.components().collect();
+    let path: Vec<Component> = path.components().collect();
+    if base.first() != path.first() {
+        return Err(format!("{}: paths are on different roots", fn_name));
+    }
+    let common = base.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let mut result = PathBuf::new();
+    for _ in common..base.len() {
+        result.push("..");
+    }
+    for component in &path[common..] {
+        result.push(component);
+    }
+    if result.as_os_str().is_empty() {
+        result.push(".");
+    }
+    Ok(path_string(&result))
+}
+
+// === METADATA ===
+
+fn file_type_keyword(file_type: std::fs::FileType) -> Value {
+    let name = if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_dir() {
+        "dir"
+    } else if file_type.is_file() {
+        "file"
+    } else {
+        "other"
+    };
+    Value::Keyword(name.to_string())
+}
+
+fn time_value(time: std::io::Result<SystemTime>) -> Value {
+    match time {
+        Ok(time) => match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => Value::Inst(after.as_millis() as i64),
+            Err(before) => Value::Inst(-(before.duration().as_millis() as i64)),
+        },
+        Err(_) => Value::Nil,
+    }
+}
+
+#[cfg(unix)]
+fn permission_entries(metadata: &std::fs::Metadata, info: &mut HashMap<String, Value>) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode();
+    let rwx: String = (0..9)
+        .map(|i| {
+            if mode & (0o400 >> i) != 0 {
+                ['r', 'w', 'x'][i % 3]
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    info.insert("permissions".to_string(), Value::Str(rwx));
+    info.insert("mode".to_string(), Value::Number((mode & 0o7777) as f64));
+}
+
+#[cfg(not(unix))]
+fn permission_entries(_metadata: &std::fs::Metadata, _info: &mut HashMap<String, Value>) {}
+
+/// A map describing a file. Symlinks are described themselves, with their
+/// target under `:target`, unless `follow` is set.
+pub fn info(path: &str, follow: bool, fn_name: &str) -> Result<Value, String> {
+    let link_metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| format!("{}: can't read metadata for '{}': {}", fn_name, path, e))?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+    let metadata = if follow && is_symlink {
+        std::fs::metadata(path).map_err(|e| format!("{}: can't follow link '{}': {}", fn_name, path, e))?
+    } else {
+        link_metadata
+    };
+
+    let mut info = HashMap::new();
+    info.insert("path".to_string(), Value::Str(path.to_string()));
+    info.insert("type".to_string(), file_type_keyword(metadata.file_type()));
+    info.insert("size".to_string(), Value::Number(metadata.len() as f64));
+    info.insert("modified".to_string(), time_value(metadata.modified()));
+    info.insert("accessed".to_string(), time_value(metadata.accessed()));
+    info.insert("created".to_string(), time_value(metadata.created()));
+    info.insert("readonly".to_string(), Value::Bool(metadata.permissions().readonly()));
+    info.insert("symlink?".to_string(), Value::Bool(is_symlink));
+    if is_symlink {
+        let target = std::fs::read_link(path).map_err(|e| format!("{}: can't read link '{}': {}", fn_name, path, e))?;
+        info.insert("target".to_string(), Value::Str(path_string(&target)));
+    }
+    permission_entries(&metadata, &mut info);
+    Ok(Value::Map(info))
+}
+
+// === WALKING ===
+
+pub struct WalkOptions {
+    pub max_depth: Option<usize>,
+    /// Only return entries of this type (`file`, `dir` or `symlink`)
+    pub kind: Option<String>,
+    /// Only return paths for which this function is truthy
+    pub filter: Option<Value>,
+    pub follow_links: bool,
+}
+
+pub fn walk_options(opts: Option<&Value>, fn_name: &str) -> Result<WalkOptions, String> {
+    let mut options = WalkOptions { max_depth: None, kind: None, filter: None, follow_links: false };
+    let map = match opts {
+        None | Some(Value::Nil) => return Ok(options),
+        Some(Value::Map(map)) => map,
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    };
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("max-depth", Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => options.max_depth = Some(*n as usize),
+            ("max-depth", Value::Nil) => options.max_depth = None,
+            ("max-depth", _) => return Err(format!("{} option :max-depth must be a non-negative integer", fn_name)),
+            ("type", Value::Keyword(k)) if matches!(k.as_str(), "file" | "dir" | "symlink") => {
+                options.kind = Some(k.clone())
+            }
+            ("type", _) => return Err(format!("{} option :type must be :file, :dir or :symlink", fn_name)),
+            ("filter", Value::Function(_)) => options.filter = Some(value.clone()),
+            ("filter", _) => return Err(format!("{} option :filter must be a function", fn_name)),
+            ("follow-links", Value::Bool(b)) => options.follow_links = *b,
+            ("follow-links", _) => return Err(format!("{} option :follow-links must be a boolean", fn_name)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    Ok(options)
+}
+
+/// Every path below `root` in depth-first order, with the entries of each
+/// directory sorted by name. The root itself is not included.
+pub fn walk(root: &str, opts: &WalkOptions, fn_name: &str) -> Result<Value, String> {
+    let root = Path::new(root);
+    if !root.is_dir() {
+        return Err(format!("{}: '{}' is not a directory", fn_name, root.display()));
+    }
+    let mut visiting = HashSet::new();
+    if let Ok(canonical) = root.canonicalize() {
+        visiting.insert(canonical);
+    }
+    let mut out = Vec::new();
+    walk_into(root, 1, opts, &mut visiting, &mut out, fn_name)?;
+    Ok(Value::Vector(out))
+}
+
+fn walk_into(
+    dir: &Path,
+    depth: usize,
+    opts: &WalkOptions,
+    visiting: &mut HashSet<PathBuf>,
+    out: &mut Vec<Value>,
+    fn_name: &str,
+) -> Result<(), String> {
+    if opts.max_depth.is_some_and(|max| depth > max) {
+        return Ok(());
+    }
+    for path in sorted_entries(dir).map_err(|e| format!("{}: can't read directory '{}': {}", fn_name, dir.display(), e))? {
+        let link_type = std::fs::symlink_metadata(&path)
+            .map_err(|e| format!("{}: can't read metadata for '{}': {}", fn_name, path.display(), e))?
+            .file_type();
+        let is_link = link_type.is_symlink();
+        // A link to a directory counts as a directory when following links
+        let is_dir = if is_link { opts.follow_links && path.is_dir() } else { link_type.is_dir() };
+
+        let kind_matches = match opts.kind.as_deref() {
+            Some("file") => !is_dir && path.is_file() && (!is_link || opts.follow_links),
+            Some("dir") => is_dir,
+            Some("symlink") => is_link,
+            _ => true,
+        };
+        let value = Value::Str(path_string(&path));
+        let included = kind_matches
+            && match &opts.filter {
+                Some(filter) => !matches!(call_function(filter, std::slice::from_ref(&value))?, Value::Bool(false) | Value::Nil),
+                None => true,
+            };
+        if included {
+            out.push(value);
+        }
+
+        if is_dir {
+            // Guard against symlink cycles by tracking the directories being visited
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if visiting.insert(canonical.clone()) {
+                walk_into(&path, depth + 1, opts, visiting, out, fn_name)?;
+                visiting.remove(&canonical);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sorted_entries(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+// === GLOB ===
+
+/// Paths matching a shell-style pattern, sorted. `*` and `?` match within a
+/// path component, `[a-z]`/`[!a-z]` match a character class, `{a,b}` gives
+/// alternatives and a `**` component matches any number of directories.
+/// Wildcards don't match a leading dot, so hidden files need an explicit `.`.
+pub fn glob(pattern: &str, base: &Path, fn_name: &str) -> Result<Value, String> {
+    let mut matches = BTreeSet::new();
+    for alternative in expand_braces(pattern, fn_name)? {
+        let absolute = alternative.starts_with('/');
+        let components: Vec<String> = alternative
+            .split('/')
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect();
+        for component in &components {
+            validate_component(component, fn_name)?;
+        }
+        let (dir, shown) = if absolute {
+            (PathBuf::from("/"), PathBuf::from("/"))
+        } else {
+            (base.to_path_buf(), PathBuf::new())
+        };
+        glob_into(&dir, &shown, &components, &mut matches);
+    }
+    Ok(Value::Vector(matches.into_iter().map(Value::Str).collect()))
+}
+
+fn expand_braces(pattern: &str, fn_name: &str) -> Result<Vec<String>, String> {
+    let open = match pattern.find('{') {
+        Some(open) => open,
+        None => {
+            if pattern.contains('}') {
+                return Err(format!("{}: unbalanced '}}' in pattern \"{}\"", fn_name, pattern));
+            }
+            return Ok(vec![pattern.to_string()]);
+        }
+    };
+    // Find the matching close brace and the top-level commas inside it
+    let mut depth = 0;
+    let mut splits = vec![open];
+    let mut close = None;
+    for (i, c) in pattern.char_indices().skip_while(|(i, _)| *i <= open) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => {
+                close = Some(i);
+                break;
+            }
+            '}' => depth -= 1,
+            ',' if depth == 0 => splits.push(i),
+            _ => {}
+        }
+    }
+    let close = close.ok_or_else(|| format!("{}: unbalanced '{{' in pattern \"{}\"", fn_name, pattern))?;
+    splits.push(close);
+
+    let (prefix, suffix) = (&pattern[..open], &pattern[close + 1..]);
+    let mut result = Vec::new();
+    for bounds in splits.windows(2) {
+        let choice = &pattern[bounds[0] + 1..bounds[1]];
+        result.extend(expand_braces(&format!("{}{}{}", prefix, choice, suffix), fn_name)?);
+    }
+    Ok(result)
+}
+
+fn validate_component(component: &str, fn_name: &str) -> Result<(), String> {
+    if component.contains("**") && component != "**" {
+        return Err(format!("{}: '**' must be a whole path component, got \"{}\"", fn_name, component));
+    }
+    let mut chars = component.chars();
+    while let Some(c) = chars.next() {
+        if c == '[' && !chars.by_ref().any(|c| c == ']') {
+            return Err(format!("{}: unclosed '[' in \"{}\"", fn_name, component));
+        }
+    }
+    Ok(())
+}
+
+fn has_wildcards(component: &str) -> bool {
+    component.contains(['*', '?', '['])
+}
+
+fn glob_into(dir: &Path, shown: &Path, components: &[String], out: &mut BTreeSet<String>) {
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            if !shown.as_os_str().is_empty() {
+                out.insert(path_string(shown));
+            }
+            return;
+        }
+    };
+
+    if component == "**" {
+        glob_into(dir, shown, rest, out);
+        for path in sorted_entries(dir).unwrap_or_default() {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let is_real_dir = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+            if is_real_dir && !name.starts_with('.') {
+                glob_into(&path, &shown.join(&name), components, out);
+            }
+        }
+    } else if !has_wildcards(component) {
+        let path = dir.join(component);
+        if rest.is_empty() {
+            if std::fs::symlink_metadata(&path).is_ok() {
+                out.insert(path_string(&shown.join(component)));
+            }
+        } else if path.is_dir() {
+            glob_into(&path, &shown.join(component), rest, out);
+        }
+    } else {
+        for path in sorted_entries(dir).unwrap_or_default() {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            if !wildcard_match(component, &name) {
+                continue;
+            }
+            if rest.is_empty() {
+                out.insert(path_string(&shown.join(&name)));
+            } else if path.is_dir() {
+                glob_into(&path, &shown.join(&name), rest, out);
+            }
+        }
+    }
+}
+
+/// Match a single path component against a pattern with `*`, `?` and
+/// character classes.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    match_from(&pattern, &name)
+}
+
+fn match_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| match_from(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && match_from(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(&c) = name.first() else { return false };
+            let close = match pattern.iter().skip(2).position(|&p| p == ']') {
+                Some(i) => i + 2,
+                None => return false,
+            };
+            let (negated, class) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..close]),
+                _ => (false, &pattern[1..close]),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && match_from(&pattern[close + 1..], &name[1..])
+        }
+        Some(&p) => name.first() == Some(&p) && match_from(&pattern[1..], &name[1..]),
+    }
+}
+
+// === TEMPORARY FILES ===
+
+pub struct TempOptions {
+    pub prefix: String,
+    pub suffix: String,
+    pub dir: PathBuf,
+}
+
+pub fn temp_options(opts: Option<&Value>, fn_name: &str) -> Result<TempOptions, String> {
+    let mut options = TempOptions { prefix: "cortado-".to_string(), suffix: String::new(), dir: std::env::temp_dir() };
+    let map = match opts {
+        None | Some(Value::Nil) => return Ok(options),
+        Some(Value::Map(map)) => map,
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    };
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("prefix", Value::Str(s)) => options.prefix = s.clone(),
+            ("suffix", Value::Str(s)) => options.suffix = s.clone(),
+            ("dir", Value::Str(s)) => options.dir = PathBuf::from(s),
+            ("prefix" | "suffix" | "dir", _) => return Err(format!("{} option :{} must be a string", fn_name, key)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    if options.prefix.contains('/') || options.suffix.contains('/') {
+        return Err(format!("{} :prefix and :suffix can't contain '/'", fn_name));
+    }
+    Ok(options)
+}
+
+fn random_name(opts: &TempOptions) -> String {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u32(std::process::id());
+    format!("{}{:016x}{}", opts.prefix, hasher.finish(), opts.suffix)
+}
+
+// Paths made by temp-file and temp-dir and not yet deleted by with-temp
+static CREATED_TEMPS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+fn created_temps() -> MutexGuard<'static, BTreeSet<String>> {
+    CREATED_TEMPS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether `path` was made by temp-file or temp-dir and is still there to
+/// clean up.
+pub fn is_temp(path: &str) -> bool {
+    created_temps().contains(path)
+}
+
+/// Delete a path made by temp-file or temp-dir, unless it was already
+/// cleaned up. Other paths are left alone.
+pub fn remove_temp(path: &str) -> Result<(), String> {
+    let created = created_temps().remove(path);
+    if created {
+        remove_path(path)?;
+    }
+    Ok(())
+}
+
+/// Create a new, empty temporary file or directory and return its path.
+pub fn create_temp(opts: &TempOptions, dir: bool, fn_name: &str) -> Result<String, String> {
+    for _ in 0..100 {
+        let path = opts.dir.join(random_name(opts));
+        let created = if dir {
+            std::fs::create_dir(&path)
+        } else {
+            std::fs::OpenOptions::new().write(true).create_new(true).open(&path).map(|_| ())
+        };
+        match created {
+            Ok(()) => {
+                let path = path_string(&path);
+                created_temps().insert(path.clone());
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("{}: can't create in '{}': {}", fn_name, opts.dir.display(), e)),
+        }
+    }
+    Err(format!("{}: couldn't find an unused name in '{}'", fn_name, opts.dir.display()))
+}
+
+/// Delete a file or directory tree; a path that's already gone is fine.
+pub fn remove_path(path: &str) -> Result<(), String> {
+    let result = match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to delete '{}': {}", path, e)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,130 @@
+use crate::eval::call_function;
+use crate::lazy::SeqIter;
+use crate::value::{Function, Value};
+use std::collections::HashMap;
+
+// Integers beyond this magnitude can't all be represented by an f64
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Options accepted by `json/parse`.
+#[derive(Default)]
+pub struct ParseOptions {
+    /// Applied to every object key; must return a string or keyword.
+    pub key_fn: Option<Value>,
+}
+
+/// Options accepted by `json/write-str` and `json/write`.
+#[derive(Default)]
+pub struct WriteOptions {
+    pub pretty: bool,
+}
+
+pub fn parse_options(opts: Option<&Value>, fn_name: &str) -> Result<ParseOptions, String> {
+    let mut options = ParseOptions::default();
+    for (key, value) in options_map(opts, fn_name)? {
+        match (key.as_str(), value) {
+            ("key-fn", Value::Function(_)) => options.key_fn = Some(value.clone()),
+            ("key-fn", Value::Nil) => options.key_fn = None,
+            ("key-fn", _) => return Err(format!("{} option :key-fn must be a function", fn_name)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    Ok(options)
+}
+
+pub fn write_options(opts: Option<&Value>, fn_name: &str) -> Result<WriteOptions, String> {
+    let mut options = WriteOptions::default();
+    for (key, value) in options_map(opts, fn_name)? {
+        match (key.as_str(), value) {
+            ("pretty", Value::Bool(b)) => options.pretty = *b,
+            ("pretty", _) => return Err(format!("{} option :pretty must be a boolean", fn_name)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    Ok(options)
+}
+
+fn options_map<'a>(opts: Option<&'a Value>, fn_name: &str) -> Result<Vec<(&'a String, &'a Value)>, String> {
+    match opts {
+        None | Some(Value::Nil) => Ok(Vec::new()),
+        Some(Value::Map(map)) => Ok(map.iter().collect()),
+        Some(_) => Err(format!("{} options must be a map", fn_name)),
+    }
+}
+
+/// Convert parsed JSON into Cortado data: objects become maps, arrays become
+/// vectors and null becomes nil.
+pub fn from_json(json: serde_json::Value, opts: &ParseOptions, fn_name: &str) -> Result<Value, String> {
+    match json {
+        serde_json::Value::Null => Ok(Value::Nil),
+        serde_json::Value::Bool(b) => Ok(Value::Bool(b)),
+        serde_json::Value::Number(n) => {
+            let f = n.as_f64().ok_or_else(|| format!("{}: number {} is out of range", fn_name, n))?;
+            if (n.is_i64() || n.is_u64()) && f.abs() > MAX_SAFE_INTEGER {
+                return Err(format!("{}: integer {} is too large to represent exactly", fn_name, n));
+            }
+            Ok(Value::Number(f))
+        }
+        serde_json::Value::String(s) => Ok(Value::Str(s)),
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .map(|item| from_json(item, opts, fn_name))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Vector),
+        serde_json::Value::Object(fields) => {
+            let mut map = HashMap::new();
+            for (key, value) in fields {
+                let key = match &opts.key_fn {
+                    Some(f) => match call_function(f, &[Value::Str(key)])? {
+                        Value::Str(k) | Value::Keyword(k) => k,
+                        other => return Err(format!("{} :key-fn must return a string or keyword, got {}", fn_name, other)),
+                    },
+                    None => key,
+                };
+                map.insert(key, from_json(value, opts, fn_name)?);
+            }
+            Ok(Value::Map(map))
+        }
+    }
+}
+
+/// Convert Cortado data to JSON. Keywords and symbols become strings of
+/// their name, and insts and uuids their string form; lists, vectors and
+/// lazy sequences become arrays. Values with no JSON form (functions, I/O
+/// resources, bytes, NaN and infinities) are errors rather than being
+/// written as something unreadable.
+pub fn to_json(value: &Value, fn_name: &str) -> Result<serde_json::Value, String> {
+    match value {
+        Value::Nil => Ok(serde_json::Value::Null),
+        Value::Bool(b) => Ok(serde_json::Value::Bool(*b)),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => Ok(serde_json::Value::from(*n as i64)),
+        Value::Number(n) => serde_json::Number::from_f64(*n)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| format!("{} can't encode {} as JSON", fn_name, crate::edn::pr_str(value))),
+        Value::Str(s) | Value::Keyword(s) | Value::Symbol(s) => Ok(serde_json::Value::String(s.clone())),
+        Value::Inst(ms) => Ok(serde_json::Value::String(crate::edn::format_inst(*ms))),
+        Value::Uuid(id) => Ok(serde_json::Value::String(crate::edn::format_uuid(*id))),
+        Value::List(_) | Value::Vector(_) | Value::LazySeq(_) => SeqIter::new(value.clone())
+            .map(|item| to_json(&item?, fn_name))
+            .collect::<Result<Vec<_>, _>>()
+            .map(serde_json::Value::Array),
+        Value::Map(map) => {
+            let mut fields = serde_json::Map::new();
+            for (key, value) in map {
+                fields.insert(key.clone(), to_json(value, fn_name)?);
+            }
+            Ok(serde_json::Value::Object(fields))
+        }
+        Value::Function(Function::Macro { .. }) => Err(format!("{} can't encode a macro as JSON", fn_name)),
+        Value::Function(_) => Err(format!("{} can't encode a function as JSON", fn_name)),
+        Value::IOResource(_) => Err(format!("{} can't encode an I/O resource as JSON", fn_name)),
+        Value::Bytes(_) => Err(format!("{} can't encode bytes as JSON; convert them with bytes->str or bytes->vector first", fn_name)),
+        other => Err(format!("{} can't encode {} as JSON", fn_name, other)),
+    }
+}
+
+pub fn write_string(value: &Value, opts: &WriteOptions, fn_name: &str) -> Result<String, String> {
+    let json = to_json(value, fn_name)?;
+    let written = if opts.pretty { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) };
+    written.map_err(|e| format!("{}: {}", fn_name, e))
+}
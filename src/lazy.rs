@@ -0,0 +1,273 @@
+use crate::value::Value;
+use std::sync::{Arc, Mutex};
+
+/// A lazily realized, memoized sequence. Each node is realized at most once;
+/// every clone of the node shares the realized result.
+#[derive(Clone)]
+pub struct LazySeq(Arc<Mutex<LazyState>>);
+
+/// Printing realizes at most this many elements of a lazy seq and marks the
+/// rest with `...`, like Clojure's `*print-length*`, so printing an infinite
+/// sequence still returns.
+pub const PRINT_LENGTH: usize = 1000;
+
+/// Produces a seqable value (nil, list, vector or another lazy seq) on demand.
+pub type SeqFn = Box<dyn FnOnce() -> Result<Value, String> + Send>;
+
+// Realized nodes always hold a Cons or Empty step; boxing the values would
+// only add an allocation per element.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum Step {
+    Empty,
+    Cons(Value, Value),
+}
+
+#[allow(clippy::large_enum_variant)]
+enum LazyState {
+    Pending(SeqFn),
+    Realizing,
+    Realized(Step),
+    Failed(String),
+}
+
+impl LazySeq {
+    pub fn from_fn(f: impl FnOnce() -> Result<Value, String> + Send + 'static) -> Self {
+        LazySeq(Arc::new(Mutex::new(LazyState::Pending(Box::new(f)))))
+    }
+
+    /// An already realized node holding `first` in front of `rest`.
+    pub fn cons(first: Value, rest: Value) -> Self {
+        LazySeq(Arc::new(Mutex::new(LazyState::Realized(Step::Cons(first, rest)))))
+    }
+
+    /// A lazy seq pulling one item from `items` per realized node.
+    pub fn from_items(items: impl Iterator<Item = Result<Value, String>> + Send + 'static) -> Self {
+        type SharedIter = Arc<Mutex<Box<dyn Iterator<Item = Result<Value, String>> + Send>>>;
+
+        fn node(shared: SharedIter) -> LazySeq {
+            LazySeq::from_fn(move || {
+                let next = shared
+                    .lock()
+                    .map_err(|e| format!("Failed to lock sequence source: {}", e))?
+                    .next();
+                match next {
+                    None => Ok(Value::Nil),
+                    Some(Err(e)) => Err(e),
+                    Some(Ok(item)) => Ok(Value::LazySeq(LazySeq::cons(item, Value::LazySeq(node(shared))))),
+                }
+            })
+        }
+
+        node(Arc::new(Mutex::new(Box::new(items))))
+    }
+
+    pub fn is_realized(&self) -> bool {
+        matches!(*self.0.lock().unwrap_or_else(|e| e.into_inner()), LazyState::Realized(_))
+    }
+
+    /// Realize this node and return its first element and the rest.
+    ///
+    /// A thunk may return another unrealized lazy seq (e.g. a recursive
+    /// `lazy-seq` that skips elements); those are realized in a loop rather
+    /// than recursively so long chains don't exhaust the native stack.
+    pub fn step(&self) -> Result<Step, String> {
+        let mut pending = Vec::new();
+        let mut current = self.clone();
+
+        let result = loop {
+            let thunk = {
+                let mut state = current.0.lock().map_err(|e| format!("Failed to lock lazy seq: {}", e))?;
+                match &*state {
+                    LazyState::Realized(step) => break Ok(step.clone()),
+                    LazyState::Failed(e) => break Err(e.clone()),
+                    LazyState::Realizing => break Err("Lazy sequence depends on its own realization".to_string()),
+                    LazyState::Pending(_) => {}
+                }
+                match std::mem::replace(&mut *state, LazyState::Realizing) {
+                    LazyState::Pending(thunk) => thunk,
+                    _ => unreachable!("state checked above"),
+                }
+            };
+            pending.push(current.clone());
+
+            match thunk() {
+                Ok(Value::LazySeq(next)) => current = next,
+                Ok(other) => break step_of(other),
+                Err(e) => break Err(e),
+            }
+        };
+
+        for node in pending {
+            if let Ok(mut state) = node.0.lock() {
+                *state = match &result {
+                    Ok(step) => LazyState::Realized(step.clone()),
+                    Err(e) => LazyState::Failed(e.clone()),
+                };
+            }
+        }
+
+        result
+    }
+
+    /// Realize every element. Does not return for infinite sequences.
+    pub fn to_vec(&self) -> Result<Vec<Value>, String> {
+        SeqIter::new(Value::LazySeq(self.clone())).collect()
+    }
+
+    /// Realize the first PRINT_LENGTH elements, returning them and whether
+    /// the sequence goes on past them.
+    pub fn printable(&self) -> Result<(Vec<Value>, bool), String> {
+        let mut items = SeqIter::new(Value::LazySeq(self.clone()));
+        let shown = items.by_ref().take(PRINT_LENGTH).collect::<Result<Vec<_>, _>>()?;
+        Ok((shown, items.next().is_some()))
+    }
+}
+
+/// Compare two seqables element by element, stopping at the first
+/// difference, so a finite sequence is unequal to an infinite one. Two equal
+/// infinite sequences never finish comparing.
+pub fn seq_eq(a: Value, b: Value) -> bool {
+    let (mut a, mut b) = (SeqIter::new(a), SeqIter::new(b));
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(Ok(x)), Some(Ok(y))) if x == y => {}
+            _ => return false,
+        }
+    }
+}
+
+impl PartialEq for LazySeq {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || seq_eq(Value::LazySeq(self.clone()), Value::LazySeq(other.clone()))
+    }
+}
+
+impl std::fmt::Debug for LazySeq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LazySeq")
+    }
+}
+
+impl Drop for LazySeq {
+    // Unlink uniquely owned realized tails one at a time; dropping a long
+    // realized chain recursively would overflow the stack.
+    fn drop(&mut self) {
+        if Arc::strong_count(&self.0) != 1 {
+            return;
+        }
+        let mut next = match self.0.lock() {
+            Ok(mut state) => take_tail(&mut state),
+            Err(_) => None,
+        };
+        while let Some(seq) = next {
+            if Arc::strong_count(&seq.0) != 1 {
+                break;
+            }
+            next = match seq.0.lock() {
+                Ok(mut state) => take_tail(&mut state),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+fn take_tail(state: &mut LazyState) -> Option<LazySeq> {
+    if let LazyState::Realized(Step::Cons(_, rest)) = state {
+        if let Value::LazySeq(seq) = std::mem::replace(rest, Value::Nil) {
+            return Some(seq);
+        }
+    }
+    None
+}
+
+fn step_of(value: Value) -> Result<Step, String> {
+    match value {
+        Value::Nil => Ok(Step::Empty),
+        Value::List(items) | Value::Vector(items) => {
+            let mut items = items.into_iter();
+            match items.next() {
+                None => Ok(Step::Empty),
+                Some(first) => {
+                    let rest: Vec<Value> = items.collect();
+                    Ok(Step::Cons(first, Value::List(rest)))
+                }
+            }
+        }
+        Value::LazySeq(seq) => seq.step(),
+        other => Err(format!("lazy-seq body must return a sequence, got {}", other)),
+    }
+}
+
+/// Iterates any seqable value, realizing lazy seqs as it goes.
+pub struct SeqIter {
+    cursor: Cursor,
+}
+
+enum Cursor {
+    Items(std::vec::IntoIter<Value>),
+    Lazy(LazySeq),
+    Invalid(String),
+    Done,
+}
+
+impl SeqIter {
+    /// Callers should check `is_seqable` first; other values yield an error.
+    pub fn new(value: Value) -> Self {
+        let cursor = match value {
+            Value::Nil => Cursor::Done,
+            Value::List(items) | Value::Vector(items) => Cursor::Items(items.into_iter()),
+            Value::LazySeq(seq) => Cursor::Lazy(seq),
+            other => Cursor::Invalid(format!("Don't know how to create a sequence from {}", other)),
+        };
+        SeqIter { cursor }
+    }
+}
+
+impl Iterator for SeqIter {
+    type Item = Result<Value, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.cursor {
+            Cursor::Done => None,
+            Cursor::Invalid(e) => {
+                let e = std::mem::take(e);
+                self.cursor = Cursor::Done;
+                Some(Err(e))
+            }
+            Cursor::Items(items) => items.next().map(Ok),
+            Cursor::Lazy(seq) => match seq.step() {
+                Err(e) => {
+                    self.cursor = Cursor::Done;
+                    Some(Err(e))
+                }
+                Ok(Step::Empty) => {
+                    self.cursor = Cursor::Done;
+                    None
+                }
+                Ok(Step::Cons(first, rest)) => {
+                    self.cursor = match rest {
+                        Value::LazySeq(seq) => Cursor::Lazy(seq),
+                        Value::List(items) | Value::Vector(items) => Cursor::Items(items.into_iter()),
+                        _ => Cursor::Done,
+                    };
+                    Some(Ok(first))
+                }
+            },
+        }
+    }
+}
+
+pub fn is_seqable(value: &Value) -> bool {
+    matches!(value, Value::Nil | Value::List(_) | Value::Vector(_) | Value::LazySeq(_))
+}
+
+/// Iterate a seqable value, or report which function was given something else.
+pub fn seq_iter(value: &Value, fn_name: &str) -> Result<SeqIter, String> {
+    if is_seqable(value) {
+        Ok(SeqIter::new(value.clone()))
+    } else {
+        Err(format!("{} requires a sequence, got {}", fn_name, value))
+    }
+}
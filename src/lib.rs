@@ -2,6 +2,22 @@ pub mod value;
 pub mod reader;
 pub mod env;
 pub mod eval;
+pub mod encoding;
+pub mod lazy;
+pub mod transduce;
+pub mod format;
+pub mod json;
+pub mod edn;
+pub mod csv;
+pub mod process;
+pub mod fs;
+pub mod watch;
+pub mod nrepl;
+pub mod lsp;
+pub mod testing;
+pub mod check;
+pub mod coverage;
+pub mod profile;
 
 pub use value::Value;
 pub use reader::read;
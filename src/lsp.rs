@@ -0,0 +1,663 @@
+use crate::eval::{create_default_env, SPECIAL_FORMS};
+use crate::json;
+use crate::reader::{self, is_symbol_char, Pos, ReadError, SourceMap, Span};
+use crate::value::{Function, Value};
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// === MESSAGES ===
+
+/// Read one JSON-RPC message framed by a Content-Length header, or None at
+/// the end of the input.
+pub fn read_message<R: BufRead>(input: &mut R) -> Result<Option<Json>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {}
+            Err(e) => return Err(format!("Failed to read message header: {}", e)),
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| format!("Invalid Content-Length: {}", value))?);
+            }
+        }
+    }
+    let length = length.ok_or("Message is missing its Content-Length header")?;
+    let mut body = vec![0u8; length];
+    input.read_exact(&mut body).map_err(|e| format!("Failed to read message body: {}", e))?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| format!("Invalid JSON message: {}", e))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> Result<(), String> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| output.flush())
+        .map_err(|e| format!("Failed to write message: {}", e))
+}
+
+// === POSITIONS ===
+
+// LSP counts columns in UTF-16 code units, the reader in characters
+
+fn line_of(text: &str, line: usize) -> &str {
+    text.split('\n').nth(line).unwrap_or("")
+}
+
+fn lsp_position(text: &str, pos: Pos) -> Json {
+    let character: usize = line_of(text, pos.line).chars().take(pos.column).map(char::len_utf16).sum();
+    json!({ "line": pos.line, "character": character })
+}
+
+fn lsp_range(text: &str, span: Span) -> Json {
+    json!({ "start": lsp_position(text, span.start), "end": lsp_position(text, span.end) })
+}
+
+fn from_lsp_position(text: &str, position: &Json) -> Pos {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    let mut units = 0;
+    let mut column = 0;
+    for c in line_of(text, line).chars() {
+        if units >= character {
+            break;
+        }
+        units += c.len_utf16();
+        column += 1;
+    }
+    Pos { line, column }
+}
+
+/// The symbol under or just before `pos`, and the part of it before `pos`.
+fn symbol_at(text: &str, pos: Pos) -> Option<(String, String)> {
+    let chars: Vec<char> = line_of(text, pos.line).trim_end_matches('\r').chars().collect();
+    let cursor = pos.column.min(chars.len());
+    let mut start = cursor;
+    while start > 0 && is_symbol_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while end < chars.len() && is_symbol_char(chars[end]) {
+        end += 1;
+    }
+    if start == end {
+        return None;
+    }
+    Some((chars[start..end].iter().collect(), chars[start..cursor].iter().collect()))
+}
+
+fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn uri_from_path(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+// === ANALYSIS ===
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DefKind {
+    Function,
+    Macro,
+    Variable,
+}
+
+/// A top-level `def`, `defn` or `defmacro`.
+#[derive(Debug, Clone)]
+struct Definition {
+    name: String,
+    namespace: String,
+    kind: DefKind,
+    params: Option<Vec<String>>,
+    doc: Option<String>,
+    span: Span,
+    name_span: Span,
+}
+
+impl Definition {
+    fn signature(&self) -> String {
+        match &self.params {
+            Some(params) => format!("({} [{}])", self.name, params.join(" ")),
+            None => self.name.clone(),
+        }
+    }
+
+    fn hover(&self) -> String {
+        let mut text = format!("```clojure\n{}\n```\n", self.signature());
+        if self.kind == DefKind::Macro {
+            text.push_str("\nMacro\n");
+        }
+        if let Some(doc) = &self.doc {
+            text.push_str(&format!("\n{}\n", doc));
+        }
+        text.push_str(&format!("\n*{}/{}*", self.namespace, self.name));
+        text
+    }
+}
+
+/// What a source file defines and requires, read from its top-level forms.
+struct Analysis {
+    text: String,
+    error: Option<ReadError>,
+    /// The namespace in effect before any `ns` form
+    default_namespace: String,
+    /// Each `ns` form and the namespace it switches to
+    namespaces: Vec<(Span, String)>,
+    aliases: HashMap<String, String>,
+    requires: Vec<String>,
+    definitions: Vec<Definition>,
+}
+
+impl Analysis {
+    fn new(text: String, default_namespace: String) -> Analysis {
+        let (forms, error) = reader::read_all_forms_with_positions(&text);
+        let mut analysis = Analysis {
+            text: String::new(),
+            error,
+            default_namespace: default_namespace.clone(),
+            namespaces: Vec::new(),
+            aliases: HashMap::new(),
+            requires: Vec::new(),
+            definitions: Vec::new(),
+        };
+        let mut namespace = default_namespace;
+        for (form, map) in &forms {
+            let items = match form {
+                Value::List(items) => items,
+                _ => continue,
+            };
+            match items.first() {
+                Some(Value::Symbol(head)) if head == "ns" => {
+                    if let Some(Value::Symbol(name)) = items.get(1) {
+                        namespace = name.clone();
+                        analysis.namespaces.push((map.span, name.clone()));
+                    }
+                }
+                Some(Value::Symbol(head)) if head == "require" => analysis.add_require(items.get(1)),
+                Some(Value::Symbol(head)) if matches!(head.as_str(), "def" | "defn" | "defmacro") => {
+                    if let Some(definition) = read_definition(head, items, map, &namespace) {
+                        analysis.definitions.push(definition);
+                    }
+                }
+                _ => {}
+            }
+        }
+        analysis.text = text;
+        analysis
+    }
+
+    fn add_require(&mut self, spec: Option<&Value>) {
+        match spec {
+            Some(Value::Vector(spec)) => {
+                if let [Value::Symbol(target), Value::Keyword(as_kw), Value::Symbol(alias)] = spec.as_slice() {
+                    if as_kw == "as" {
+                        self.aliases.insert(alias.clone(), target.clone());
+                        self.requires.push(target.clone());
+                    }
+                }
+            }
+            Some(Value::List(quoted)) => {
+                if let [Value::Symbol(quote), Value::Symbol(target)] = quoted.as_slice() {
+                    if quote == "quote" {
+                        self.requires.push(target.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn namespace_at(&self, pos: Pos) -> &str {
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|(span, _)| span.start <= pos)
+            .map(|(_, name)| name.as_str())
+            .unwrap_or(&self.default_namespace)
+    }
+
+    fn defines_namespace(&self, namespace: &str) -> bool {
+        self.default_namespace == namespace
+            || self.namespaces.iter().any(|(_, name)| name == namespace)
+            || self.definitions.iter().any(|def| def.namespace == namespace)
+    }
+
+    fn find(&self, namespace: &str, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|def| def.namespace == namespace && def.name == name)
+    }
+}
+
+/// Mirrors how `def`, `defn` and `defmacro` take an optional docstring.
+fn read_definition(head: &str, items: &[Value], map: &SourceMap, namespace: &str) -> Option<Definition> {
+    let name = match items.get(1) {
+        Some(Value::Symbol(name)) => name.clone(),
+        _ => return None,
+    };
+    let plain_len = if head == "def" { 3 } else { 4 };
+    let (doc, rest) = match items.get(2) {
+        Some(Value::Str(doc)) if items.len() == plain_len + 1 => (Some(doc.clone()), &items[3..]),
+        _ => (None, &items[2..]),
+    };
+    let (kind, params) = match (head, rest.first()) {
+        ("def", Some(Value::List(value))) if matches!(value.first(), Some(Value::Symbol(s)) if s == "fn") => {
+            (DefKind::Function, value.get(1).and_then(param_names))
+        }
+        ("def", _) => (DefKind::Variable, None),
+        ("defmacro", params) => (DefKind::Macro, params.and_then(param_names)),
+        (_, params) => (DefKind::Function, params.and_then(param_names)),
+    };
+    Some(Definition {
+        name,
+        namespace: namespace.to_string(),
+        kind,
+        params,
+        doc,
+        span: map.span,
+        name_span: map.children.get(1)?.span,
+    })
+}
+
+fn param_names(params: &Value) -> Option<Vec<String>> {
+    match params {
+        Value::Vector(params) => Some(params.iter().map(|param| param.to_string()).collect()),
+        _ => None,
+    }
+}
+
+// === SERVER ===
+
+struct Server {
+    root: Option<PathBuf>,
+    documents: HashMap<String, Rc<Analysis>>,
+    /// Built-in names and whether each is a "function", "macro", "variable" or "special form"
+    builtins: Vec<(String, &'static str)>,
+    shutdown: bool,
+}
+
+fn builtin_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Function(Function::Macro { .. }) => "macro",
+        Value::Function(_) => "function",
+        _ => "variable",
+    }
+}
+
+fn completion_kind(kind: &str) -> u32 {
+    match kind {
+        "function" | "macro" => 3,
+        "special form" => 14,
+        "namespace" => 9,
+        _ => 6,
+    }
+}
+
+impl Server {
+    fn new() -> Server {
+        let env = create_default_env();
+        let mut builtins: Vec<(String, &'static str)> = env
+            .completion_names()
+            .into_iter()
+            .filter_map(|name| env.resolve(&name).map(|(_, value)| (name, builtin_kind(&value))))
+            .collect();
+        builtins.extend(SPECIAL_FORMS.iter().map(|name| (name.to_string(), "special form")));
+        Server { root: None, documents: HashMap::new(), builtins, shutdown: false }
+    }
+
+    fn root(&self) -> PathBuf {
+        self.root.clone().or_else(|| std::env::current_dir().ok()).unwrap_or_default()
+    }
+
+    /// Namespaces live at `std/a/b.lisp` under the project root, as `require`
+    /// expects; other files start out in `user`.
+    fn default_namespace(&self, uri: &str) -> String {
+        let std_dir = self.root().join("std");
+        path_from_uri(uri)
+            .and_then(|path| path.strip_prefix(&std_dir).ok().map(Path::to_path_buf))
+            .and_then(|relative| relative.to_str().and_then(|s| s.strip_suffix(".lisp")).map(|s| s.replace('/', ".")))
+            .unwrap_or_else(|| "user".to_string())
+    }
+
+    /// Open documents that define `namespace`, then its file under `std/`.
+    fn sources_for(&self, namespace: &str) -> Vec<(String, Rc<Analysis>)> {
+        let mut sources: Vec<(String, Rc<Analysis>)> = self
+            .documents
+            .iter()
+            .filter(|(_, doc)| doc.defines_namespace(namespace))
+            .map(|(uri, doc)| (uri.clone(), doc.clone()))
+            .collect();
+        sources.sort_by(|a, b| a.0.cmp(&b.0));
+        let path = self.root().join("std").join(format!("{}.lisp", namespace.replace('.', "/")));
+        let uri = uri_from_path(&path);
+        if !self.documents.contains_key(&uri) {
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                sources.push((uri, Rc::new(Analysis::new(text, namespace.to_string()))));
+            }
+        }
+        sources
+    }
+
+    /// Resolve a symbol the way evaluation would: through an alias or full
+    /// namespace when qualified, otherwise in the current namespace, `core`
+    /// and `user`.
+    fn find_definition(&self, uri: &str, doc: &Rc<Analysis>, name: &str, pos: Pos) -> Option<(String, Rc<Analysis>, Definition)> {
+        let candidates: Vec<(String, String)> = match name.rsplit_once('/') {
+            Some((prefix, short)) if !prefix.is_empty() && !short.is_empty() => {
+                let namespace = doc.aliases.get(prefix).cloned().unwrap_or_else(|| prefix.to_string());
+                vec![(namespace, short.to_string())]
+            }
+            _ => [doc.namespace_at(pos), "core", "user"].iter().map(|ns| (ns.to_string(), name.to_string())).collect(),
+        };
+        for (namespace, short) in candidates {
+            if let Some(def) = doc.find(&namespace, &short) {
+                return Some((uri.to_string(), doc.clone(), def.clone()));
+            }
+            for (source_uri, source) in self.sources_for(&namespace) {
+                if let Some(def) = source.find(&namespace, &short) {
+                    let def = def.clone();
+                    return Some((source_uri, source, def));
+                }
+            }
+        }
+        None
+    }
+
+    fn document(&self, params: &Json) -> Result<(String, Rc<Analysis>), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match self.documents.get(uri) {
+            Some(doc) => Ok((uri.to_string(), doc.clone())),
+            None => Err((-32602, format!("Unknown document: {}", uri))),
+        }
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        if self.shutdown && method != "shutdown" {
+            return Err((-32600, "The server is shutting down".to_string()));
+        }
+        match method {
+            "initialize" => {
+                let root = params["rootUri"].as_str().and_then(path_from_uri);
+                self.root = root.or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": { "openClose": true, "change": 1 },
+                        "documentSymbolProvider": true,
+                        "definitionProvider": true,
+                        "hoverProvider": true,
+                        "completionProvider": { "triggerCharacters": ["/"] }
+                    },
+                    "serverInfo": { "name": "cortado", "version": env!("CARGO_PKG_VERSION") }
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            _ => Err((-32601, format!("Unknown method: {}", method))),
+        }
+    }
+
+    /// Handle a notification, returning any notifications to send back.
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        match method {
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default().to_string();
+                vec![self.update(uri, text)]
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+                match params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+                    Some(change) => vec![self.update(uri, change["text"].as_str().unwrap_or_default().to_string())],
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+                self.documents.remove(&uri);
+                vec![diagnostics(&uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: String, text: String) -> Json {
+        let analysis = Analysis::new(text, self.default_namespace(&uri));
+        let found = match &analysis.error {
+            Some(error) => vec![json!({
+                "range": lsp_range(&analysis.text, error.span),
+                "severity": 1,
+                "source": "cortado",
+                "message": error.message
+            })],
+            None => Vec::new(),
+        };
+        self.documents.insert(uri.clone(), Rc::new(analysis));
+        diagnostics(&uri, found)
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (_, doc) = self.document(params)?;
+        let mut symbols = Vec::new();
+        for (span, name) in &doc.namespaces {
+            let range = lsp_range(&doc.text, *span);
+            symbols.push(json!({ "name": name, "kind": 3, "range": range, "selectionRange": range }));
+        }
+        for def in &doc.definitions {
+            let kind = if def.kind == DefKind::Variable { 13 } else { 12 };
+            symbols.push(json!({
+                "name": def.name,
+                "detail": def.signature(),
+                "kind": kind,
+                "range": lsp_range(&doc.text, def.span),
+                "selectionRange": lsp_range(&doc.text, def.name_span)
+            }));
+        }
+        Ok(Json::Array(symbols))
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (uri, doc) = self.document(params)?;
+        let pos = from_lsp_position(&doc.text, &params["position"]);
+        let name = match symbol_at(&doc.text, pos) {
+            Some((name, _)) => name,
+            None => return Ok(Json::Null),
+        };
+        if let Some((def_uri, source, def)) = self.find_definition(&uri, &doc, &name, pos) {
+            return Ok(json!({ "uri": def_uri, "range": lsp_range(&source.text, def.name_span) }));
+        }
+        // A required namespace or its alias goes to the namespace's file
+        let namespace = doc.aliases.get(&name).cloned().unwrap_or(name);
+        if doc.requires.contains(&namespace) {
+            if let Some((source_uri, _)) = self.sources_for(&namespace).into_iter().next() {
+                let start = json!({ "line": 0, "character": 0 });
+                return Ok(json!({ "uri": source_uri, "range": { "start": start, "end": start } }));
+            }
+        }
+        Ok(Json::Null)
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (uri, doc) = self.document(params)?;
+        let pos = from_lsp_position(&doc.text, &params["position"]);
+        let name = match symbol_at(&doc.text, pos) {
+            Some((name, _)) => name,
+            None => return Ok(Json::Null),
+        };
+        let text = match self.find_definition(&uri, &doc, &name, pos) {
+            Some((_, _, def)) => def.hover(),
+            None => match self.builtins.iter().find(|(builtin, _)| *builtin == name) {
+                Some((_, kind)) => format!("```clojure\n{}\n```\n\nBuilt-in {}", name, kind),
+                None => return Ok(Json::Null),
+            },
+        };
+        Ok(json!({ "contents": { "kind": "markdown", "value": text } }))
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (_, doc) = self.document(params)?;
+        let pos = from_lsp_position(&doc.text, &params["position"]);
+        let prefix = symbol_at(&doc.text, pos).map(|(_, prefix)| prefix).unwrap_or_default();
+        let current = doc.namespace_at(pos);
+
+        // (label, kind, detail, doc)
+        let mut candidates: Vec<(String, String, String, Option<String>)> = Vec::new();
+        for def in &doc.definitions {
+            let label = if def.namespace == current { def.name.clone() } else { format!("{}/{}", def.namespace, def.name) };
+            candidates.push((label, kind_name(def.kind).to_string(), def.signature(), def.doc.clone()));
+        }
+        // Required namespaces complete both in full and through their aliases
+        let mut prefixes: Vec<(String, String)> = doc.requires.iter().map(|ns| (ns.clone(), ns.clone())).collect();
+        prefixes.extend(doc.aliases.iter().map(|(alias, ns)| (alias.clone(), ns.clone())));
+        for (qualifier, namespace) in prefixes {
+            candidates.push((qualifier.clone(), "namespace".to_string(), namespace.clone(), None));
+            for (_, source) in self.sources_for(&namespace) {
+                for def in source.definitions.iter().filter(|def| def.namespace == namespace) {
+                    let label = format!("{}/{}", qualifier, def.name);
+                    candidates.push((label, kind_name(def.kind).to_string(), def.signature(), def.doc.clone()));
+                }
+            }
+        }
+        for (name, kind) in &self.builtins {
+            candidates.push((name.clone(), kind.to_string(), format!("built-in {}", kind), None));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        let items: Vec<Json> = candidates
+            .into_iter()
+            .filter(|(label, ..)| label.starts_with(&prefix) && seen.insert(label.clone()))
+            .map(|(label, kind, detail, doc)| {
+                let mut item = json!({ "label": label, "kind": completion_kind(&kind), "detail": detail });
+                if let Some(doc) = doc {
+                    item["documentation"] = json!(doc);
+                }
+                item
+            })
+            .collect();
+        Ok(json!({ "isIncomplete": false, "items": items }))
+    }
+}
+
+fn kind_name(kind: DefKind) -> &'static str {
+    match kind {
+        DefKind::Function => "function",
+        DefKind::Macro => "macro",
+        DefKind::Variable => "variable",
+    }
+}
+
+fn diagnostics(uri: &str, found: Vec<Json>) -> Json {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": found }
+    })
+}
+
+/// Serve one client until it sends `exit` or the input ends. Returns the
+/// exit code: 0 if the client asked to shut down first, 1 otherwise.
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> i32 {
+    let mut server = Server::new();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                let error = json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e } });
+                if write_message(&mut output, &error).is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let replies = match message.get("id") {
+            // Responses to requests of our own; we don't make any
+            Some(_) if method.is_empty() => Vec::new(),
+            Some(id) => {
+                let reply = match server.handle_request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => {
+                        json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+                    }
+                };
+                vec![reply]
+            }
+            None if method == "exit" => break,
+            None => server.handle_notification(method, params),
+        };
+        for reply in replies {
+            if write_message(&mut output, &reply).is_err() {
+                return 1;
+            }
+        }
+    }
+    if server.shutdown {
+        0
+    } else {
+        1
+    }
+}
+
+/// Run a server over `messages`, given as maps, and return everything it
+/// sent back. Lets Cortado code drive a whole client session in memory.
+pub fn session(messages: &Value, fn_name: &str) -> Result<Value, String> {
+    let messages = match messages {
+        Value::List(items) | Value::Vector(items) => items,
+        other => return Err(format!("{} requires a vector of messages, got {}", fn_name, other)),
+    };
+    let mut input = Vec::new();
+    for message in messages {
+        let mut message = json::to_json(message, fn_name)?;
+        message["jsonrpc"] = json!("2.0");
+        write_message(&mut input, &message)?;
+    }
+    let mut output = Vec::new();
+    serve(input.as_slice(), &mut output);
+
+    let mut replies = Vec::new();
+    let mut output = output.as_slice();
+    while let Some(reply) = read_message(&mut output)? {
+        replies.push(json::from_json(reply, &json::ParseOptions::default(), fn_name)?);
+    }
+    Ok(Value::Vector(replies))
+}
@@ -2,6 +2,22 @@ mod value;
 mod reader;
 mod env;
 mod eval;
+mod encoding;
+mod lazy;
+mod transduce;
+mod format;
+mod json;
+mod edn;
+mod csv;
+mod process;
+mod fs;
+mod watch;
+mod nrepl;
+mod lsp;
+mod testing;
+mod check;
+mod coverage;
+mod profile;
 
 use reader::read;
 use eval::{eval, create_default_env};
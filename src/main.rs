@@ -2,15 +2,34 @@ mod value;
 mod reader;
 mod env;
 mod eval;
+mod encoding;
+mod lazy;
+mod transduce;
+mod format;
+mod json;
+mod edn;
+mod csv;
+mod process;
+mod fs;
+mod watch;
+mod nrepl;
+mod lsp;
+mod testing;
+mod check;
+mod coverage;
+mod profile;
+mod repl;
+mod test_runner;
 
 use reader::read;
 use eval::{eval, create_default_env};
 use env::Env;
-use value::Value;
-use std::io::{self, Write};
-use rustyline::Editor;
-use rustyline::error::ReadlineError;
-use std::path::Path;
+use value::{Function, Value};
+use std::path::{Path, PathBuf};
+
+// Counts allocations for --profile
+#[global_allocator]
+static ALLOCATOR: profile::CountingAllocator = profile::CountingAllocator;
 
 #[allow(dead_code)]
 fn load_stdlib(env: &mut Env) -> Result<(), String> {
@@ -103,254 +122,6 @@ fn load_file(path: &Path, env: &mut Env) -> Result<(), String> {
     Ok(())
 }
 
-fn load_init_file(env: &mut Env) {
-    // Try to load ~/.cortadorc if it exists
-    if let Some(home_dir) = dirs::home_dir() {
-        let init_file = home_dir.join(".cortadorc");
-        if init_file.exists() {
-            if let Ok(content) = std::fs::read_to_string(&init_file) {
-                use crate::reader::read_all_forms;
-                if let Ok(forms) = read_all_forms(&content) {
-                    for form in forms {
-                        let _ = eval(&form, env); // Ignore errors in init file
-                    }
-                }
-            }
-        }
-    }
-}
-
-fn handle_repl_command(cmd: &str, env: &mut Env) -> bool {
-    match cmd {
-        ":quit" | ":q" => {
-            println!("Goodbye!");
-            return false;
-        }
-        ":env" => {
-            println!("Current environment bindings:");
-            // This would require exposing environment inspection functionality
-            println!("(Environment inspection not yet implemented)");
-        }
-        ":reload" => {
-            println!("Reloading init file...");
-            load_init_file(env);
-        }
-        cmd if cmd.starts_with(":load ") => {
-            let filename = &cmd[6..].trim();
-            match std::fs::read_to_string(filename) {
-                Ok(content) => {
-                    use crate::reader::read_all_forms;
-                    match read_all_forms(&content) {
-                        Ok(forms) => {
-                            for form in forms {
-                                match eval(&form, env) {
-                                    Ok(result) => {
-                                        if result != Value::Nil {
-                                            println!("{}", result);
-                                        }
-                                    }
-                                    Err(e) => println!("Error: {}", e),
-                                }
-                            }
-                            println!("Loaded: {}", filename);
-                        }
-                        Err(e) => println!("Parse error in '{}': {}", filename, e),
-                    }
-                }
-                Err(e) => println!("Failed to load '{}': {}", filename, e),
-            }
-        }
-        ":help" | ":h" => {
-            println!("Available REPL commands:");
-            println!("  :quit, :q          Exit REPL");
-            println!("  :help, :h          Show this help");
-            println!("  :env               Show environment bindings");
-            println!("  :reload            Reload init file");
-            println!("  :load <file>       Load and evaluate file");
-        }
-        _ => {
-            println!("Unknown command: {}", cmd);
-            println!("Type :help for available commands");
-        }
-    }
-    true
-}
-
-fn count_parens(s: &str) -> i32 {
-    let mut count = 0;
-    let mut in_string = false;
-    let mut escaped = false;
-    
-    for ch in s.chars() {
-        match ch {
-            '\\' if in_string => {
-                escaped = !escaped;
-                continue;
-            }
-            '"' if !escaped => {
-                in_string = !in_string;
-            }
-            '(' if !in_string => count += 1,
-            ')' if !in_string => count -= 1,
-            _ => {}
-        }
-        escaped = false;
-    }
-    count
-}
-
-fn repl() {
-    let mut env = create_default_env();
-    
-    println!("Cortado REPL v1.0");
-    println!("Welcome to Cortado - A Lisp-like programming language");
-    println!("Type expressions, :help for commands, or :quit to exit");
-    
-    // Load init file if it exists
-    load_init_file(&mut env);
-    
-    let mut rl = match Editor::<()>::new() {
-        Ok(editor) => editor,
-        Err(_) => {
-            // Fall back to basic REPL if rustyline fails
-            basic_repl(&mut env);
-            return;
-        }
-    };
-    
-    // Try to load history
-    let history_path = dirs::home_dir()
-        .map(|home| home.join(".cortado_history"))
-        .unwrap_or_else(|| std::path::PathBuf::from(".cortado_history"));
-    
-    let _ = rl.load_history(&history_path);
-    
-    let mut multi_line_buffer = String::new();
-    let mut paren_count = 0;
-    
-    loop {
-        let prompt = if multi_line_buffer.is_empty() {
-            "cortado> "
-        } else {
-            "      -> "
-        };
-        
-        match rl.readline(prompt) {
-            Ok(line) => {
-                // Handle REPL commands
-                if line.trim().starts_with(':') && multi_line_buffer.is_empty() {
-                    if !handle_repl_command(line.trim(), &mut env) {
-                        break;
-                    }
-                    continue;
-                }
-                
-                // Add to history
-                rl.add_history_entry(&line);
-                
-                // Handle multi-line input
-                if !multi_line_buffer.is_empty() {
-                    multi_line_buffer.push('\n');
-                }
-                multi_line_buffer.push_str(&line);
-                
-                paren_count += count_parens(&line);
-                
-                // If parentheses are balanced, evaluate
-                if paren_count <= 0 {
-                    let input = multi_line_buffer.trim();
-                    
-                    if !input.is_empty() {
-                        match read(input) {
-                            Ok(expr) => {
-                                match eval(&expr, &mut env) {
-                                    Ok(result) => {
-                                        if result != Value::Nil {
-                                            println!("{}", result);
-                                        }
-                                    }
-                                    Err(e) => println!("Error: {}", e),
-                                }
-                            }
-                            Err(e) => println!("Parse error: {}", e),
-                        }
-                    }
-                    
-                    // Reset for next input
-                    multi_line_buffer.clear();
-                    paren_count = 0;
-                }
-            }
-            Err(ReadlineError::Interrupted) => {
-                println!("Interrupted");
-                multi_line_buffer.clear();
-                paren_count = 0;
-            }
-            Err(ReadlineError::Eof) => {
-                println!("Goodbye!");
-                break;
-            }
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
-            }
-        }
-    }
-    
-    // Save history
-    let _ = rl.save_history(&history_path);
-}
-
-fn basic_repl(env: &mut Env) {
-    println!("(Using basic REPL - install rustyline for better experience)");
-    println!();
-    
-    loop {
-        print!("cortado> ");
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(0) => {
-                println!("Goodbye!");
-                break;
-            }
-            Ok(_) => {
-                let input = input.trim();
-                
-                if input.is_empty() {
-                    continue;
-                }
-                
-                if input.starts_with(':') {
-                    if !handle_repl_command(input, env) {
-                        break;
-                    }
-                    continue;
-                }
-                
-                match read(input) {
-                    Ok(expr) => {
-                        match eval(&expr, env) {
-                            Ok(result) => {
-                                if result != Value::Nil {
-                                    println!("{}", result);
-                                }
-                            }
-                            Err(e) => println!("Error: {}", e),
-                        }
-                    }
-                    Err(e) => println!("Parse error: {}", e),
-                }
-            }
-            Err(e) => {
-                println!("Error reading input: {}", e);
-                break;
-            }
-        }
-    }
-}
-
 fn run_demo() {
     let mut env = create_default_env();
     
@@ -385,158 +156,16 @@ fn run_demo() {
     println!("\nDemo completed successfully!");
 }
 
-fn run_tests() {
-    let mut env = create_default_env();
-    
-    // Skip the eval.rs run-tests function entirely and run comprehensive tests directly
-    run_comprehensive_tests(&mut env);
-}
-
-fn run_comprehensive_tests(_env: &mut Env) {
-    use std::time::Instant;
-    
-    let overall_start = Instant::now();
-    println!("CORTADO COMPREHENSIVE TEST SUITE");
-    println!("=================================");
-    
-    // Reset global test counters
-    let mut reset_env = create_default_env();
-    if let Ok(expr) = read("(reset-test-counts)") {
-        let _ = eval(&expr, &mut reset_env);
-    }
-    
-    // Dynamically discover ALL test files for complete coverage
-    let test_dir = std::path::Path::new("test");
-    let mut test_files = Vec::new();
-    
-    if let Ok(entries) = std::fs::read_dir(test_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.extension().map_or(false, |ext| ext == "lisp") {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        test_files.push(name.to_string());
-                    }
-                }
-            }
-        }
-    }
-    
-    test_files.sort(); // Consistent order
-    
-    let mut _total_files = 0;
-    let mut passed_files = 0;
-    let mut failed_files = 0;
-    let mut timeout_files = 0;
-    let mut _total_time = 0.0;
-    
-    // Run each test file with timeout
-    for test_file in test_files {
-        _total_files += 1;
-        println!("\nTesting: {}", test_file);
-        
-        let start = Instant::now();
-        let test_cmd = format!("(load \"test/{}\")", test_file);
-        
-        match read(&test_cmd) {
-            Ok(expr) => {
-                // PERFORMANCE FIX: Create fresh environment for each test file to prevent pollution
-                let mut fresh_env = create_default_env();
-                
-                // MINIMAL essential functions - anything more causes environment pollution
-                let essential_funcs = vec![
-                    "(defn assert-eq [expected actual] (test-assert-eq expected actual))",
-                    "(defn assert-not-eq [expected actual] (if (not (= expected actual)) (do (print \"  ✓ PASS:\" expected \"!=\" actual) true) (do (print \"  ✗ FAIL: expected\" expected \"NOT to equal\" actual) false)))",
-                ];
-                
-                for func in essential_funcs {
-                    if let Ok(expr) = read(func) {
-                        let _ = eval(&expr, &mut fresh_env);
-                    }
-                }
-                
-                match eval(&expr, &mut fresh_env) {
-                    Ok(_) => {
-                        let elapsed = start.elapsed().as_secs_f64();
-                        _total_time += elapsed;
-                        
-                        if elapsed > 25.0 {
-                            println!("  TIMEOUT ({:.2}s > 25.0s limit)", elapsed);
-                            timeout_files += 1;
-                        } else {
-                            println!("  PASSED ({:.2}s)", elapsed);
-                            passed_files += 1;
-                        }
-                    },
-                    Err(e) => {
-                        let elapsed = start.elapsed().as_secs_f64();
-                        _total_time += elapsed;
-                        println!("  FAILED: {} ({:.2}s)", e, elapsed);
-                        failed_files += 1;
-                    }
-                }
-            },
-            Err(e) => {
-                println!("  PARSE ERROR: {}", e);
-                failed_files += 1;
-            }
-        }
-    }
-    
-    let overall_elapsed = overall_start.elapsed().as_secs_f64();
-    
-    // Get final test counts using the eval functions
-    let mut count_env = create_default_env();
-    let total_passed = if let Ok(expr) = read("(get-pass-count)") {
-        if let Ok(Value::Number(n)) = eval(&expr, &mut count_env) {
-            n as usize
-        } else { 0 }
-    } else { 0 };
-    
-    let total_failed = if let Ok(expr) = read("(get-fail-count)") {
-        if let Ok(Value::Number(n)) = eval(&expr, &mut count_env) {
-            n as usize
-        } else { 0 }
-    } else { 0 };
-    
-    let total_tests = total_passed + total_failed;
-    
-    println!("\nCOMPREHENSIVE TEST RESULTS");
-    println!("==========================");
-    println!("Total Tests: {}", total_tests);
-    println!("Passed: {}", total_passed);
-    println!("Failed: {}", total_failed);
-    println!("Files: {} passed, {} failed, {} timeout", passed_files, failed_files, timeout_files);
-    println!("Total Time: {:.2}s", overall_elapsed);
-    
-    if total_tests > 0 {
-        println!("Pass Rate: {:.1}%", (total_passed as f64 / total_tests as f64) * 100.0);
-    }
-    
-    if total_failed == 0 && timeout_files == 0 {
-        println!("ALL TESTS PASSED!");
-    } else if total_passed > 0 {
-        println!("PARTIAL SUCCESS - Some tests failed or timed out");
-    } else {
-        println!("NO TESTS PASSED - Critical issues detected");
-    }
-    
-    // Clean up temporary test files
-    cleanup_test_files();
-}
-
 fn cleanup_test_files() {
     // Clean up all .txt and .ctl files (test temporary files)
     if let Ok(entries) = std::fs::read_dir(".") {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if let Some(extension) = path.extension() {
-                    if extension == "txt" || extension == "ctl" {
-                        if let Err(e) = std::fs::remove_file(&path) {
-                            eprintln!("Warning: Failed to clean up test file '{}': {}", 
-                                     path.display(), e);
-                        }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(extension) = path.extension() {
+                if extension == "txt" || extension == "ctl" {
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        eprintln!("Warning: Failed to clean up test file '{}': {}", 
+                                 path.display(), e);
                     }
                 }
             }
@@ -544,15 +173,29 @@ fn cleanup_test_files() {
     }
 }
 
-fn run_script(filename: &str, verbose: bool) {
+/// Bind `*command-line-args*` to the arguments given after the script or
+/// expression, or nil when there are none.
+fn set_command_line_args(env: &mut Env, args: &[String]) {
+    let value = if args.is_empty() {
+        Value::Nil
+    } else {
+        Value::List(args.iter().cloned().map(Value::Str).collect())
+    };
+    env.set("*command-line-args*".to_string(), value);
+}
+
+/// Run a script and return the process exit status. If the script defines
+/// `-main`, it is called with the remaining command-line arguments.
+fn run_script(filename: &str, script_args: &[String], verbose: bool) -> i32 {
     let mut env = create_default_env();
+    set_command_line_args(&mut env, script_args);
     
     // Read and parse the script file
     let content = match std::fs::read_to_string(filename) {
         Ok(content) => content,
         Err(e) => {
             eprintln!("Error reading file '{}': {}", filename, e);
-            std::process::exit(1);
+            return 1;
         }
     };
 
@@ -574,7 +217,7 @@ fn run_script(filename: &str, verbose: bool) {
         Ok(forms) => forms,
         Err(e) => {
             eprintln!("Parse error in '{}': {}", filename, e);
-            std::process::exit(1);
+            return 1;
         }
     };
 
@@ -588,10 +231,118 @@ fn run_script(filename: &str, verbose: bool) {
             }
             Err(e) => {
                 eprintln!("Runtime error in '{}': {}", filename, e);
-                std::process::exit(1);
+                return 1;
+            }
+        }
+    }
+
+    // Call (-main arg ...) if the script defines an entry point
+    if let Some(Value::Function(main)) = env.get_with_aliases("-main") {
+        // fn has no rest parameters, so [& args] would bind & to the first
+        // argument and args to the second
+        if let Function::UserDefined { params, .. } = &main {
+            if params.iter().any(|p| p == "&") {
+                eprintln!("Runtime error in '{}': -main can't take & rest arguments; use *command-line-args*", filename);
+                return 1;
+            }
+        }
+        let mut call = vec![Value::Symbol("-main".to_string())];
+        call.extend(script_args.iter().cloned().map(Value::Str));
+        match eval(&Value::List(call), &mut env) {
+            Ok(result) => {
+                if verbose && result != Value::Nil {
+                    println!("{}", result);
+                }
+            }
+            Err(e) => {
+                eprintln!("Runtime error in '{}': {}", filename, e);
+                return 1;
             }
         }
     }
+
+    0
+}
+
+/// Run a script, then re-run it whenever the script or anything under the
+/// watched paths changes. Without a script, changed .lisp files under the
+/// watched paths are run instead. Runs until interrupted.
+fn run_watch(paths: &[String], script: Option<(&str, &[String])>, verbose: bool) {
+    use crate::watch::{ChangeKind, Snapshot};
+    use std::time::Duration;
+
+    let mut snapshots: Vec<Snapshot> = paths.iter().map(|p| Snapshot::take(Path::new(p), true)).collect();
+    if let Some((script_file, _)) = script {
+        snapshots.push(Snapshot::take(Path::new(script_file), false));
+    }
+
+    let run = |files: &[String]| {
+        for file in files {
+            let code = match script {
+                Some((script_file, script_args)) => run_script_in_child(script_file, script_args, verbose),
+                None => run_script_in_child(file, &[], verbose),
+            };
+            if code != 0 {
+                eprintln!("[{} exited with status {}]", file, code);
+            }
+        }
+    };
+
+    let initial: Vec<String> = match script {
+        Some((script_file, _)) => vec![script_file.to_string()],
+        None => paths.iter().filter(|p| p.ends_with(".lisp") && Path::new(p).is_file()).cloned().collect(),
+    };
+    run(&initial);
+    println!("Watching {} for changes (Ctrl-C to stop)...", paths.join(", "));
+
+    loop {
+        std::thread::sleep(Duration::from_millis(300));
+        let mut changes: Vec<_> = snapshots.iter_mut().flat_map(|s| s.changes()).collect();
+        if changes.is_empty() {
+            continue;
+        }
+        // Editors often save in several steps; let them finish first
+        std::thread::sleep(Duration::from_millis(100));
+        changes.extend(snapshots.iter_mut().flat_map(|s| s.changes()));
+
+        let mut changed: Vec<String> = changes.iter().map(|c| c.path.display().to_string()).collect();
+        changed.sort();
+        changed.dedup();
+        println!("\n--- Changed: {} ---", changed.join(", "));
+
+        let files = match script {
+            Some((script_file, _)) => vec![script_file.to_string()],
+            None => changed
+                .into_iter()
+                .filter(|p| p.ends_with(".lisp"))
+                .filter(|p| changes.iter().any(|c| c.path.display().to_string() == *p && c.kind != ChangeKind::Deleted))
+                .collect(),
+        };
+        run(&files);
+    }
+}
+
+/// Run a script in a fresh cortado process and return its exit status, so
+/// a script that calls `exit` ends only its own run and not the watch.
+fn run_script_in_child(filename: &str, script_args: &[String], verbose: bool) -> i32 {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Error: Couldn't find the cortado executable: {}", e);
+            return 1;
+        }
+    };
+    let mut command = std::process::Command::new(exe);
+    if verbose {
+        command.arg("--verbose");
+    }
+    match command.arg(filename).args(script_args).status() {
+        Ok(status) => process::exit_code(status),
+        Err(e) => {
+            eprintln!("Error: Couldn't run '{}': {}", filename, e);
+            1
+        }
+    }
 }
 
 fn run_examples() {
@@ -605,26 +356,24 @@ fn run_examples() {
     let mut example_files = Vec::new();
     
     if let Ok(entries) = std::fs::read_dir(examples_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.extension().map_or(false, |ext| ext == "lisp") {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        // Skip certain test files that aren't meant to be run directly
-                        // Also skip known slow examples for now
-                        if !name.starts_with("test-") && !name.contains("cortadorc") {
-                            // Skip problematic examples that are too slow
-                            let slow_examples = vec![
-                                "06-real-world-app.lisp",
-                                "07-advanced-topics.lisp", 
-                                "03-data-processing.lisp",
-                                "04-threading-macros.lisp"
-                            ];
-                            if !slow_examples.contains(&name) {
-                                example_files.push(name.to_string());
-                            } else {
-                                println!("Skipping slow example: {}", name);
-                            }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "lisp") {
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    // Skip certain test files that aren't meant to be run directly
+                    // Also skip known slow examples for now
+                    if !name.starts_with("test-") && !name.contains("cortadorc") {
+                        // Skip problematic examples that are too slow
+                        let slow_examples = [
+                            "06-real-world-app.lisp",
+                            "07-advanced-topics.lisp", 
+                            "03-data-processing.lisp",
+                            "04-threading-macros.lisp"
+                        ];
+                        if !slow_examples.contains(&name) {
+                            example_files.push(name.to_string());
+                        } else {
+                            println!("Skipping slow example: {}", name);
                         }
                     }
                 }
@@ -684,8 +433,9 @@ fn run_examples() {
     }
 }
 
-fn run_eval_expression(expr: &str, verbose: bool) {
+fn run_eval_expression(expr: &str, expr_args: &[String], verbose: bool) {
     let mut env = create_default_env();
+    set_command_line_args(&mut env, expr_args);
     
     match read(expr) {
         Ok(parsed) => {
@@ -708,30 +458,81 @@ fn run_eval_expression(expr: &str, verbose: bool) {
     }
 }
 
+/// Serve nREPL clients until the process is killed.
+fn run_nrepl(port: u16) {
+    let (port, server) = match nrepl::start(port) {
+        Ok(started) => started,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // Editors find the server through .nrepl-port in the project directory
+    if let Err(e) = std::fs::write(".nrepl-port", port.to_string()) {
+        eprintln!("Warning: couldn't write .nrepl-port: {}", e);
+    }
+    println!("nREPL server started on port {} on host 127.0.0.1 - nrepl://127.0.0.1:{}", port, port);
+    let _ = server.join();
+}
+
 fn print_usage() {
     println!("Cortado - A Lisp-like programming language");
     println!();
     println!("USAGE:");
-    println!("    cortado [OPTIONS] [SCRIPT]");
+    println!("    cortado [OPTIONS] [SCRIPT] [ARGS...]");
     println!();
     println!("ARGS:");
     println!("    <SCRIPT>    Script file to execute (.lisp)");
+    println!("    <ARGS>      Arguments for the script, bound to *command-line-args*");
+    println!("                and passed to -main if the script defines it");
     println!();
     println!("OPTIONS:");
     println!("    -e, --eval <EXPR>    Evaluate expression and exit");
     println!("    -v, --verbose        Show evaluation results in script mode");
+    println!("    -w, --watch <PATH>   Re-run the script when PATH or the script changes;");
+    println!("                         without a script, re-run changed .lisp files");
+    println!("    --profile            Time the script's function calls, print a table of");
+    println!("                         them to stderr and write profile.folded for");
+    println!("                         flamegraph tools");
+    println!("    --profile-file <PATH>");
+    println!("                         Write the folded stacks to PATH instead");
+    println!("    --nrepl [PORT]       Start an nREPL server on localhost (any free port");
+    println!("                         by default) and write its port to .nrepl-port");
     println!("    -h, --help          Show this help message");
     println!();
     println!("COMMANDS:");
     println!("    demo                Run language demo");
-    println!("    test                Run comprehensive test suite");
+    println!("    test [PATHS]        Run the test files under PATHS (default: test/)");
+    println!("      --filter <NAME>   Only run deftests whose name contains NAME");
+    println!("      --jobs <N>        Run N files at a time (default: one per CPU)");
+    println!("      --format <FMT>    Report as text, junit, tap or json (default: text)");
+    println!("      --timeout <SECS>  Fail a test or file load that runs longer (default: 30)");
+    println!("      --coverage        Summarize which branches and functions of the files");
+    println!("                        the tests load ran, and write coverage/lcov.info");
+    println!("      --coverage-file <PATH>");
+    println!("                        Write the coverage report to PATH instead");
+    println!("    lsp                 Run a language server over stdin and stdout");
     println!("    examples            Run all example programs");
     println!();
     println!("EXAMPLES:");
     println!("    cortado                     # Start REPL");
     println!("    cortado script.lisp         # Run script");
     println!("    cortado -v script.lisp      # Run script with verbose output");
+    println!("    cortado script.lisp a b     # Run script with arguments");
+    println!("    cortado --watch test/       # Re-run test files as they change");
     println!("    cortado -e '(+ 1 2 3)'      # Evaluate expression");
+    println!("    cortado --nrepl 7888        # Serve nREPL clients on port 7888");
+    println!("    cortado --profile script.lisp");
+    println!("    cortado test --format junit > results.xml");
+}
+
+/// Exit with an error if --profile was given for something other than a
+/// script, which is all it profiles.
+fn reject_profile(profile_path: &Option<PathBuf>, mode: &str) {
+    if profile_path.is_some() {
+        eprintln!("Error: --profile only works when running a script, not with {}", mode);
+        std::process::exit(1);
+    }
 }
 
 fn main() {
@@ -739,13 +540,16 @@ fn main() {
     
     if args.len() == 1 {
         // No arguments - start REPL
-        repl();
+        repl::repl();
         return;
     }
 
     let mut i = 1;
     let mut verbose = false;
     let mut eval_expr: Option<String> = None;
+    let mut eval_args: &[String] = &[];
+    let mut watch_paths: Vec<String> = Vec::new();
+    let mut profile_path: Option<PathBuf> = None;
     
     // Parse command line arguments
     while i < args.len() {
@@ -758,23 +562,80 @@ fn main() {
                 verbose = true;
                 i += 1;
             }
+            "--profile" => {
+                profile_path.get_or_insert_with(|| PathBuf::from("profile.folded"));
+                i += 1;
+            }
+            "--profile-file" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --profile-file requires a path");
+                    std::process::exit(1);
+                }
+                profile_path = Some(PathBuf::from(&args[i + 1]));
+                i += 2;
+            }
             "-e" | "--eval" => {
                 if i + 1 >= args.len() {
                     eprintln!("Error: --eval requires an expression");
                     std::process::exit(1);
                 }
                 eval_expr = Some(args[i + 1].clone());
+                // Anything after the expression is passed to it as arguments
+                eval_args = &args[i + 2..];
+                break;
+            }
+            "-w" | "--watch" => {
+                if i + 1 >= args.len() {
+                    eprintln!("Error: --watch requires a path");
+                    std::process::exit(1);
+                }
+                watch_paths.push(args[i + 1].clone());
                 i += 2;
             }
+            "--nrepl" => {
+                // The port is optional; without one any free port is used
+                let port = match args.get(i + 1).map(|arg| arg.parse::<u16>()) {
+                    Some(Ok(port)) => port,
+                    Some(Err(_)) if !args[i + 1].starts_with('-') => {
+                        eprintln!("Error: --nrepl port must be a number, got '{}'", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                    _ => 0,
+                };
+                reject_profile(&profile_path, "--nrepl");
+                run_nrepl(port);
+                return;
+            }
+            "lsp" => {
+                // Speaks LSP on stdin and stdout, so nothing else may print
+                reject_profile(&profile_path, "lsp");
+                let code = lsp::serve(std::io::stdin().lock(), std::io::stdout().lock());
+                std::process::exit(code);
+            }
             "demo" => {
+                reject_profile(&profile_path, "demo");
                 run_demo();
                 return;
             }
             "test" => {
-                run_tests();
-                return;
+                // Everything after `test` belongs to the test runner
+                reject_profile(&profile_path, "test");
+                let options = match test_runner::Options::parse(&args[i + 1..]) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                if options.worker {
+                    std::process::exit(test_runner::run_worker(&options));
+                }
+                let code = test_runner::run(options);
+                cleanup_test_files();
+                std::process::exit(code);
             }
             "examples" => {
+                reject_profile(&profile_path, "examples");
                 run_examples();
                 return;
             }
@@ -784,16 +645,32 @@ fn main() {
                 std::process::exit(1);
             }
             script_file => {
-                // Treat as script file
-                run_script(script_file, verbose);
-                return;
+                // Treat as script file; the rest of the arguments belong to it
+                if !watch_paths.is_empty() {
+                    reject_profile(&profile_path, "--watch");
+                    run_watch(&watch_paths, Some((script_file, &args[i + 1..])), verbose);
+                    return;
+                }
+                if let Some(path) = profile_path {
+                    profile::start(script_file, Some(path));
+                }
+                let code = run_script(script_file, &args[i + 1..], verbose);
+                profile::report_script();
+                std::process::exit(code);
             }
         }
     }
     
     // Handle --eval option
-    if let Some(expr) = eval_expr {
-        run_eval_expression(&expr, verbose);
+    if !watch_paths.is_empty() {
+        reject_profile(&profile_path, "--watch");
+        run_watch(&watch_paths, None, verbose);
+    } else if let Some(expr) = eval_expr {
+        reject_profile(&profile_path, "--eval");
+        run_eval_expression(&expr, eval_args, verbose);
+    } else if profile_path.is_some() {
+        eprintln!("Error: --profile requires a script file");
+        std::process::exit(1);
     } else {
         // No script file provided
         print_usage();
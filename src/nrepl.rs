@@ -0,0 +1,592 @@
+use crate::edn;
+use crate::env::Env;
+use crate::eval::{
+    capture_output, clear_error_trace, create_default_env, definition, eval, record_error, set_interrupt_flag,
+    take_captured_output, SPECIAL_FORMS,
+};
+use crate::reader;
+use crate::value::{Function, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+// === BENCODE ===
+
+/// A bencoded value, the wire format of nREPL messages. Kept apart from
+/// `Value` so messages can be handed between threads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<String, Bencode>),
+}
+
+pub type Message = BTreeMap<String, Bencode>;
+
+impl Bencode {
+    pub fn str(s: &str) -> Bencode {
+        Bencode::Bytes(s.as_bytes().to_vec())
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Bencode::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(n) => out.extend_from_slice(format!("i{}e", n).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            }
+            Bencode::List(items) => {
+                out.push(b'l');
+                for item in items {
+                    item.encode(out);
+                }
+                out.push(b'e');
+            }
+            Bencode::Dict(entries) => {
+                // BTreeMap keeps the keys sorted, as bencode requires
+                out.push(b'd');
+                for (key, value) in entries {
+                    Bencode::str(key).encode(out);
+                    value.encode(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+
+    /// Read one value, or None if the input ends cleanly before it starts.
+    pub fn decode<R: BufRead>(input: &mut R) -> Result<Option<Bencode>, String> {
+        match read_byte(input)? {
+            None => Ok(None),
+            Some(first) => decode_from(first, input).map(Some),
+        }
+    }
+
+    /// Convert a message map built in Lisp. Keywords and symbols are sent as
+    /// strings; numbers must be integers.
+    pub fn from_value(value: &Value) -> Result<Bencode, String> {
+        match value {
+            Value::Str(s) | Value::Keyword(s) | Value::Symbol(s) => Ok(Bencode::str(s)),
+            Value::Bytes(bytes) => Ok(Bencode::Bytes(bytes.clone())),
+            Value::Number(n) if n.fract() == 0.0 => Ok(Bencode::Int(*n as i64)),
+            Value::List(items) | Value::Vector(items) => {
+                items.iter().map(Bencode::from_value).collect::<Result<_, _>>().map(Bencode::List)
+            }
+            Value::Map(map) => map
+                .iter()
+                .map(|(key, value)| Bencode::from_value(value).map(|value| (key.clone(), value)))
+                .collect::<Result<_, _>>()
+                .map(Bencode::Dict),
+            other => Err(format!("Can't bencode {}", other)),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Bencode::Int(n) => Value::Number(*n as f64),
+            Bencode::Bytes(bytes) => Value::Str(String::from_utf8_lossy(bytes).into_owned()),
+            Bencode::List(items) => Value::Vector(items.iter().map(Bencode::to_value).collect()),
+            Bencode::Dict(entries) => {
+                Value::Map(entries.iter().map(|(key, value)| (key.clone(), value.to_value())).collect::<HashMap<_, _>>())
+            }
+        }
+    }
+}
+
+fn read_byte<R: BufRead>(input: &mut R) -> Result<Option<u8>, String> {
+    let mut byte = [0u8];
+    match input.read(&mut byte) {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(byte[0])),
+        Err(e) => Err(format!("bencode read failed: {}", e)),
+    }
+}
+
+fn expect_byte<R: BufRead>(input: &mut R) -> Result<u8, String> {
+    read_byte(input)?.ok_or_else(|| "Unexpected end of bencode input".to_string())
+}
+
+/// Read digits up to `end`, with `first` already consumed.
+fn read_integer<R: BufRead>(first: u8, end: u8, input: &mut R) -> Result<i64, String> {
+    let mut digits = String::new();
+    let mut byte = first;
+    while byte != end {
+        if !(byte.is_ascii_digit() || (byte == b'-' && digits.is_empty())) {
+            return Err(format!("Invalid bencode integer byte '{}'", byte as char));
+        }
+        digits.push(byte as char);
+        byte = expect_byte(input)?;
+    }
+    digits.parse().map_err(|_| format!("Invalid bencode integer '{}'", digits))
+}
+
+fn decode_from<R: BufRead>(first: u8, input: &mut R) -> Result<Bencode, String> {
+    match first {
+        b'i' => {
+            let first = expect_byte(input)?;
+            read_integer(first, b'e', input).map(Bencode::Int)
+        }
+        b'l' => {
+            let mut items = Vec::new();
+            loop {
+                match expect_byte(input)? {
+                    b'e' => return Ok(Bencode::List(items)),
+                    byte => items.push(decode_from(byte, input)?),
+                }
+            }
+        }
+        b'd' => {
+            let mut entries = BTreeMap::new();
+            loop {
+                let key = match expect_byte(input)? {
+                    b'e' => return Ok(Bencode::Dict(entries)),
+                    byte => decode_from(byte, input)?,
+                };
+                let key = key.as_str().ok_or("bencode dictionary keys must be strings")?.to_string();
+                let byte = expect_byte(input)?;
+                entries.insert(key, decode_from(byte, input)?);
+            }
+        }
+        b'0'..=b'9' => {
+            let len = read_integer(first, b':', input)? as usize;
+            let mut bytes = vec![0u8; len];
+            input.read_exact(&mut bytes).map_err(|_| "Unexpected end of bencode input".to_string())?;
+            Ok(Bencode::Bytes(bytes))
+        }
+        other => Err(format!("Invalid bencode byte '{}'", other as char)),
+    }
+}
+
+// === SERVER ===
+
+const OPS: &[&str] =
+    &["clone", "close", "complete", "describe", "eval", "info", "interrupt", "load-file", "lookup", "ls-sessions"];
+
+/// The write half of a connection, shared by every session answering on it.
+#[derive(Clone)]
+struct Transport(Arc<Mutex<TcpStream>>);
+
+/// Sends responses to one request, tagged with its id and session.
+struct Reply {
+    transport: Transport,
+    id: Option<Bencode>,
+    session: Option<String>,
+}
+
+impl Reply {
+    fn send(&self, fields: Vec<(&str, Bencode)>) {
+        let mut message: Message = fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect();
+        if let Some(id) = &self.id {
+            message.insert("id".to_string(), id.clone());
+        }
+        if let Some(session) = &self.session {
+            message.insert("session".to_string(), Bencode::str(session));
+        }
+        let mut bytes = Vec::new();
+        Bencode::Dict(message).encode(&mut bytes);
+        if let Ok(mut stream) = self.transport.0.lock() {
+            // A client that went away just misses its responses
+            let _ = stream.write_all(&bytes);
+        }
+    }
+
+    /// Send `statuses` followed by "done", ending the responses to this request.
+    fn done(&self, statuses: &[&str], mut fields: Vec<(&str, Bencode)>) {
+        let status = statuses.iter().chain(["done"].iter()).map(|s| Bencode::str(s)).collect();
+        fields.push(("status", Bencode::List(status)));
+        self.send(fields);
+    }
+}
+
+struct Session {
+    requests: Sender<(Message, Reply)>,
+    interrupt: Arc<AtomicBool>,
+    /// The id of the eval in progress, if any
+    running: Arc<Mutex<Option<Bencode>>>,
+}
+
+fn sessions() -> &'static Mutex<HashMap<String, Session>> {
+    static SESSIONS: OnceLock<Mutex<HashMap<String, Session>>> = OnceLock::new();
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn new_session_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut id = 0u128;
+    for _ in 0..2 {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        id = (id << 64) | hasher.finish() as u128;
+    }
+    // Mark it as a random (version 4) UUID
+    id = (id & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+    edn::format_uuid(id)
+}
+
+/// Start a session with its own environment on its own thread, since an
+/// `Env` can't be shared between threads.
+fn spawn_session() -> String {
+    let id = new_session_id();
+    let (requests, received) = mpsc::channel::<(Message, Reply)>();
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let running = Arc::new(Mutex::new(None));
+    let session = Session { requests, interrupt: interrupt.clone(), running: running.clone() };
+    sessions().lock().unwrap().insert(id.clone(), session);
+
+    std::thread::spawn(move || {
+        let mut env = create_default_env();
+        capture_output();
+        set_interrupt_flag(interrupt.clone());
+        // Ends once the session is closed and its sender dropped
+        for (message, reply) in received {
+            match op_of(&message) {
+                "eval" | "load-file" => {
+                    interrupt.store(false, Ordering::SeqCst);
+                    *running.lock().unwrap() = reply.id.clone().or(Some(Bencode::str("")));
+                    eval_op(&message, &reply, &mut env, &interrupt);
+                    *running.lock().unwrap() = None;
+                }
+                "complete" => complete_op(&message, &reply, &env),
+                "info" => info_op(&message, &reply, &env, false),
+                "lookup" => info_op(&message, &reply, &env, true),
+                _ => reply.done(&["error", "unknown-op"], vec![]),
+            }
+        }
+    });
+    id
+}
+
+fn op_of(message: &Message) -> &str {
+    message.get("op").and_then(Bencode::as_str).unwrap_or("")
+}
+
+fn string_field<'a>(message: &'a Message, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|key| message.get(*key).and_then(Bencode::as_str))
+}
+
+/// Bind to `port` on localhost, 0 picking a free one, and accept connections
+/// on a background thread. Returns the port actually bound.
+pub fn start(port: u16) -> Result<(u16, std::thread::JoinHandle<()>), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("nREPL server couldn't bind port {}: {}", port, e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let handle = std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || handle_connection(stream));
+        }
+    });
+    Ok((port, handle))
+}
+
+fn handle_connection(stream: TcpStream) {
+    let transport = match stream.try_clone() {
+        Ok(writer) => Transport(Arc::new(Mutex::new(writer))),
+        Err(_) => return,
+    };
+    let mut reader = BufReader::new(stream);
+    // Requests that name no session share one belonging to the connection
+    let mut own_session: Option<String> = None;
+    while let Ok(Some(request)) = Bencode::decode(&mut reader) {
+        if let Bencode::Dict(message) = request {
+            handle_message(message, &transport, &mut own_session);
+        }
+    }
+    if let Some(id) = own_session {
+        sessions().lock().unwrap().remove(&id);
+    }
+}
+
+fn handle_message(message: Message, transport: &Transport, own_session: &mut Option<String>) {
+    let session = string_field(&message, &["session"]).map(str::to_string);
+    let mut reply = Reply { transport: transport.clone(), id: message.get("id").cloned(), session: session.clone() };
+    match op_of(&message) {
+        "describe" => {
+            let ops = OPS.iter().map(|op| (op.to_string(), Bencode::Dict(BTreeMap::new()))).collect();
+            let version = BTreeMap::from([("version-string".to_string(), Bencode::str(env!("CARGO_PKG_VERSION")))]);
+            let versions = BTreeMap::from([("cortado".to_string(), Bencode::Dict(version))]);
+            reply.done(&[], vec![("ops", Bencode::Dict(ops)), ("versions", Bencode::Dict(versions))]);
+        }
+        "clone" => {
+            // Environments can't be copied between threads, so a clone starts fresh
+            let id = spawn_session();
+            reply.done(&[], vec![("new-session", Bencode::str(&id))]);
+        }
+        "close" => match session.and_then(|id| sessions().lock().unwrap().remove(&id)) {
+            Some(closed) => {
+                closed.interrupt.store(true, Ordering::SeqCst);
+                reply.done(&["session-closed"], vec![]);
+            }
+            None => reply.done(&["error", "unknown-session"], vec![]),
+        },
+        "ls-sessions" => {
+            let mut ids: Vec<String> = sessions().lock().unwrap().keys().cloned().collect();
+            ids.sort();
+            reply.done(&[], vec![("sessions", Bencode::List(ids.iter().map(|id| Bencode::str(id)).collect()))]);
+        }
+        "interrupt" => {
+            let sessions = sessions().lock().unwrap();
+            let target = match session.as_ref().or(own_session.as_ref()).and_then(|id| sessions.get(id)) {
+                Some(target) => target,
+                None => return reply.done(&["error", "unknown-session"], vec![]),
+            };
+            let running = target.running.lock().unwrap().clone();
+            match (running, message.get("interrupt-id")) {
+                (None, _) => reply.done(&["session-idle"], vec![]),
+                (Some(running), Some(wanted)) if &running != wanted => reply.done(&["interrupt-id-mismatch"], vec![]),
+                (Some(_), _) => {
+                    target.interrupt.store(true, Ordering::SeqCst);
+                    reply.done(&[], vec![]);
+                }
+            }
+        }
+        "eval" | "load-file" | "complete" | "info" | "lookup" => {
+            let id = match session {
+                Some(id) => id,
+                None => own_session.get_or_insert_with(spawn_session).clone(),
+            };
+            reply.session = Some(id.clone());
+            let sessions = sessions().lock().unwrap();
+            match sessions.get(&id) {
+                Some(target) => {
+                    let _ = target.requests.send((message, reply));
+                }
+                None => reply.done(&["error", "unknown-session"], vec![]),
+            }
+        }
+        _ => reply.done(&["error", "unknown-op"], vec![]),
+    }
+}
+
+// === SESSION OPS ===
+
+fn send_output(reply: &Reply) {
+    let out = take_captured_output();
+    if !out.is_empty() {
+        reply.send(vec![("out", Bencode::str(&out))]);
+    }
+}
+
+/// Evaluate `code`, or a `load-file` request's `file`, one form at a time,
+/// sending output and each value as it goes.
+fn eval_op(message: &Message, reply: &Reply, env: &mut Env, interrupt: &AtomicBool) {
+    let code = match string_field(message, &["code", "file"]) {
+        Some(code) => code,
+        None => return reply.done(&["error", "no-code"], vec![]),
+    };
+    if let Some(ns) = string_field(message, &["ns"]) {
+        env.set_namespace(ns.to_string());
+    }
+    clear_error_trace();
+    let forms = match reader::read_all_forms(code) {
+        Ok(forms) => forms,
+        Err(e) => return report_error(reply, &format!("Parse error: {}", e)),
+    };
+    for form in forms {
+        let result = eval(&form, env);
+        send_output(reply);
+        match result {
+            Ok(value) => reply.send(vec![
+                ("value", Bencode::str(&edn::pr_str(&value))),
+                ("ns", Bencode::str(env.get_namespace())),
+            ]),
+            Err(_) if interrupt.load(Ordering::SeqCst) => return reply.done(&["interrupted"], vec![]),
+            Err(e) => {
+                record_error(&e);
+                return report_error(reply, &e);
+            }
+        }
+    }
+    reply.done(&[], vec![]);
+}
+
+fn report_error(reply: &Reply, message: &str) {
+    reply.send(vec![("err", Bencode::str(&format!("Error: {}\n", message)))]);
+    reply.done(&["eval-error"], vec![("ex", Bencode::str(message))]);
+}
+
+fn kind_of(value: &Value) -> &'static str {
+    match value {
+        Value::Function(Function::Macro { .. }) => "macro",
+        Value::Function(_) => "function",
+        _ => "var",
+    }
+}
+
+fn complete_op(message: &Message, reply: &Reply, env: &Env) {
+    let prefix = string_field(message, &["prefix", "symbol"]).unwrap_or("");
+    let mut completions: Vec<(String, &str)> = env
+        .completion_names()
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .filter_map(|name| env.resolve(&name).map(|(_, value)| (name, kind_of(&value))))
+        .collect();
+    completions.extend(
+        SPECIAL_FORMS.iter().filter(|name| name.starts_with(prefix)).map(|name| (name.to_string(), "special-form")),
+    );
+    completions.sort();
+    completions.dedup_by(|a, b| a.0 == b.0);
+    let candidates = completions
+        .into_iter()
+        .map(|(name, kind)| {
+            Bencode::Dict(BTreeMap::from([
+                ("candidate".to_string(), Bencode::str(&name)),
+                ("type".to_string(), Bencode::str(kind)),
+            ]))
+        })
+        .collect();
+    reply.done(&[], vec![("completions", Bencode::List(candidates))]);
+}
+
+/// `info` answers with the fields themselves, `lookup` nests them under "info".
+fn info_op(message: &Message, reply: &Reply, env: &Env, nested: bool) {
+    let sym = string_field(message, &["sym", "symbol"]).unwrap_or("");
+    let mut info = BTreeMap::new();
+    if SPECIAL_FORMS.contains(&sym) {
+        info.insert("name".to_string(), Bencode::str(sym));
+        info.insert("special-form".to_string(), Bencode::str("true"));
+    } else if let Some((qualified, value)) = env.resolve(sym) {
+        let (ns, name) = qualified.rsplit_once('/').unwrap_or(("core", qualified.as_str()));
+        info.insert("name".to_string(), Bencode::str(name));
+        info.insert("ns".to_string(), Bencode::str(ns));
+        info.insert("type".to_string(), Bencode::str(kind_of(&value)));
+        if let Value::Function(Function::UserDefined { params, .. } | Function::Macro { params, .. }) = &value {
+            info.insert("arglists-str".to_string(), Bencode::str(&format!("([{}])", params.join(" "))));
+        }
+        if let Some(doc) = definition(&qualified).and_then(|def| def.doc) {
+            info.insert("doc".to_string(), Bencode::str(&doc));
+        }
+    } else {
+        return reply.done(&["no-info"], vec![]);
+    }
+    if nested {
+        reply.done(&[], vec![("info", Bencode::Dict(info))]);
+    } else {
+        let fields = info.iter().map(|(key, value)| (key.as_str(), value.clone())).collect();
+        reply.done(&[], fields);
+    }
+}
+
+// === CLIENT ===
+
+struct Client {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+    /// Responses read while waiting for a different request
+    pending: Vec<Message>,
+    next_id: u64,
+}
+
+thread_local! {
+    static CLIENTS: RefCell<BTreeMap<u64, Client>> = const { RefCell::new(BTreeMap::new()) };
+    static NEXT_CLIENT: RefCell<u64> = const { RefCell::new(1) };
+}
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn client_id(conn: &Value, fn_name: &str) -> Result<u64, String> {
+    match conn {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as u64),
+        other => Err(format!("{} requires an nREPL connection, got {}", fn_name, other)),
+    }
+}
+
+fn with_client<T>(conn: &Value, fn_name: &str, f: impl FnOnce(&mut Client) -> Result<T, String>) -> Result<T, String> {
+    let id = client_id(conn, fn_name)?;
+    CLIENTS.with(|clients| match clients.borrow_mut().get_mut(&id) {
+        Some(client) => f(client),
+        None => Err(format!("{}: no open nREPL connection {}", fn_name, id)),
+    })
+}
+
+/// Connect to an nREPL server on localhost and return the connection id.
+pub fn connect(port: &Value, fn_name: &str) -> Result<Value, String> {
+    let port = match port {
+        Value::Number(n) if *n > 0.0 && *n <= u16::MAX as f64 && n.fract() == 0.0 => *n as u16,
+        other => return Err(format!("{} requires a port number, got {}", fn_name, other)),
+    };
+    let stream = TcpStream::connect(("127.0.0.1", port)).map_err(|e| format!("{}: {}", fn_name, e))?;
+    stream.set_read_timeout(Some(RESPONSE_TIMEOUT)).map_err(|e| format!("{}: {}", fn_name, e))?;
+    let reader = BufReader::new(stream.try_clone().map_err(|e| format!("{}: {}", fn_name, e))?);
+    let id = NEXT_CLIENT.with(|next| {
+        let mut next = next.borrow_mut();
+        *next += 1;
+        *next - 1
+    });
+    let client = Client { writer: stream, reader, pending: Vec::new(), next_id: 1 };
+    CLIENTS.with(|clients| clients.borrow_mut().insert(id, client));
+    Ok(Value::Number(id as f64))
+}
+
+/// Send a message, giving it an id if it has none, and return the id.
+pub fn send(conn: &Value, message: &Value, fn_name: &str) -> Result<Value, String> {
+    let mut message = match Bencode::from_value(message).map_err(|e| format!("{}: {}", fn_name, e))? {
+        Bencode::Dict(message) => message,
+        _ => return Err(format!("{} requires a message map", fn_name)),
+    };
+    with_client(conn, fn_name, |client| {
+        if !message.contains_key("id") {
+            message.insert("id".to_string(), Bencode::str(&client.next_id.to_string()));
+            client.next_id += 1;
+        }
+        let id = message["id"].to_value();
+        let mut bytes = Vec::new();
+        Bencode::Dict(message).encode(&mut bytes);
+        client.writer.write_all(&bytes).map_err(|e| format!("{}: {}", fn_name, e))?;
+        Ok(id)
+    })
+}
+
+fn is_done(message: &Message) -> bool {
+    matches!(message.get("status"), Some(Bencode::List(statuses)) if statuses.contains(&Bencode::str("done")))
+}
+
+/// Collect the responses to request `id` up to and including the one whose
+/// status says it's done.
+pub fn responses(conn: &Value, id: &Value, fn_name: &str) -> Result<Value, String> {
+    let id = Bencode::from_value(id).map_err(|e| format!("{}: {}", fn_name, e))?;
+    with_client(conn, fn_name, |client| {
+        let mut collected = Vec::new();
+        loop {
+            let position = client.pending.iter().position(|message| message.get("id") == Some(&id));
+            let message = match position {
+                Some(position) => client.pending.remove(position),
+                None => match Bencode::decode(&mut client.reader) {
+                    Ok(Some(Bencode::Dict(message))) => {
+                        if message.get("id") != Some(&id) {
+                            client.pending.push(message);
+                            continue;
+                        }
+                        message
+                    }
+                    Ok(Some(_)) => continue,
+                    Ok(None) => return Err(format!("{}: connection closed", fn_name)),
+                    Err(e) => return Err(format!("{}: {}", fn_name, e)),
+                },
+            };
+            let done = is_done(&message);
+            collected.push(Bencode::Dict(message).to_value());
+            if done {
+                return Ok(Value::Vector(collected));
+            }
+        }
+    })
+}
+
+pub fn close(conn: &Value, fn_name: &str) -> Result<Value, String> {
+    let id = client_id(conn, fn_name)?;
+    let removed = CLIENTS.with(|clients| clients.borrow_mut().remove(&id));
+    Ok(Value::Bool(removed.is_some()))
+}
@@ -0,0 +1,320 @@
+use crate::eval::str_of;
+use crate::lazy::{is_seqable, seq_iter};
+use crate::value::{with_open, IOResource, Value};
+use std::collections::HashMap;
+use std::io::{BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+enum Input {
+    Text(String),
+    Stream(Box<dyn Read + Send>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Redirect {
+    Pipe,
+    Inherit,
+}
+
+/// Options accepted by `sh` and `proc/start`.
+pub struct ProcessOptions {
+    dir: Option<String>,
+    env: Vec<(String, String)>,
+    clear_env: bool,
+    input: Option<Input>,
+    out: Redirect,
+    err: Redirect,
+    timeout: Option<Duration>,
+    check: bool,
+}
+
+/// Split call arguments into the command line and an optional trailing
+/// options map. The command is either the leading string arguments or a
+/// single vector: `(sh "ls" "-l")` or `(sh ["ls" "-l"] {:dir "/tmp"})`.
+pub fn command_args<'a>(args: &'a [Value], fn_name: &str) -> Result<(Vec<String>, Option<&'a Value>), String> {
+    let (command, opts) = match args.split_last() {
+        Some((Value::Map(_), command)) => (command, args.last()),
+        _ => (args, None),
+    };
+    let argv: Vec<String> = match command {
+        [list] if is_seqable(list) => seq_iter(list, fn_name)?.map(|arg| arg.map(|a| str_of(&a))).collect::<Result<_, _>>()?,
+        _ => command
+            .iter()
+            .map(|arg| match arg {
+                Value::Str(s) => Ok(s.clone()),
+                other => Err(format!("{} arguments must be strings, got {}", fn_name, other)),
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    if argv.is_empty() {
+        return Err(format!("{} requires a command", fn_name));
+    }
+    Ok((argv, opts))
+}
+
+pub fn options(opts: Option<&Value>, fn_name: &str) -> Result<ProcessOptions, String> {
+    let mut options = ProcessOptions {
+        dir: None,
+        env: Vec::new(),
+        clear_env: false,
+        input: None,
+        out: Redirect::Pipe,
+        err: Redirect::Pipe,
+        timeout: None,
+        check: false,
+    };
+    let map = match opts {
+        None | Some(Value::Nil) => return Ok(options),
+        Some(Value::Map(map)) => map,
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    };
+    for (key, value) in map {
+        match (key.as_str(), value) {
+            ("dir", Value::Str(dir)) => options.dir = Some(dir.clone()),
+            ("dir", _) => return Err(format!("{} option :dir must be a string", fn_name)),
+            ("env", Value::Map(vars)) => {
+                options.env = vars.iter().map(|(k, v)| (k.clone(), str_of(v))).collect();
+            }
+            ("env", _) => return Err(format!("{} option :env must be a map", fn_name)),
+            ("clear-env", Value::Bool(b)) => options.clear_env = *b,
+            ("check", Value::Bool(b)) => options.check = *b,
+            ("clear-env", _) | ("check", _) => return Err(format!("{} option :{} must be a boolean", fn_name, key)),
+            ("in", Value::Str(text)) => options.input = Some(Input::Text(text.clone())),
+            ("in", Value::IOResource(resource)) => options.input = Some(Input::Stream(take_input(resource, fn_name)?)),
+            ("in", Value::Nil) => options.input = None,
+            ("in", _) => return Err(format!("{} option :in must be a string, reader or input-stream", fn_name)),
+            ("out", _) => options.out = redirect(value, key, fn_name)?,
+            ("err", _) => options.err = redirect(value, key, fn_name)?,
+            ("timeout", Value::Number(ms)) if *ms >= 0.0 => options.timeout = Some(Duration::from_millis(*ms as u64)),
+            ("timeout", _) => return Err(format!("{} option :timeout must be a number of milliseconds", fn_name)),
+            _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+        }
+    }
+    Ok(options)
+}
+
+fn redirect(value: &Value, key: &str, fn_name: &str) -> Result<Redirect, String> {
+    match value {
+        Value::Keyword(k) if k == "pipe" => Ok(Redirect::Pipe),
+        Value::Keyword(k) if k == "inherit" => Ok(Redirect::Inherit),
+        _ => Err(format!("{} option :{} must be :pipe or :inherit", fn_name, key)),
+    }
+}
+
+// The process takes over a reader or input stream given as :in, so it's
+// closed for everyone else
+fn take_input(resource: &IOResource, fn_name: &str) -> Result<Box<dyn Read + Send>, String> {
+    let taken: Option<Box<dyn Read + Send>> = match resource {
+        IOResource::Reader(r) => r.lock().map_err(|e| format!("Failed to lock reader: {}", e))?.take().map(|r| Box::new(r) as _),
+        IOResource::InputStream(r) => r.lock().map_err(|e| format!("Failed to lock input-stream: {}", e))?.take(),
+        other => return Err(format!("{} option :in can't read from a {}", fn_name, other.kind())),
+    };
+    taken.ok_or_else(|| format!("{} option :in: Cannot use closed {}", fn_name, resource.kind()))
+}
+
+fn spawn(argv: &[String], opts: &mut ProcessOptions, stdin: Stdio, fn_name: &str) -> Result<Child, String> {
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]);
+    if let Some(dir) = &opts.dir {
+        command.current_dir(dir);
+    }
+    if opts.clear_env {
+        command.env_clear();
+    }
+    command.envs(opts.env.iter().map(|(k, v)| (k, v)));
+    let stdio = |redirect: Redirect| if redirect == Redirect::Pipe { Stdio::piped() } else { Stdio::inherit() };
+    command
+        .stdin(if opts.input.is_some() { Stdio::piped() } else { stdin })
+        .stdout(stdio(opts.out))
+        .stderr(stdio(opts.err));
+    // With a timeout the process leads its own group, so the whole group
+    // can be killed: a shell's children would otherwise keep running and
+    // hold our output pipes open
+    #[cfg(unix)]
+    if opts.timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command.spawn().map_err(|e| format!("{}: failed to run `{}`: {}", fn_name, argv[0], e))?;
+
+    // Feed :in from a thread so a process that fills its output pipe before
+    // reading all its input can't deadlock us
+    if let Some(input) = opts.input.take() {
+        let mut stdin = child.stdin.take().expect("stdin is piped when :in is given");
+        std::thread::spawn(move || {
+            let _ = match input {
+                Input::Text(text) => stdin.write_all(text.as_bytes()),
+                Input::Stream(mut source) => std::io::copy(&mut source, &mut stdin).map(|_| ()),
+            };
+        });
+    }
+    Ok(child)
+}
+
+/// A process's exit status, or 128 plus the signal that killed it.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            // Shell convention for a process killed by a signal
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+// Wait for `child`, or kill it once `timeout` passes and return None
+fn wait_timeout(child: &mut Child, timeout: Option<Duration>, fn_name: &str) -> Result<Option<ExitStatus>, String> {
+    let wait_error = |e: std::io::Error| format!("{}: failed to wait for process: {}", fn_name, e);
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait().map(Some).map_err(wait_error),
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().map_err(wait_error)? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill_group(child);
+            child.wait().map_err(wait_error)?;
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+// Kill `child` and, if it leads a process group, everything in the group
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: kill has no memory safety requirements; a child that doesn't
+    // lead a group makes it fail with ESRCH, which is fine to ignore
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+fn read_to_string_in_background(source: Option<impl Read + Send + 'static>) -> Option<JoinHandle<String>> {
+    source.map(|mut source| {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = source.read_to_end(&mut bytes);
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+    })
+}
+
+/// Run a command to completion, returning `{:exit :out :err}`.
+pub fn sh(argv: &[String], mut opts: ProcessOptions, fn_name: &str) -> Result<Value, String> {
+    let mut child = spawn(argv, &mut opts, Stdio::null(), fn_name)?;
+    let out = read_to_string_in_background(child.stdout.take());
+    let err = read_to_string_in_background(child.stderr.take());
+    let status = wait_timeout(&mut child, opts.timeout, fn_name)?;
+    let collect = |handle: Option<JoinHandle<String>>| handle.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
+    let (out, err) = (collect(out), collect(err));
+
+    let status = match status {
+        Some(status) => status,
+        None => {
+            let ms = opts.timeout.map_or(0, |t| t.as_millis());
+            return Err(format!("{}: `{}` timed out after {} ms and was killed", fn_name, argv[0], ms));
+        }
+    };
+    let code = exit_code(status);
+    if opts.check && code != 0 {
+        return Err(format!("{}: `{}` exited with status {}: {}", fn_name, argv.join(" "), code, err.trim_end()));
+    }
+
+    let mut result = HashMap::new();
+    result.insert("exit".to_string(), Value::Number(code as f64));
+    result.insert("out".to_string(), Value::Str(out));
+    result.insert("err".to_string(), Value::Str(err));
+    Ok(Value::Map(result))
+}
+
+/// Start a command without waiting for it. The result map holds the
+/// process under :proc and its :pid, plus an :in writer (unless :in was
+/// given) and :out/:err readers for piped output. With :check it also
+/// holds `:check true`, which makes `proc/wait` fail on a non-zero exit.
+pub fn start(argv: &[String], mut opts: ProcessOptions, fn_name: &str) -> Result<Value, String> {
+    let mut child = spawn(argv, &mut opts, Stdio::piped(), fn_name)?;
+    let mut result = HashMap::new();
+    result.insert("pid".to_string(), Value::Number(child.id() as f64));
+    if opts.check {
+        result.insert("check".to_string(), Value::Bool(true));
+    }
+    if let Some(stdin) = child.stdin.take() {
+        result.insert("in".to_string(), Value::IOResource(IOResource::writer(stdin)));
+    }
+    if let Some(stdout) = child.stdout.take() {
+        result.insert("out".to_string(), Value::IOResource(IOResource::reader(BufReader::new(stdout))));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        result.insert("err".to_string(), Value::IOResource(IOResource::reader(BufReader::new(stderr))));
+    }
+    result.insert("proc".to_string(), Value::IOResource(IOResource::process(child)));
+    Ok(Value::Map(result))
+}
+
+/// The process resource of a `proc/start` result (or the resource itself).
+pub fn process_arg<'a>(value: &'a Value, fn_name: &str) -> Result<&'a IOResource, String> {
+    let resource = match value {
+        Value::Map(map) => map.get("proc"),
+        other => Some(other),
+    };
+    match resource {
+        Some(Value::IOResource(resource @ IOResource::Process(_))) => Ok(resource),
+        _ => Err(format!("{} requires a process started with proc/start, got {}", fn_name, value)),
+    }
+}
+
+/// Wait for a started process, closing its :in writer first so it sees end
+/// of input. Returns the exit code, or an error after killing the process
+/// if `timeout` passes first or if it was started with :check and exits
+/// non-zero.
+pub fn wait(value: &Value, timeout: Option<Duration>, fn_name: &str) -> Result<Value, String> {
+    let resource = process_arg(value, fn_name)?;
+    let mut check = false;
+    if let Value::Map(map) = value {
+        if let Some(Value::IOResource(stdin @ IOResource::Writer(_))) = map.get("in") {
+            stdin.close()?;
+        }
+        check = map.get("check") == Some(&Value::Bool(true));
+    }
+    let IOResource::Process(process) = resource else { unreachable!("process_arg returns a process") };
+    let status = with_open(process, "process", |child| wait_timeout(child, timeout, fn_name))?;
+    match status {
+        Some(status) if check && exit_code(status) != 0 => {
+            let pid = match value {
+                Value::Map(map) => map.get("pid").map_or(String::new(), |pid| format!(" {}", pid)),
+                _ => String::new(),
+            };
+            Err(format!("{}: process{} exited with status {}", fn_name, pid, exit_code(status)))
+        }
+        Some(status) => Ok(Value::Number(exit_code(status) as f64)),
+        None => Err(format!("{}: process timed out after {} ms and was killed", fn_name, timeout.map_or(0, |t| t.as_millis()))),
+    }
+}
+
+pub fn kill(value: &Value, fn_name: &str) -> Result<Value, String> {
+    let IOResource::Process(process) = process_arg(value, fn_name)? else { unreachable!("process_arg returns a process") };
+    with_open(process, "process", |child| {
+        // Killing a process that already exited is fine
+        if child.try_wait().map_err(|e| format!("{}: {}", fn_name, e))?.is_none() {
+            child.kill().map_err(|e| format!("{}: failed to kill process: {}", fn_name, e))?;
+        }
+        Ok(Value::Nil)
+    })
+}
+
+pub fn is_alive(value: &Value, fn_name: &str) -> Result<Value, String> {
+    let IOResource::Process(process) = process_arg(value, fn_name)? else { unreachable!("process_arg returns a process") };
+    with_open(process, "process", |child| {
+        let exited = child.try_wait().map_err(|e| format!("{}: {}", fn_name, e))?;
+        Ok(Value::Bool(exited.is_none()))
+    })
+}
@@ -0,0 +1,336 @@
+use crate::env::Env;
+use crate::eval::{eval, write_out};
+use crate::value::Value;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// The profiler behind `cortado --profile` and `(profile ...)`. Every call
+// made through `eval_call` or `call_function` pushes a frame, and popping it
+// adds the call's time and allocations to its function's totals. A timer
+// thread ticks every SAMPLE_INTERVAL, and at the next push or pop the ticks
+// are charged to the stack then running, giving the folded stacks that
+// flamegraph tools read.
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_ROWS: usize = 40;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+// Counting costs an atomic add per allocation, so it's off unless profiling
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+fn count_allocation() {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The system allocator, counting allocations so profiles can report them.
+/// Only a binary that installs it as its `#[global_allocator]` gets counts.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+fn allocations() -> u64 {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+struct Frame {
+    name: String,
+    start: Instant,
+    allocations: u64,
+    child_time: Duration,
+    child_allocations: u64,
+}
+
+#[derive(Default, Clone)]
+pub struct Stats {
+    pub calls: u64,
+    /// Time and allocations from entering the function to leaving it,
+    /// counted once for recursive calls
+    pub inclusive: Duration,
+    pub allocations: u64,
+    /// Leaving out the functions it called
+    pub exclusive: Duration,
+    pub exclusive_allocations: u64,
+}
+
+struct Profiler {
+    label: String,
+    folded: Option<PathBuf>,
+    start: Instant,
+    stack: Vec<Frame>,
+    /// How many frames of each function are on the stack
+    active: HashMap<String, usize>,
+    stats: HashMap<String, Stats>,
+    samples: HashMap<String, u64>,
+    ticks: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Profiler {
+    /// Charge the ticks since the last sample to the running stack.
+    fn sample(&mut self) {
+        let ticks = self.ticks.swap(0, Ordering::Relaxed);
+        if ticks > 0 {
+            let stack: Vec<&str> =
+                std::iter::once(self.label.as_str()).chain(self.stack.iter().map(|frame| frame.name.as_str())).collect();
+            *self.samples.entry(stack.join(";")).or_insert(0) += ticks;
+        }
+    }
+}
+
+/// What a finished profile recorded.
+pub struct Report {
+    pub label: String,
+    /// Where to write the folded stacks, if anywhere
+    pub folded_path: Option<PathBuf>,
+    pub elapsed: Duration,
+    pub stats: HashMap<String, Stats>,
+    pub samples: HashMap<String, u64>,
+}
+
+thread_local! {
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// Start profiling this thread's calls under `label`, the root of every
+/// folded stack. `folded` is where the report should write them.
+pub fn start(label: &str, folded: Option<PathBuf>) {
+    let ticks = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let (ticks, stop) = (ticks.clone(), stop.clone());
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(SAMPLE_INTERVAL);
+                ticks.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+    let profiler = Profiler {
+        label: label.replace(';', ":"),
+        folded,
+        start: Instant::now(),
+        stack: Vec::new(),
+        active: HashMap::new(),
+        stats: HashMap::new(),
+        samples: HashMap::new(),
+        ticks,
+        stop,
+    };
+    PROFILER.with(|p| *p.borrow_mut() = Some(profiler));
+    ACTIVE.with(|active| active.set(true));
+    COUNTING.store(true, Ordering::Relaxed);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.with(Cell::get)
+}
+
+/// Stop profiling and return what was recorded, if a profile was running.
+pub fn finish() -> Option<Report> {
+    ACTIVE.with(|active| active.set(false));
+    COUNTING.store(false, Ordering::Relaxed);
+    let mut profiler = PROFILER.with(|p| p.borrow_mut().take())?;
+    profiler.sample();
+    profiler.stop.store(true, Ordering::Relaxed);
+    Some(Report {
+        label: profiler.label,
+        folded_path: profiler.folded,
+        elapsed: profiler.start.elapsed(),
+        stats: profiler.stats,
+        samples: profiler.samples,
+    })
+}
+
+/// Pops its frame when dropped, so a call is counted however it returns.
+pub struct Guard(());
+
+/// Push a frame for a call to the function `name` gives, if profiling.
+pub fn enter(name: impl FnOnce() -> String) -> Option<Guard> {
+    if !is_active() {
+        return None;
+    }
+    let name = name();
+    PROFILER.with(|p| {
+        let mut p = p.borrow_mut();
+        let profiler = p.as_mut()?;
+        profiler.sample();
+        *profiler.active.entry(name.clone()).or_insert(0) += 1;
+        profiler.stack.push(Frame {
+            name,
+            start: Instant::now(),
+            allocations: allocations(),
+            child_time: Duration::ZERO,
+            child_allocations: 0,
+        });
+        Some(Guard(()))
+    })
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        PROFILER.with(|p| {
+            let mut p = p.borrow_mut();
+            let Some(profiler) = p.as_mut() else { return };
+            profiler.sample();
+            let Some(frame) = profiler.stack.pop() else { return };
+            let elapsed = frame.start.elapsed();
+            let allocated = allocations() - frame.allocations;
+            if let Some(parent) = profiler.stack.last_mut() {
+                parent.child_time += elapsed;
+                parent.child_allocations += allocated;
+            }
+            let active = profiler.active.entry(frame.name.clone()).or_insert(1);
+            *active -= 1;
+            let outermost = *active == 0;
+            let stats = profiler.stats.entry(frame.name).or_default();
+            stats.calls += 1;
+            stats.exclusive += elapsed.saturating_sub(frame.child_time);
+            stats.exclusive_allocations += allocated.saturating_sub(frame.child_allocations);
+            if outermost {
+                stats.inclusive += elapsed;
+                stats.allocations += allocated;
+            }
+        });
+    }
+}
+
+impl Report {
+    /// The functions by exclusive time, most first.
+    pub fn table(&self) -> String {
+        let mut functions: Vec<(&String, &Stats)> = self.stats.iter().collect();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        let calls: u64 = self.stats.values().map(|s| s.calls).sum();
+        let samples: u64 = self.samples.values().sum();
+
+        let ms = |d: Duration| format!("{:.3}", d.as_secs_f64() * 1000.0);
+        let mut rows = vec![["Function", "Calls", "Incl ms", "Excl ms", "Allocs", "Excl allocs"].map(String::from)];
+        for (name, stats) in functions.iter().take(MAX_ROWS) {
+            rows.push([
+                name.to_string(),
+                stats.calls.to_string(),
+                ms(stats.inclusive),
+                ms(stats.exclusive),
+                stats.allocations.to_string(),
+                stats.exclusive_allocations.to_string(),
+            ]);
+        }
+        let widths: Vec<usize> = (0..6).map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap_or(0)).collect();
+
+        let mut out = format!(
+            "{}: {} ms, {} calls, {} samples\n",
+            self.label,
+            ms(self.elapsed),
+            calls,
+            samples
+        );
+        for row in &rows {
+            // The name on the left, the numbers lined up on the right
+            let mut line = format!("  {:<width$}", row[0], width = widths[0]);
+            for (cell, width) in row.iter().zip(&widths).skip(1) {
+                line.push_str(&format!("  {:>width$}", cell, width = width));
+            }
+            out.push_str(&line);
+            out.push('\n');
+        }
+        if functions.len() > MAX_ROWS {
+            out.push_str(&format!("  ... and {} more\n", functions.len() - MAX_ROWS));
+        }
+        out
+    }
+
+    /// One `root;caller;callee count` line per sampled stack, the format
+    /// flamegraph.pl and inferno read.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &u64)> = self.samples.iter().collect();
+        stacks.sort();
+        stacks.iter().map(|(stack, count)| format!("{} {}\n", stack, count)).collect()
+    }
+
+    /// Write the folded stacks where the profile was asked to.
+    pub fn write_folded(&self) -> Result<(), String> {
+        match &self.folded_path {
+            Some(path) => std::fs::write(path, self.folded()).map_err(|e| format!("Couldn't write {}: {}", path.display(), e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Finish a `cortado --profile` run: print the table to stderr, away from
+/// the script's own output, and write the folded stacks. `exit` calls this
+/// too, so a script that exits early is still reported.
+pub fn report_script() {
+    if let Some(report) = finish() {
+        eprint!("\n{}", report.table());
+        match report.write_folded() {
+            Ok(()) => {
+                if let Some(path) = &report.folded_path {
+                    eprintln!("Wrote folded stacks to {}", path.display());
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+}
+
+/// (profile expr) or (profile {:folded "out.folded"} expr) evaluates expr,
+/// prints a table of the calls it made and returns its value. Inside a
+/// profile already running, it just evaluates expr.
+pub fn eval_profile(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let (opts, expr) = match list {
+        [_, expr] => (Value::Nil, expr),
+        [_, opts, expr] => (eval(opts, env)?, expr),
+        _ => return Err("profile requires an expression, optionally after an options map".to_string()),
+    };
+    let folded = match &opts {
+        Value::Nil => None,
+        Value::Map(map) => match map.get("folded") {
+            None | Some(Value::Nil) => None,
+            Some(Value::Str(path)) => Some(PathBuf::from(path)),
+            Some(other) => return Err(format!("profile :folded must be a file path, got {}", other)),
+        },
+        other => return Err(format!("profile options must be a map, got {}", other)),
+    };
+    if is_active() {
+        return eval(expr, env);
+    }
+
+    start("profile", folded);
+    let result = eval(expr, env);
+    let written = match finish() {
+        Some(report) => {
+            write_out(&report.table());
+            report.write_folded()
+        }
+        None => Ok(()),
+    };
+    // An error from the expression matters more than one writing the stacks
+    let value = result?;
+    written.map(|()| value)
+}
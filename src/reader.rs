@@ -1,3 +1,4 @@
+use crate::edn::{self, ReadOptions};
 use crate::value::Value;
 use std::collections::HashMap;
 
@@ -13,6 +14,8 @@ enum Token {
     Number(f64),
     Str(String),
     Keyword(String),
+    Regex(String),
+    Tag(String),
     Bool(bool),
     Nil,
     Quote,
@@ -20,287 +23,446 @@ enum Token {
     Unquote,
 }
 
-fn tokenize(input: &str) -> Result<Vec<Token>, String> {
-    let mut tokens = Vec::new();
+/// A position in source text. Lines and columns count from zero, and
+/// columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The text a token or form was read from, `end` being just past its last
+/// character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+/// Where a form was read from. `children` follows the items of a list or
+/// vector, and the keys and values of a map in the order they were written.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    pub span: Span,
+    pub children: Vec<SourceMap>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadError {
+    pub kind: ReadErrorKind,
+    pub message: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadErrorKind {
+    /// The input can't be read however it continues.
+    Invalid,
+    /// The input stops part-way through a form, e.g. an open list or string.
+    UnexpectedEof,
+}
+
+/// Tokenize, recording where each token came from. On an error the tokens
+/// before it are returned along with it.
+fn tokenize(input: &str) -> (Vec<Token>, Vec<Span>, Option<ReadError>) {
     let chars: Vec<char> = input.chars().collect();
+    let positions = char_positions(&chars);
+    let mut tokens = Vec::new();
+    let mut spans = Vec::new();
     let mut i = 0;
 
     while i < chars.len() {
-        match chars[i] {
-            ' ' | '\t' | '\n' | '\r' | ',' => {
-                i += 1;
-            }
-            '(' => {
-                tokens.push(Token::LeftParen);
-                i += 1;
-            }
-            ')' => {
-                tokens.push(Token::RightParen);
-                i += 1;
+        let start = i;
+        let count = tokens.len();
+        if let Err((kind, message)) = next_token(&chars, &mut i, &mut tokens) {
+            let end = i.clamp(start + 1, chars.len());
+            let span = Span { start: positions[start], end: positions[end] };
+            return (tokens, spans, Some(ReadError { kind, message, span }));
+        }
+        if tokens.len() > count {
+            spans.push(Span { start: positions[start], end: positions[i] });
+        }
+    }
+
+    (tokens, spans, None)
+}
+
+/// The position of every character, plus one for the end of the input.
+fn char_positions(chars: &[char]) -> Vec<Pos> {
+    let mut positions = Vec::with_capacity(chars.len() + 1);
+    let mut pos = Pos::default();
+    for c in chars {
+        positions.push(pos);
+        if *c == '\n' {
+            pos = Pos { line: pos.line + 1, column: 0 };
+        } else {
+            pos.column += 1;
+        }
+    }
+    positions.push(pos);
+    positions
+}
+
+/// Read what starts at `chars[*i]` and advance past it: a token, which is
+/// pushed, or whitespace or a comment, which is skipped.
+fn next_token(chars: &[char], i: &mut usize, tokens: &mut Vec<Token>) -> Result<(), (ReadErrorKind, String)> {
+    match chars[*i] {
+        ' ' | '\t' | '\n' | '\r' | ',' => {
+            *i += 1;
+        }
+        '(' => {
+            tokens.push(Token::LeftParen);
+            *i += 1;
+        }
+        ')' => {
+            tokens.push(Token::RightParen);
+            *i += 1;
+        }
+        '[' => {
+            tokens.push(Token::LeftBracket);
+            *i += 1;
+        }
+        ']' => {
+            tokens.push(Token::RightBracket);
+            *i += 1;
+        }
+        '{' => {
+            tokens.push(Token::LeftBrace);
+            *i += 1;
+        }
+        '}' => {
+            tokens.push(Token::RightBrace);
+            *i += 1;
+        }
+        '"' => {
+            *i += 1;
+            let mut string = String::new();
+            while *i < chars.len() && chars[*i] != '"' {
+                if chars[*i] == '\\' && *i + 1 < chars.len() {
+                    *i += 1;
+                    match chars[*i] {
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        '\\' => string.push('\\'),
+                        '"' => string.push('"'),
+                        _ => {
+                            string.push('\\');
+                            string.push(chars[*i]);
+                        }
+                    }
+                } else {
+                    string.push(chars[*i]);
+                }
+                *i += 1;
             }
-            '[' => {
-                tokens.push(Token::LeftBracket);
-                i += 1;
+            if *i >= chars.len() {
+                return Err((ReadErrorKind::UnexpectedEof, "Unterminated string".to_string()));
             }
-            ']' => {
-                tokens.push(Token::RightBracket);
-                i += 1;
+            *i += 1;
+            tokens.push(Token::Str(string));
+        }
+        ':' => {
+            *i += 1;
+            let mut keyword = String::new();
+            while *i < chars.len() && is_symbol_char(chars[*i]) {
+                keyword.push(chars[*i]);
+                *i += 1;
             }
-            '{' => {
-                tokens.push(Token::LeftBrace);
-                i += 1;
+            if keyword.is_empty() {
+                return Err((ReadErrorKind::Invalid, "Invalid keyword".to_string()));
             }
-            '}' => {
-                tokens.push(Token::RightBrace);
-                i += 1;
+            tokens.push(Token::Keyword(keyword));
+        }
+        '\'' => {
+            tokens.push(Token::Quote);
+            *i += 1;
+        }
+        '`' => {
+            tokens.push(Token::Quasiquote);
+            *i += 1;
+        }
+        '~' => {
+            tokens.push(Token::Unquote);
+            *i += 1;
+        }
+        ';' => {
+            while *i < chars.len() && chars[*i] != '\n' {
+                *i += 1;
             }
-            '"' => {
-                i += 1;
-                let mut string = String::new();
-                while i < chars.len() && chars[i] != '"' {
-                    if chars[i] == '\\' && i + 1 < chars.len() {
-                        i += 1;
-                        match chars[i] {
-                            'n' => string.push('\n'),
-                            'r' => string.push('\r'),
-                            't' => string.push('\t'),
-                            '\\' => string.push('\\'),
-                            '"' => string.push('"'),
-                            _ => {
-                                string.push('\\');
-                                string.push(chars[i]);
-                            }
-                        }
-                    } else {
-                        string.push(chars[i]);
+        }
+        '#' => {
+            // Handle shebang line
+            if *i + 1 < chars.len() && chars[*i + 1] == '!' {
+                // Skip entire shebang line
+                while *i < chars.len() && chars[*i] != '\n' {
+                    *i += 1;
+                }
+            } else if *i + 1 < chars.len() && chars[*i + 1] == '"' {
+                // Regex literal: escapes are passed through to the regex
+                // engine untouched, so #"\d+" needs no double escaping
+                *i += 2;
+                let mut pattern = String::new();
+                while *i < chars.len() && chars[*i] != '"' {
+                    if chars[*i] == '\\' && *i + 1 < chars.len() {
+                        pattern.push(chars[*i]);
+                        *i += 1;
                     }
-                    i += 1;
+                    pattern.push(chars[*i]);
+                    *i += 1;
                 }
-                if i >= chars.len() {
-                    return Err("Unterminated string".to_string());
+                if *i >= chars.len() {
+                    return Err((ReadErrorKind::UnexpectedEof, "Unterminated regex".to_string()));
                 }
-                i += 1;
-                tokens.push(Token::Str(string));
-            }
-            ':' => {
-                i += 1;
-                let mut keyword = String::new();
-                while i < chars.len() && is_symbol_char(chars[i]) {
-                    keyword.push(chars[i]);
-                    i += 1;
+                *i += 1;
+                tokens.push(Token::Regex(pattern));
+            } else if *i + 1 < chars.len() && chars[*i + 1] == '#' {
+                // Symbolic numbers: ##Inf, ##-Inf and ##NaN
+                *i += 2;
+                let mut name = String::new();
+                while *i < chars.len() && is_symbol_char(chars[*i]) {
+                    name.push(chars[*i]);
+                    *i += 1;
                 }
-                if keyword.is_empty() {
-                    return Err("Invalid keyword".to_string());
+                match name.as_str() {
+                    "Inf" => tokens.push(Token::Number(f64::INFINITY)),
+                    "-Inf" => tokens.push(Token::Number(f64::NEG_INFINITY)),
+                    "NaN" => tokens.push(Token::Number(f64::NAN)),
+                    _ => return Err((ReadErrorKind::Invalid, format!("Unknown symbolic value: ##{}", name))),
                 }
-                tokens.push(Token::Keyword(keyword));
-            }
-            '\'' => {
-                tokens.push(Token::Quote);
-                i += 1;
-            }
-            '`' => {
-                tokens.push(Token::Quasiquote);
-                i += 1;
-            }
-            '~' => {
-                tokens.push(Token::Unquote);
-                i += 1;
-            }
-            ';' => {
-                while i < chars.len() && chars[i] != '\n' {
-                    i += 1;
+            } else if *i + 1 < chars.len() && chars[*i + 1] == '<' {
+                return Err((ReadErrorKind::Invalid, "Unreadable form: values printed as #<...> can't be read back".to_string()));
+            } else if *i + 1 < chars.len() && is_symbol_start(chars[*i + 1]) {
+                // Tagged literal such as #inst "..."; the tag applies to
+                // the next form
+                *i += 1;
+                let mut tag = String::new();
+                while *i < chars.len() && is_symbol_char(chars[*i]) {
+                    tag.push(chars[*i]);
+                    *i += 1;
                 }
+                tokens.push(Token::Tag(tag));
+            } else {
+                return Err((ReadErrorKind::Invalid, "Unexpected character: #".to_string()));
             }
-            '#' => {
-                // Handle shebang line
-                if i + 1 < chars.len() && chars[i + 1] == '!' {
-                    // Skip entire shebang line
-                    while i < chars.len() && chars[i] != '\n' {
-                        i += 1;
-                    }
-                } else {
-                    return Err(format!("Unexpected character: #"));
+        }
+        _ => {
+            if chars[*i].is_numeric() || (chars[*i] == '-' && *i + 1 < chars.len() && chars[*i + 1].is_numeric()) {
+                let mut num_str = String::new();
+                if chars[*i] == '-' {
+                    num_str.push('-');
+                    *i += 1;
                 }
-            }
-            _ => {
-                if chars[i].is_numeric() || (chars[i] == '-' && i + 1 < chars.len() && chars[i + 1].is_numeric()) {
-                    let mut num_str = String::new();
-                    if chars[i] == '-' {
-                        num_str.push('-');
-                        i += 1;
-                    }
-                    while i < chars.len() && (chars[i].is_numeric() || chars[i] == '.') {
-                        num_str.push(chars[i]);
-                        i += 1;
-                    }
-                    match num_str.parse::<f64>() {
-                        Ok(n) => tokens.push(Token::Number(n)),
-                        Err(_) => return Err(format!("Invalid number: {}", num_str)),
-                    }
-                } else if is_symbol_start(chars[i]) {
-                    let mut symbol = String::new();
-                    while i < chars.len() && is_symbol_char(chars[i]) {
-                        symbol.push(chars[i]);
-                        i += 1;
-                    }
-                    match symbol.as_str() {
-                        "true" => tokens.push(Token::Bool(true)),
-                        "false" => tokens.push(Token::Bool(false)),
-                        "nil" => tokens.push(Token::Nil),
-                        _ => tokens.push(Token::Symbol(symbol)),
-                    }
-                } else {
-                    return Err(format!("Unexpected character: {}", chars[i]));
+                while *i < chars.len() && (chars[*i].is_numeric() || chars[*i] == '.') {
+                    num_str.push(chars[*i]);
+                    *i += 1;
+                }
+                match num_str.parse::<f64>() {
+                    Ok(n) => tokens.push(Token::Number(n)),
+                    Err(_) => return Err((ReadErrorKind::Invalid, format!("Invalid number: {}", num_str))),
+                }
+            } else if is_symbol_start(chars[*i]) {
+                let mut symbol = String::new();
+                while *i < chars.len() && is_symbol_char(chars[*i]) {
+                    symbol.push(chars[*i]);
+                    *i += 1;
+                }
+                match symbol.as_str() {
+                    "true" => tokens.push(Token::Bool(true)),
+                    "false" => tokens.push(Token::Bool(false)),
+                    "nil" => tokens.push(Token::Nil),
+                    _ => tokens.push(Token::Symbol(symbol)),
                 }
+            } else {
+                return Err((ReadErrorKind::Invalid, format!("Unexpected character: {}", chars[*i])));
             }
         }
     }
-
-    Ok(tokens)
+    Ok(())
 }
 
 fn is_symbol_start(c: char) -> bool {
     c.is_alphabetic() || "+-*/<>=!?&%|_".contains(c)
 }
 
-fn is_symbol_char(c: char) -> bool {
+pub(crate) fn is_symbol_char(c: char) -> bool {
     c.is_alphanumeric() || "+-*/<>=!?&%|_-.".contains(c)
 }
 
-struct Parser {
+struct Parser<'a> {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
     pos: usize,
+    tag_options: Option<&'a ReadOptions>,
 }
 
-impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+impl<'a> Parser<'a> {
+    fn new(tokens: Vec<Token>, spans: Vec<Span>) -> Self {
+        Parser { tokens, spans, pos: 0, tag_options: None }
     }
 
     fn parse(&mut self) -> Result<Value, String> {
+        self.parse_form().map(|(value, _)| value).map_err(|e| e.message)
+    }
+
+    /// An error at the token `index`, or at the end of the input when the
+    /// tokens have run out.
+    fn error_at(&self, index: usize, message: String) -> ReadError {
+        let span = match self.spans.get(index) {
+            Some(span) => *span,
+            None => {
+                let end = self.spans.last().map(|span| span.end).unwrap_or_default();
+                Span { start: end, end }
+            }
+        };
+        ReadError { kind: ReadErrorKind::Invalid, message, span }
+    }
+
+    /// An error for input that ends before the form starting at the token
+    /// `index` is complete.
+    fn eof_at(&self, index: usize, message: String) -> ReadError {
+        ReadError { kind: ReadErrorKind::UnexpectedEof, ..self.error_at(index, message) }
+    }
+
+    /// The source of the tokens from `start` up to the current one.
+    fn span_from(&self, start: usize) -> Span {
+        Span { start: self.spans[start].start, end: self.spans[self.pos - 1].end }
+    }
+
+    /// A form that is just the current token.
+    fn atom(&mut self, value: Value) -> Result<(Value, SourceMap), ReadError> {
+        self.pos += 1;
+        Ok((value, SourceMap { span: self.spans[self.pos - 1], children: Vec::new() }))
+    }
+
+    fn parse_form(&mut self) -> Result<(Value, SourceMap), ReadError> {
         if self.pos >= self.tokens.len() {
-            return Err("Unexpected end of input".to_string());
+            return Err(self.eof_at(self.pos, "Unexpected end of input".to_string()));
         }
 
+        let start = self.pos;
         match &self.tokens[self.pos].clone() {
             Token::LeftParen => self.parse_list(),
             Token::LeftBracket => self.parse_vector(),
             Token::LeftBrace => self.parse_map(),
-            Token::Symbol(s) => {
-                self.pos += 1;
-                Ok(Value::Symbol(s.clone()))
-            }
-            Token::Number(n) => {
-                self.pos += 1;
-                Ok(Value::Number(*n))
-            }
-            Token::Str(s) => {
-                self.pos += 1;
-                Ok(Value::Str(s.clone()))
-            }
-            Token::Keyword(k) => {
-                self.pos += 1;
-                Ok(Value::Keyword(k.clone()))
-            }
-            Token::Bool(b) => {
+            Token::Symbol(s) => self.atom(Value::Symbol(s.clone())),
+            Token::Number(n) => self.atom(Value::Number(*n)),
+            Token::Str(s) => self.atom(Value::Str(s.clone())),
+            Token::Keyword(k) => self.atom(Value::Keyword(k.clone())),
+            Token::Regex(pattern) => match regex::Regex::new(pattern) {
+                Ok(regex) => self.atom(Value::Regex(regex)),
+                Err(e) => Err(self.error_at(start, format!("Invalid regex #\"{}\": {}", pattern, e))),
+            },
+            Token::Tag(tag) => {
                 self.pos += 1;
-                Ok(Value::Bool(*b))
-            }
-            Token::Nil => {
-                self.pos += 1;
-                Ok(Value::Nil)
-            }
-            Token::Quote => {
-                self.pos += 1;
-                let quoted = self.parse()?;
-                Ok(Value::List(vec![Value::Symbol("quote".to_string()), quoted]))
-            }
-            Token::Quasiquote => {
-                self.pos += 1;
-                let quoted = self.parse()?;
-                Ok(Value::List(vec![Value::Symbol("quasiquote".to_string()), quoted]))
-            }
-            Token::Unquote => {
-                self.pos += 1;
-                let unquoted = self.parse()?;
-                Ok(Value::List(vec![Value::Symbol("unquote".to_string()), unquoted]))
+                if self.pos >= self.tokens.len() {
+                    return Err(self.eof_at(start, format!("Tag #{} is missing its form", tag)));
+                }
+                let (form, map) = self.parse_form()?;
+                let value = edn::read_tagged(tag, form, self.tag_options).map_err(|e| self.error_at(start, e))?;
+                Ok((value, SourceMap { span: self.span_from(start), children: vec![map] }))
             }
-            _ => Err(format!("Unexpected token: {:?}", self.tokens[self.pos])),
+            Token::Bool(b) => self.atom(Value::Bool(*b)),
+            Token::Nil => self.atom(Value::Nil),
+            Token::Quote => self.parse_prefixed("quote"),
+            Token::Quasiquote => self.parse_prefixed("quasiquote"),
+            Token::Unquote => self.parse_prefixed("unquote"),
+            _ => Err(self.error_at(start, format!("Unexpected token: {:?}", self.tokens[self.pos]))),
         }
     }
 
-    fn parse_list(&mut self) -> Result<Value, String> {
+    /// `'x` and friends read as `(quote x)`; the prefix stands in for the
+    /// symbol's position.
+    fn parse_prefixed(&mut self, symbol: &str) -> Result<(Value, SourceMap), ReadError> {
+        let start = self.pos;
         self.pos += 1;
-        let mut items = Vec::new();
-
-        while self.pos < self.tokens.len() {
-            match &self.tokens[self.pos] {
-                Token::RightParen => {
-                    self.pos += 1;
-                    return Ok(Value::List(items));
-                }
-                _ => items.push(self.parse()?),
-            }
-        }
-
-        Err("Unterminated list".to_string())
+        let (form, map) = self.parse_form()?;
+        let prefix = SourceMap { span: self.spans[start], children: Vec::new() };
+        let value = Value::List(vec![Value::Symbol(symbol.to_string()), form]);
+        Ok((value, SourceMap { span: self.span_from(start), children: vec![prefix, map] }))
     }
 
-    fn parse_vector(&mut self) -> Result<Value, String> {
+    /// Parse forms up to the `close` token, returning them and their maps.
+    fn parse_items(&mut self, close: &Token, kind: &str) -> Result<(Vec<Value>, SourceMap), ReadError> {
+        let start = self.pos;
         self.pos += 1;
         let mut items = Vec::new();
+        let mut children = Vec::new();
 
         while self.pos < self.tokens.len() {
-            match &self.tokens[self.pos] {
-                Token::RightBracket => {
-                    self.pos += 1;
-                    return Ok(Value::Vector(items));
-                }
-                _ => items.push(self.parse()?),
+            if &self.tokens[self.pos] == close {
+                self.pos += 1;
+                return Ok((items, SourceMap { span: self.span_from(start), children }));
             }
+            let (item, map) = self.parse_form()?;
+            items.push(item);
+            children.push(map);
         }
 
-        Err("Unterminated vector".to_string())
+        Err(self.eof_at(start, format!("Unterminated {}", kind)))
+    }
+
+    fn parse_list(&mut self) -> Result<(Value, SourceMap), ReadError> {
+        let (items, map) = self.parse_items(&Token::RightParen, "list")?;
+        Ok((Value::List(items), map))
     }
 
-    fn parse_map(&mut self) -> Result<Value, String> {
+    fn parse_vector(&mut self) -> Result<(Value, SourceMap), ReadError> {
+        let (items, map) = self.parse_items(&Token::RightBracket, "vector")?;
+        Ok((Value::Vector(items), map))
+    }
+
+    fn parse_map(&mut self) -> Result<(Value, SourceMap), ReadError> {
+        let start = self.pos;
         self.pos += 1;
         let mut map = HashMap::new();
+        let mut children = Vec::new();
 
         while self.pos < self.tokens.len() {
             match &self.tokens[self.pos] {
                 Token::RightBrace => {
                     self.pos += 1;
-                    return Ok(Value::Map(map));
+                    return Ok((Value::Map(map), SourceMap { span: self.span_from(start), children }));
                 }
                 _ => {
-                    let key = match self.parse()? {
-                        Value::Keyword(k) => k,
-                        Value::Str(s) => s,
-                        _ => return Err("Map keys must be keywords or strings".to_string()),
+                    let key_start = self.pos;
+                    let key = match self.parse_form()? {
+                        (Value::Keyword(k), key_map) | (Value::Str(k), key_map) => {
+                            children.push(key_map);
+                            k
+                        }
+                        _ => return Err(self.error_at(key_start, "Map keys must be keywords or strings".to_string())),
                     };
                     
                     if self.pos >= self.tokens.len() {
-                        return Err("Map missing value for key".to_string());
+                        return Err(self.eof_at(self.pos, "Map missing value for key".to_string()));
                     }
                     
-                    let value = self.parse()?;
+                    let (value, value_map) = self.parse_form()?;
+                    children.push(value_map);
                     map.insert(key, value);
                 }
             }
         }
 
-        Err("Unterminated map".to_string())
+        Err(self.eof_at(start, "Unterminated map".to_string()))
+    }
+}
+
+/// Tokenize `input` for parsing, failing on the first tokenizing error.
+fn parser_for<'a>(input: &str) -> Result<Parser<'a>, String> {
+    match tokenize(input) {
+        (_, _, Some(error)) => Err(error.message),
+        (tokens, spans, None) => Ok(Parser::new(tokens, spans)),
     }
 }
 
 pub fn read(input: &str) -> Result<Value, String> {
-    let tokens = tokenize(input)?;
-    if tokens.is_empty() {
+    let mut parser = parser_for(input)?;
+    if parser.tokens.is_empty() {
         return Err("Empty input".to_string());
     }
     
-    let mut parser = Parser::new(tokens);
     let result = parser.parse()?;
     
     if parser.pos < parser.tokens.len() {
@@ -311,17 +473,53 @@ pub fn read(input: &str) -> Result<Value, String> {
 }
 
 pub fn read_all_forms(input: &str) -> Result<Vec<Value>, String> {
-    let tokens = tokenize(input)?;
-    if tokens.is_empty() {
-        return Ok(Vec::new());
+    let (forms, error) = read_all_forms_with_positions(input);
+    match error {
+        Some(error) => Err(error.message),
+        None => Ok(forms.into_iter().map(|(form, _)| form).collect()),
     }
-    
-    let mut parser = Parser::new(tokens);
+}
+
+/// Read every form along with where it came from, for tools such as the
+/// language server. The forms before a read error are returned with it.
+pub fn read_all_forms_with_positions(input: &str) -> (Vec<(Value, SourceMap)>, Option<ReadError>) {
+    let (tokens, spans, token_error) = tokenize(input);
+    let mut parser = Parser::new(tokens, spans);
     let mut forms = Vec::new();
     
     while parser.pos < parser.tokens.len() {
-        forms.push(parser.parse()?);
+        match parser.parse_form() {
+            Ok(form) => forms.push(form),
+            // A tokenizing error cut the input short, so it's the one to report
+            Err(error) => return (forms, Some(token_error.unwrap_or(error))),
+        }
     }
     
-    Ok(forms)
-}
\ No newline at end of file
+    (forms, token_error)
+}
+
+/// Whether `input` fails to read only because it stops part-way through a
+/// form, e.g. an open list or string, so an interactive reader should ask
+/// for more rather than report an error.
+pub fn is_incomplete(input: &str) -> bool {
+    matches!(read_all_forms_with_positions(input).1, Some(ReadError { kind: ReadErrorKind::UnexpectedEof, .. }))
+}
+
+/// Read a single form as data, without evaluating it. Blank input reads as
+/// nil; tagged literals are resolved with `opts` before the registered and
+/// built-in tag readers.
+pub fn read_edn(input: &str, opts: &ReadOptions) -> Result<Value, String> {
+    let mut parser = parser_for(input)?;
+    if parser.tokens.is_empty() {
+        return Ok(Value::Nil);
+    }
+
+    parser.tag_options = Some(opts);
+    let result = parser.parse()?;
+
+    if parser.pos < parser.tokens.len() {
+        return Err("Extra input after expression".to_string());
+    }
+
+    Ok(result)
+}
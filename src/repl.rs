@@ -0,0 +1,635 @@
+use crate::env::Env;
+use crate::eval::{clear_error_trace, create_default_env, definition, eval, record_error, SPECIAL_FORMS};
+use crate::reader::{self, is_symbol_char, read};
+use crate::value::{Function, Value};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::time::Instant;
+
+fn load_init_file(env: &mut Env) {
+    // Try to load ~/.cortadorc if it exists
+    if let Some(home_dir) = dirs::home_dir() {
+        let init_file = home_dir.join(".cortadorc");
+        if init_file.exists() {
+            if let Ok(content) = std::fs::read_to_string(&init_file) {
+                use crate::reader::read_all_forms;
+                if let Ok(forms) = read_all_forms(&content) {
+                    for form in forms {
+                        let _ = eval(&form, env); // Ignore errors in init file
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A REPL command such as `:doc`. Commands are listed in `:help` in the
+/// order they appear in `COMMANDS`.
+struct Command {
+    names: &'static [&'static str],
+    args: &'static str,
+    help: &'static str,
+    /// Runs the command with the rest of the line; returns false to exit
+    run: fn(&str, &mut Env) -> bool,
+}
+
+const COMMANDS: &[Command] = &[
+    Command { names: &[":quit", ":q"], args: "", help: "Exit REPL", run: cmd_quit },
+    Command { names: &[":help", ":h"], args: "", help: "Show this help", run: cmd_help },
+    Command { names: &[":env"], args: "[ns]", help: "List the bindings in a namespace (default: current)", run: cmd_env },
+    Command { names: &[":doc"], args: "<name>", help: "Show the parameters and docstring of a definition", run: cmd_doc },
+    Command { names: &[":source"], args: "<name>", help: "Show the form that defined a name", run: cmd_source },
+    Command { names: &[":time"], args: "<expr>", help: "Evaluate an expression and report how long it took", run: cmd_time },
+    Command { names: &[":type"], args: "<expr>", help: "Evaluate an expression and show the type of its value", run: cmd_type },
+    Command { names: &[":ns"], args: "[name]", help: "Show or switch the current namespace", run: cmd_ns },
+    Command { names: &[":require"], args: "<ns> [:as alias]", help: "Load a namespace, optionally under an alias", run: cmd_require },
+    Command { names: &[":load"], args: "<file>", help: "Load and evaluate file", run: cmd_load },
+    Command { names: &[":reload"], args: "", help: "Reload init file", run: cmd_reload },
+    Command { names: &[":clear"], args: "", help: "Clear the screen", run: cmd_clear },
+];
+
+fn run_command(line: &str, env: &mut Env) -> bool {
+    let (name, arg) = match line.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (line, ""),
+    };
+    match COMMANDS.iter().find(|command| command.names.contains(&name)) {
+        Some(command) => (command.run)(arg, env),
+        None => {
+            println!("Unknown command: {}", name);
+            println!("Type :help for available commands");
+            true
+        }
+    }
+}
+
+fn cmd_quit(_arg: &str, _env: &mut Env) -> bool {
+    println!("Goodbye!");
+    false
+}
+
+fn cmd_help(_arg: &str, _env: &mut Env) -> bool {
+    let usage = |command: &Command| format!("{} {}", command.names.join(", "), command.args).trim_end().to_string();
+    let width = COMMANDS.iter().map(|command| usage(command).chars().count()).max().unwrap_or(0);
+    println!("Available REPL commands:");
+    for command in COMMANDS {
+        println!("  {:width$}  {}", usage(command), command.help, width = width);
+    }
+    true
+}
+
+/// A one-line rendering of a value, cut short if it's long.
+fn summarize(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > 60 {
+        format!("{}...", text.chars().take(57).collect::<String>())
+    } else {
+        text
+    }
+}
+
+fn cmd_env(arg: &str, env: &mut Env) -> bool {
+    let ns = if arg.is_empty() { env.get_namespace().to_string() } else { arg.to_string() };
+    let prefix = format!("{}/", ns);
+    let mut bindings = env.get_namespace_functions(&ns);
+    bindings.sort_by(|a, b| a.0.cmp(&b.0));
+    bindings.dedup_by(|a, b| a.0 == b.0);
+    if bindings.is_empty() {
+        println!("No bindings in namespace {}", ns);
+        return true;
+    }
+    println!("Bindings in namespace {}:", ns);
+    for (name, value) in bindings {
+        println!("  {} = {}", name.strip_prefix(&prefix).unwrap_or(&name), summarize(&value));
+    }
+    true
+}
+
+fn cmd_doc(arg: &str, env: &mut Env) -> bool {
+    if arg.is_empty() {
+        println!("Usage: :doc <name>");
+        return true;
+    }
+    let (name, value) = match env.resolve(arg) {
+        Some(found) => found,
+        None => {
+            println!("No definition found for {}", arg);
+            return true;
+        }
+    };
+    println!("-------------------------");
+    println!("{}", name);
+    match &value {
+        Value::Function(Function::UserDefined { params, .. }) => println!("([{}])", params.join(" ")),
+        Value::Function(Function::Macro { params, .. }) => println!("Macro\n([{}])", params.join(" ")),
+        Value::Function(Function::Native(_)) => println!("Built-in function"),
+        other => println!("{}", other.type_name()),
+    }
+    match definition(&name).and_then(|def| def.doc) {
+        Some(doc) => println!("  {}", doc.replace('\n', "\n  ")),
+        None if matches!(value, Value::Function(Function::Native(_))) => {}
+        None => println!("  (no docstring)"),
+    }
+    true
+}
+
+fn cmd_source(arg: &str, env: &mut Env) -> bool {
+    if arg.is_empty() {
+        println!("Usage: :source <name>");
+        return true;
+    }
+    match env.resolve(arg) {
+        Some((name, _)) => match definition(&name) {
+            Some(def) => println!("{}", def.form),
+            None => println!("No source available for {} (it is built in)", arg),
+        },
+        None => println!("No definition found for {}", arg),
+    }
+    true
+}
+
+/// Read and evaluate a command's expression argument, printing any error.
+fn eval_arg(arg: &str, env: &mut Env) -> Option<Value> {
+    clear_error_trace();
+    let expr = match read(arg) {
+        Ok(expr) => expr,
+        Err(e) => {
+            report_error("Parse error", &e, env);
+            return None;
+        }
+    };
+    match eval(&expr, env) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            report_error("Error", &e, env);
+            None
+        }
+    }
+}
+
+fn cmd_time(arg: &str, env: &mut Env) -> bool {
+    let start = Instant::now();
+    if let Some(value) = eval_arg(arg, env) {
+        let elapsed = start.elapsed();
+        println!("{}", value);
+        println!("Elapsed time: {:.3} ms", elapsed.as_secs_f64() * 1000.0);
+    }
+    true
+}
+
+fn cmd_type(arg: &str, env: &mut Env) -> bool {
+    if let Some(value) = eval_arg(arg, env) {
+        println!("{}", value.type_name());
+    }
+    true
+}
+
+fn cmd_ns(arg: &str, env: &mut Env) -> bool {
+    if arg.is_empty() {
+        println!("{}", env.get_namespace());
+    } else if arg.contains(char::is_whitespace) || arg.contains('/') {
+        println!("Invalid namespace name: {}", arg);
+    } else {
+        env.set_namespace(arg.to_string());
+        println!("Switched to namespace {}", arg);
+    }
+    true
+}
+
+fn cmd_require(arg: &str, env: &mut Env) -> bool {
+    let words: Vec<&str> = arg.split_whitespace().collect();
+    let form = match words.as_slice() {
+        [ns] => format!("(require '{})", ns),
+        [ns, ":as", alias] => format!("(require [{} :as {}])", ns, alias),
+        _ => {
+            println!("Usage: :require <ns> [:as alias]");
+            return true;
+        }
+    };
+    if eval_arg(&form, env).is_some() {
+        println!("Loaded {}", words[0]);
+    }
+    true
+}
+
+fn cmd_load(arg: &str, env: &mut Env) -> bool {
+    if arg.is_empty() {
+        println!("Usage: :load <file>");
+        return true;
+    }
+    let form = Value::List(vec![Value::Symbol("load".to_string()), Value::Str(arg.to_string())]);
+    match eval(&form, env) {
+        Ok(_) => println!("Loaded: {}", arg),
+        Err(e) => println!("Error: {}", e),
+    }
+    true
+}
+
+fn cmd_reload(_arg: &str, env: &mut Env) -> bool {
+    println!("Reloading init file...");
+    load_init_file(env);
+    true
+}
+
+fn cmd_clear(_arg: &str, _env: &mut Env) -> bool {
+    print!("\x1b[2J\x1b[H");
+    let _ = io::stdout().flush();
+    true
+}
+
+// === LINE EDITING ===
+
+const STRING_COLOR: &str = "\x1b[32m";
+const KEYWORD_COLOR: &str = "\x1b[36m";
+const COMMENT_COLOR: &str = "\x1b[90m";
+const BRACKET_COLOR: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, PartialEq)]
+enum SpanKind {
+    Str,
+    Keyword,
+    Comment,
+    Open(char),
+    Close(char),
+}
+
+/// Split a line into the spans that get highlighted, as byte ranges. Text in
+/// strings and comments never counts as a bracket.
+fn scan(line: &str) -> Vec<(usize, usize, SpanKind)> {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let end_of = |i: usize| chars.get(i).map_or(line.len(), |&(offset, _)| offset);
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        match c {
+            ';' => {
+                let mut j = i;
+                while j < chars.len() && chars[j].1 != '\n' {
+                    j += 1;
+                }
+                spans.push((start, end_of(j), SpanKind::Comment));
+                i = j;
+            }
+            '"' | '#' if c == '"' || chars.get(i + 1).is_some_and(|&(_, n)| n == '"') => {
+                // Strings and #"regex" literals; an unterminated one runs to the end
+                let mut j = if c == '#' { i + 2 } else { i + 1 };
+                while j < chars.len() && chars[j].1 != '"' {
+                    j += if chars[j].1 == '\\' { 2 } else { 1 };
+                }
+                let j = (j + 1).min(chars.len());
+                spans.push((start, end_of(j), SpanKind::Str));
+                i = j;
+            }
+            ':' if chars.get(i + 1).is_some_and(|&(_, n)| is_symbol_char(n)) => {
+                let mut j = i + 1;
+                while j < chars.len() && is_symbol_char(chars[j].1) {
+                    j += 1;
+                }
+                spans.push((start, end_of(j), SpanKind::Keyword));
+                i = j;
+            }
+            '(' | '[' | '{' => {
+                spans.push((start, start + 1, SpanKind::Open(c)));
+                i += 1;
+            }
+            ')' | ']' | '}' => {
+                spans.push((start, start + 1, SpanKind::Close(c)));
+                i += 1;
+            }
+            _ => {
+                // Skip whole symbols so a ':' inside one isn't taken as a keyword
+                i += 1;
+                while is_symbol_char(c) && i < chars.len() && (is_symbol_char(chars[i].1) || chars[i].1 == ':') {
+                    i += 1;
+                }
+            }
+        }
+    }
+    spans
+}
+
+/// The byte offset of the bracket matching the one under or just before the
+/// cursor, if that bracket is matched.
+fn matching_bracket(spans: &[(usize, usize, SpanKind)], pos: usize) -> Option<usize> {
+    let is_bracket = |kind: SpanKind| matches!(kind, SpanKind::Open(_) | SpanKind::Close(_));
+    let at_cursor = spans
+        .iter()
+        .position(|&(start, _, kind)| start == pos && is_bracket(kind))
+        .or_else(|| spans.iter().position(|&(start, _, kind)| start + 1 == pos && is_bracket(kind)))?;
+
+    let partner = |open: char| match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    };
+    let mut depth = 0;
+    match spans[at_cursor].2 {
+        SpanKind::Open(open) => {
+            for &(start, _, kind) in &spans[at_cursor + 1..] {
+                match kind {
+                    SpanKind::Open(_) => depth += 1,
+                    SpanKind::Close(close) if depth == 0 => return (close == partner(open)).then_some(start),
+                    SpanKind::Close(_) => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+        SpanKind::Close(close) => {
+            for &(start, _, kind) in spans[..at_cursor].iter().rev() {
+                match kind {
+                    SpanKind::Close(_) => depth += 1,
+                    SpanKind::Open(open) if depth == 0 => return (close == partner(open)).then_some(start),
+                    SpanKind::Open(_) => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+fn highlight_line(line: &str, pos: usize) -> String {
+    let spans = scan(line);
+    let flash = matching_bracket(&spans, pos);
+    let mut out = String::with_capacity(line.len() + 32);
+    let mut last = 0;
+    for &(start, end, kind) in &spans {
+        let color = match kind {
+            SpanKind::Str => STRING_COLOR,
+            SpanKind::Keyword => KEYWORD_COLOR,
+            SpanKind::Comment => COMMENT_COLOR,
+            _ if flash == Some(start) => BRACKET_COLOR,
+            _ => continue,
+        };
+        out.push_str(&line[last..start]);
+        out.push_str(color);
+        out.push_str(&line[start..end]);
+        out.push_str(RESET);
+        last = end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+/// Bindings plus special forms, which aren't bound but should complete too.
+fn completion_names(env: &Env) -> Vec<String> {
+    let mut names = env.completion_names();
+    names.extend(SPECIAL_FORMS.iter().map(|s| s.to_string()));
+    names.sort();
+    names
+}
+
+/// Completion, highlighting and input validation for the REPL.
+struct ReplHelper {
+    names: Vec<String>,
+}
+
+impl ReplHelper {
+    fn new(env: &Env) -> Self {
+        ReplHelper { names: completion_names(env) }
+    }
+
+    /// Pick up definitions made since the last input.
+    fn refresh(&mut self, env: &Env) {
+        self.names = completion_names(env);
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_symbol_char(c))
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &line[start..pos];
+
+        // A ':' at the start of the line begins a REPL command
+        if line[..start].trim_start() == ":" {
+            let typed = format!(":{}", word);
+            let commands = COMMANDS
+                .iter()
+                .flat_map(|command| command.names.iter())
+                .filter(|name| name.starts_with(&typed))
+                .map(|name| name.to_string())
+                .collect();
+            return Ok((start - 1, commands));
+        }
+        if word.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+        let candidates = self.names.iter().filter(|name| name.starts_with(word)).cloned().collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight_line(line, pos))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // Colors depend on the whole line, so redraw on every change
+        true
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        if !input.trim_start().starts_with(':') && reader::is_incomplete(input) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+/// The REPL's history variables: `*1`, `*2` and `*3` hold the last three
+/// results and `*e` the last error.
+fn init_history_vars(env: &mut Env) {
+    for name in ["*1", "*2", "*3", "*e"] {
+        env.set(name.to_string(), Value::Nil);
+    }
+}
+
+fn push_result(value: Value, env: &mut Env) {
+    let previous = [env.get("*1"), env.get("*2")];
+    env.set("*3".to_string(), previous[1].clone().unwrap_or(Value::Nil));
+    env.set("*2".to_string(), previous[0].clone().unwrap_or(Value::Nil));
+    env.set("*1".to_string(), value);
+}
+
+/// Print an error and bind it, with the calls it unwound through, to `*e`.
+fn report_error(label: &str, message: &str, env: &mut Env) {
+    println!("{}: {}", label, message);
+    let error = record_error(message);
+    env.set("*e".to_string(), error);
+}
+
+/// Read and evaluate every form in a complete input, printing results.
+fn eval_input(input: &str, env: &mut Env) {
+    clear_error_trace();
+    let forms = match reader::read_all_forms(input) {
+        Ok(forms) => forms,
+        Err(e) => {
+            report_error("Parse error", &e, env);
+            return;
+        }
+    };
+    for form in forms {
+        match eval(&form, env) {
+            Ok(result) => {
+                if result != Value::Nil {
+                    println!("{}", result);
+                }
+                push_result(result, env);
+            }
+            Err(e) => {
+                report_error("Error", &e, env);
+                return;
+            }
+        }
+    }
+}
+
+/// The prompt names the current namespace, e.g. `user=> `.
+fn prompt(env: &Env) -> String {
+    format!("{}=> ", env.get_namespace())
+}
+
+pub fn repl() {
+    let mut env = create_default_env();
+    
+    println!("Cortado REPL v1.0");
+    println!("Welcome to Cortado - A Lisp-like programming language");
+    println!("Type expressions, :help for commands, or :quit to exit");
+    
+    // Load init file if it exists
+    load_init_file(&mut env);
+    init_history_vars(&mut env);
+    
+    let mut rl = match Editor::<ReplHelper>::new() {
+        Ok(editor) => editor,
+        Err(_) => {
+            // Fall back to basic REPL if rustyline fails
+            basic_repl(&mut env);
+            return;
+        }
+    };
+    rl.set_helper(Some(ReplHelper::new(&env)));
+    
+    // Try to load history
+    let history_path = dirs::home_dir()
+        .map(|home| home.join(".cortado_history"))
+        .unwrap_or_else(|| std::path::PathBuf::from(".cortado_history"));
+    
+    let _ = rl.load_history(&history_path);
+    
+    loop {
+        // The helper's validator keeps editing until the reader has whole forms
+        match rl.readline(&prompt(&env)) {
+            Ok(input) => {
+                if input.trim().is_empty() {
+                    continue;
+                }
+                rl.add_history_entry(input.as_str());
+
+                // Handle REPL commands
+                if input.trim_start().starts_with(':') {
+                    if !run_command(input.trim(), &mut env) {
+                        break;
+                    }
+                } else {
+                    eval_input(&input, &mut env);
+                }
+
+                if let Some(helper) = rl.helper_mut() {
+                    helper.refresh(&env);
+                }
+            }
+            Err(ReadlineError::Interrupted) => {
+                println!("Interrupted");
+            }
+            Err(ReadlineError::Eof) => {
+                println!("Goodbye!");
+                break;
+            }
+            Err(err) => {
+                println!("Error: {:?}", err);
+                break;
+            }
+        }
+    }
+    
+    // Save history
+    let _ = rl.save_history(&history_path);
+}
+
+fn basic_repl(env: &mut Env) {
+    println!("(Using basic REPL - install rustyline for better experience)");
+    println!();
+    
+    let mut buffer = String::new();
+    loop {
+        let prompt = prompt(env);
+        if buffer.is_empty() {
+            print!("{}", prompt);
+        } else {
+            print!("{}-> ", " ".repeat(prompt.chars().count() - 3));
+        }
+        io::stdout().flush().unwrap();
+        
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => {
+                println!("Goodbye!");
+                break;
+            }
+            Ok(_) => {
+                if buffer.is_empty() {
+                    let input = line.trim();
+                    if input.is_empty() {
+                        continue;
+                    }
+                    if input.starts_with(':') {
+                        if !run_command(input, env) {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+
+                // Keep reading lines until the forms are complete
+                buffer.push_str(&line);
+                if reader::is_incomplete(&buffer) {
+                    continue;
+                }
+                eval_input(&buffer, env);
+                buffer.clear();
+            }
+            Err(e) => {
+                println!("Error reading input: {}", e);
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use crate::coverage::{self, Point};
+use crate::eval::{
+    capture_output, check_arity, create_default_env, eval, set_deadline, str_of, take_captured_output, TIMED_OUT,
+};
+use crate::lsp;
+use crate::reader::read;
+use crate::testing;
+use crate::value::{Function, Value};
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// How long past its timeout a worker gets to stop on its own before it's
+// killed
+const KILL_GRACE: Duration = Duration::from_secs(2);
+
+// Helpers every test file can use without requiring std/test
+const PRELUDE: &[&str] = &[
+    "(defn assert-eq [expected actual] (test-assert-eq expected actual))",
+    "(defn assert-not-eq [expected actual] (is (not= expected actual)))",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Junit,
+    Tap,
+    Json,
+}
+
+pub struct Options {
+    pub paths: Vec<String>,
+    pub filter: Option<String>,
+    pub jobs: usize,
+    pub format: Format,
+    pub timeout: Duration,
+    /// Where to write an lcov report of the code the tests ran, when asked
+    pub coverage: Option<PathBuf>,
+    /// Run the one file given and print its result as JSON; `run` starts a
+    /// worker process like this for each file
+    pub worker: bool,
+}
+
+impl Options {
+    /// Parse the arguments that follow `cortado test`.
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            paths: Vec::new(),
+            filter: None,
+            jobs: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            format: Format::Text,
+            timeout: Duration::from_secs(30),
+            coverage: None,
+            worker: false,
+        };
+        let mut i = 0;
+        while i < args.len() {
+            let value = |name: &str| args.get(i + 1).cloned().ok_or(format!("{} requires a value", name));
+            match args[i].as_str() {
+                "--filter" => options.filter = Some(value("--filter")?),
+                "-j" | "--jobs" => {
+                    options.jobs = match value("--jobs")?.parse::<usize>() {
+                        Ok(jobs) if jobs > 0 => jobs,
+                        _ => return Err(format!("--jobs must be a positive number, got '{}'", args[i + 1])),
+                    }
+                }
+                "--format" => {
+                    options.format = match value("--format")?.as_str() {
+                        "text" => Format::Text,
+                        "junit" => Format::Junit,
+                        "tap" => Format::Tap,
+                        "json" => Format::Json,
+                        other => return Err(format!("--format must be text, junit, tap or json, got '{}'", other)),
+                    }
+                }
+                "--timeout" => {
+                    options.timeout = match value("--timeout")?.parse::<f64>() {
+                        Ok(secs) if secs > 0.0 => Duration::from_secs_f64(secs),
+                        _ => return Err(format!("--timeout must be a positive number of seconds, got '{}'", args[i + 1])),
+                    }
+                }
+                "--coverage" => {
+                    options.coverage.get_or_insert_with(|| PathBuf::from("coverage/lcov.info"));
+                    i += 1;
+                    continue;
+                }
+                "--coverage-file" => options.coverage = Some(PathBuf::from(value("--coverage-file")?)),
+                "--worker" => {
+                    options.worker = true;
+                    i += 1;
+                    continue;
+                }
+                arg if arg.starts_with('-') => return Err(format!("Unknown test option '{}'", arg)),
+                path => {
+                    options.paths.push(path.to_string());
+                    i += 1;
+                    continue;
+                }
+            }
+            i += 2;
+        }
+        if options.paths.is_empty() {
+            options.paths.push("test".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// A deftest, or the assertions a file makes at its top level. Failures are
+/// JSON objects as `--format json` prints them.
+struct TestCase {
+    name: String,
+    pass: usize,
+    fail: usize,
+    error: usize,
+    seconds: f64,
+    failures: Vec<serde_json::Value>,
+}
+
+struct FileResult {
+    path: String,
+    cases: Vec<TestCase>,
+    /// Why the file stopped part way, if it did
+    error: Option<String>,
+    seconds: f64,
+    /// What the file's run covered, when coverage is on
+    coverage: Vec<Point>,
+}
+
+impl FileResult {
+    fn totals(&self) -> (usize, usize, usize) {
+        self.cases.iter().fold((0, 0, 0), |(p, f, e), case| (p + case.pass, f + case.fail, e + case.error))
+    }
+
+    fn failed(&self) -> bool {
+        let (_, fail, error) = self.totals();
+        fail > 0 || error > 0 || self.error.is_some()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let cases: Vec<serde_json::Value> = self
+            .cases
+            .iter()
+            .map(|case| {
+                json!({
+                    "name": case.name,
+                    "pass": case.pass,
+                    "fail": case.fail,
+                    "error": case.error,
+                    "seconds": case.seconds,
+                    "failures": case.failures,
+                })
+            })
+            .collect();
+        let coverage: Vec<serde_json::Value> = self.coverage.iter().map(Point::to_json).collect();
+        json!({ "file": self.path, "seconds": self.seconds, "error": self.error, "tests": cases, "coverage": coverage })
+    }
+
+    fn from_json(json: &serde_json::Value) -> Option<FileResult> {
+        let count = |case: &serde_json::Value, key: &str| case[key].as_u64().unwrap_or(0) as usize;
+        let cases = json["tests"]
+            .as_array()?
+            .iter()
+            .map(|case| TestCase {
+                name: case["name"].as_str().unwrap_or_default().to_string(),
+                pass: count(case, "pass"),
+                fail: count(case, "fail"),
+                error: count(case, "error"),
+                seconds: case["seconds"].as_f64().unwrap_or_default(),
+                failures: case["failures"].as_array().cloned().unwrap_or_default(),
+            })
+            .collect();
+        Some(FileResult {
+            path: json["file"].as_str()?.to_string(),
+            cases,
+            error: json["error"].as_str().map(str::to_string),
+            seconds: json["seconds"].as_f64().unwrap_or_default(),
+            coverage: json["coverage"].as_array().into_iter().flatten().filter_map(Point::from_json).collect(),
+        })
+    }
+}
+
+/// The `.lisp` files under `paths`, in order.
+fn discover(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("Couldn't read {}: {}", dir.display(), e))?;
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                walk(&path, files)?;
+            } else if path.extension().is_some_and(|ext| ext == "lisp") {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            walk(path, &mut files)?;
+        } else if path.is_file() {
+            files.push(path.to_path_buf());
+        } else {
+            return Err(format!("No such file or directory: {}", path.display()));
+        }
+    }
+    Ok(files)
+}
+
+// === WORKER ===
+
+fn field(map: &Value, key: &str) -> Value {
+    match map {
+        Value::Map(map) => map.get(key).cloned().unwrap_or(Value::Nil),
+        _ => Value::Nil,
+    }
+}
+
+fn number(map: &Value, key: &str) -> f64 {
+    match field(map, key) {
+        Value::Number(n) => n,
+        _ => 0.0,
+    }
+}
+
+fn text(map: &Value, key: &str) -> String {
+    match field(map, key) {
+        Value::Str(s) => s,
+        _ => String::new(),
+    }
+}
+
+/// A failure map from `run-tests` as JSON, with its forms and values printed
+/// since they needn't be JSON data, and the report `is` printed for it.
+fn failure_json(failure: &Value) -> serde_json::Value {
+    let printed = |key: &str| crate::edn::pr_str(&field(failure, key));
+    let testing: Vec<String> = match field(failure, "testing") {
+        Value::Vector(contexts) => contexts.iter().map(str_of).collect(),
+        _ => Vec::new(),
+    };
+    json!({
+        "type": field(failure, "type").to_string().trim_start_matches(':'),
+        "test": text(failure, "test"),
+        "testing": testing,
+        "expression": printed("expression"),
+        "expected": printed("expected"),
+        "actual": printed("actual"),
+        "message": match field(failure, "message") {
+            Value::Str(message) => Some(message),
+            _ => None,
+        },
+        "report": testing::describe(failure),
+    })
+}
+
+/// Load a file into a fresh environment, then run the deftests it
+/// registered but didn't run itself.
+fn run_file(path: &Path, options: &Options) -> FileResult {
+    let start = Instant::now();
+    capture_output();
+    testing::configure(options.filter.clone(), Some(options.timeout));
+    if options.worker {
+        testing::announce_starts();
+    }
+    if options.coverage.is_some() {
+        // Cover the code the file tests, not the file itself
+        coverage::enable(vec![path.display().to_string()]);
+    }
+    let mut env = create_default_env();
+    // Tests drive the language server in memory; scripts have no use for it
+    env.set(
+        "lsp/session".to_string(),
+        Value::Function(Function::Native(|args| {
+            check_arity(args, 1, 1, "lsp/session")?;
+            lsp::session(&args[0], "lsp/session")
+        })),
+    );
+    for helper in PRELUDE {
+        if let Ok(form) = read(helper) {
+            let _ = eval(&form, &mut env);
+        }
+    }
+
+    let load = Value::List(vec![Value::Symbol("load".to_string()), Value::Str(path.display().to_string())]);
+    set_deadline(Some(start + options.timeout));
+    let mut error = eval(&load, &mut env).err().map(|e| match e.ends_with(TIMED_OUT) {
+        true => format!("Timed out loading after {}s", options.timeout.as_secs_f64()),
+        false => e,
+    });
+    set_deadline(None);
+
+    let mut cases = Vec::new();
+    let top = testing::counts();
+    if options.filter.is_none() && top.pass + top.fail + top.error > 0 {
+        cases.push(TestCase {
+            name: "(top level)".to_string(),
+            pass: top.pass,
+            fail: top.fail,
+            error: top.error,
+            seconds: start.elapsed().as_secs_f64(),
+            failures: testing::failures().iter().map(failure_json).collect(),
+        });
+    }
+    match testing::run_remaining() {
+        Ok(summary) => {
+            let failures = match field(&summary, "failures") {
+                Value::Vector(failures) => failures,
+                _ => Vec::new(),
+            };
+            if let Value::Vector(tests) = field(&summary, "tests") {
+                for test in tests {
+                    let name = text(&test, "name");
+                    cases.push(TestCase {
+                        failures: failures.iter().filter(|f| text(f, "test") == name).map(failure_json).collect(),
+                        pass: number(&test, "pass") as usize,
+                        fail: number(&test, "fail") as usize,
+                        error: number(&test, "error") as usize,
+                        seconds: number(&test, "ms") / 1000.0,
+                        name,
+                    });
+                }
+            }
+        }
+        Err(e) => error = error.or(Some(e)),
+    }
+    take_captured_output();
+
+    FileResult {
+        path: path.display().to_string(),
+        cases,
+        error,
+        seconds: start.elapsed().as_secs_f64(),
+        coverage: coverage::points(),
+    }
+}
+
+/// Run the one file in `options.paths` and print its result as a line of
+/// JSON, for the `cortado test` process that started this one.
+pub fn run_worker(options: &Options) -> i32 {
+    let result = run_file(Path::new(&options.paths[0]), options);
+    println!("{}", result.to_json());
+    if result.failed() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Run a file in its own process, so a crash or runaway memory use takes
+/// down only that file. Timeouts are checked inside the worker, but code
+/// that never returns to the evaluator can't see them, so a worker still
+/// running KILL_GRACE after its current test's timeout is killed.
+fn run_in_worker(path: &Path, options: &Options) -> FileResult {
+    let start = Instant::now();
+    let crashed = |error: String| FileResult {
+        path: path.display().to_string(),
+        cases: Vec::new(),
+        error: Some(error),
+        seconds: start.elapsed().as_secs_f64(),
+        coverage: Vec::new(),
+    };
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return crashed(format!("Couldn't find the cortado executable: {}", e)),
+    };
+    let mut command = Command::new(exe);
+    command.args(["test", "--worker", "--timeout", &options.timeout.as_secs_f64().to_string()]);
+    if let Some(filter) = &options.filter {
+        command.args(["--filter", filter]);
+    }
+    if options.coverage.is_some() {
+        command.arg("--coverage");
+    }
+    command.arg(path).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return crashed(format!("Couldn't start a worker: {}", e)),
+    };
+    // Loading the file starts the clock, and each test the worker announces
+    // restarts it
+    let running = Arc::new(Mutex::new((start, None::<String>)));
+    // Drain both pipes on their own threads so neither fills up and blocks
+    // the worker while we watch the clock
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let stdout = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stdout.read_to_string(&mut text);
+        text
+    });
+    let stderr = BufReader::new(child.stderr.take().expect("stderr is piped"));
+    let stderr = {
+        let running = running.clone();
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            for line in stderr.lines().map_while(Result::ok) {
+                match line.strip_prefix(testing::STARTED) {
+                    Some(test) => *running.lock().unwrap() = (Instant::now(), Some(test.to_string())),
+                    None => {
+                        text.push_str(&line);
+                        text.push('\n');
+                    }
+                }
+            }
+            text
+        })
+    };
+
+    let mut timed_out = None;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => {}
+            Err(e) => break Err(e),
+        }
+        let (since, test) = running.lock().unwrap().clone();
+        if since.elapsed() >= options.timeout + KILL_GRACE {
+            let _ = child.kill();
+            timed_out = Some(test);
+            break child.wait();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    if let Some(test) = timed_out {
+        // Don't wait for the pipes: anything the worker started may still
+        // hold them open
+        let seconds = options.timeout.as_secs_f64();
+        return crashed(match test {
+            Some(test) => format!("Timed out after {}s in {}", seconds, test),
+            None => format!("Timed out after {}s loading", seconds),
+        });
+    }
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    // The result is the last line; anything printed straight to stdout
+    // comes before it
+    let result = stdout.lines().rev().find(|line| !line.trim().is_empty());
+    match result.and_then(|line| serde_json::from_str(line).ok()).and_then(|json| FileResult::from_json(&json)) {
+        Some(result) => result,
+        None => {
+            let status = status.map(|s| s.to_string()).unwrap_or_else(|e| e.to_string());
+            let detail = stderr.lines().find(|line| !line.trim().is_empty()).unwrap_or("no output");
+            crashed(format!("Crashed ({}): {}", status, detail))
+        }
+    }
+}
+
+/// Run the test files under `options.paths`, `options.jobs` at a time, print
+/// a report in the chosen format and return the exit code.
+pub fn run(options: Options) -> i32 {
+    let files = match discover(&options.paths) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+    let start = Instant::now();
+    let options = Arc::new(options);
+    let queue = Arc::new(Mutex::new(files.clone().into_iter().enumerate()));
+    let (sender, receiver) = mpsc::channel();
+
+    let workers: Vec<_> = (0..options.jobs.min(files.len()))
+        .map(|_| {
+            let (queue, sender, options) = (queue.clone(), sender.clone(), options.clone());
+            std::thread::spawn(move || loop {
+                let next = queue.lock().unwrap().next();
+                let Some((index, path)) = next else { break };
+                if sender.send((index, run_in_worker(&path, &options))).is_err() {
+                    break;
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+
+    let mut results: Vec<(usize, FileResult)> = Vec::new();
+    for (index, result) in receiver {
+        if options.format == Format::Text {
+            print_text_file(&result);
+        }
+        results.push((index, result));
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+    results.sort_by_key(|(index, _)| *index);
+    let results: Vec<FileResult> = results.into_iter().map(|(_, result)| result).collect();
+    let seconds = start.elapsed().as_secs_f64();
+
+    match options.format {
+        Format::Text => print_text_summary(&results, seconds),
+        Format::Junit => print!("{}", junit(&results, seconds)),
+        Format::Tap => print!("{}", tap(&results)),
+        Format::Json => println!("{}", json_report(&results, seconds)),
+    }
+    if let Some(path) = &options.coverage {
+        if let Err(e) = write_coverage(&results, path, options.format) {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    }
+    if results.iter().any(FileResult::failed) {
+        1
+    } else {
+        0
+    }
+}
+
+// === REPORTS ===
+
+fn indent(text: &str, by: &str) -> String {
+    text.lines().map(|line| format!("{}{}\n", by, line)).collect()
+}
+
+fn report(case: &TestCase) -> String {
+    case.failures.iter().map(|failure| failure["report"].as_str().unwrap_or_default()).collect()
+}
+
+fn totals(results: &[FileResult]) -> (usize, usize, usize) {
+    results.iter().map(FileResult::totals).fold((0, 0, 0), |a, t| (a.0 + t.0, a.1 + t.1, a.2 + t.2))
+}
+
+fn print_text_file(result: &FileResult) {
+    let (pass, fail, error) = result.totals();
+    println!(
+        "{} {} ({} assertions, {} failures, {} errors, {:.2}s)",
+        if result.failed() { "FAIL" } else { "PASS" },
+        result.path,
+        pass + fail + error,
+        fail,
+        error,
+        result.seconds
+    );
+    for case in &result.cases {
+        print!("{}", indent(&report(case), "    "));
+    }
+    if let Some(e) = &result.error {
+        println!("    ERROR: {}", e);
+    }
+}
+
+fn print_text_summary(results: &[FileResult], seconds: f64) {
+    let tests: usize = results.iter().map(|r| r.cases.iter().filter(|c| c.name != "(top level)").count()).sum();
+    let (pass, fail, error) = totals(results);
+    let broken = results.iter().filter(|r| r.error.is_some()).count();
+    println!();
+    println!("Ran {} tests in {} files containing {} assertions ({:.2}s).", tests, results.len(), pass + fail + error, seconds);
+    println!("{} failures, {} errors, {} files stopped early.", fail, error, broken);
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn junit(results: &[FileResult], seconds: f64) -> String {
+    let mut suites = String::new();
+    let (mut all_tests, mut all_failures, mut all_errors) = (0, 0, 0);
+    for result in results {
+        let path = xml_escape(&result.path);
+        let (mut failures, mut errors) = (0, 0);
+        let mut cases = String::new();
+        for case in &result.cases {
+            let name = xml_escape(&case.name);
+            if case.failures.is_empty() {
+                cases.push_str(&format!("    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"/>\n", path, name, case.seconds));
+                continue;
+            }
+            // A case has one outcome, and an error outranks a failure
+            let tag = if case.error > 0 { "error" } else { "failure" };
+            if case.error > 0 {
+                errors += 1;
+            } else {
+                failures += 1;
+            }
+            cases.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">\n      <{} message=\"{} failures, {} errors\">{}</{}>\n    </testcase>\n",
+                path,
+                name,
+                case.seconds,
+                tag,
+                case.fail,
+                case.error,
+                xml_escape(&report(case)),
+                tag
+            ));
+        }
+        let mut tests = result.cases.len();
+        if let Some(e) = &result.error {
+            cases.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"(load)\" time=\"0.000\">\n      <error message=\"{}\"/>\n    </testcase>\n",
+                path,
+                xml_escape(e)
+            ));
+            tests += 1;
+            errors += 1;
+        }
+        suites.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n{}  </testsuite>\n",
+            path, tests, failures, errors, result.seconds, cases
+        ));
+        all_tests += tests;
+        all_failures += failures;
+        all_errors += errors;
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n{}</testsuites>\n",
+        all_tests, all_failures, all_errors, seconds, suites
+    )
+}
+
+fn tap(results: &[FileResult]) -> String {
+    let mut lines = Vec::new();
+    for result in results {
+        for case in &result.cases {
+            let number = lines.len() + 1;
+            if case.failures.is_empty() {
+                lines.push(format!("ok {} - {} {}", number, result.path, case.name));
+            } else {
+                lines.push(format!("not ok {} - {} {}\n  ---\n{}  ...", number, result.path, case.name, indent(&report(case), "  ")));
+            }
+        }
+        if let Some(e) = &result.error {
+            lines.push(format!("not ok {} - {} (load)\n  ---\n  error: {}\n  ...", lines.len() + 1, result.path, e));
+        }
+    }
+    let mut out = format!("TAP version 13\n1..{}\n", lines.len());
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Merge the files' coverage, write it to `path` as lcov and print a
+/// summary, to stderr when stdout carries a machine-readable report.
+fn write_coverage(results: &[FileResult], path: &Path, format: Format) -> Result<(), String> {
+    let points = coverage::merge(results.iter().flat_map(|result| result.coverage.iter().cloned()));
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Couldn't create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, coverage::lcov(&points)).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))?;
+    let report = if points.is_empty() {
+        format!("\nNo files were loaded to cover; wrote an empty {}\n", path.display())
+    } else {
+        format!("\nCoverage:\n{}Wrote {}\n", coverage::summary(&points), path.display())
+    };
+    match format {
+        Format::Text => print!("{}", report),
+        _ => eprint!("{}", report),
+    }
+    Ok(())
+}
+
+fn json_report(results: &[FileResult], seconds: f64) -> serde_json::Value {
+    let (pass, fail, error) = totals(results);
+    let files: Vec<serde_json::Value> = results.iter().map(FileResult::to_json).collect();
+    json!({ "pass": pass, "fail": fail, "error": error, "seconds": seconds, "files": files })
+}
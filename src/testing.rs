@@ -0,0 +1,617 @@
+use crate::edn::pr_str;
+use crate::env::Env;
+use crate::eval::{
+    call_function, clear_error_trace, eval, str_of, is_interruption, record_definition, set_deadline, write_out, SPECIAL_FORMS,
+    TIMED_OUT,
+};
+use crate::value::{Function, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// A test defined with `deftest`: a function of no arguments.
+struct Test {
+    name: String,
+    namespace: String,
+    function: Value,
+}
+
+#[derive(Default)]
+struct Fixtures {
+    once: Vec<Value>,
+    each: Vec<Value>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Counts {
+    pub test: usize,
+    pub pass: usize,
+    pub fail: usize,
+    pub error: usize,
+}
+
+impl Counts {
+    fn since(&self, before: &Counts) -> Counts {
+        Counts {
+            test: self.test - before.test,
+            pass: self.pass - before.pass,
+            fail: self.fail - before.fail,
+            error: self.error - before.error,
+        }
+    }
+
+    fn insert_into(&self, map: &mut HashMap<String, Value>) {
+        map.insert("test".to_string(), Value::Number(self.test as f64));
+        map.insert("pass".to_string(), Value::Number(self.pass as f64));
+        map.insert("fail".to_string(), Value::Number(self.fail as f64));
+        map.insert("error".to_string(), Value::Number(self.error as f64));
+    }
+}
+
+thread_local! {
+    static TESTS: RefCell<Vec<Test>> = const { RefCell::new(Vec::new()) };
+    static FIXTURES: RefCell<HashMap<String, Fixtures>> = RefCell::new(HashMap::new());
+    // Assertions since reset-test-counts, or since the start of a run-tests
+    static COUNTS: RefCell<Counts> = RefCell::new(Counts::default());
+    static FAILURES: RefCell<Vec<Value>> = const { RefCell::new(Vec::new()) };
+    static CURRENT_TEST: RefCell<Option<String>> = const { RefCell::new(None) };
+    static CONTEXTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    // Namespaces a run-tests call has already covered
+    static RAN: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+    // Set by the test runner: only tests whose name contains FILTER run, and
+    // each may take up to TIMEOUT
+    static FILTER: RefCell<Option<String>> = const { RefCell::new(None) };
+    static TIMEOUT: Cell<Option<Duration>> = const { Cell::new(None) };
+    // Set in test runner workers, which write STARTED and each test's name
+    // to stderr as it begins so the runner knows when its timeout starts
+    static ANNOUNCE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the stderr line a worker writes as each test starts.
+pub const STARTED: &str = "\u{1}cortado-test-started ";
+
+/// Write a STARTED line to stderr as each test starts.
+pub fn announce_starts() {
+    ANNOUNCE.with(|announce| announce.set(true));
+}
+
+/// Run only tests whose qualified name contains `filter`, stopping any that
+/// takes longer than `timeout`.
+pub fn configure(filter: Option<String>, timeout: Option<Duration>) {
+    FILTER.with(|f| *f.borrow_mut() = filter);
+    TIMEOUT.with(|t| t.set(timeout));
+}
+
+pub fn counts() -> Counts {
+    COUNTS.with(|counts| *counts.borrow())
+}
+
+/// The failures recorded outside `run-tests` since the last reset.
+pub fn failures() -> Vec<Value> {
+    FAILURES.with(|failures| failures.borrow().clone())
+}
+
+pub fn reset_counts() {
+    COUNTS.with(|counts| *counts.borrow_mut() = Counts::default());
+    FAILURES.with(|failures| failures.borrow_mut().clear());
+}
+
+pub fn record_pass() {
+    COUNTS.with(|counts| counts.borrow_mut().pass += 1);
+}
+
+/// Count a failed or erroring assertion and print what went wrong, naming
+/// the test and `testing` contexts it happened in.
+fn record_problem(kind: &str, expression: &Value, expected: Value, actual: Value, message: Option<String>) {
+    let failure = push_problem(kind, expression, expected, actual, message);
+    write_out(&format!("\n{}", describe(&failure)));
+}
+
+/// The report printed for a failure map from `run-tests`.
+pub fn describe(failure: &Value) -> String {
+    let field = |key: &str| match failure {
+        Value::Map(map) => map.get(key).cloned().unwrap_or(Value::Nil),
+        _ => Value::Nil,
+    };
+    let kind = match field("type") {
+        Value::Keyword(kind) => kind.to_uppercase(),
+        _ => "FAIL".to_string(),
+    };
+    let test = match field("test") {
+        Value::Str(test) => test,
+        _ => "(no test)".to_string(),
+    };
+
+    let mut report = format!("{} in {}\n", kind, test);
+    if let Value::Vector(contexts) = field("testing") {
+        if !contexts.is_empty() {
+            let contexts: Vec<String> = contexts.iter().map(str_of).collect();
+            report.push_str(&format!("  {}\n", contexts.join(" ")));
+        }
+    }
+    if let Value::Str(message) = field("message") {
+        report.push_str(&format!("  {}\n", message));
+    }
+    report.push_str(&format!("  expression: {}\n", field("expression")));
+    report.push_str(&format!("    expected: {}\n", pr_str(&field("expected"))));
+    report.push_str(&format!("      actual: {}\n", pr_str(&field("actual"))));
+    report
+}
+
+fn push_problem(kind: &str, expression: &Value, expected: Value, actual: Value, message: Option<String>) -> Value {
+    COUNTS.with(|counts| {
+        let mut counts = counts.borrow_mut();
+        if kind == "fail" {
+            counts.fail += 1;
+        } else {
+            counts.error += 1;
+        }
+    });
+
+    let mut failure = HashMap::new();
+    failure.insert("type".to_string(), Value::Keyword(kind.to_string()));
+    let test = CURRENT_TEST.with(|test| test.borrow().clone());
+    failure.insert("test".to_string(), test.map(Value::Str).unwrap_or(Value::Nil));
+    let contexts = CONTEXTS.with(|contexts| contexts.borrow().clone());
+    failure.insert("testing".to_string(), Value::Vector(contexts.into_iter().map(Value::Str).collect()));
+    failure.insert("expression".to_string(), expression.clone());
+    failure.insert("expected".to_string(), expected);
+    failure.insert("actual".to_string(), actual);
+    failure.insert("message".to_string(), message.map(Value::Str).unwrap_or(Value::Nil));
+    let failure = Value::Map(failure);
+    FAILURES.with(|failures| failures.borrow_mut().push(failure.clone()));
+    failure
+}
+
+/// Count and report a failed assertion made outside `is`, as `defspec` does.
+pub fn report_fail(expression: &Value, expected: Value, actual: Value, message: String) {
+    record_problem("fail", expression, expected, actual, Some(message));
+}
+
+/// Count a failure its caller has already reported, as `test-assert-eq` does.
+pub fn record_fail(expression: &Value, expected: Value, actual: Value) {
+    push_problem("fail", expression, expected, actual, None);
+}
+
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Bool(false) | Value::Nil)
+}
+
+fn symbol(name: &str) -> Value {
+    Value::Symbol(name.to_string())
+}
+
+// === SPECIAL FORMS ===
+
+/// (deftest name body...) defines `name` as a function of no arguments and
+/// registers it for `run-tests`.
+pub fn eval_deftest(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let name = match list.get(1) {
+        Some(Value::Symbol(name)) => name,
+        _ => return Err("deftest requires a name".to_string()),
+    };
+    let body = match &list[2..] {
+        [] => Value::Nil,
+        [form] => form.clone(),
+        forms => Value::List([vec![symbol("do")], forms.to_vec()].concat()),
+    };
+    define_test(name, body, list, env)
+}
+
+/// Bind `name` to a function of no arguments running `body` and register it
+/// as a test, recording `form` as its definition.
+pub(crate) fn define_test(name: &str, body: Value, form: &[Value], env: &mut Env) -> Result<Value, String> {
+    let function = eval(&Value::List(vec![symbol("fn"), Value::Vector(Vec::new()), body]), env)?;
+
+    let qualified = env.qualify(name);
+    record_definition(qualified.clone(), form, None);
+    env.set_namespaced(name.to_string(), function.clone());
+    let test = Test { name: qualified, namespace: env.get_namespace().to_string(), function: function.clone() };
+    TESTS.with(|tests| {
+        let mut tests = tests.borrow_mut();
+        // Re-evaluating a deftest replaces the test in place
+        match tests.iter_mut().find(|existing| existing.name == test.name) {
+            Some(existing) => *existing = test,
+            None => tests.push(test),
+        }
+    });
+    Ok(function)
+}
+
+/// (testing "context" body...) labels the assertions in its body.
+pub fn eval_testing(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    if list.len() < 2 {
+        return Err("testing requires a description".to_string());
+    }
+    let description = match eval(&list[1], env)? {
+        Value::Str(s) => s,
+        other => other.to_string(),
+    };
+    CONTEXTS.with(|contexts| contexts.borrow_mut().push(description));
+    let mut result = Ok(Value::Nil);
+    for form in &list[2..] {
+        result = eval(form, env);
+        if result.is_err() {
+            break;
+        }
+    }
+    CONTEXTS.with(|contexts| contexts.borrow_mut().pop());
+    result
+}
+
+/// (is form) or (is form "message") counts a pass when `form` is truthy and
+/// otherwise reports it with expected and actual values. An error while
+/// evaluating it is reported too, rather than ending the test.
+pub fn eval_is(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    if list.len() != 2 && list.len() != 3 {
+        return Err("is requires a form and an optional message".to_string());
+    }
+    let message = match list.get(2) {
+        Some(message) => match eval(message, env)? {
+            Value::Str(s) => Some(s),
+            other => Some(other.to_string()),
+        },
+        None => None,
+    };
+    assert_form(&list[1], message, env)
+}
+
+fn assert_form(form: &Value, message: Option<String>, env: &mut Env) -> Result<Value, String> {
+    match check(form, env) {
+        Ok(Check::Pass(value)) => {
+            record_pass();
+            Ok(value)
+        }
+        Ok(Check::Fail { expected, actual }) => {
+            record_problem("fail", form, expected, actual, message);
+            Ok(Value::Bool(false))
+        }
+        Err(e) if is_interruption(&e) => Err(e),
+        Err(e) => {
+            clear_error_trace();
+            record_problem("error", form, form.clone(), Value::Str(e), message);
+            Ok(Value::Bool(false))
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+enum Check {
+    Pass(Value),
+    Fail { expected: Value, actual: Value },
+}
+
+fn check(form: &Value, env: &mut Env) -> Result<Check, String> {
+    let items = match form {
+        Value::List(items) if !items.is_empty() => items,
+        _ => return plain_check(form, env),
+    };
+    let head = match &items[0] {
+        Value::Symbol(head) => head,
+        _ => return plain_check(form, env),
+    };
+    match head.as_str() {
+        "thrown?" | "thrown-with-msg?" => {
+            return match thrown(items, env)? {
+                Thrown::Matched(message) => Ok(Check::Pass(Value::Str(message))),
+                Thrown::Returned(value) => Ok(Check::Fail { expected: form.clone(), actual: value }),
+                Thrown::Mismatched(message) => Ok(Check::Fail { expected: form.clone(), actual: Value::Str(message) }),
+            };
+        }
+        name if SPECIAL_FORMS.contains(&name) => return plain_check(form, env),
+        _ => {}
+    }
+    if let Some(Value::Function(Function::Macro { .. })) = env.get_with_aliases(head) {
+        return plain_check(form, env);
+    }
+
+    // A call: evaluate the arguments separately so a failure can show them
+    let function = eval(&items[0], env)?;
+    let args = items[1..].iter().map(|arg| eval(arg, env)).collect::<Result<Vec<_>, _>>()?;
+    let result = call_function(&function, &args)?;
+    if is_truthy(&result) {
+        return Ok(Check::Pass(result));
+    }
+    if head == "=" && args.len() == 2 {
+        return Ok(Check::Fail { expected: args[0].clone(), actual: args[1].clone() });
+    }
+    let call = Value::List([vec![items[0].clone()], args].concat());
+    Ok(Check::Fail { expected: form.clone(), actual: Value::List(vec![symbol("not"), call]) })
+}
+
+fn plain_check(form: &Value, env: &mut Env) -> Result<Check, String> {
+    let value = eval(form, env)?;
+    if is_truthy(&value) {
+        Ok(Check::Pass(value))
+    } else {
+        Ok(Check::Fail { expected: form.clone(), actual: value })
+    }
+}
+
+enum Thrown {
+    Matched(String),
+    Mismatched(String),
+    Returned(Value),
+}
+
+/// Evaluate the body of `(thrown? body...)` or `(thrown-with-msg? re body...)`.
+fn thrown(items: &[Value], env: &mut Env) -> Result<Thrown, String> {
+    let with_msg = matches!(&items[0], Value::Symbol(s) if s == "thrown-with-msg?");
+    let (pattern, body) = if with_msg {
+        if items.len() < 3 {
+            return Err("thrown-with-msg? requires a regex and a body".to_string());
+        }
+        match eval(&items[1], env)? {
+            Value::Regex(regex) => (Some(regex), &items[2..]),
+            other => return Err(format!("thrown-with-msg? requires a regex, got {}", other)),
+        }
+    } else {
+        if items.len() < 2 {
+            return Err("thrown? requires a body".to_string());
+        }
+        (None, &items[1..])
+    };
+
+    let mut value = Value::Nil;
+    for form in body {
+        match eval(form, env) {
+            Ok(result) => value = result,
+            Err(message) if is_interruption(&message) => return Err(message),
+            Err(message) => {
+                clear_error_trace();
+                return Ok(match pattern {
+                    Some(regex) if !regex.is_match(&message) => Thrown::Mismatched(message),
+                    _ => Thrown::Matched(message),
+                });
+            }
+        }
+    }
+    Ok(Thrown::Returned(value))
+}
+
+/// (thrown? body...) is true when evaluating the body fails, and
+/// (thrown-with-msg? re body...) when it fails with a message matching `re`.
+pub fn eval_thrown(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    Ok(Value::Bool(matches!(thrown(list, env)?, Thrown::Matched(_))))
+}
+
+/// (are [x y] (= x y) 1 1 2 2) checks the template with each group of
+/// arguments in turn, as though written out as separate `is` forms.
+pub fn eval_are(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    if list.len() < 3 {
+        return Err("are requires a binding vector and a template".to_string());
+    }
+    let names = match &list[1] {
+        Value::Vector(names) if !names.is_empty() => names
+            .iter()
+            .map(|name| match name {
+                Value::Symbol(name) => Ok(name.clone()),
+                other => Err(format!("are bindings must be symbols, got {}", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err("are requires a non-empty binding vector".to_string()),
+    };
+    let args = &list[3..];
+    if !args.len().is_multiple_of(names.len()) {
+        return Err(format!("are has {} arguments, not a multiple of its {} bindings", args.len(), names.len()));
+    }
+
+    let mut all_passed = true;
+    for group in args.chunks(names.len()) {
+        let bindings: HashMap<&str, &Value> = names.iter().map(String::as_str).zip(group).collect();
+        let form = substitute(&list[2], &bindings);
+        all_passed &= is_truthy(&assert_form(&form, None, env)?);
+    }
+    Ok(Value::Bool(all_passed))
+}
+
+fn substitute(form: &Value, bindings: &HashMap<&str, &Value>) -> Value {
+    match form {
+        Value::Symbol(name) => bindings.get(name.as_str()).map(|value| (*value).clone()).unwrap_or_else(|| form.clone()),
+        Value::List(items) => Value::List(items.iter().map(|item| substitute(item, bindings)).collect()),
+        Value::Vector(items) => Value::Vector(items.iter().map(|item| substitute(item, bindings)).collect()),
+        Value::Map(map) => Value::Map(map.iter().map(|(k, v)| (k.clone(), substitute(v, bindings))).collect()),
+        _ => form.clone(),
+    }
+}
+
+/// (use-fixtures :each f...) wraps every test in the current namespace, and
+/// (use-fixtures :once f...) the namespace's tests as a whole. A fixture is
+/// a function of the test to run, which it must call.
+pub fn eval_use_fixtures(list: &[Value], env: &mut Env) -> Result<Value, String> {
+    let kind = match list.get(1) {
+        Some(Value::Keyword(kind)) if kind == "each" || kind == "once" => kind.clone(),
+        _ => return Err("use-fixtures requires :each or :once".to_string()),
+    };
+    let mut fixtures = Vec::new();
+    for form in &list[2..] {
+        match eval(form, env)? {
+            fixture @ Value::Function(_) => fixtures.push(fixture),
+            other => return Err(format!("use-fixtures requires functions, got {}", other)),
+        }
+    }
+    let namespace = env.get_namespace().to_string();
+    FIXTURES.with(|all| {
+        let mut all = all.borrow_mut();
+        let entry = all.entry(namespace).or_default();
+        if kind == "each" {
+            entry.each = fixtures;
+        } else {
+            entry.once = fixtures;
+        }
+    });
+    Ok(Value::Nil)
+}
+
+// === RUNNING ===
+
+/// A function of no arguments that calls `fixture` with `inner`.
+fn wrap(fixture: &Value, inner: Value) -> Value {
+    let mut env = Env::new();
+    env.set("fixture".to_string(), fixture.clone());
+    env.set("inner".to_string(), inner);
+    let body = Value::List(vec![symbol("fixture"), symbol("inner")]);
+    Value::Function(Function::UserDefined { params: Vec::new(), body: Box::new(body), env })
+}
+
+fn with_fixtures(fixtures: &[Value], inner: Value) -> Value {
+    fixtures.iter().rev().fold(inner, |inner, fixture| wrap(fixture, inner))
+}
+
+thread_local! {
+    // Per-test results for the run in progress
+    static RESULTS: RefCell<Vec<Value>> = const { RefCell::new(Vec::new()) };
+}
+
+fn run_test(name: &str, function: &Value, each: &[Value]) -> Result<(), String> {
+    if FILTER.with(|filter| filter.borrow().as_ref().is_some_and(|filter| !name.contains(filter.as_str()))) {
+        return Ok(());
+    }
+    CURRENT_TEST.with(|test| *test.borrow_mut() = Some(name.to_string()));
+    let before = counts();
+    let start = Instant::now();
+    if ANNOUNCE.with(Cell::get) {
+        eprintln!("{}{}", STARTED, name);
+    }
+    let timeout = TIMEOUT.with(|t| t.get());
+    let outer_deadline = set_deadline(timeout.map(|timeout| start + timeout));
+    let outcome = call_function(&with_fixtures(each, function.clone()), &[]);
+    set_deadline(outer_deadline);
+    match outcome {
+        Ok(_) => {}
+        Err(e) if e.ends_with(TIMED_OUT) => {
+            clear_error_trace();
+            let expression = Value::List(vec![Value::Symbol(name.to_string())]);
+            let message = format!("Timed out after {}s", timeout.map(|t| t.as_secs_f64()).unwrap_or_default());
+            record_problem("error", &expression, Value::Nil, Value::Str(e), Some(message));
+        }
+        Err(e) if is_interruption(&e) => {
+            CURRENT_TEST.with(|test| *test.borrow_mut() = None);
+            return Err(e);
+        }
+        Err(e) => {
+            clear_error_trace();
+            let expression = Value::List(vec![Value::Symbol(name.to_string())]);
+            record_problem("error", &expression, Value::Nil, Value::Str(e), Some("Uncaught error".to_string()));
+        }
+    }
+    COUNTS.with(|counts| counts.borrow_mut().test += 1);
+    CURRENT_TEST.with(|test| *test.borrow_mut() = None);
+
+    let mut result = HashMap::new();
+    result.insert("name".to_string(), Value::Str(name.to_string()));
+    counts().since(&before).insert_into(&mut result);
+    result.remove("test");
+    result.insert("ms".to_string(), Value::Number(start.elapsed().as_secs_f64() * 1000.0));
+    RESULTS.with(|results| results.borrow_mut().push(Value::Map(result)));
+    Ok(())
+}
+
+/// Run the tests of the namespace given as the only argument, with its
+/// :each fixtures. The function a :once fixture is handed calls this.
+fn run_namespace_tests(args: &[Value]) -> Result<Value, String> {
+    let namespace = match args {
+        [Value::Str(namespace)] => namespace,
+        _ => return Err("run-namespace-tests requires a namespace".to_string()),
+    };
+    let tests: Vec<(String, Value)> = TESTS.with(|tests| {
+        tests
+            .borrow()
+            .iter()
+            .filter(|test| &test.namespace == namespace)
+            .map(|test| (test.name.clone(), test.function.clone()))
+            .collect()
+    });
+    let each = FIXTURES.with(|all| all.borrow().get(namespace).map(|f| f.each.clone()).unwrap_or_default());
+    for (name, function) in tests {
+        run_test(&name, &function, &each)?;
+    }
+    Ok(Value::Nil)
+}
+
+fn namespace_runner(namespace: &str) -> Value {
+    let mut env = Env::new();
+    env.set("run-namespace-tests".to_string(), Value::Function(Function::Native(run_namespace_tests)));
+    let body = Value::List(vec![symbol("run-namespace-tests"), Value::Str(namespace.to_string())]);
+    Value::Function(Function::UserDefined { params: Vec::new(), body: Box::new(body), env })
+}
+
+/// Run the tests registered in `namespaces`, or every test, and return
+/// {:test :pass :fail :error :tests [...] :failures [...]}.
+pub fn run_tests(args: &[Value]) -> Result<Value, String> {
+    let mut namespaces: Vec<String> = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Symbol(name) | Value::Str(name) => namespaces.push(name.clone()),
+            other => return Err(format!("run-tests requires namespace names, got {}", other)),
+        }
+    }
+    if namespaces.is_empty() {
+        namespaces = test_namespaces(|_| true);
+    }
+    run(&namespaces)
+}
+
+/// Run the tests in namespaces no `run-tests` call has covered yet, as the
+/// test runner does once a file has loaded.
+pub fn run_remaining() -> Result<Value, String> {
+    let ran = RAN.with(|ran| ran.borrow().clone());
+    run(&test_namespaces(|namespace| !ran.contains(namespace)))
+}
+
+fn test_namespaces(include: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut namespaces: Vec<String> = Vec::new();
+    TESTS.with(|tests| {
+        for test in tests.borrow().iter() {
+            if include(&test.namespace) && !namespaces.contains(&test.namespace) {
+                namespaces.push(test.namespace.clone());
+            }
+        }
+    });
+    namespaces
+}
+
+fn run(namespaces: &[String]) -> Result<Value, String> {
+    // A run counts on its own and leaves the assert-eq counters as they were
+    let outer_counts = COUNTS.with(|counts| std::mem::take(&mut *counts.borrow_mut()));
+    let outer_failures = FAILURES.with(|failures| std::mem::take(&mut *failures.borrow_mut()));
+    let outer_results = RESULTS.with(|results| std::mem::take(&mut *results.borrow_mut()));
+    let mut interrupted = None;
+    for namespace in namespaces {
+        RAN.with(|ran| ran.borrow_mut().insert(namespace.clone()));
+        write_out(&format!("\nTesting {}\n", namespace));
+        let once = FIXTURES.with(|all| all.borrow().get(namespace).map(|f| f.once.clone()).unwrap_or_default());
+        match call_function(&with_fixtures(&once, namespace_runner(namespace)), &[]) {
+            Ok(_) => {}
+            Err(e) if is_interruption(&e) => {
+                interrupted = Some(e);
+                break;
+            }
+            Err(e) => {
+                clear_error_trace();
+                let expression = Value::List(vec![symbol("use-fixtures"), Value::Keyword("once".to_string())]);
+                record_problem("error", &expression, Value::Nil, Value::Str(e), Some(format!("Fixture failed in {}", namespace)));
+            }
+        }
+    }
+    let ran = COUNTS.with(|counts| std::mem::replace(&mut *counts.borrow_mut(), outer_counts));
+    let failures = FAILURES.with(|failures| std::mem::replace(&mut *failures.borrow_mut(), outer_failures));
+    let results = RESULTS.with(|results| std::mem::replace(&mut *results.borrow_mut(), outer_results));
+    if let Some(e) = interrupted {
+        return Err(e);
+    }
+
+    write_out(&format!(
+        "\nRan {} tests containing {} assertions.\n{} failures, {} errors.\n",
+        ran.test,
+        ran.pass + ran.fail + ran.error,
+        ran.fail,
+        ran.error
+    ));
+
+    let mut summary = HashMap::new();
+    ran.insert_into(&mut summary);
+    summary.insert("tests".to_string(), Value::Vector(results));
+    summary.insert("failures".to_string(), Value::Vector(failures));
+    Ok(Value::Map(summary))
+}
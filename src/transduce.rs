@@ -0,0 +1,254 @@
+use crate::eval::{call_function, reader_lines};
+use crate::lazy::{self, SeqIter};
+use crate::value::{Function, IOResource, Value};
+use std::collections::VecDeque;
+
+/// One step of a transducer. A composed transducer is a sequence of steps
+/// applied left to right, so `(comp (map f) (filter p))` maps first.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Xform {
+    Map(Value),
+    Filter(Value),
+    Take(usize),
+    PartitionBy(Value),
+    Dedupe,
+}
+
+impl Xform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Xform::Map(_) => "map",
+            Xform::Filter(_) => "filter",
+            Xform::Take(_) => "take",
+            Xform::PartitionBy(_) => "partition-by",
+            Xform::Dedupe => "dedupe",
+        }
+    }
+}
+
+/// Whether a reduction should keep consuming input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+// Per-run state of each step; a transducer value itself is stateless and can
+// be reused across reductions.
+enum Stage {
+    Map(Value),
+    Filter(Value),
+    Take { remaining: usize },
+    PartitionBy { f: Value, key: Box<Value>, chunk: Vec<Value> },
+    Dedupe { prev: Option<Value> },
+}
+
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    pub fn new(xforms: &[Xform]) -> Self {
+        let stages = xforms
+            .iter()
+            .map(|xform| match xform {
+                Xform::Map(f) => Stage::Map(f.clone()),
+                Xform::Filter(pred) => Stage::Filter(pred.clone()),
+                Xform::Take(n) => Stage::Take { remaining: *n },
+                Xform::PartitionBy(f) => Stage::PartitionBy { f: f.clone(), key: Box::new(Value::Nil), chunk: Vec::new() },
+                Xform::Dedupe => Stage::Dedupe { prev: None },
+            })
+            .collect();
+        Pipeline { stages }
+    }
+
+    /// Feed one input through every stage, handing whatever comes out to `sink`.
+    pub fn push(&mut self, item: Value, sink: &mut dyn FnMut(Value) -> Result<Flow, String>) -> Result<Flow, String> {
+        push(&mut self.stages, item, sink)
+    }
+
+    /// Flush stages that hold buffered input (partition-by) once input ends.
+    pub fn finish(&mut self, sink: &mut dyn FnMut(Value) -> Result<Flow, String>) -> Result<(), String> {
+        for i in 0..self.stages.len() {
+            let (stage, downstream) = self.stages[i..].split_first_mut().expect("index in bounds");
+            if let Stage::PartitionBy { chunk, .. } = stage {
+                if !chunk.is_empty() {
+                    let chunk = std::mem::take(chunk);
+                    push(downstream, Value::Vector(chunk), sink)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn push(stages: &mut [Stage], item: Value, sink: &mut dyn FnMut(Value) -> Result<Flow, String>) -> Result<Flow, String> {
+    let (stage, downstream) = match stages.split_first_mut() {
+        Some(split) => split,
+        None => return sink(item),
+    };
+    match stage {
+        Stage::Map(f) => {
+            let mapped = call_function(f, &[item])?;
+            push(downstream, mapped, sink)
+        }
+        Stage::Filter(pred) => {
+            if is_truthy(&call_function(pred, std::slice::from_ref(&item))?) {
+                push(downstream, item, sink)
+            } else {
+                Ok(Flow::Continue)
+            }
+        }
+        Stage::Take { remaining } => {
+            if *remaining == 0 {
+                return Ok(Flow::Stop);
+            }
+            *remaining -= 1;
+            let flow = push(downstream, item, sink)?;
+            if *remaining == 0 {
+                Ok(Flow::Stop)
+            } else {
+                Ok(flow)
+            }
+        }
+        Stage::PartitionBy { f, key, chunk } => {
+            let item_key = call_function(f, std::slice::from_ref(&item))?;
+            if chunk.is_empty() || item_key == **key {
+                chunk.push(item);
+                **key = item_key;
+                return Ok(Flow::Continue);
+            }
+            let full = std::mem::replace(chunk, vec![item]);
+            **key = item_key;
+            let flow = push(downstream, Value::Vector(full), sink)?;
+            if flow == Flow::Stop {
+                chunk.clear();
+            }
+            Ok(flow)
+        }
+        Stage::Dedupe { prev } => {
+            if prev.as_ref() == Some(&item) {
+                return Ok(Flow::Continue);
+            }
+            *prev = Some(item.clone());
+            push(downstream, item, sink)
+        }
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Bool(false) | Value::Nil)
+}
+
+pub type Items = Box<dyn Iterator<Item = Result<Value, String>> + Send>;
+
+/// The items of anything that can be reduced: sequences, maps (as `[k v]`
+/// entries in key order) and readers (one string per line).
+pub fn items(coll: &Value, fn_name: &str) -> Result<Items, String> {
+    match coll {
+        Value::Map(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let entries: Vec<Value> = entries
+                .into_iter()
+                .map(|(k, v)| Value::Vector(vec![Value::Keyword(k.clone()), v.clone()]))
+                .collect();
+            Ok(Box::new(entries.into_iter().map(Ok)))
+        }
+        Value::IOResource(IOResource::Reader(reader)) => Ok(Box::new(reader_lines(reader))),
+        _ if lazy::is_seqable(coll) => Ok(Box::new(SeqIter::new(coll.clone()))),
+        _ => Err(format!("{} requires a collection or reader, got {}", fn_name, coll)),
+    }
+}
+
+/// Reduce `coll` with `f`, passing each item through `xforms` first. Stops
+/// early when `f` returns a `reduced` value or a transducer is done.
+pub fn transduce(xforms: &[Xform], f: &Value, init: Value, coll: &Value, fn_name: &str) -> Result<Value, String> {
+    let mut acc = Some(init);
+    let mut reduced = false;
+    let mut step = |item: Value| -> Result<Flow, String> {
+        // Input flushed at the end (e.g. by partition-by) must not reach a
+        // reducing function that already asked to stop
+        if reduced {
+            return Ok(Flow::Stop);
+        }
+        let current = acc.take().expect("accumulator present between steps");
+        match call_function(f, &[current, item])? {
+            Value::Reduced(result) => {
+                acc = Some(*result);
+                reduced = true;
+                Ok(Flow::Stop)
+            }
+            result => {
+                acc = Some(result);
+                Ok(Flow::Continue)
+            }
+        }
+    };
+    run(xforms, coll, fn_name, &mut step)?;
+    Ok(acc.expect("accumulator present after reduction"))
+}
+
+/// Drive `sink` with every item of `coll` after `xforms`, including the
+/// flush at the end of input.
+pub fn run(xforms: &[Xform], coll: &Value, fn_name: &str, sink: &mut dyn FnMut(Value) -> Result<Flow, String>) -> Result<(), String> {
+    let mut pipeline = Pipeline::new(xforms);
+    for item in items(coll, fn_name)? {
+        if pipeline.push(item?, sink)? == Flow::Stop {
+            break;
+        }
+    }
+    pipeline.finish(sink)
+}
+
+/// Lazily apply `xforms` to `coll`, as used by `sequence` and `eduction`.
+pub fn sequence(xforms: Vec<Xform>, coll: &Value, fn_name: &str) -> Result<Items, String> {
+    let mut source = items(coll, fn_name)?;
+    let mut pipeline = Pipeline::new(&xforms);
+    let mut ready: VecDeque<Value> = VecDeque::new();
+    let mut done = false;
+
+    Ok(Box::new(std::iter::from_fn(move || {
+        loop {
+            if let Some(item) = ready.pop_front() {
+                return Some(Ok(item));
+            }
+            if done {
+                return None;
+            }
+            let mut collect = |out: Value| {
+                ready.push_back(out);
+                Ok(Flow::Continue)
+            };
+            let outcome = match source.next() {
+                Some(Ok(item)) => pipeline.push(item, &mut collect).map(|flow| flow == Flow::Stop),
+                Some(Err(e)) => Err(e),
+                None => Ok(true),
+            };
+            match outcome {
+                Ok(false) => {}
+                Ok(true) => {
+                    done = true;
+                    if let Err(e) = pipeline.finish(&mut collect) {
+                        return Some(Err(e));
+                    }
+                }
+                Err(e) => {
+                    done = true;
+                    ready.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+    })))
+}
+
+/// The steps of a transducer argument, or an error naming the caller.
+pub fn xforms(value: &Value, fn_name: &str) -> Result<Vec<Xform>, String> {
+    match value {
+        Value::Transducer(xforms) => Ok(xforms.clone()),
+        // (comp) with nothing to compose is identity, the empty transducer
+        Value::Function(Function::Composed(fns)) if fns.is_empty() => Ok(Vec::new()),
+        _ => Err(format!("{} requires a transducer, got {}", fn_name, value)),
+    }
+}
@@ -3,7 +3,11 @@ use std::fmt;
 use std::io::{BufRead, Read, Write};
 use std::sync::{Arc, Mutex};
 use crate::env::Env;
+use crate::lazy::{self, LazySeq, Step};
+use crate::transduce::Xform;
+use crate::check::Generator;
 
+#[allow(unpredictable_function_pointer_comparisons)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Function {
     Native(fn(&[Value]) -> Result<Value, String>),
@@ -17,14 +21,124 @@ pub enum Function {
         body: Box<Value>,
         env: Env,
     },
+    /// `(comp f g h)`: h takes every argument, then g and f run on the result
+    Composed(Vec<Value>),
 }
 
+// Each handle holds `None` once it has been closed, so every clone of the
+// resource observes the close.
 #[derive(Clone)]
 pub enum IOResource {
-    Reader(Arc<Mutex<Box<dyn BufRead + Send>>>),
-    Writer(Arc<Mutex<Box<dyn Write + Send>>>),
-    InputStream(Arc<Mutex<Box<dyn Read + Send>>>),
-    OutputStream(Arc<Mutex<Box<dyn Write + Send>>>),
+    Reader(Arc<Mutex<Option<Box<dyn BufRead + Send>>>>),
+    Writer(Arc<Mutex<Option<Box<dyn Write + Send>>>>),
+    InputStream(Arc<Mutex<Option<Box<dyn Read + Send>>>>),
+    OutputStream(Arc<Mutex<Option<Box<dyn Write + Send>>>>),
+    /// A running or finished child process; closing it kills the process if
+    /// it's still running
+    Process(Arc<Mutex<Option<Box<std::process::Child>>>>),
+}
+
+impl IOResource {
+    pub fn reader(r: impl BufRead + Send + 'static) -> Self {
+        IOResource::Reader(Arc::new(Mutex::new(Some(Box::new(r)))))
+    }
+
+    pub fn writer(w: impl Write + Send + 'static) -> Self {
+        IOResource::Writer(Arc::new(Mutex::new(Some(Box::new(w)))))
+    }
+
+    pub fn input_stream(r: impl Read + Send + 'static) -> Self {
+        IOResource::InputStream(Arc::new(Mutex::new(Some(Box::new(r)))))
+    }
+
+    pub fn output_stream(w: impl Write + Send + 'static) -> Self {
+        IOResource::OutputStream(Arc::new(Mutex::new(Some(Box::new(w)))))
+    }
+
+    pub fn process(child: std::process::Child) -> Self {
+        IOResource::Process(Arc::new(Mutex::new(Some(Box::new(child)))))
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IOResource::Reader(_) => "reader",
+            IOResource::Writer(_) => "writer",
+            IOResource::InputStream(_) => "input-stream",
+            IOResource::OutputStream(_) => "output-stream",
+            IOResource::Process(_) => "process",
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            IOResource::Reader(r) => r.lock().map(|g| g.is_none()).unwrap_or(true),
+            IOResource::Writer(w) => w.lock().map(|g| g.is_none()).unwrap_or(true),
+            IOResource::InputStream(r) => r.lock().map(|g| g.is_none()).unwrap_or(true),
+            IOResource::OutputStream(w) => w.lock().map(|g| g.is_none()).unwrap_or(true),
+            IOResource::Process(p) => p.lock().map(|g| g.is_none()).unwrap_or(true),
+        }
+    }
+
+    /// Flush any buffered output. Readers have nothing to flush, but still
+    /// report an error once closed.
+    pub fn flush(&self) -> Result<(), String> {
+        match self {
+            IOResource::Reader(r) => with_open(r, "reader", |_| Ok(())),
+            IOResource::InputStream(r) => with_open(r, "input-stream", |_| Ok(())),
+            IOResource::Process(p) => with_open(p, "process", |_| Ok(())),
+            IOResource::Writer(w) => with_open(w, "writer", |w| {
+                w.flush().map_err(|e| format!("Failed to flush writer: {}", e))
+            }),
+            IOResource::OutputStream(w) => with_open(w, "output-stream", |w| {
+                w.flush().map_err(|e| format!("Failed to flush output-stream: {}", e))
+            }),
+        }
+    }
+
+    /// Flush (for writers) and release the underlying handle. Closing an
+    /// already closed resource is a no-op.
+    pub fn close(&self) -> Result<(), String> {
+        fn take<T: ?Sized>(res: &Mutex<Option<Box<T>>>, kind: &str) -> Result<Option<Box<T>>, String> {
+            res.lock()
+                .map(|mut guard| guard.take())
+                .map_err(|e| format!("Failed to lock {}: {}", kind, e))
+        }
+
+        match self {
+            IOResource::Reader(r) => take(r, "reader").map(|_| ()),
+            IOResource::InputStream(r) => take(r, "input-stream").map(|_| ()),
+            IOResource::Writer(w) => match take(w, "writer")? {
+                Some(mut w) => w.flush().map_err(|e| format!("Failed to flush writer on close: {}", e)),
+                None => Ok(()),
+            },
+            IOResource::OutputStream(w) => match take(w, "output-stream")? {
+                Some(mut w) => w.flush().map_err(|e| format!("Failed to flush output-stream on close: {}", e)),
+                None => Ok(()),
+            },
+            IOResource::Process(p) => match take(p, "process")? {
+                Some(mut child) => {
+                    if child.try_wait().map_err(|e| format!("Failed to check process: {}", e))?.is_none() {
+                        child.kill().map_err(|e| format!("Failed to kill process: {}", e))?;
+                    }
+                    child.wait().map(|_| ()).map_err(|e| format!("Failed to wait for process: {}", e))
+                }
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+/// Lock an IO handle and run `f` on it, failing if the resource was closed.
+pub fn with_open<T: ?Sized, R>(
+    res: &Mutex<Option<Box<T>>>,
+    kind: &str,
+    f: impl FnOnce(&mut T) -> Result<R, String>,
+) -> Result<R, String> {
+    let mut guard = res.lock().map_err(|e| format!("Failed to lock {}: {}", kind, e))?;
+    match guard.as_mut() {
+        Some(inner) => f(inner),
+        None => Err(format!("Cannot use closed {}", kind)),
+    }
 }
 
 impl std::fmt::Debug for IOResource {
@@ -34,6 +148,7 @@ impl std::fmt::Debug for IOResource {
             IOResource::Writer(_) => write!(f, "IOResource::Writer"),
             IOResource::InputStream(_) => write!(f, "IOResource::InputStream"),
             IOResource::OutputStream(_) => write!(f, "IOResource::OutputStream"),
+            IOResource::Process(_) => write!(f, "IOResource::Process"),
         }
     }
 }
@@ -44,7 +159,7 @@ impl PartialEq for IOResource {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Value {
     Symbol(String),
     Number(f64),
@@ -57,9 +172,86 @@ pub enum Value {
     Keyword(String),
     Function(Function),
     IOResource(IOResource),
+    Bytes(Vec<u8>),
+    LazySeq(LazySeq),
+    Transducer(Vec<Xform>),
+    /// Wraps a reduction result to stop the reduction early
+    Reduced(Box<Value>),
+    Regex(regex::Regex),
+    /// An instant in time as milliseconds since the Unix epoch, read as `#inst`
+    Inst(i64),
+    /// Read as `#uuid`
+    Uuid(u128),
+    /// A test.check style generator from `gen/`, or a property from `prop/for-all`
+    Generator(Box<Generator>),
     Uninitialized,
 }
 
+impl Value {
+    /// The name `type` reports for a value, e.g. "number" or "lazy-seq".
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Symbol(_) => "symbol",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "boolean",
+            Value::Nil => "nil",
+            Value::Str(_) => "string",
+            Value::List(_) => "list",
+            Value::Vector(_) => "vector",
+            Value::Map(_) => "map",
+            Value::Keyword(_) => "keyword",
+            Value::Function(Function::Macro { .. }) => "macro",
+            Value::Function(_) => "function",
+            Value::IOResource(resource) => resource.kind(),
+            Value::Bytes(_) => "bytes",
+            Value::LazySeq(_) => "lazy-seq",
+            Value::Transducer(_) => "transducer",
+            Value::Reduced(_) => "reduced",
+            Value::Regex(_) => "regex",
+            Value::Inst(_) => "inst",
+            Value::Uuid(_) => "uuid",
+            Value::Generator(_) => "generator",
+            Value::Uninitialized => "uninitialized",
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Symbol(a), Value::Symbol(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Vector(a), Value::Vector(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Keyword(a), Value::Keyword(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::IOResource(a), Value::IOResource(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::Transducer(a), Value::Transducer(b)) => a == b,
+            (Value::Reduced(a), Value::Reduced(b)) => a == b,
+            (Value::Regex(a), Value::Regex(b)) => a.as_str() == b.as_str(),
+            (Value::Inst(a), Value::Inst(b)) => a == b,
+            (Value::Uuid(a), Value::Uuid(b)) => a == b,
+            (Value::Generator(a), Value::Generator(b)) => a == b,
+            (Value::Uninitialized, Value::Uninitialized) => true,
+            // Lazy seqs compare element-wise with each other and with lists,
+            // and an empty one is nil just like `(list)`
+            (Value::LazySeq(a), Value::LazySeq(b)) => a == b,
+            (Value::LazySeq(seq), Value::List(items)) | (Value::List(items), Value::LazySeq(seq)) => {
+                lazy::seq_eq(Value::LazySeq(seq.clone()), Value::List(items.clone()))
+            }
+            (Value::LazySeq(seq), Value::Nil) | (Value::Nil, Value::LazySeq(seq)) => {
+                matches!(seq.step(), Ok(Step::Empty))
+            }
+            _ => false,
+        }
+    }
+}
+
 impl Eq for Value {}
 
 impl std::hash::Hash for Value {
@@ -114,6 +306,41 @@ impl std::hash::Hash for Value {
             Value::Uninitialized => {
                 11u8.hash(state);
             }
+            Value::Bytes(b) => {
+                12u8.hash(state);
+                b.hash(state);
+            }
+            // Realizes every element and hashes like the list (or nil) it
+            // compares equal to; one that fails to realize hashes as nil
+            Value::LazySeq(seq) => match seq.to_vec() {
+                Ok(items) if !items.is_empty() => {
+                    5u8.hash(state);
+                    items.hash(state);
+                }
+                _ => 3u8.hash(state),
+            },
+            Value::Transducer(_) => {
+                13u8.hash(state);
+            }
+            Value::Reduced(v) => {
+                14u8.hash(state);
+                v.hash(state);
+            }
+            Value::Regex(re) => {
+                15u8.hash(state);
+                re.as_str().hash(state);
+            }
+            Value::Inst(ms) => {
+                16u8.hash(state);
+                ms.hash(state);
+            }
+            Value::Uuid(id) => {
+                17u8.hash(state);
+                id.hash(state);
+            }
+            Value::Generator(_) => {
+                18u8.hash(state);
+            }
         }
     }
 }
@@ -173,13 +400,36 @@ impl fmt::Display for Value {
                 Function::Macro { params, .. } => {
                     write!(f, "#<macro({})>", params.join(" "))
                 }
+                Function::Composed(_) => write!(f, "#<function(comp)>"),
             },
-            Value::IOResource(resource) => match resource {
-                IOResource::Reader(_) => write!(f, "#<reader>"),
-                IOResource::Writer(_) => write!(f, "#<writer>"),
-                IOResource::InputStream(_) => write!(f, "#<input-stream>"),
-                IOResource::OutputStream(_) => write!(f, "#<output-stream>"),
+            Value::IOResource(resource) => write!(f, "#<{}>", resource.kind()),
+            Value::Bytes(bytes) => {
+                write!(f, "#bytes[")?;
+                for (i, b) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", b)?;
+                }
+                write!(f, "]")
+            }
+            Value::LazySeq(seq) => match seq.printable() {
+                Ok((items, false)) => write!(f, "{}", Value::List(items)),
+                Ok((items, true)) => {
+                    let shown = Value::List(items).to_string();
+                    write!(f, "{} ...)", &shown[..shown.len() - 1])
+                }
+                Err(e) => write!(f, "#<lazy-seq error: {}>", e),
             },
+            Value::Transducer(xforms) => {
+                let names: Vec<&str> = xforms.iter().map(|x| x.name()).collect();
+                write!(f, "#<transducer({})>", names.join(" "))
+            }
+            Value::Reduced(v) => write!(f, "#<reduced {}>", v),
+            Value::Regex(re) => write!(f, "#\"{}\"", re.as_str()),
+            Value::Inst(ms) => write!(f, "#inst \"{}\"", crate::edn::format_inst(*ms)),
+            Value::Uuid(id) => write!(f, "#uuid \"{}\"", crate::edn::format_uuid(*id)),
+            Value::Generator(generator) => write!(f, "#<generator({})>", generator.name()),
             Value::Uninitialized => write!(f, "#<uninitialized>"),
         }
     }
@@ -0,0 +1,243 @@
+use crate::eval::call_function;
+use crate::fs::path_string;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+impl Change {
+    pub fn to_value(&self) -> Value {
+        let mut event = HashMap::new();
+        event.insert("path".to_string(), Value::Str(path_string(&self.path)));
+        event.insert("kind".to_string(), Value::Keyword(self.kind.name().to_string()));
+        Value::Map(event)
+    }
+}
+
+#[derive(PartialEq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    is_dir: bool,
+}
+
+/// The state of a watched path, compared with a fresh scan on every poll.
+/// A path that doesn't exist yet is fine; it shows up as created later.
+pub struct Snapshot {
+    root: PathBuf,
+    recursive: bool,
+    entries: BTreeMap<PathBuf, Stamp>,
+}
+
+impl Snapshot {
+    pub fn take(root: &Path, recursive: bool) -> Self {
+        let mut snapshot = Snapshot { root: root.to_path_buf(), recursive, entries: BTreeMap::new() };
+        snapshot.entries = snapshot.scan();
+        snapshot
+    }
+
+    fn scan(&self) -> BTreeMap<PathBuf, Stamp> {
+        let mut entries = BTreeMap::new();
+        scan_into(&self.root, true, self.recursive, &mut entries);
+        entries
+    }
+
+    /// Rescan and report what changed since the last call, sorted by path.
+    /// Directories are reported when created or deleted but not modified.
+    pub fn changes(&mut self) -> Vec<Change> {
+        let current = self.scan();
+        let mut changes = Vec::new();
+        for (path, stamp) in &current {
+            match self.entries.get(path) {
+                None => changes.push(Change { path: path.clone(), kind: ChangeKind::Created }),
+                Some(old) if old != stamp && !(old.is_dir && stamp.is_dir) => {
+                    changes.push(Change { path: path.clone(), kind: ChangeKind::Modified })
+                }
+                _ => {}
+            }
+        }
+        for path in self.entries.keys() {
+            if !current.contains_key(path) {
+                changes.push(Change { path: path.clone(), kind: ChangeKind::Deleted });
+            }
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        self.entries = current;
+        changes
+    }
+}
+
+fn scan_into(path: &Path, top: bool, recursive: bool, entries: &mut BTreeMap<PathBuf, Stamp>) {
+    // Symlinks are stamped themselves rather than followed
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+    let is_dir = metadata.is_dir();
+    entries.insert(
+        path.to_path_buf(),
+        Stamp { modified: metadata.modified().ok(), len: metadata.len(), is_dir },
+    );
+    if is_dir && (top || recursive) {
+        if let Ok(children) = std::fs::read_dir(path) {
+            for child in children.flatten() {
+                scan_into(&child.path(), false, recursive, entries);
+            }
+        }
+    }
+}
+
+// === REGISTERED WATCHES ===
+
+struct Watch {
+    snapshot: Snapshot,
+    callback: Value,
+}
+
+thread_local! {
+    static WATCHES: RefCell<BTreeMap<u64, Watch>> = const { RefCell::new(BTreeMap::new()) };
+    static NEXT_ID: RefCell<u64> = const { RefCell::new(1) };
+}
+
+/// Start watching a path and return the watch id.
+pub fn watch(path: &str, callback: Value, opts: Option<&Value>, fn_name: &str) -> Result<Value, String> {
+    if !matches!(callback, Value::Function(_)) {
+        return Err(format!("{} requires a callback function, got {}", fn_name, callback));
+    }
+    let mut recursive = true;
+    match opts {
+        None | Some(Value::Nil) => {}
+        Some(Value::Map(map)) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("recursive", Value::Bool(b)) => recursive = *b,
+                    ("recursive", _) => return Err(format!("{} option :recursive must be a boolean", fn_name)),
+                    _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+                }
+            }
+        }
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    }
+
+    let id = NEXT_ID.with(|next| {
+        let mut next = next.borrow_mut();
+        *next += 1;
+        *next - 1
+    });
+    let snapshot = Snapshot::take(Path::new(path), recursive);
+    WATCHES.with(|watches| watches.borrow_mut().insert(id, Watch { snapshot, callback }));
+    Ok(Value::Number(id as f64))
+}
+
+pub fn unwatch(id: &Value, fn_name: &str) -> Result<Value, String> {
+    let id = match id {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => *n as u64,
+        other => return Err(format!("{} requires a watch id, got {}", fn_name, other)),
+    };
+    let removed = WATCHES.with(|watches| watches.borrow_mut().remove(&id));
+    Ok(Value::Bool(removed.is_some()))
+}
+
+/// Check every watch once, calling its callback with each change. A callback
+/// that returns `:stop` removes its watch. Returns the events delivered.
+///
+/// Changes are consumed as soon as they're seen, so a failing callback
+/// doesn't stop the rest being delivered; the first error is returned once
+/// every event has been.
+pub fn poll() -> Result<Value, String> {
+    let ids: Vec<u64> = WATCHES.with(|watches| watches.borrow().keys().copied().collect());
+    let mut delivered = Vec::new();
+    let mut first_error = None;
+    for id in ids {
+        // Release the registry before calling back, so callbacks can add or
+        // remove watches themselves
+        let found = WATCHES.with(|watches| {
+            watches
+                .borrow_mut()
+                .get_mut(&id)
+                .map(|watch| (watch.snapshot.changes(), watch.callback.clone()))
+        });
+        let (changes, callback) = match found {
+            Some(found) => found,
+            None => continue,
+        };
+        for change in changes {
+            let event = change.to_value();
+            let result = call_function(&callback, std::slice::from_ref(&event));
+            delivered.push(event);
+            match result {
+                Ok(Value::Keyword(k)) if k == "stop" => {
+                    WATCHES.with(|watches| watches.borrow_mut().remove(&id));
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(Value::Vector(delivered)),
+    }
+}
+
+/// Poll every `:interval` ms until no watches are left or `:timeout` ms pass.
+pub fn run(opts: Option<&Value>, fn_name: &str) -> Result<Value, String> {
+    let mut interval = Duration::from_millis(250);
+    let mut timeout = None;
+    match opts {
+        None | Some(Value::Nil) => {}
+        Some(Value::Map(map)) => {
+            for (key, value) in map {
+                match (key.as_str(), value) {
+                    ("interval", Value::Number(ms)) if *ms > 0.0 => interval = Duration::from_millis(*ms as u64),
+                    ("timeout", Value::Number(ms)) if *ms >= 0.0 => timeout = Some(Duration::from_millis(*ms as u64)),
+                    ("timeout", Value::Nil) => timeout = None,
+                    ("interval" | "timeout", _) => {
+                        return Err(format!("{} option :{} must be a number of milliseconds", fn_name, key))
+                    }
+                    _ => return Err(format!("{} does not support option :{}", fn_name, key)),
+                }
+            }
+        }
+        Some(_) => return Err(format!("{} options must be a map", fn_name)),
+    }
+
+    let start = Instant::now();
+    loop {
+        poll()?;
+        if WATCHES.with(|watches| watches.borrow().is_empty()) {
+            return Ok(Value::Nil);
+        }
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            return Ok(Value::Nil);
+        }
+        std::thread::sleep(interval);
+    }
+}
@@ -7,8 +7,7 @@
    :size (file-size path)
    :path path})
 
-;; with-open macro - simple resource management
+;; with-open is a special form that closes its resources in reverse order,
+;; even when the body fails; this alias keeps io/with-open working
 (defmacro with-open [bindings body]
-  `(let [~(first bindings) ~(second bindings)]
-     (let [result# ~body]
-       result#)))
\ No newline at end of file
+  `(with-open ~bindings ~body))
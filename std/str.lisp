@@ -15,10 +15,5 @@
 (defn str-concat [x y]
   (str x y))
 
-;; Join list elements with separator (simplified version)
-(defn join [sep xs]
-  (if (nil? xs)
-    ""
-    (if (nil? (rest xs))
-      (str (first xs))
-      (str (first xs) sep (join sep (rest xs))))))
+;; str/join, str/split, str/trim and the rest of the string library are
+;; native functions, available without requiring this module
@@ -0,0 +1,78 @@
+;; Property-based testing tests
+;; Generators, combinators, quick-check with seeds and shrinking, and defspec
+;; reporting through run-tests.
+
+(print "=== PROPERTY TESTING TESTS ===")
+
+(defn all? [pred coll] (empty? (filter (fn [x] (not (pred x))) coll)))
+(defn of-type? [name] (fn [x] (= name (type x))))
+
+(print "Testing generators...")
+
+(assert-eq 10 (count (gen/sample gen/int)))
+(assert-eq 5 (count (gen/sample gen/nat 5)))
+(assert-eq true (all? (fn [n] (and (>= n 0) (<= n 20))) (gen/sample gen/nat 21)))
+(assert-eq true (all? (fn [n] (and (>= n 3) (<= n 6))) (gen/sample (gen/choose 3 6) 50)))
+(assert-eq true (all? (of-type? :boolean) (gen/sample gen/boolean)))
+(assert-eq true (all? string? (gen/sample gen/string 30)))
+(assert-eq true (all? (of-type? :keyword) (gen/sample gen/keyword 30)))
+(assert-eq true (all? (of-type? :symbol) (gen/sample gen/symbol 30)))
+(assert-eq true (all? (fn [x] (or (= x :a) (= x :b))) (gen/sample (gen/elements [:a :b]))))
+(assert-eq 7 (gen/generate (gen/return 7)))
+(assert-eq 3 (count (gen/generate (gen/vector gen/int 3))))
+(assert-eq true (all? map? (gen/sample (gen/map gen/keyword gen/int))))
+(assert-eq :generator (type gen/any))
+
+;; The same seed makes the same value
+(assert-eq (gen/generate gen/any 20 42) (gen/generate gen/any 20 42))
+
+(print "Testing combinators...")
+
+(assert-eq true (all? even? (gen/sample (gen/fmap (fn [n] (* 2 n)) gen/int) 30)))
+(assert-eq true (all? odd? (gen/sample (gen/such-that odd? gen/int) 30)))
+(assert-eq true (all? (fn [v] (all? (of-type? :boolean) v))
+                        (gen/sample (gen/bind gen/nat (fn [n] (gen/vector gen/boolean n))))))
+(assert-eq true (all? (fn [x] (or (number? x) (string? x)))
+                        (gen/sample (gen/one-of [gen/int gen/string]) 30)))
+(assert-eq true (all? (fn [t] (and (number? (first t)) (string? (first (rest t)))))
+                        (gen/sample (gen/tuple gen/int gen/string))))
+
+(print "Testing quick-check...")
+
+(def commutative (check/quick-check 100 (prop/for-all [a gen/int b gen/int] (= (+ a b) (+ b a)))))
+(assert-eq true (get commutative :pass?))
+(assert-eq 100 (get commutative :num-tests))
+
+;; Anything the printer prints, the EDN reader reads back
+(assert-eq true (get (check/quick-check 100 (prop/for-all [x gen/any] (= x (edn/read-string (pr-str x))))) :pass?))
+
+;; Failures shrink to the simplest input that still fails
+(def short-vectors (prop/for-all [v (gen/vector gen/int)] (< (count v) 3)))
+(def failed (check/quick-check 100 short-vectors {:seed 7}))
+(assert-eq false (get failed :pass?))
+(assert-eq [[0 0 0]] (get (get failed :shrunk) :smallest))
+
+;; A seed reproduces the same run
+(assert-eq failed (check/quick-check 100 short-vectors {:seed 7}))
+
+;; A property that throws fails with the error as its result
+(def throwing (check/quick-check 10 (prop/for-all [n gen/nat] (undefined-fn n)) {:seed 1}))
+(assert-eq "Undefined symbol: undefined-fn" (get throwing :result))
+
+(print "Testing defspec...")
+
+(ns check-test.specs)
+
+(defspec always-natural 50 (prop/for-all [n gen/nat] (>= n 0)))
+(defspec below-ten {:num-tests 100 :seed 3} (prop/for-all [n gen/nat] (< n 10)))
+
+(ns user)
+
+(def specs (run-tests 'check-test.specs))
+(assert-eq 2 (get specs :test))
+(assert-eq 1 (get specs :pass))
+(assert-eq 1 (get specs :fail))
+(assert-eq "check-test.specs/below-ten" (get (first (get specs :failures)) :test))
+(assert-eq [10] (get (get (first (get specs :failures)) :actual) :smallest))
+
+(print "=== ALL PROPERTY TESTING TESTS PASSED ===")
@@ -194,4 +194,32 @@
 
 (print "✓ String operations")
 
+;; === DOCSTRINGS AND TYPES ===
+(print "Testing docstrings and type...")
+
+;; Definitions accept an optional docstring after the name
+(defn documented-square "Square a number." [x] (* x x))
+(assert-eq 9 (documented-square 3))
+(def documented-answer "The answer." 42)
+(assert-eq 42 documented-answer)
+(defmacro documented-unless "Inverse of if." [c body] `(if ~c nil ~body))
+(assert-eq 1 (documented-unless false 1))
+(def plain-string "just a string")
+(assert-eq "just a string" plain-string)
+
+(assert-eq :number (type 1))
+(assert-eq :string (type "s"))
+(assert-eq :keyword (type :k))
+(assert-eq :vector (type [1]))
+(assert-eq :map (type {:a 1}))
+(assert-eq :nil (type nil))
+(assert-eq :function (type documented-square))
+(assert-eq :function (type map))
+(assert-eq :lazy-seq (type (range 3)))
+
+(print "✓ Docstrings and type")
+
+;; pst prints an error map like the REPL's *e
+(assert-eq nil (pst {:message "boom" :trace ["(explode)"]}))
+
 (print "=== ALL CORE LANGUAGE TESTS PASSED ===")
\ No newline at end of file
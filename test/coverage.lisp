@@ -0,0 +1,89 @@
+;; Coverage tests
+;; cortado test --coverage-file run on a fixture library and its tests,
+;; checked through the lcov records and the summary table
+
+(print "=== COVERAGE TESTS ===")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+(def library-source
+  (str "(require 'core.control)\n"
+       "(defn sign [n]\n"
+       "  (if (< n 0) \"negative\" \"non-negative\"))\n"
+       "(defn size [n]\n"
+       "  (cond (< n 10) \"small\"\n"
+       "        (< n 100) \"medium\"\n"
+       "        \"large\"))\n"
+       "(defn size-q [n]\n"
+       "  (core.control/cond (< n 10) \"small\"\n"
+       "                     (< n 100) \"medium\"\n"
+       "                     \"large\"))\n"
+       "(defn unused [x] (* x 2))\n"))
+
+(defn lines-of [text]
+  (into [] (str/split-lines text)))
+
+(with-temp [dir (temp-dir)
+            report (temp-file {:suffix ".info"})]
+  (do
+    (def library (path/join dir "shapes.lisp"))
+    (def tests (path/join dir "shapes_test.lisp"))
+    (spit library library-source)
+    (spit tests (str "(load " (pr-str library) ")\n"
+                     "(deftest signs (is (= \"negative\" (sign -1))) (is (= \"negative\" (sign -2))))\n"
+                     "(deftest sizes (is (= \"small\" (size 1))) (is (= \"large\" (size 1000))) (is (= \"medium\" (size-q 50))))\n"))
+
+    (def run (sh cortado "test" "--coverage-file" report tests))
+    (assert-eq 0 (get run :exit))
+
+    ;; The library's record; the required core.control gets one of its own
+    (def record
+      (lines-of (first (filter (fn [section] (str/includes? section (str "SF:" library)))
+                               (str/split (slurp report) "end_of_record")))))
+
+    ;; === FUNCTIONS ===
+    (print "Testing function records...")
+
+    (assert-eq ["FN:2,user/sign" "FN:4,user/size" "FN:8,user/size-q" "FN:12,user/unused"]
+               (into [] (filter (fn [line] (str/starts-with? line "FN:")) record)))
+    (assert-eq ["FNDA:2,user/sign" "FNDA:2,user/size" "FNDA:1,user/size-q" "FNDA:0,user/unused"]
+               (into [] (filter (fn [line] (str/starts-with? line "FNDA:")) record)))
+    (assert-eq true (str/includes? (slurp report) "FNF:4\nFNH:3\n"))
+
+    (print "✓ Function records")
+
+    ;; === BRANCHES ===
+    (print "Testing branch records...")
+
+    ;; Branches are listed under the line their if or cond starts on. Each
+    ;; if has two; each cond has one per result plus its default, whether
+    ;; written cond or core.control/cond
+    (assert-eq ["BRDA:3,0,0,2" "BRDA:3,0,1,0"
+                "BRDA:5,1,0,1" "BRDA:5,1,1,0" "BRDA:5,1,2,1"
+                "BRDA:9,2,0,0" "BRDA:9,2,1,1" "BRDA:9,2,2,0"]
+               (into [] (filter (fn [line] (str/starts-with? line "BRDA:")) record)))
+    (assert-eq true (str/includes? (slurp report) "BRF:8\nBRH:4\n"))
+
+    (print "✓ Branch records")
+
+    ;; === LINES ===
+    (print "Testing line records...")
+
+    (assert-eq ["DA:1,1" "DA:2,2" "DA:3,2" "DA:4,2" "DA:5,1" "DA:6,0"
+                "DA:7,1" "DA:8,1" "DA:9,0" "DA:10,1" "DA:11,0" "DA:12,1"]
+               (into [] (filter (fn [line] (str/starts-with? line "DA:")) record)))
+    (assert-eq true (str/includes? (slurp report) "LF:12\nLH:9\n"))
+
+    (print "✓ Line records")
+
+    ;; === SUMMARY ===
+    (print "Testing the summary table...")
+
+    (assert-eq true (str/includes? (get run :out) "Coverage:\n  Namespace"))
+    (is (re-find #"\n  user +9/12 75\.0% +4/8 50\.0% +3/4 75\.0%\n" (get run :out)))
+    (assert-eq true (str/includes? (get run :out) (str "Wrote " report "\n")))
+
+    (print "✓ Summary table")))
+
+(print "=== ALL COVERAGE TESTS PASSED ===")
@@ -0,0 +1,73 @@
+;; CSV tests
+;; csv/read, csv/read-str, csv/write and csv/write-str
+
+(print "=== CSV TESTS ===")
+
+;; === READING ===
+(print "Testing csv/read-str...")
+
+(assert-eq [["a" "b" "c"] ["1" "2" "3"]] (csv/read-str "a,b,c\n1,2,3\n"))
+(assert-eq [["a" "b"] ["1" "2"]] (csv/read-str "a,b\r\n1,2"))
+(assert-eq [["x" "" ""]] (csv/read-str "x,,"))
+(assert-eq [["a"] ["b"]] (csv/read-str "a\n\nb\n"))
+(assert-eq [] (csv/read-str ""))
+
+;; Quoting: separators, doubled quotes and line breaks inside fields
+(assert-eq [["Smith, Jo" "say \"hi\"" ""]] (csv/read-str "\"Smith, Jo\",\"say \"\"hi\"\"\",\"\""))
+(assert-eq [["multi\nline" "next"] ["2" "3"]] (csv/read-str "\"multi\nline\",next\n2,3"))
+(assert-eq [[""]] (csv/read-str "\"\""))
+
+;; Custom separators and quotes, e.g. TSV
+(assert-eq [["a" "b,c"] ["1" "2"]] (csv/read-str "a\tb,c\n1\t2" {:separator "\t"}))
+(assert-eq [["a;b" "c"]] (csv/read-str "'a;b';c" {:separator ";" :quote "'"}))
+
+;; Header rows become map keys
+(def people (csv/read-str "name,city\nAda,London\nGrace,\"New York, NY\"\n" {:header true}))
+(assert-eq [{:name "Ada" :city "London"} {:name "Grace" :city "New York, NY"}] people)
+(assert-eq "London" (get (first people) :city))
+(assert-eq [] (csv/read-str "" {:header true}))
+
+(print "✓ csv/read-str")
+
+;; === WRITING ===
+(print "Testing csv/write-str...")
+
+(assert-eq "a,b\n1,2\n" (csv/write-str [["a" "b"] [1 2]]))
+(assert-eq "\"Smith, Jo\",\"say \"\"hi\"\"\",\"two\nlines\"\n" (csv/write-str [["Smith, Jo" "say \"hi\"" "two\nlines"]]))
+(assert-eq "1.5,,x,k\n" (csv/write-str [[1.5 nil 'x :k]]))
+(assert-eq "a\tb c\r\n" (csv/write-str [["a" "b c"]] {:separator "\t" :newline :crlf}))
+(assert-eq "\"\"\n" (csv/write-str [[""]]))
+
+;; Maps get a header row: sorted keys, or :columns in the given order
+(assert-eq "age,name\n36,Ada\n" (csv/write-str [{:name "Ada" :age 36}]))
+(assert-eq "name,age\nAda,36\nGrace,\n" (csv/write-str [{:name "Ada" :age 36} {:name "Grace"}] {:columns [:name :age]}))
+
+(print "✓ csv/write-str")
+
+;; === ROUND TRIPS ===
+(print "Testing round trips...")
+
+(def tricky [["plain" "with,comma" "with \"quotes\""] ["multi\nline" "" "trailing space "] [""]])
+(assert-eq tricky (csv/read-str (csv/write-str tricky)))
+(assert-eq tricky (csv/read-str (csv/write-str tricky {:separator "\t"}) {:separator "\t"}))
+(assert-eq people (csv/read-str (csv/write-str people {:columns [:name :city]}) {:header true}))
+
+(print "✓ Round trips")
+
+;; === FILES AND READERS ===
+(print "Testing files and readers...")
+
+(csv/write "csv-test.txt" [{:id 1 :item "tea, green"} {:id 2 :item "coffee"}])
+(assert-eq "id,item\n1,\"tea, green\"\n2,coffee\n" (slurp "csv-test.txt"))
+(assert-eq [{:id "1" :item "tea, green"} {:id "2" :item "coffee"}] (csv/read "csv-test.txt" {:header true}))
+(assert-eq [["id" "item"] ["1" "tea, green"] ["2" "coffee"]]
+  (with-open [in (reader "csv-test.txt")] (csv/read in)))
+
+(with-open [out (writer "csv-test.txt")]
+  (csv/write out [["x" "y"] [1 2]]))
+(assert-eq [["x" "y"] ["1" "2"]] (csv/read "csv-test.txt"))
+(delete-file "csv-test.txt")
+
+(print "✓ Files and readers")
+
+(print "=== ALL CSV TESTS PASSED ===")
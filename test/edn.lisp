@@ -0,0 +1,131 @@
+;; EDN tests
+;; pr-str/prn, edn/read-string, tagged literals and the round-trip guarantee
+
+(print "=== EDN TESTS ===")
+
+;; === READABLE PRINTING ===
+(print "Testing pr-str...")
+
+(assert-eq "\"line\\nbreak\"" (pr-str "line\nbreak"))
+(assert-eq "\"say \\\"hi\\\"\"" (pr-str "say \"hi\""))
+(assert-eq "{:a 1 :b \"two\"}" (pr-str {:b "two" :a 1}))
+(assert-eq "{\"has space\" 1}" (pr-str (assoc {} "has space" 1)))
+(assert-eq "(1 :k sym nil true)" (pr-str (list 1 :k 'sym nil true)))
+(assert-eq "[1.5 -2]" (pr-str [1.5 -2]))
+(assert-eq "(0 1 2)" (pr-str (range 3)))
+(assert-eq "1 \"a\"" (pr-str 1 "a"))
+(assert-eq "" (pr-str))
+
+;; str and print stay human-oriented
+(assert-eq "line\nbreak" (str "line\nbreak"))
+(assert-eq nil (prn "prn output" {:a [1 2]}))
+
+(print "✓ pr-str")
+
+;; === READING DATA ===
+(print "Testing edn/read-string...")
+
+(assert-eq {:a [1 2] :b "x"} (edn/read-string "{:a [1 2] :b \"x\"}"))
+(assert-eq '(+ 1 2) (edn/read-string "(+ 1 2)"))
+(assert-eq 'sym (edn/read-string "sym"))
+(assert-eq nil (edn/read-string ""))
+(assert-eq nil (edn/read-string nil))
+
+(print "✓ edn/read-string")
+
+;; === TAGGED LITERALS ===
+(print "Testing #inst, #uuid and tag readers...")
+
+(def launch #inst "2024-02-29T12:30:15.123+01:00")
+(assert-eq true (inst? launch))
+(assert-eq "#inst \"2024-02-29T11:30:15.123Z\"" (pr-str launch))
+(assert-eq 1000 (inst-ms #inst "1970-01-01T00:00:01Z"))
+(assert-eq -1 (inst-ms #inst "1969-12-31T23:59:59.999Z"))
+(assert-eq #inst "2000-01-01T00:00:00Z" #inst "2000")
+(assert-eq launch (edn/read-string (pr-str launch)))
+
+(def id #uuid "F81D4FAE-7DEC-11D0-A765-00A0C91E6BF6")
+(assert-eq true (uuid? id))
+(assert-eq "f81d4fae-7dec-11d0-a765-00a0c91e6bf6" (str id))
+(assert-eq id (edn/read-string (pr-str id)))
+
+(assert-eq (bytes [1 2 255]) (edn/read-string (pr-str (bytes [1 2 255]))))
+
+;; Per-call readers, a default for unknown tags, and registered readers
+(assert-eq {:x 1 :y 2}
+  (edn/read-string "#point [1 2]" {:readers {:point (fn [v] {:x (first v) :y (first (rest v))})}}))
+(assert-eq ['money 5] (edn/read-string "#money 5" {:default (fn [tag v] [tag v])}))
+(register-tag-reader! 'celsius (fn [degrees] {:kelvin (+ degrees 273)}))
+(assert-eq {:kelvin 373} (edn/read-string "#celsius 100"))
+
+(print "✓ Tagged literals")
+
+;; === ROUND TRIPS ===
+;; For every generated value v, (edn/read-string (pr-str v)) equals v.
+;; Values come from a seeded Park-Miller generator so failures reproduce.
+(print "Testing read/print round trips...")
+
+(defn next-seed [s] (% (* s 16807) 2147483647))
+(defn pick [s coll] (first (drop (% s (count coll)) coll)))
+
+(def sample-strings (list "" "plain" "line\nbreak" "tab\there" "quote\"d" "back\\slash" "ünïcödé ☕" "semi;colon" "#not-a-tag"))
+(def sample-keywords (list :a :b-c :ns/key :x? :<>))
+(def sample-symbols (list 'foo 'bar-baz 'ns/sym '+ 'odd?))
+(def sample-tagged (list #inst "2024-01-02T03:04:05.678Z" #inst "1900-06-15" #uuid "00000000-0000-0000-0000-000000000000" (bytes [0 127 255])))
+
+;; Each generator returns [value next-seed]
+(defn gen-scalar [s]
+  (let [kind (% s 9) s2 (next-seed s)]
+    (if (= kind 0) [nil s2]
+    (if (= kind 1) [(even? s2) s2]
+    (if (= kind 2) [(- (% s2 2001) 1000) s2]
+    (if (= kind 3) [(/ (- (% s2 200001) 100000) 7) s2]
+    (if (= kind 4) [(pick s2 sample-strings) s2]
+    (if (= kind 5) [(pick s2 sample-keywords) s2]
+    (if (= kind 6) [(pick s2 sample-symbols) s2]
+    (if (= kind 7) [(pick s2 sample-tagged) s2]
+    [(/ (% s2 1000) 1024) s2]))))))))))
+
+;; Collections and their contents are generated by one self-recursive
+;; function: :value makes any value, :items a list of n values and :entries a
+;; map with n entries
+(defn gen [mode s depth n]
+  (if (= mode :value)
+    (if (or (= depth 0) (< (% s 10) 5))
+      (gen-scalar (next-seed s))
+      (let [kind (% s 3) size (% (next-seed s) 4) s2 (next-seed (next-seed s))]
+        (if (= kind 0)
+          (gen :items s2 depth size)
+          (if (= kind 1)
+            (let [items (gen :items s2 depth size)] [(into [] (first items)) (first (rest items))])
+            (gen :entries s2 depth size)))))
+    (if (= n 0)
+      [(if (= mode :items) (list) {}) s]
+      (let [head (gen :value s (- depth 1) 0)
+            s2 (next-seed (first (rest head)))
+            tail (gen mode s2 depth (- n 1))]
+        [(if (= mode :items)
+           (cons (first head) (first tail))
+           (assoc (first tail) (pick s2 sample-strings) (first head)))
+         (first (rest tail))]))))
+
+(defn round-trips? [v] (= v (edn/read-string (pr-str v))))
+
+(def generated (doall (map (fn [i] (first (gen :value (next-seed (+ 42 i)) 3 0))) (range 40))))
+(assert-eq 40 (count generated))
+(assert-eq [] (into [] (filter (fn [v] (not (round-trips? v)))) generated))
+
+;; Values print the same after a round trip, too
+(assert-eq [] (into [] (filter (fn [v] (not (= (pr-str v) (pr-str (edn/read-string (pr-str v))))))) generated))
+
+(print "✓ Round trips")
+
+;; === UNREADABLE VALUES ===
+(print "Testing unreadable values...")
+
+(assert-eq "#<function(x)>" (pr-str (fn [x] x)))
+(assert-eq "##Inf" (pr-str (edn/read-string "##Inf")))
+
+(print "✓ Unreadable values")
+
+(print "=== ALL EDN TESTS PASSED ===")
@@ -0,0 +1,73 @@
+;; Process environment tests
+;; getenv, setenv, env-map and *command-line-args*
+
+(print "=== ENVIRONMENT TESTS ===")
+
+;; === ENVIRONMENT VARIABLES ===
+(print "Testing getenv and setenv...")
+
+(assert-eq nil (getenv "CORTADO_ENV_TEST"))
+(setenv "CORTADO_ENV_TEST" "espresso")
+(assert-eq "espresso" (getenv "CORTADO_ENV_TEST"))
+(assert-eq "espresso" (get (env-map) "CORTADO_ENV_TEST"))
+
+;; Values are converted with str, nil removes the variable
+(setenv "CORTADO_ENV_TEST" 42)
+(assert-eq "42" (getenv "CORTADO_ENV_TEST"))
+(setenv "CORTADO_ENV_TEST" nil)
+(assert-eq nil (getenv "CORTADO_ENV_TEST"))
+(assert-eq nil (get (env-map) "CORTADO_ENV_TEST"))
+
+;; Child processes see the changes
+(setenv "CORTADO_ENV_TEST" "inherited")
+(assert-eq "inherited\n" (get (sh "sh" "-c" "echo $CORTADO_ENV_TEST") :out))
+(setenv "CORTADO_ENV_TEST" nil)
+
+(assert-eq (getenv "PATH") (get (env-map) "PATH"))
+
+(print "✓ getenv and setenv")
+
+;; === COMMAND-LINE ARGUMENTS ===
+(print "Testing *command-line-args*...")
+
+;; Only scripts run from the command line receive arguments
+(assert-eq nil *command-line-args*)
+
+(print "✓ *command-line-args*")
+
+;; === RUNNING SCRIPTS ===
+(print "Testing scripts run by the cortado binary...")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+(defn run-script [source args]
+  (with-temp [script (temp-file)]
+    (do (spit script source)
+        (sh (concat [cortado script] args)))))
+
+(def args-run (run-script "(println (count *command-line-args*) (first *command-line-args*))" ["in.csv" "out"]))
+(assert-eq 0 (get args-run :exit))
+(assert-eq "2 in.csv\n" (get args-run :out))
+
+(def main-run (run-script "(defn -main [input output] (println input \"->\" output))" ["in.csv" "out"]))
+(assert-eq 0 (get main-run :exit))
+(assert-eq "in.csv -> out\n" (get main-run :out))
+
+(def rest-main-run (run-script "(defn -main [& args] (println args))" ["a" "b"]))
+(assert-eq 1 (get rest-main-run :exit))
+(assert-eq "" (get rest-main-run :out))
+(assert-eq true (str/includes? (get rest-main-run :err) "-main can't take & rest arguments"))
+
+(def failing-run (run-script "(println \"before\") (undefined-fn) (println \"after\")" []))
+(assert-eq 1 (get failing-run :exit))
+(assert-eq "before\n" (get failing-run :out))
+(assert-eq true (str/includes? (get failing-run :err) "Undefined symbol: undefined-fn"))
+
+(def exit-run (run-script "(println \"before\") (exit 3) (println \"after\")" []))
+(assert-eq 3 (get exit-run :exit))
+(assert-eq "before\n" (get exit-run :out))
+
+(print "✓ Running scripts")
+
+(print "=== ALL ENVIRONMENT TESTS PASSED ===")
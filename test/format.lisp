@@ -0,0 +1,88 @@
+;; Format tests
+;; format and printf directives: conversions, flags, width, precision and
+;; positional arguments
+
+(print "=== FORMAT TESTS ===")
+
+;; === CONVERSIONS ===
+(print "Testing conversions...")
+
+(assert-eq "Hello World" (format "Hello %s" "World"))
+(assert-eq "42 k [1 2] nil" (format "%s %s %s %s" 42 :k [1 2] nil))
+(assert-eq "42" (format "%d" 42))
+(assert-eq "-7" (format "%d" -7))
+(assert-eq "3.14" (format "%.2f" 3.14159))
+(assert-eq "2.500000" (format "%f" 2.5))
+(assert-eq "3" (format "%.0f" 3.0))
+(assert-eq "1.234500e+03" (format "%e" 1234.5))
+(assert-eq "1.23E-04" (format "%.2E" 0.000123))
+(assert-eq "ff FF 17" (format "%x %X %o" 255 255 15))
+(assert-eq "ffffffffffffffff" (format "%x" -1))
+(assert-eq "100%" (format "%d%%" 100))
+(assert-eq "a\nb" (format "a%nb"))
+
+(print "✓ Conversions")
+
+;; === WIDTH, PADDING AND FLAGS ===
+(print "Testing width and flags...")
+
+(assert-eq "   42" (format "%5d" 42))
+(assert-eq "42   |" (format "%-5d|" 42))
+(assert-eq "00042" (format "%05d" 42))
+(assert-eq "-0042" (format "%05d" -42))
+(assert-eq "+42 -42" (format "%+d %+d" 42 -42))
+(assert-eq " 42" (format "% d" 42))
+(assert-eq "1,234,567" (format "%,d" 1234567))
+(assert-eq "-1,234.50" (format "%,.2f" -1234.5))
+(assert-eq "  3.14" (format "%6.2f" 3.14159))
+(assert-eq "003.14" (format "%06.2f" 3.14159))
+(assert-eq "abc" (format "%.3s" "abcdef"))
+(assert-eq "|  hi|hi  |" (format "|%4s|%-4s|" "hi" "hi"))
+
+;; Width counts characters, not bytes
+(assert-eq "  ñ" (format "%3s" "ñ"))
+(assert-eq "日本 |" (format "%-3s|" "日本"))
+
+(print "✓ Width and flags")
+
+;; === POSITIONAL ARGUMENTS ===
+(print "Testing positional arguments...")
+
+(assert-eq "World Hello" (format "%2$s %1$s" "Hello" "World"))
+(assert-eq "7 7 7" (format "%1$d %1$d %1$d" 7))
+(assert-eq "  a|b" (format "%1$3s|%2$s" "a" "b"))
+
+
+;; Bare %1 placeholders are rejected rather than read as a width
+(is (thrown-with-msg? #"%1 is not a directive; write %1\$s" (format "%1 and %2" "a" "b")))
+(is (thrown-with-msg? #"%2 is not a directive" (format "value=%2" "a" "b")))
+
+(print "✓ Positional arguments")
+
+;; === ERRORS ===
+(print "Testing format errors...")
+
+(is (thrown-with-msg? #"%d requires a number, got \"abc\"" (format "%d" "abc")))
+(is (thrown-with-msg? #"%.2f requires a number, got :k" (format "%.2f" :k)))
+(is (thrown-with-msg? #"%x requires an integer, got 1.5" (format "%x" 1.5)))
+(is (thrown-with-msg? #"%s needs argument 2 but only 1 was given" (format "%s %s" "a")))
+(is (thrown-with-msg? #"%2\$s needs argument 2" (format "%2$s" "a")))
+(is (thrown-with-msg? #"argument 2 \(\"extra\"\) is never used" (format "%s" "a" "extra")))
+(is (thrown-with-msg? #"unknown conversion 'q'" (format "%q" 1)))
+(is (thrown-with-msg? #"incomplete directive" (format "50%")))
+(is (thrown-with-msg? #"printf requires a format string" (printf 42)))
+(is (thrown-with-msg? #"never used" (printf "%s" 1 2)))
+
+(print "✓ Format errors")
+
+;; === PRINTF ===
+(print "Testing printf...")
+
+(assert-eq "printf: x=002.5\n" (with-out-str (printf "printf: %s=%05.1f%n" "x" 2.5)))
+(assert-eq "   7|b" (with-out-str (printf "%4d|%2$s" 7 "b")))
+(assert-eq nil (printf ""))
+(assert-eq "outer inner" (with-out-str (printf "outer %s" (with-out-str (printf "inner")))))
+
+(print "✓ printf")
+
+(print "=== ALL FORMAT TESTS PASSED ===")
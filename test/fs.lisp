@@ -0,0 +1,111 @@
+;; Filesystem tests
+;; path/ functions, walk-dir, glob, file-info, temp files and create-dir
+
+(print "=== FILESYSTEM TESTS ===")
+
+;; === PATHS ===
+(print "Testing path functions...")
+
+(assert-eq "a/b/c.txt" (path/join "a" "b" "c.txt"))
+(assert-eq "/etc/hosts" (path/join "a" "/etc" "hosts"))
+(assert-eq "a/b" (path/parent "a/b/c.txt"))
+(assert-eq nil (path/parent "c.txt"))
+(assert-eq "c.txt" (path/file-name "a/b/c.txt"))
+(assert-eq "gz" (path/extension "archive.tar.gz"))
+(assert-eq nil (path/extension "Makefile"))
+(assert-eq nil (path/extension ".bashrc"))
+
+(assert-eq "/a/c" (path/absolute "/a/b/../c/."))
+(assert-eq (path/join (path/canonical ".") "x/y") (path/absolute "x/./y"))
+(assert-eq "../c/d" (path/relative-to "/a/b" "/a/c/d"))
+(assert-eq "b/c" (path/relative-to "/a" "/a/b/c"))
+(assert-eq "." (path/relative-to "/a/b" "/a/b/"))
+
+(print "✓ path functions")
+
+;; === TEMP FILES AND DIRECTORIES ===
+(print "Testing temp files...")
+
+(def kept (temp-file {:prefix "kept-" :suffix ".txt"}))
+(assert-eq true (file-exists? kept))
+(assert-eq ".txt" (str "." (path/extension kept)))
+(assert-eq 0 (file-size kept))
+(delete-file kept)
+
+(def outside
+  (with-temp [dir (temp-dir)
+              notes (temp-file {:dir dir :suffix ".md"})]
+    (do
+      (assert-eq true (directory? dir))
+      (assert-eq dir (path/parent notes))
+      dir)))
+(assert-eq false (file-exists? outside))
+
+;; with-temp refuses paths it didn't create, leaving them in place
+(spit "fs-real.txt" "keep me")
+(is (thrown-with-msg? #"only binds paths made by temp-file or temp-dir"
+      (with-temp [f "fs-real.txt"] (slurp f))))
+(assert-eq "keep me" (slurp "fs-real.txt"))
+(delete-file "fs-real.txt")
+(is (thrown-with-msg? #"only binds paths made by temp-file or temp-dir"
+      (with-temp [d "."] d)))
+(assert-eq true (file-exists? "test/fs.lisp"))
+
+;; An earlier temp path is still deleted when a later binding is refused
+(def early (temp-file))
+(is (thrown? (with-temp [t early bad "nope.txt"] t)))
+(assert-eq false (file-exists? early))
+
+(print "✓ temp files")
+
+;; === CREATE-DIR, WALK-DIR AND GLOB ===
+(print "Testing walk-dir and glob...")
+
+(with-temp [root (temp-dir)]
+  (do
+    (create-dir (path/join root "src/nested/deep"))
+    (spit (path/join root "src/main.lisp") "(print 1)")
+    (spit (path/join root "src/util.lisp") "")
+    (spit (path/join root "src/nested/notes.txt") "hello")
+    (spit (path/join root "src/nested/deep/x.lisp") "")
+    (spit (path/join root "src/.hidden.lisp") "")
+    (spit (path/join root "README.md") "")
+
+    (create-dir (path/join root "src/plain") {:parents false})
+    (assert-eq true (directory? (path/join root "src/plain")))
+    (delete-dir (path/join root "src/plain"))
+
+    (def rel (fn [paths] (into [] (map (fn [p] (path/relative-to root p)) paths))))
+    (assert-eq ["README.md" "src"] (rel (walk-dir root {:max-depth 1})))
+    (assert-eq ["src" "src/nested" "src/nested/deep"] (rel (walk-dir root {:type :dir})))
+    (assert-eq ["src/.hidden.lisp" "src/main.lisp" "src/nested/deep/x.lisp" "src/util.lisp"]
+      (rel (walk-dir root {:type :file :filter (fn [p] (= "lisp" (path/extension p)))})))
+
+    (assert-eq ["src/main.lisp" "src/util.lisp"] (glob "src/*.lisp" {:dir root}))
+    (assert-eq ["src/main.lisp" "src/nested/deep/x.lisp" "src/util.lisp"] (glob "**/*.lisp" {:dir root}))
+    (assert-eq ["src/.hidden.lisp"] (glob "src/.*.lisp" {:dir root}))
+    (assert-eq ["README.md" "src/nested/notes.txt"] (glob "{*.md,src/*/*.txt}" {:dir root}))
+    (assert-eq ["src/main.lisp"] (glob "src/[lm]ai?.lisp" {:dir root}))
+    (assert-eq [] (glob "*.rs" {:dir root}))
+    (assert-eq [(path/join root "README.md")] (glob (path/join root "*.md")))
+
+    ;; === METADATA ===
+    (def info (file-info (path/join root "src/nested/notes.txt")))
+    (assert-eq :file (get info :type))
+    (assert-eq 5 (get info :size))
+    (assert-eq true (inst? (get info :modified)))
+    (assert-eq false (get info :symlink?))
+    (assert-eq :dir (get (file-info (path/join root "src")) :type))
+
+    (sh "ln" "-s" "notes.txt" (path/join root "src/nested/link"))
+    (def link-info (file-info (path/join root "src/nested/link")))
+    (assert-eq :symlink (get link-info :type))
+    (assert-eq "notes.txt" (get link-info :target))
+    (assert-eq :file (get (file-info (path/join root "src/nested/link") {:follow-links true}) :type))
+
+    (sh "chmod" "640" (path/join root "README.md"))
+    (assert-eq "rw-r-----" (get (file-info (path/join root "README.md")) :permissions))))
+
+(print "✓ walk-dir, glob and metadata")
+
+(print "=== ALL FILESYSTEM TESTS PASSED ===")
@@ -0,0 +1,178 @@
+;; IO resource lifecycle tests
+;; close, flush, closed? and the with-open special form
+
+(print "=== IO RESOURCE TESTS ===")
+
+;; === CLOSE AND FLUSH ===
+(print "Testing close and flush...")
+
+(def w (writer "resource-close.txt"))
+(spit w "buffered")
+(assert-eq nil (flush w))
+(assert-eq false (closed? w))
+(assert-eq nil (close w))
+(assert-eq true (closed? w))
+(assert-eq nil (close w))  ; closing twice is a no-op
+(assert-eq "buffered" (slurp "resource-close.txt"))
+
+(def r (reader "resource-close.txt"))
+(close r)
+(assert-eq true (closed? r))
+
+(print "✓ Close and flush")
+
+;; === WITH-OPEN ===
+(print "Testing with-open...")
+
+(def opened-writer (writer "resource-with-open.txt"))
+(with-open [out opened-writer]
+  (spit out "line one"))
+(assert-eq true (closed? opened-writer))
+(assert-eq "line one" (slurp "resource-with-open.txt"))
+
+(assert-eq "line one"
+  (with-open [in (reader "resource-with-open.txt")]
+    (read-line in)))
+
+;; Resources are closed in reverse order and the body result is returned
+(def copied
+  (with-open [in (reader "resource-with-open.txt")
+              out (writer "resource-copy.txt")]
+    (copy in out)))
+(assert-eq 8 copied)
+(assert-eq "line one" (slurp "resource-copy.txt"))
+
+;; The io/with-open alias delegates to the special form
+(require 'io)
+(assert-eq "line one"
+  (io/with-open [in (reader "resource-copy.txt")]
+    (slurp in)))
+
+;; Resources are closed even when the body raises an error
+(def failing-reader (reader "resource-with-open.txt"))
+(is (thrown-with-msg? #"Undefined symbol: body-failed"
+      (with-open [in failing-reader]
+        (body-failed))))
+(assert-eq true (closed? failing-reader))
+
+;; Using a resource after it is closed is an error
+(is (thrown-with-msg? #"closed reader" (read-line failing-reader)))
+(is (thrown-with-msg? #"closed writer" (spit opened-writer "too late")))
+
+(print "✓ With-open")
+
+;; === OPTIONS ===
+(print "Testing reader/writer options...")
+
+;; :append keeps existing content
+(spit "resource-append.txt" "first\n")
+(spit "resource-append.txt" "second\n" {:append true})
+(with-open [out (writer "resource-append.txt" {:append true})]
+  (spit out "third\n"))
+(assert-eq "first\nsecond\nthird\n" (slurp "resource-append.txt"))
+
+;; Without :append the file is truncated
+(spit "resource-append.txt" "fresh")
+(assert-eq "fresh" (slurp "resource-append.txt"))
+
+;; :encoding round-trips through latin1 and UTF-16
+(spit "resource-latin1.txt" "café" {:encoding "latin1"})
+(assert-eq 4 (file-size "resource-latin1.txt"))
+(assert-eq "café" (slurp "resource-latin1.txt" {:encoding "latin1"}))
+
+(with-open [out (writer "resource-utf16.txt" {:encoding "UTF-16"})]
+  (spit out "héllo"))
+(assert-eq 12 (file-size "resource-utf16.txt"))  ; BOM + 5 code units
+(assert-eq "héllo" (with-open [in (reader "resource-utf16.txt" {:encoding "utf-16"})]
+                     (read-line in)))
+
+(spit "resource-utf16.txt" " wörld" {:encoding "utf-16" :append true})
+(assert-eq "héllo wörld" (slurp "resource-utf16.txt" {:encoding "utf-16"}))
+
+;; :buffer-size only changes buffering, not content
+(with-open [out (writer "resource-buffer.txt" {:buffer-size 4})]
+  (spit out "small buffers work"))
+(assert-eq "small buffers work"
+  (with-open [in (reader "resource-buffer.txt" {:buffer-size 2})]
+    (read-line in)))
+
+;; :create-dirs creates missing parent directories
+(spit "resource-dir/nested/out.txt" "nested" {:create-dirs true})
+(assert-eq "nested" (slurp "resource-dir/nested/out.txt"))
+
+(print "✓ Reader/writer options")
+
+;; === STREAMING READS ===
+(print "Testing streaming readers...")
+
+(spit "resource-lines.txt" "alpha\nbeta\r\ngamma")
+(assert-eq '("alpha" "beta" "gamma")
+  (with-open [in (reader "resource-lines.txt")]
+    (doall (line-seq in))))
+
+;; line-seq is lazy: lines must be realized before the reader closes
+(def closed-lines (with-open [in (reader "resource-lines.txt")] (line-seq in)))
+(assert-eq false (realized? closed-lines))
+
+;; line-seq continues from the reader's current position
+(assert-eq '("gamma")
+  (with-open [in (reader "resource-lines.txt")]
+    (do (read-line in)
+        (read-line in)
+        (doall (line-seq in)))))
+
+(spit "resource-chars.txt" "añ€")
+(def char-reader (reader "resource-chars.txt"))
+(assert-eq "a" (read-char char-reader))
+(assert-eq "ñ" (read-char char-reader))
+(assert-eq "€" (read-char char-reader))
+(assert-eq nil (read-char char-reader))
+(close char-reader)
+
+(print "✓ Streaming readers")
+
+;; === BINARY IO ===
+(print "Testing binary IO...")
+
+(def payload (bytes [0 1 127 128 255]))
+(assert-eq true (bytes? payload))
+(assert-eq false (bytes? "text"))
+(assert-eq 5 (count payload))
+(assert-eq [0 1 127 128 255] (bytes->vector payload))
+(assert-eq "hé" (bytes->str (bytes "hé")))
+(assert-eq "é" (bytes->str (bytes [233]) "latin1"))
+
+(with-open [out (output-stream "resource-binary.bin")]
+  (write-bytes out payload))
+(assert-eq 5 (file-size "resource-binary.bin"))
+
+(with-open [in (input-stream "resource-binary.bin")]
+  (do (assert-eq (bytes [0 1 127]) (read-bytes in 3))
+      (assert-eq (bytes [128 255]) (read-bytes in 10))
+      (assert-eq nil (read-bytes in 10))))
+
+;; Streams copy byte-for-byte
+(with-open [in (input-stream "resource-binary.bin")
+            out (output-stream "resource-binary-copy.bin")]
+  (copy in out))
+(assert-eq payload
+  (with-open [in (input-stream "resource-binary-copy.bin")]
+    (read-bytes in 100)))
+
+(print "✓ Binary IO")
+
+;; === CLEANUP ===
+(delete-file "resource-close.txt")
+(delete-file "resource-with-open.txt")
+(delete-file "resource-copy.txt")
+(delete-file "resource-append.txt")
+(delete-file "resource-latin1.txt")
+(delete-file "resource-utf16.txt")
+(delete-file "resource-buffer.txt")
+(delete-dir "resource-dir")
+(delete-file "resource-lines.txt")
+(delete-file "resource-chars.txt")
+(delete-file "resource-binary.bin")
+(delete-file "resource-binary-copy.bin")
+
+(print "=== ALL IO RESOURCE TESTS PASSED ===")
@@ -0,0 +1,70 @@
+;; JSON tests
+;; json/parse, json/write-str and json/write
+
+(print "=== JSON TESTS ===")
+
+;; === PARSING ===
+(print "Testing json/parse...")
+
+(assert-eq {:name "Ada" :age 36} (json/parse "{\"name\": \"Ada\", \"age\": 36}"))
+(assert-eq [1 2.5 -3 true false nil] (json/parse "[1, 2.5, -3, true, false, null]"))
+(assert-eq "héllo ✓" (json/parse "\"h\\u00e9llo \\u2713\""))
+(assert-eq {:items [{:id 1} {:id 2}]} (json/parse "{\"items\": [{\"id\": 1}, {\"id\": 2}]}"))
+(assert-eq 1500 (json/parse "1.5e3"))
+(assert-eq nil (json/parse " null "))
+
+;; Map keys work as keywords or strings; :key-fn rewrites them
+(def person (json/parse "{\"first_name\": \"Ada\"}"))
+(assert-eq "Ada" (get person :first_name))
+(assert-eq "Ada" (get person "first_name"))
+(assert-eq {:first-name "Ada"}
+  (json/parse "{\"first_name\": \"Ada\"}" {:key-fn (fn [k] (str/replace k "_" "-"))}))
+
+(print "✓ json/parse")
+
+;; === WRITING ===
+(print "Testing json/write-str...")
+
+(assert-eq "{\"a\":1,\"b\":[1,2.5,null]}" (json/write-str {:b [1 2.5 nil] :a 1}))
+(assert-eq "[1,2,3]" (json/write-str (list 1 2 3)))
+(assert-eq "[0,1,2]" (json/write-str (range 3)))
+(assert-eq "\"say \\\"hi\\\"\"" (json/write-str "say \"hi\""))
+(assert-eq "true" (json/write-str true))
+
+;; Keywords and symbols are written as their names
+(assert-eq "[\"red\",\"green\"]" (json/write-str [:red 'green]))
+
+(assert-eq "{\n  \"a\": [\n    1,\n    2\n  ]\n}" (json/write-str {:a [1 2]} {:pretty true}))
+
+
+;; Values with no JSON form are errors
+(is (thrown-with-msg? #"json/write-str can't encode a function as JSON" (json/write-str {:f inc})))
+(is (thrown-with-msg? #"can't encode a function" (json/write-str [(fn [x] x)])))
+(is (thrown-with-msg? #"can't encode ##NaN as JSON" (json/write-str (edn/read-string "##NaN"))))
+(is (thrown-with-msg? #"can't encode ##Inf as JSON" (json/write-str [(edn/read-string "##Inf")])))
+(is (thrown-with-msg? #"can't encode ##-Inf as JSON" (json/write-str {:x (edn/read-string "##-Inf")})))
+(is (thrown-with-msg? #"can't encode bytes as JSON; convert them with bytes->str" (json/write-str (bytes [1 2]))))
+
+(print "✓ json/write-str")
+
+;; === ROUND TRIPS ===
+(print "Testing round trips...")
+
+(def doc {:name "cortado" :version 1.5 :tags ["lisp" "rust"] :meta {:stable false :owner nil}})
+(assert-eq doc (json/parse (json/write-str doc)))
+(assert-eq doc (json/parse (json/write-str doc {:pretty true})))
+
+(print "✓ Round trips")
+
+;; === READERS AND WRITERS ===
+(print "Testing json/write and parsing from a reader...")
+
+(with-open [out (writer "json-test.txt")]
+  (json/write out doc {:pretty true}))
+(assert-eq doc (with-open [in (reader "json-test.txt")] (json/parse in)))
+(assert-eq doc (json/parse (slurp "json-test.txt")))
+(delete-file "json-test.txt")
+
+(print "✓ json/write and readers")
+
+(print "=== ALL JSON TESTS PASSED ===")
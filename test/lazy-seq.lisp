@@ -0,0 +1,112 @@
+;; Lazy sequence tests
+;; lazy-seq, realization and memoization, and the lazy sequence functions
+
+(print "=== LAZY SEQUENCE TESTS ===")
+
+;; === LAZY-SEQ SPECIAL FORM ===
+(print "Testing lazy-seq...")
+
+(defn naturals-from [n]
+  (lazy-seq (cons n (naturals-from (+ n 1)))))
+
+(assert-eq '(5 6 7) (take 3 (naturals-from 5)))
+(assert-eq 5 (first (naturals-from 5)))
+(assert-eq 6 (first (rest (naturals-from 5))))
+(assert-eq '(1 2) (lazy-seq (list 1 2)))
+(assert-eq '(1 2) (lazy-seq [1 2]))
+(assert-eq nil (lazy-seq nil))
+(assert-eq true (empty? (lazy-seq nil)))
+(assert-eq nil (first (lazy-seq nil)))
+(assert-eq "(1 2 3)" (str (lazy-seq (list 1 2 3))))
+
+;; The body runs once, on first use, and the result is memoized
+(def side-effects (writer "lazy-effects.txt"))
+(def effectful (lazy-seq (do (spit side-effects "x") (list 1 2))))
+(assert-eq false (realized? effectful))
+(assert-eq 1 (first effectful))
+(assert-eq true (realized? effectful))
+(assert-eq 2 (count effectful))
+(close side-effects)
+(assert-eq "x" (slurp "lazy-effects.txt"))
+
+(print "✓ lazy-seq")
+
+;; === SEQUENCE PRIMITIVES ===
+(print "Testing first/rest/cons/count...")
+
+(def lazy-abc (map (fn [x] x) (list "a" "b" "c")))
+(assert-eq "a" (first lazy-abc))
+(assert-eq '("b" "c") (rest lazy-abc))
+(assert-eq nil (rest (rest (rest lazy-abc))))
+(assert-eq '("z" "a" "b" "c") (cons "z" lazy-abc))
+(assert-eq 3 (count lazy-abc))
+(assert-eq 3 (length lazy-abc))
+(assert-eq true (seq? lazy-abc))
+(assert-eq false (list? lazy-abc))
+(assert-eq nil (seq (filter (fn [x] false) (list 1 2))))
+(assert-eq '(1 2) (seq [1 2]))
+(assert-eq 10 (apply + (range 5)))
+
+(print "✓ Sequence primitives")
+
+;; === LAZY SEQUENCE FUNCTIONS ===
+(print "Testing map/filter/take/drop/iterate/range/concat...")
+
+(assert-eq '(2 3 4) (map inc (list 1 2 3)))
+(assert-eq '(11 22) (map + [1 2 3] (list 10 20)))
+(assert-eq nil (map inc nil))
+(assert-eq '(0 2 4) (filter even? (range 6)))
+(assert-eq nil (filter even? (list 1 3)))
+(assert-eq '(1 2) (take 2 [1 2 3]))
+(assert-eq nil (take 0 (list 1 2)))
+(assert-eq '(1 2) (take 10 (list 1 2)))
+(assert-eq '(3 4) (drop 2 (list 1 2 3 4)))
+(assert-eq nil (drop 5 (list 1 2)))
+(assert-eq '(1 2 4 8) (take 4 (iterate (fn [x] (* x 2)) 1)))
+(assert-eq '(0 1 2) (range 3))
+(assert-eq '(2 3 4) (range 2 5))
+(assert-eq '(10 7 4) (range 10 1 -3))
+(assert-eq nil (range 5 5))
+(assert-eq '(1 2 3 4) (concat (list 1 2) [3] nil (range 4 5)))
+(assert-eq nil (concat))
+
+;; Infinite sequences are fine as long as only a prefix is realized
+(assert-eq '(0 1 2) (take 3 (range)))
+(assert-eq 1000 (first (drop 1000 (range))))
+(assert-eq '(0 4 16) (take 3 (filter even? (map (fn [x] (* x x)) (range)))))
+
+;; Functions are only called for realized elements
+(def squares (map (fn [x] (* x x)) (range)))
+(assert-eq 100 (first (drop 10 squares)))
+(assert-eq 100000 (count (range 100000)))
+
+;; Macros built with concat still expand to evaluable code
+(defmacro unless [c body] (concat (list 'if c nil) (list body)))
+(assert-eq "ran" (unless false "ran"))
+
+(print "✓ Lazy sequence functions")
+
+;; === EQUALITY ===
+(assert-eq true (= nil (filter even? nil)))
+(assert-not-eq '(1 2) (lazy-seq (list 1 2 3)))
+
+;; Comparison stops at the first difference, so it works on infinite seqs
+(assert-not-eq '(0 1 2) (range))
+(assert-eq false (= (naturals-from 0) (range 1 10)))
+
+;; === PRINTING ===
+(print "Testing printing...")
+
+;; Only the first 1000 elements print; the rest are marked with ...
+(assert-eq "(0 1 2)" (str (range 3)))
+(assert-eq true (str/ends-with? (str (range 1000)) " 999)"))
+(assert-eq true (str/ends-with? (str (range 1001)) " 999 ...)"))
+(assert-eq true (str/starts-with? (str (naturals-from 0)) "(0 1 2 3"))
+(assert-eq true (str/ends-with? (pr-str (range)) " 999 ...)"))
+
+(print "✓ Printing")
+
+;; === CLEANUP ===
+(delete-file "lazy-effects.txt")
+
+(print "=== ALL LAZY SEQUENCE TESTS PASSED ===")
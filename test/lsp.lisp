@@ -0,0 +1,101 @@
+;; Language server tests
+;; lsp/session, defined only for test files, runs a server over a list of
+;; messages and returns its replies
+
+(print "=== LSP TESTS ===")
+
+(defn reply-to [replies id]
+  (first (filter (fn [r] (= (get r :id) id)) replies)))
+
+(defn notifications [replies]
+  (into [] (filter (fn [r] (= (get r :method) "textDocument/publishDiagnostics")) replies)))
+
+(defn pos [line character] {:line line :character character})
+
+(defn request [id method params] {:id id :method method :params params})
+
+(defn at [id method uri line character]
+  (request id method {:textDocument {:uri uri} :position (pos line character)}))
+
+(def main-text "(ns app.main)\n(require [geometry.shapes :as g])\n(defn twice \"Doubles x.\" [x] (* 2 x))\n(g/area 3)\n(twice (str/upper-case \"é\"))\n")
+
+(with-temp [root (temp-dir)]
+  (do
+    (create-dir (path/join root "std/geometry") {:parents true})
+    (spit (path/join root "std/geometry/shapes.lisp")
+          ";; Shapes\n(defn area \"Area of a square with side s.\" [s] (* s s))\n(def unit 1)\n")
+    (def root-uri (str "file://" root))
+    (def main-uri (str root-uri "/src/main.lisp"))
+    (def shapes-uri (str root-uri "/std/geometry/shapes.lisp"))
+
+    (def replies
+      (lsp/session
+        [(request 1 "initialize" {:rootUri root-uri :capabilities {}})
+         {:method "initialized" :params {}}
+         {:method "textDocument/didOpen" :params {:textDocument {:uri main-uri :languageId "cortado" :version 1 :text main-text}}}
+         (request 2 "textDocument/documentSymbol" {:textDocument {:uri main-uri}})
+         (at 3 "textDocument/definition" main-uri 3 3)
+         (at 4 "textDocument/definition" main-uri 4 2)
+         (at 5 "textDocument/hover" main-uri 3 4)
+         (at 6 "textDocument/hover" main-uri 4 10)
+         (at 7 "textDocument/completion" main-uri 3 3)
+         (at 8 "textDocument/definition" main-uri 1 12)
+         {:method "textDocument/didChange" :params {:textDocument {:uri main-uri :version 2} :contentChanges [{:text "(defn broken [x]\n  (+ x 1"}]}}
+         (request 9 "textDocument/frobnicate" {})
+         (request 10 "shutdown" nil)
+         {:method "exit"}]))
+
+    (print "Testing initialize...")
+    (def capabilities (get (get (reply-to replies 1) :result) :capabilities))
+    (assert-eq true (get capabilities :definitionProvider))
+    (assert-eq true (get capabilities :hoverProvider))
+    (assert-eq ["/"] (get (get capabilities :completionProvider) :triggerCharacters))
+
+    (print "Testing diagnostics...")
+    (def published (notifications replies))
+    (assert-eq 2 (count published))
+    (assert-eq [] (get (get (first published) :params) :diagnostics))
+    (def problem (first (get (get (first (rest published)) :params) :diagnostics)))
+    (assert-eq "Unterminated list" (get problem :message))
+    (assert-eq 1 (get problem :severity))
+    ;; Reported at the list left open on the second line
+    (assert-eq (pos 1 2) (get (get problem :range) :start))
+
+    (print "Testing document symbols...")
+    (def symbols (get (reply-to replies 2) :result))
+    (assert-eq ["app.main" "twice"] (into [] (map (fn [s] (get s :name)) symbols)))
+    (assert-eq [3 12] (into [] (map (fn [s] (get s :kind)) symbols)))
+    (assert-eq "(twice [x])" (get (first (rest symbols)) :detail))
+    (assert-eq {:start (pos 2 6) :end (pos 2 11)} (get (first (rest symbols)) :selectionRange))
+
+    (print "Testing go-to-definition...")
+    ;; Through the alias into the required namespace's file
+    (assert-eq {:uri shapes-uri :range {:start (pos 1 6) :end (pos 1 10)}} (get (reply-to replies 3) :result))
+    ;; A definition in the same file
+    (assert-eq {:uri main-uri :range {:start (pos 2 6) :end (pos 2 11)}} (get (reply-to replies 4) :result))
+    ;; The namespace in a require goes to the top of its file
+    (assert-eq shapes-uri (get (get (reply-to replies 8) :result) :uri))
+
+    (print "Testing hover...")
+    (def area-hover (get (get (get (reply-to replies 5) :result) :contents) :value))
+    (assert-eq "```clojure\n(area [s])\n```\n\nArea of a square with side s.\n\n*geometry.shapes/area*" area-hover)
+    (def builtin-hover (get (get (get (reply-to replies 6) :result) :contents) :value))
+    (assert-eq "```clojure\nstr/upper-case\n```\n\nBuilt-in function" builtin-hover)
+
+    (print "Testing completion...")
+    (def labels (into [] (map (fn [item] (get item :label)) (get (get (reply-to replies 7) :result) :items))))
+    (assert-eq ["g/area" "g/unit"] labels)
+
+    (print "Testing errors and shutdown...")
+    (assert-eq -32601 (get (get (reply-to replies 9) :error) :code))
+    (assert-eq true (contains? (reply-to replies 10) :result))
+    (assert-eq nil (get (reply-to replies 10) :result))))
+
+;; lsp/session is only defined for test files, not for scripts
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+(def script-run (sh cortado "-e" "(lsp/session [])"))
+(assert-eq 1 (get script-run :exit))
+(assert-eq true (str/includes? (get script-run :err) "Undefined symbol: lsp/session"))
+
+(print "=== ALL LSP TESTS PASSED ===")
@@ -0,0 +1,99 @@
+;; nREPL tests
+;; A server on a free local port, driven by the nrepl/ client functions
+
+(print "=== NREPL TESTS ===")
+
+(def port (nrepl/start-server))
+(def conn (nrepl/connect port))
+
+(defn values-of [responses]
+  (into [] (map (fn [r] (get r :value)) (filter (fn [r] (contains? r :value)) responses))))
+
+(defn status-of [responses]
+  (get (first (filter (fn [r] (contains? r :status)) responses)) :status))
+
+(print "Testing describe and sessions...")
+
+(def described (first (nrepl/message conn {:op "describe"})))
+(assert-eq true (contains? (get described :ops) "eval"))
+(assert-eq true (contains? (get described :ops) "interrupt"))
+(assert-eq ["done"] (get described :status))
+
+(def session (get (first (nrepl/message conn {:op "clone"})) :new-session))
+(assert-eq 36 (count session))
+(def listed (get (first (nrepl/message conn {:op "ls-sessions"})) :sessions))
+(assert-eq 1 (count (filter (fn [id] (= id session)) listed)))
+
+(print "Testing eval...")
+
+(def responses (nrepl/message conn {:op "eval" :code "(+ 1 2)" :session session}))
+(assert-eq ["3"] (values-of responses))
+(assert-eq "user" (get (first responses) :ns))
+(assert-eq session (get (first responses) :session))
+(assert-eq ["done"] (status-of responses))
+
+;; Definitions persist in the session, and each form gets a value
+(nrepl/message conn {:op "eval" :code "(defn twice \"Doubles x.\" [x] (* 2 x))" :session session})
+(assert-eq ["10" "\"hi\""] (values-of (nrepl/message conn {:op "eval" :code "(twice 5) \"hi\"" :session session})))
+
+;; Printed output arrives as :out before the value
+(def printed (nrepl/message conn {:op "eval" :code "(println \"hello\") 7" :session session}))
+(assert-eq "hello\n" (get (first printed) :out))
+(assert-eq ["nil" "7"] (values-of printed))
+
+;; Errors report :err and :ex with an eval-error status
+(def failed (nrepl/message conn {:op "eval" :code "(undefined-fn 1)" :session session}))
+(assert-eq ["eval-error" "done"] (status-of failed))
+(assert-eq true (str/starts-with? (get (first failed) :err) "Error: "))
+(assert-eq ["eval-error" "done"] (status-of (nrepl/message conn {:op "eval" :code "(+ 1" :session session})))
+
+(print "Testing load-file...")
+
+(def loaded (nrepl/message conn {:op "load-file" :file "(def loaded-x 41) (inc loaded-x)" :file-path "x.lisp" :session session}))
+(assert-eq ["41" "42"] (values-of loaded))
+(assert-eq ["41"] (values-of (nrepl/message conn {:op "eval" :code "loaded-x" :session session})))
+
+(print "Testing complete...")
+
+(def completions (get (first (nrepl/message conn {:op "complete" :prefix "twi" :session session})) :completions))
+(assert-eq [{:candidate "twice" :type "function"}] completions)
+(def special (get (first (nrepl/message conn {:op "complete" :prefix "letr" :session session})) :completions))
+(assert-eq [{:candidate "letrec" :type "special-form"}] special)
+
+(print "Testing info and lookup...")
+
+(def info (first (nrepl/message conn {:op "info" :sym "twice" :session session})))
+(assert-eq "Doubles x." (get info :doc))
+(assert-eq "([x])" (get info :arglists-str))
+(assert-eq "user" (get info :ns))
+(def looked-up (first (nrepl/message conn {:op "lookup" :sym "twice" :session session})))
+(assert-eq "twice" (get (get looked-up :info) :name))
+(assert-eq ["no-info" "done"] (status-of (nrepl/message conn {:op "info" :sym "no-such-thing" :session session})))
+
+(print "Testing interrupt...")
+
+(def slow (nrepl/send conn {:op "eval" :code "(sleep-ms 10000)" :session session}))
+(sleep-ms 100)
+(assert-eq ["done"] (status-of (nrepl/message conn {:op "interrupt" :interrupt-id slow :session session})))
+(assert-eq ["interrupted" "done"] (status-of (nrepl/responses conn slow)))
+;; The session carries on afterwards
+(assert-eq ["2"] (values-of (nrepl/message conn {:op "eval" :code "(inc 1)" :session session})))
+(assert-eq ["session-idle" "done"] (status-of (nrepl/message conn {:op "interrupt" :session session})))
+
+(print "Testing sessionless requests, unknown ops and close...")
+
+;; Requests without a session share one belonging to the connection
+(nrepl/message conn {:op "eval" :code "(def shared 1)"})
+(assert-eq ["1"] (values-of (nrepl/message conn {:op "eval" :code "shared"})))
+(assert-eq ["eval-error" "done"] (status-of (nrepl/message conn {:op "eval" :code "shared" :session session})))
+(def shared-slow (nrepl/send conn {:op "eval" :code "(sleep-ms 10000)"}))
+(sleep-ms 100)
+(nrepl/message conn {:op "interrupt"})
+(assert-eq ["interrupted" "done"] (status-of (nrepl/responses conn shared-slow)))
+
+(assert-eq ["error" "unknown-op" "done"] (status-of (nrepl/message conn {:op "frobnicate"})))
+(assert-eq ["session-closed" "done"] (status-of (nrepl/message conn {:op "close" :session session})))
+(assert-eq ["error" "unknown-session" "done"] (status-of (nrepl/message conn {:op "eval" :code "1" :session session})))
+(assert-eq true (nrepl/close conn))
+
+(print "=== ALL NREPL TESTS PASSED ===")
@@ -0,0 +1,87 @@
+;; Subprocess tests
+;; sh and the proc/ process API (uses standard Unix commands)
+
+(print "=== PROCESS TESTS ===")
+
+;; === SH ===
+(print "Testing sh...")
+
+(def hello (sh "echo" "hello"))
+(assert-eq 0 (get hello :exit))
+(assert-eq "hello\n" (get hello :out))
+(assert-eq "" (get hello :err))
+(assert-eq {:exit 3 :out "out\n" :err "err\n"} (sh "sh" "-c" "echo out; echo err >&2; exit 3"))
+(assert-eq "a b\n" (get (sh ["echo" "a b"]) :out))
+
+;; Input, working directory and environment
+(assert-eq "piped in" (get (sh "cat" {:in "piped in"}) :out))
+(assert-eq "/\n" (get (sh "pwd" {:dir "/"}) :out))
+(assert-eq "brewing\n" (get (sh "sh" "-c" "echo $CORTADO_STATE" {:env {:CORTADO_STATE "brewing"}}) :out))
+(assert-eq "\n" (get (sh "sh" "-c" "echo $HOME" {:clear-env true}) :out))
+
+(spit "process-input.txt" "line one\nline two\n")
+(with-open [in (reader "process-input.txt")]
+  (assert-eq "2\n" (get (sh "sh" "-c" "wc -l | tr -d ' '" {:in in}) :out)))
+
+;; Without :in the process sees end of input instead of waiting for it
+(assert-eq "" (get (sh "cat") :out))
+
+(print "✓ sh")
+
+;; === PROCESS API ===
+(print "Testing proc/start...")
+
+(def upper (proc/start ["tr" "a-z" "A-Z"]))
+(spit (get upper :in) "shout\n")
+(assert-eq 0 (proc/wait upper))
+(assert-eq "SHOUT" (read-line (get upper :out)))
+
+;; Streaming output line by line
+(def counter (proc/start "sh" "-c" "for i in 1 2 3; do echo $i; done"))
+(assert-eq ["1" "2" "3"] (into [] (line-seq (get counter :out))))
+(assert-eq 0 (proc/wait counter))
+
+;; Piping one process into another
+(def producer (proc/start "printf" "b\\na\\nc\\n"))
+(def sorter (proc/start "sort" {:in (get producer :out)}))
+(assert-eq "a\nb\nc\n" (slurp (get sorter :out)))
+(assert-eq 0 (proc/wait sorter))
+
+;; Killing and timeouts
+(def sleeper (proc/start "sleep" "5"))
+(assert-eq true (proc/alive? sleeper))
+(proc/kill sleeper)
+(assert-eq 137 (proc/wait sleeper))
+(assert-eq false (proc/alive? sleeper))
+
+(def closed-sleeper (proc/start "sleep" "5"))
+(close (get closed-sleeper :proc))
+(assert-eq true (closed? (get closed-sleeper :proc)))
+
+;; A timeout kills the process's children too, so sh returns promptly
+;; instead of waiting for them to close its output pipes
+(def started (now-ms))
+(is (thrown-with-msg? #"timed out after 200 ms"
+      (sh "sh" "-c" "sleep 5; echo hi" {:timeout 200})))
+(assert-eq true (< (- (now-ms) started) 2000))
+
+
+;; With :check, a non-zero exit is an error that includes stderr, from sh
+;; directly and from proc/wait for a started process
+(is (thrown-with-msg? #"sh: `sh -c echo oops >&2; exit 3` exited with status 3: oops"
+      (sh "sh" "-c" "echo oops >&2; exit 3" {:check true})))
+(assert-eq 0 (get (sh "true" {:check true}) :exit))
+(assert-eq 3 (get (sh "sh" "-c" "exit 3") :exit))
+
+(def checked (proc/start "sh" "-c" "exit 4" {:check true}))
+(assert-eq true (get checked :check))
+(is (thrown-with-msg? #"proc/wait: process \d+ exited with status 4" (proc/wait checked)))
+(assert-eq 0 (proc/wait (proc/start "true" {:check true})))
+(assert-eq 4 (proc/wait (proc/start "sh" "-c" "exit 4")))
+
+(print "✓ proc/start")
+
+;; === CLEANUP ===
+(delete-file "process-input.txt")
+
+(print "=== ALL PROCESS TESTS PASSED ===")
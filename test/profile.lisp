@@ -0,0 +1,102 @@
+;; Profiler tests
+;; The profile form returns its expression's value and prints a table of
+;; calls, its folded stacks name the calls that were running when the
+;; sampler ticked, and cortado --profile does the same for a whole script.
+
+(print "=== PROFILER TESTS ===")
+
+(defn square [x] (* x x))
+(defn nap [] (sleep-ms 30))
+
+(print "Testing profile...")
+
+(assert-eq 81 (profile (square 9)))
+(assert-eq 3 (profile {} (+ 1 2)))
+
+;; A profile inside another just evaluates
+(assert-eq 4 (profile (profile (square 2))))
+
+(print "Testing the table...")
+
+;; Each call is counted, but a recursive function's inclusive time only
+;; once, for its outermost call, so it can't exceed the whole profile's
+(defn countdown [n] (if (= n 0) 0 (countdown (- n 1))))
+(def table (with-out-str (profile (countdown 200))))
+(def table-lines (str/split-lines table))
+(def elapsed (edn/read-string (first (re-seq #"[\d.]+" (first table-lines)))))
+(assert-eq true (str/starts-with? (first table-lines) "profile: "))
+(assert-eq ["Function" "Calls" "Incl" "ms" "Excl" "ms" "Allocs" "Excl" "allocs"]
+           (into [] (re-seq #"\S+" (first (rest table-lines)))))
+(def countdown-row
+  (into [] (re-seq #"\S+" (first (filter (fn [line] (str/starts-with? line "  countdown ")) table-lines)))))
+(assert-eq "201" (first (rest countdown-row)))
+(assert-eq true (<= (edn/read-string (first (rest (rest countdown-row)))) elapsed))
+(assert-eq true (str/includes? table "\n  =  "))
+
+(print "Testing folded stacks...")
+
+(with-temp [out (temp-file)]
+  (do
+    (profile {:folded out} (nap))
+    (def stacks (str/split-lines (slurp out)))
+    (assert-eq true (> (count stacks) 0))
+    (assert-eq true (str/starts-with? (first stacks) "profile"))
+    (assert-eq true (str/includes? (slurp out) "profile;nap;sleep-ms "))))
+
+(print "Testing errors...")
+
+(is (thrown-with-msg? #"options must be a map" (profile [1] (+ 1 2))))
+(is (thrown-with-msg? #"requires an expression" (profile)))
+
+;; An error from the expression wins over failing to write the stacks
+(def unwritable "profile-missing-dir/out.folded")
+(with-out-str
+  (do (is (thrown-with-msg? #"Undefined symbol: undefined-fn" (profile {:folded unwritable} (undefined-fn))))
+      (is (thrown-with-msg? #"Couldn't write profile-missing-dir/out.folded" (profile {:folded unwritable} (+ 1 2))))))
+
+(print "Testing cortado --profile...")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+(defn folded-lines [path]
+  (str/split-lines (slurp path)))
+
+(with-temp [script (temp-file {:suffix ".lisp"})
+            folded (temp-file {:suffix ".folded"})]
+  (do
+    (spit script "(defn nap [] (sleep-ms 40))\n(defn work [] (nap))\n(work)\n(println \"done\")\n")
+    (def script-run (sh cortado "--profile-file" folded script))
+    (assert-eq 0 (get script-run :exit))
+    (assert-eq "done\n" (get script-run :out))
+    ;; The table goes to stderr, away from the script's output
+    (assert-eq true (str/includes? (get script-run :err) (str script ": ")))
+    (assert-eq true (str/includes? (get script-run :err) "\n  work "))
+    (assert-eq true (str/includes? (get script-run :err) (str "Wrote folded stacks to " folded)))
+    ;; Every stack starts with the script's label
+    (def stacks (folded-lines folded))
+    (assert-eq true (> (count stacks) 0))
+    (assert-eq [] (into [] (filter (fn [line] (not (str/starts-with? line (str script ";")))) stacks)))
+    (assert-eq true (str/includes? (slurp folded) (str script ";work;nap;sleep-ms ")))
+
+    ;; A script that calls exit is still reported
+    (spit script "(defn nap [] (sleep-ms 40))\n(nap)\n(exit 3)\n(println \"unreachable\")\n")
+    (spit folded "")
+    (def exit-run (sh cortado "--profile-file" folded script))
+    (assert-eq 3 (get exit-run :exit))
+    (assert-eq "" (get exit-run :out))
+    (assert-eq true (str/includes? (get exit-run :err) (str "Wrote folded stacks to " folded)))
+    (assert-eq true (str/includes? (slurp folded) (str script ";nap;sleep-ms ")))))
+
+;; Only scripts are profiled
+(def eval-run (sh cortado "--profile" "-e" "(+ 1 2)"))
+(assert-eq 1 (get eval-run :exit))
+(assert-eq true (str/includes? (get eval-run :err) "--profile only works when running a script, not with --eval"))
+(def watch-run (sh cortado "--profile" "--watch" "test" "script.lisp"))
+(assert-eq 1 (get watch-run :exit))
+(assert-eq true (str/includes? (get watch-run :err) "not with --watch"))
+(def no-script-run (sh cortado "--profile"))
+(assert-eq 1 (get no-script-run :exit))
+(assert-eq true (str/includes? (get no-script-run :err) "--profile requires a script file"))
+
+(print "=== ALL PROFILER TESTS PASSED ===")
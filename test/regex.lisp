@@ -0,0 +1,76 @@
+;; Regular expression tests
+;; #"..." literals, re-find, re-matches, re-seq, re-groups and regex
+;; support in str/replace and str/split
+
+(print "=== REGEX TESTS ===")
+
+;; === LITERALS ===
+(print "Testing regex literals...")
+
+(assert-eq true (regex? #"\d+"))
+(assert-eq false (regex? "\\d+"))
+(assert-eq #"a+b" #"a+b")
+(assert-eq #"\d" (re-pattern "\\d"))
+(assert-eq "\\d+" (str #"\d+"))
+(assert-eq "\"hi\"" (re-find #"\"\w+\"" "say \"hi\""))
+
+(print "✓ Regex literals")
+
+;; === MATCHING ===
+(print "Testing re-find, re-matches and re-seq...")
+
+(assert-eq "123" (re-find #"\d+" "abc 123 def 45"))
+(assert-eq nil (re-find #"\d+" "no digits"))
+(assert-eq ["bob@example" "bob" "example"] (re-find #"(\w+)@(\w+)" "mail bob@example now"))
+(assert-eq ["b" nil "b"] (re-find #"(a)|(b)" "b"))
+(assert-eq "ab" (re-matches #"a|ab" "ab"))
+(assert-eq nil (re-matches #"\d+" "12x"))
+(assert-eq ["2024-05" "2024" "05"] (re-matches #"(\d+)-(\d+)" "2024-05"))
+(assert-eq '("1" "22" "333") (re-seq #"\d+" "1 22 333"))
+(assert-eq nil (re-seq #"z" "abc"))
+(assert-eq '("" "" "") (re-seq #"x*" "ab"))
+(assert-eq '("ü" "é") (re-seq #"[^\x00-\x7F]" "über café"))
+(assert-eq "1" (first (re-seq #"\d" (str/join (map str (range 1 100000))))))
+
+(print "✓ re-find, re-matches and re-seq")
+
+;; === NAMED GROUPS ===
+(print "Testing re-groups...")
+
+(def date-re #"(?P<year>\d{4})-(?P<month>\d{2})(-(?P<day>\d{2}))?")
+(assert-eq {:year "2024" :month "05" :day nil} (re-groups date-re "on 2024-05 ok"))
+(assert-eq "17" (:day (re-groups date-re "2024-05-17")))
+(assert-eq nil (re-groups date-re "no date"))
+(assert-eq {} (re-groups #"\d" "1"))
+
+(print "✓ re-groups")
+
+;; === STRING FUNCTIONS ===
+(print "Testing regex str/replace and str/split...")
+
+(assert-eq "a<1>b<22>" (str/replace "a1b22" #"\d+" "<$0>"))
+(assert-eq "aNb22" (str/replace-first "a1b22" #"\d" "N"))
+(assert-eq "1=x 2=y" (str/replace "x=1 y=2" #"(\w)=(\d)" "$2=$1"))
+(assert-eq "05/2024" (str/replace "2024-05" date-re "${month}/${year}"))
+(assert-eq "A-B" (str/replace "a-b" #"[a-z]" str/upper-case))
+(assert-eq "y:x" (str/replace "x:y" #"(\w):(\w)" (fn [m] (str (first (rest (rest m))) ":" (first (rest m))))))
+(assert-eq ["a" "b" "c"] (str/split "a1b22c" #"\d+"))
+(assert-eq ["a" "b,c"] (str/split "a, b,c" #",\s*" 2))
+
+(print "✓ Regex string functions")
+
+;; === LOG PROCESSING ===
+(print "Testing regexes over lines...")
+
+(spit "regex-log.txt" "INFO start\nERROR disk full\nINFO ok\nERROR timeout\n")
+(assert-eq ["disk full" "timeout"]
+  (with-open [in (reader "regex-log.txt")]
+    (into [] (comp (map (fn [line] (re-find #"^ERROR (.*)" line)))
+                   (filter (fn [m] (not (nil? m))))
+                   (map (fn [m] (first (rest m)))))
+          in)))
+(delete-file "regex-log.txt")
+
+(print "✓ Regexes over lines")
+
+(print "=== ALL REGEX TESTS PASSED ===")
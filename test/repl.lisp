@@ -0,0 +1,104 @@
+;; REPL tests
+;; Sessions piped into the cortado binary: the :commands, multi-line input,
+;; the history variables and stack traces
+
+(print "=== REPL TESTS ===")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+;; Runs a REPL session with a fresh HOME, so no ~/.cortadorc is loaded and
+;; history is written somewhere disposable, and returns what it printed
+(defn repl-session [input]
+  (with-temp [home (temp-dir)]
+    (get (sh cortado {:in input :env {:HOME home} :check true}) :out)))
+
+(defn prints? [output text]
+  (is (str/includes? output text)))
+
+;; === COMMANDS ===
+(print "Testing :doc and :source...")
+
+(def doc-session
+  (repl-session "(defn square \"Multiplies x by itself\" [x] (* x x))\n:doc square\n:source square\n:doc map\n:doc nothing-here\n:source +\n"))
+(prints? doc-session "-------------------------\nuser/square\n([x])\n  Multiplies x by itself\n")
+(prints? doc-session "(defn square \"Multiplies x by itself\" [x] (* x x))\n")
+(prints? doc-session "Built-in function\n")
+(prints? doc-session "No definition found for nothing-here\n")
+(prints? doc-session "No source available for + (it is built in)\n")
+
+(print "✓ :doc and :source")
+
+(print "Testing :type and :time...")
+
+(def eval-session (repl-session ":type (+ 1 2)\n:type \"s\"\n:type [1]\n:time (* 6 7)\n:time (no-such-fn)\n"))
+(prints? eval-session "number\nstring\nvector\n")
+(prints? eval-session "42\nElapsed time: ")
+(is (re-find #"Elapsed time: \d+\.\d{3} ms" eval-session))
+(prints? eval-session "Undefined symbol: no-such-fn")
+
+(print "✓ :type and :time")
+
+(print "Testing :ns, :env and :require...")
+
+(def ns-session
+  (repl-session ":ns\n(def answer 42)\n:ns scratch\n(def local 1)\n:env\n:env user\n:ns\n:env empty.ns\n:ns bad/name\n"))
+(prints? ns-session "user\n")
+(prints? ns-session "Switched to namespace scratch\n")
+(prints? ns-session "Bindings in namespace scratch:\n  local = 1\n")
+(prints? ns-session "Bindings in namespace user:\n  answer = 42\n")
+(prints? ns-session "No bindings in namespace empty.ns\n")
+(prints? ns-session "Invalid namespace name: bad/name\n")
+
+(def require-session
+  (repl-session ":require core.string :as s\n(s/join \"-\" [\"a\" \"b\"])\n:require\n:require no.such.ns\n"))
+(prints? require-session "Loaded core.string\n")
+(prints? require-session "\"a-b\"\n")
+(prints? require-session "Usage: :require <ns> [:as alias]\n")
+(is (not (str/includes? require-session "Loaded no.such.ns")))
+
+(print "✓ :ns, :env and :require")
+
+;; === MULTI-LINE INPUT ===
+(print "Testing multi-line input...")
+
+;; Unclosed lists, vectors, maps and strings keep reading lines; a complete
+;; form is evaluated straight away and a malformed one is an error at once
+(def multi-line-session
+  (repl-session "(+ 1\n2)\n[1\n2]\n(get {:a\n1} :a)\n\"ab\ncd\"\n(+ 3 4)\n)\n(str \"x)\"\n \"y\")\n"))
+(prints? multi-line-session
+  "3\n[1 2]\n1\n\"ab\ncd\"\n7\nParse error: Unexpected token: RightParen\n\"x)y\"\nGoodbye!\n")
+
+(print "✓ Multi-line input")
+
+;; === HISTORY AND ERRORS ===
+(print "Testing *1, *2, *3, *e and pst...")
+
+(def history-session
+  (repl-session "[*1 *e]\n(+ 1 2)\n(* 2 5)\n\"s\"\n[*1 *2 *3]\n*1\n"))
+(prints? history-session "[nil nil]\n3\n10\n\"s\"\n[\"s\" 10 3]\n[\"s\" 10 3]\n")
+
+;; *e keeps the message and the calls the error unwound through, innermost
+;; first; pst prints them, and a later error starts a fresh trace
+(def error-session
+  (repl-session (str "(defn inner [x] (undefined-thing x))\n"
+                     "(defn outer [x] (inner (+ x 1)))\n"
+                     "(outer 1)\n"
+                     "(get *e :message)\n"
+                     "(get *e :trace)\n"
+                     "(pst)\n"
+                     "(+ 1 2)\n"
+                     "(get *e :message)\n"
+                     "(inner 5)\n"
+                     "(pst *e)\n"
+                     "(pst 42)\n")))
+(prints? error-session "Error: Undefined symbol: undefined-thing\n\"Undefined symbol: undefined-thing\"\n")
+(prints? error-session "[\"(inner (+ x 1))\" \"(outer 1)\"]\n")
+(prints? error-session "Error: Undefined symbol: undefined-thing\n  in (inner (+ x 1))\n  in (outer 1)\n3\n")
+;; Successful input leaves *e alone
+(prints? error-session "3\n\"Undefined symbol: undefined-thing\"\n")
+(prints? error-session "Error: Undefined symbol: undefined-thing\n  in (inner 5)\nError: pst requires an error map such as *e, got 42\n")
+
+(print "✓ *1, *2, *3, *e and pst")
+
+(print "=== ALL REPL TESTS PASSED ===")
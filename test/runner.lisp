@@ -0,0 +1,130 @@
+;; Test runner tests
+;; cortado test run on fixture files: report formats, --filter, --jobs and
+;; exit codes
+
+(print "=== TEST RUNNER TESTS ===")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+(defn run-tests-in [args]
+  (sh (concat [cortado "test"] args)))
+
+(with-temp [dir (temp-dir)]
+  (do
+    (def passing (path/join dir "a_pass.lisp"))
+    (def failing (path/join dir "b_fail.lisp"))
+    (spit passing "(deftest adds (is (= 2 (+ 1 1))))\n(deftest subtracts (is (= 0 (- 1 1))))\n")
+    (spit failing "(deftest breaks (is (= 3 (+ 1 1))))\n(deftest errors (undefined-fn))\n")
+
+    ;; === EXIT CODES ===
+    (print "Testing exit codes...")
+
+    (def pass-run (run-tests-in [passing]))
+    (assert-eq 0 (get pass-run :exit))
+    (assert-eq true (str/includes? (get pass-run :out) "Ran 2 tests in 1 files containing 2 assertions"))
+
+    (assert-eq 1 (get (run-tests-in [failing]) :exit))
+    (assert-eq 1 (get (run-tests-in [passing failing]) :exit))
+
+    ;; Bad options and missing files fail before anything runs
+    (def bad-format (run-tests-in ["--format" "nope" passing]))
+    (assert-eq 1 (get bad-format :exit))
+    (assert-eq true (str/includes? (get bad-format :err) "--format must be text, junit, tap or json, got 'nope'"))
+    (assert-eq 1 (get (run-tests-in [(path/join dir "missing.lisp")]) :exit))
+
+    (print "✓ Exit codes")
+
+    ;; === FORMATS ===
+    (print "Testing --format json...")
+
+    (def json-run (run-tests-in ["--format" "json" dir]))
+    (assert-eq 1 (get json-run :exit))
+    (def report (json/parse (get json-run :out)))
+    (assert-eq [2 1 1] [(get report :pass) (get report :fail) (get report :error)])
+    (assert-eq [passing failing] (into [] (map (fn [f] (get f :file)) (get report :files))))
+    (def failing-tests (get (first (rest (get report :files))) :tests))
+    (assert-eq ["user/breaks" "user/errors"] (into [] (map (fn [t] (get t :name)) failing-tests)))
+    (def breaks-failure (first (get (first failing-tests) :failures)))
+    (assert-eq "fail" (get breaks-failure :type))
+    (assert-eq "(= 3 (+ 1 1))" (get breaks-failure :expression))
+    (assert-eq ["3" "2"] [(get breaks-failure :expected) (get breaks-failure :actual)])
+    (def errors-failure (first (get (first (rest failing-tests)) :failures)))
+    (assert-eq "error" (get errors-failure :type))
+    (assert-eq "\"Undefined symbol: undefined-fn\"" (get errors-failure :actual))
+
+    (print "✓ --format json")
+
+    (print "Testing --format tap...")
+
+    (def tap-run (run-tests-in ["--format" "tap" dir]))
+    (assert-eq 1 (get tap-run :exit))
+    (def tap-lines (str/split-lines (get tap-run :out)))
+    (assert-eq "TAP version 13" (first tap-lines))
+    (assert-eq "1..4" (first (rest tap-lines)))
+    (assert-eq [(str "ok 1 - " passing " user/adds")
+                (str "ok 2 - " passing " user/subtracts")
+                (str "not ok 3 - " failing " user/breaks")
+                (str "not ok 4 - " failing " user/errors")]
+               (into [] (filter (fn [line] (re-find #"^(not )?ok " line)) tap-lines)))
+    ;; Failure details are YAML blocks between --- and ...
+    (assert-eq true (str/includes? (get tap-run :out) "  ---\n  FAIL in user/breaks\n    expression: (= 3 (+ 1 1))\n      expected: 3\n        actual: 2\n  ...\n"))
+
+    (print "✓ --format tap")
+
+    (print "Testing --format junit...")
+
+    (def junit-run (run-tests-in ["--format" "junit" dir]))
+    (assert-eq 1 (get junit-run :exit))
+    (def junit (get junit-run :out))
+    (assert-eq true (str/starts-with? junit "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites tests=\"4\" failures=\"1\" errors=\"1\""))
+    (assert-eq true (str/ends-with? junit "</testsuites>\n"))
+    (assert-eq 2 (count (re-seq #"<testsuite " junit)))
+    (assert-eq 2 (count (re-seq #"</testsuite>" junit)))
+    (assert-eq 4 (count (re-seq #"<testcase " junit)))
+    (assert-eq true (str/includes? junit (str "<testsuite name=\"" failing "\" tests=\"2\" failures=\"1\" errors=\"1\"")))
+    (assert-eq true (str/includes? junit (str "<testcase classname=\"" passing "\" name=\"user/adds\"")))
+    (assert-eq true (str/includes? junit "<failure message=\"1 failures, 0 errors\">FAIL in user/breaks\n"))
+    ;; Text in reports is escaped
+    (assert-eq true (str/includes? junit "actual: &quot;Undefined symbol: undefined-fn&quot;\n</error>"))
+
+    (print "✓ --format junit")
+
+    ;; === FILTER ===
+    (print "Testing --filter...")
+
+    (def filtered (run-tests-in ["--filter" "adds" "--format" "tap" dir]))
+    (assert-eq 0 (get filtered :exit))
+    (assert-eq (str "TAP version 13\n1..1\nok 1 - " passing " user/adds\n") (get filtered :out))
+
+    ;; The filter matches part of the qualified name
+    (def filtered-by-ns (json/parse (get (run-tests-in ["--filter" "user/s" "--format" "json" dir]) :out)))
+    (assert-eq 1 (get filtered-by-ns :pass))
+    (assert-eq 0 (get filtered-by-ns :fail))
+
+    (print "✓ --filter")
+
+    ;; === JOBS ===
+    (print "Testing --jobs...")
+
+    ;; With one job, files run one at a time in the order given. Earlier
+    ;; files wait longer before logging, so running them at once would log
+    ;; them in reverse.
+    (def order-log (path/join dir "order.log"))
+    (defn order-file [name wait]
+      (do (spit (path/join dir (str name ".lisp"))
+                (str "(sleep-ms " wait ")\n"
+                     "(spit " (pr-str order-log) " " (pr-str (str name " ")) " {:append true})\n"
+                     "(deftest ran (is true))\n"))
+          (path/join dir (str name ".lisp"))))
+    (def order-files [(order-file "z" 300) (order-file "y" 150) (order-file "x" 0)])
+    (def ordered-run (run-tests-in (concat ["--jobs" "1"] order-files)))
+    (assert-eq 0 (get ordered-run :exit))
+    (assert-eq "z y x " (slurp order-log))
+
+    (def bad-jobs (run-tests-in ["--jobs" "0" passing]))
+    (assert-eq 1 (get bad-jobs :exit))
+
+    (print "✓ --jobs")))
+
+(print "=== ALL TEST RUNNER TESTS PASSED ===")
@@ -0,0 +1,97 @@
+;; String library tests
+;; The native str namespace, including Unicode and grapheme handling
+
+(print "=== STRING LIBRARY TESTS ===")
+
+;; "é" spelled as e + combining acute accent: one character, two code points
+(def e-acute (str/join ["e" (bytes->str (bytes [204 129]))]))
+(def family "👨‍👩‍👧")
+
+;; === SPLITTING AND JOINING ===
+(print "Testing split and join...")
+
+(assert-eq ["a" "b" "c"] (str/split "a,b,c" ","))
+(assert-eq ["a" "b,c"] (str/split "a,b,c" "," 2))
+(assert-eq ["a" "" "b"] (str/split "a,,b" ","))
+(assert-eq ["h" "é" "!"] (str/split "hé!" ""))
+(assert-eq ["x" e-acute] (str/split (str "x" e-acute) ""))
+(assert-eq ["one" "two" "three"] (str/split-lines "one\ntwo\r\nthree"))
+(assert-eq "a, b, c" (str/join ", " ["a" "b" "c"]))
+(assert-eq "abc" (str/join (list "a" "b" "c")))
+(assert-eq "1-2-3" (str/join "-" (range 1 4)))
+(assert-eq "a:b" (str/join ":" [:a "b"]))
+(assert-eq "" (str/join "," nil))
+
+(print "✓ split and join")
+
+;; === REPLACING AND TRIMMING ===
+(print "Testing replace and trim...")
+
+(assert-eq "b-b-b" (str/replace "a-a-a" "a" "b"))
+(assert-eq "b-a-a" (str/replace-first "a-a-a" "a" "b"))
+(assert-eq "naïve" (str/replace "naive" "i" "ï"))
+(assert-eq "padded" (str/trim "  padded\t\n"))
+(assert-eq "padded  " (str/triml "  padded  "))
+(assert-eq "  padded" (str/trimr "  padded  "))
+(assert-eq "wide" (str/trim (str (bytes->str (bytes [227 128 128])) "wide")))
+
+(print "✓ replace and trim")
+
+;; === CASE ===
+(print "Testing case conversion...")
+
+(assert-eq "HELLO" (str/upper-case "hello"))
+(assert-eq "STRASSE" (str/upper-case "straße"))
+(assert-eq "ÉCOLE" (str/upper-case "école"))
+(assert-eq "hello" (str/lower-case "HeLLo"))
+(assert-eq "Hello world" (str/capitalize "hELLO WORLD"))
+(assert-eq "Élan" (str/capitalize "éLAN"))
+(assert-eq "" (str/capitalize ""))
+
+(print "✓ case conversion")
+
+;; === SUBSTRINGS AND SEARCHING ===
+(print "Testing subs and searching...")
+
+(assert-eq "llo" (str/subs "hello" 2))
+(assert-eq "el" (str/subs "hello" 1 3))
+(assert-eq "ö" (str/subs "wörld" 1 2))
+(assert-eq e-acute (str/subs (str "x" e-acute "y") 1 2))
+(assert-eq family (str/subs (str family "!") 0 1))
+(assert-eq "" (str/subs "abc" 3))
+(assert-eq 2 (str/index-of "hello" "l"))
+(assert-eq 3 (str/index-of "hello" "l" 3))
+(assert-eq nil (str/index-of "hello" "z"))
+(assert-eq 2 (str/index-of "€€x" "x"))
+(assert-eq 3 (str/last-index-of "hello" "l"))
+(assert-eq 2 (str/last-index-of "hello" "l" 2))
+(assert-eq nil (str/last-index-of "hello" "l" 1))
+(assert-eq 1 (str/last-index-of "aaa" "aa"))
+(assert-eq 0 (str/last-index-of "aaa" "aa" 0))
+(assert-eq 2 (str/last-index-of "añaña" "aña"))
+(assert-eq 1 (str/index-of (str family "a") "a"))
+(assert-eq true (str/starts-with? "cortado" "cort"))
+(assert-eq false (str/starts-with? "cortado" "ado"))
+(assert-eq true (str/ends-with? "cortado" "ado"))
+(assert-eq true (str/includes? "cortado" "rta"))
+(assert-eq false (str/includes? "cortado" "xyz"))
+
+(print "✓ subs and searching")
+
+;; === PADDING AND REVERSING ===
+(print "Testing pad and reverse...")
+
+(assert-eq "   42" (str/pad-left "42" 5))
+(assert-eq "00042" (str/pad-left "42" 5 "0"))
+(assert-eq "42..." (str/pad-right "42" 5 "."))
+(assert-eq "toolong" (str/pad-left "toolong" 3))
+(assert-eq "  ñ" (str/pad-left "ñ" 3))
+(assert-eq (str " " e-acute) (str/pad-left e-acute 2))
+(assert-eq "olleh" (str/reverse "hello"))
+(assert-eq "dlröw" (str/reverse "wörld"))
+(assert-eq (str "y" e-acute "x") (str/reverse (str "x" e-acute "y")))
+(assert-eq (str "!" family) (str/reverse (str family "!")))
+
+(print "✓ pad and reverse")
+
+(print "=== ALL STRING LIBRARY TESTS PASSED ===")
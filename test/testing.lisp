@@ -0,0 +1,131 @@
+;; Test framework tests
+;; deftest, testing, is, are, thrown? and fixtures, checked through the map
+;; run-tests returns. Each group of deftests lives in its own namespace.
+
+(print "=== TEST FRAMEWORK TESTS ===")
+
+(print "Testing passing tests...")
+
+(ns testing-test.passing)
+
+(deftest passing
+  (is (= 4 (+ 2 2)))
+  (is (< 1 2) "one is less than two")
+  (testing "with are"
+    (are [x y] (= x (* 2 y))
+      2 1
+      4 2
+      10 5))
+  (is (thrown? (undefined-fn 1)))
+  (is (thrown-with-msg? #"Undefined" (undefined-fn 1))))
+
+(ns user)
+
+(def passing (run-tests 'testing-test.passing))
+(assert-eq 1 (get passing :test))
+(assert-eq 7 (get passing :pass))
+(assert-eq 0 (get passing :fail))
+(assert-eq 0 (get passing :error))
+(assert-eq [] (get passing :failures))
+(assert-eq "testing-test.passing/passing" (get (first (get passing :tests)) :name))
+(assert-eq 7 (get (first (get passing :tests)) :pass))
+
+;; A deftest is also an ordinary function
+(assert-eq "Undefined symbol: undefined-fn" (testing-test.passing/passing))
+
+(print "Testing failure reports...")
+
+(ns testing-test.failing)
+
+(deftest reports
+  (testing "outer"
+    (testing "inner"
+      (is (= 3 (+ 1 1)) "sums")))
+  (is (even? (+ 1 2)) "predicate")
+  (is nil "plain value")
+  (is (thrown? (+ 1 2)) "not thrown")
+  (is (thrown-with-msg? #"nope" (undefined-fn 1)) "wrong message")
+  (is (= 1 (undefined-fn 1)) "erroring")
+  (is (= 1 1)))
+
+(deftest uncaught
+  (undefined-fn 1))
+
+(ns user)
+
+(def failing (run-tests 'testing-test.failing))
+(assert-eq 2 (get failing :test))
+(assert-eq 1 (get failing :pass))
+(assert-eq 5 (get failing :fail))
+(assert-eq 2 (get failing :error))
+
+(defn failure [message]
+  (first (filter (fn [f] (= (get f :message) message)) (get failing :failures))))
+
+;; (is (= expected actual)) reports both sides
+(assert-eq :fail (get (failure "sums") :type))
+(assert-eq 3 (get (failure "sums") :expected))
+(assert-eq 2 (get (failure "sums") :actual))
+(assert-eq '(= 3 (+ 1 1)) (get (failure "sums") :expression))
+(assert-eq ["outer" "inner"] (get (failure "sums") :testing))
+(assert-eq "testing-test.failing/reports" (get (failure "sums") :test))
+
+;; Any other call shows the arguments it was given
+(assert-eq '(not (even? 3)) (get (failure "predicate") :actual))
+(assert-eq nil (get (failure "plain value") :actual))
+(assert-eq 3 (get (failure "not thrown") :actual))
+(assert-eq "Undefined symbol: undefined-fn" (get (failure "wrong message") :actual))
+
+;; An error in an assertion is reported and the test carries on
+(assert-eq :error (get (failure "erroring") :type))
+(assert-eq [1 5 1] (into [] (map (fn [k] (get (first (get failing :tests)) k)) [:pass :fail :error])))
+(assert-eq "testing-test.failing/uncaught" (get (failure "Uncaught error") :test))
+
+(print "Testing fixtures...")
+
+(with-temp [log (temp-file)]
+  (do
+    (ns testing-test.fixtures)
+
+    (use-fixtures :once
+      (fn [run] (do (spit log "setup " {:append true}) (run) (spit log "teardown" {:append true}))))
+    (use-fixtures :each
+      (fn [t] (do (spit log "outer " {:append true}) (t)))
+      (fn [t] (do (spit log "inner " {:append true}) (t))))
+
+    (deftest first-test (is true))
+    (deftest second-test (is true))
+
+    (ns user)
+
+    (def with-fixtures (run-tests 'testing-test.fixtures))
+    (assert-eq 2 (get with-fixtures :pass))
+    (assert-eq "setup outer inner outer inner teardown" (slurp log))))
+
+(print "Testing redefinition...")
+
+(ns testing-test.redefined)
+(deftest changing (is (= 1 2)))
+(deftest changing (is (= 1 1)))
+(ns user)
+
+(def redefined (run-tests 'testing-test.redefined))
+(assert-eq 1 (get redefined :test))
+(assert-eq 0 (get redefined :fail))
+
+(print "Testing the runner's timeout...")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+;; A test stuck where the evaluator can't stop it is killed by the runner
+(with-temp [stuck (temp-file {:suffix ".lisp"})]
+  (do
+    (spit stuck "(deftest stuck (sh \"sleep\" \"30\"))")
+    (def started (now-ms))
+    (def stuck-run (sh cortado "test" "--timeout" "0.5" stuck))
+    (assert-eq 1 (get stuck-run :exit))
+    (assert-eq true (str/includes? (get stuck-run :out) "Timed out after 0.5s in user/stuck"))
+    (assert-eq true (< (- (now-ms) started) 10000))))
+
+(print "=== ALL TEST FRAMEWORK TESTS PASSED ===")
@@ -0,0 +1,97 @@
+;; Transducer tests
+;; transduce, into, sequence, eduction, reduce and reduced
+
+(print "=== TRANSDUCER TESTS ===")
+
+;; === TRANSDUCE ===
+(print "Testing transduce...")
+
+(def inc-evens (comp (map inc) (filter even?)))
+(assert-eq 30 (transduce inc-evens + 0 (range 10)))
+(assert-eq 9 (transduce (map inc) + (list 1 2 3)))
+(assert-eq 3 (transduce (take 3) + 0 (range)))
+(assert-eq 0 (transduce (filter even?) + 0 nil))
+
+;; Transducers are reusable: per-run state starts fresh each time
+(def take-two (take 2))
+(assert-eq 3 (transduce take-two + 0 [1 2 3]))
+(assert-eq 3 (transduce take-two + 0 [1 2 3]))
+
+;; comp of plain functions composes them right to left
+(assert-eq 3 ((comp inc inc) 1))
+(assert-eq 11 ((comp inc (fn [x] (* x 2))) 5))
+(assert-eq "2" ((comp str inc) 1))
+(assert-eq 4 ((comp inc +) 1 2))
+(assert-eq 7 ((comp inc inc +) 1 2 2))
+
+;; (comp) is identity, as a function and as a transducer
+(assert-eq 5 ((comp) 5))
+(assert-eq [1 2] (into [] (comp) [1 2]))
+(is (thrown-with-msg? #"all functions or all transducers" (comp inc (map inc))))
+
+(print "✓ transduce")
+
+;; === INTO ===
+(print "Testing into...")
+
+(assert-eq [2 3 4] (into [] (map inc) [1 2 3]))
+(assert-eq [0 1 2] (into [] (take 3) (range)))
+(assert-eq [1 2 3] (into [1] [2 3]))
+(assert-eq '(3 2 1 0) (into (list 0) [1 2 3]))
+(assert-eq '(2 1) (into nil [1 2]))
+(assert-eq {:a 1 :b 2} (into {} [[:a 1] [:b 2]]))
+(assert-eq {:a 1 :b 2} (into {:a 1} {:b 2}))
+(assert-eq [[:a 1] [:b 2]] (into [] {:b 2 :a 1}))
+(assert-eq {:a 2} (into {} (map (fn [e] [(first e) (inc (first (rest e)))])) {:a 1}))
+
+(print "✓ into")
+
+;; === STATEFUL TRANSDUCERS ===
+(print "Testing partition-by and dedupe...")
+
+(assert-eq [[1 3] [2 4] [5]] (into [] (partition-by odd?) [1 3 2 4 5]))
+(assert-eq [[1 3] [2]] (into [] (comp (take 3) (partition-by odd?)) [1 3 2 4 5]))
+(assert-eq [1 2 3 1] (into [] (dedupe) [1 1 2 2 3 1 1]))
+(assert-eq '([1 1] [2]) (partition-by odd? [1 1 2]))
+(assert-eq '(1 2 1) (dedupe [1 1 2 1]))
+
+(print "✓ partition-by and dedupe")
+
+;; === SEQUENCE AND EDUCTION ===
+(print "Testing sequence and eduction...")
+
+(assert-eq '(2 4) (sequence inc-evens [1 2 3 4]))
+(assert-eq '(1 2 3) (take 3 (sequence (map inc) (range))))
+(assert-eq '(2 4 6) (eduction (map inc) (filter even?) (range 6)))
+(assert-eq '(1 2) (sequence [1 2]))
+
+(print "✓ sequence and eduction")
+
+;; === REDUCE AND REDUCED ===
+(print "Testing reduce and reduced...")
+
+(assert-eq 10 (reduce + (range 5)))
+(assert-eq 13 (reduce + 10 [1 2]))
+(assert-eq 0 (reduce + []))
+(assert-eq 6 (reduce (fn [acc x] (if (> x 3) (reduced acc) (+ acc x))) 0 (range)))
+(assert-eq true (reduced? (reduced 1)))
+(assert-eq false (reduced? 1))
+(assert-eq [1] (transduce (partition-by odd?) (fn [acc x] (reduced x)) nil [1 2 3]))
+
+(print "✓ reduce and reduced")
+
+;; === LINE SOURCES ===
+(print "Testing readers as sources...")
+
+(spit "transduce-lines.txt" "a\nbb\n\nccc\n")
+(assert-eq [1 2 3]
+  (with-open [in (reader "transduce-lines.txt")]
+    (into [] (comp (filter (fn [line] (not (empty? line)))) (map str-length)) in)))
+(assert-eq 2
+  (with-open [in (reader "transduce-lines.txt")]
+    (transduce (take 2) (fn [n line] (+ n 1)) 0 in)))
+(delete-file "transduce-lines.txt")
+
+(print "✓ Readers as sources")
+
+(print "=== ALL TRANSDUCER TESTS PASSED ===")
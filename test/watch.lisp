@@ -0,0 +1,99 @@
+;; File watching tests
+;; watch, unwatch, poll-watches and run-watches
+
+(print "=== WATCH TESTS ===")
+
+(print "Testing watch and poll-watches...")
+
+(with-temp [dir (temp-dir)
+            log (temp-file)]
+  (do
+    (def notes (path/join dir "notes.txt"))
+    (def id (watch dir (fn [event] (spit log (str (get event :kind) " " (path/file-name (get event :path)) "\n") {:append true}))))
+    (assert-eq [] (poll-watches))
+
+    ;; Created, modified and deleted files inside the watched directory
+    (spit notes "first")
+    (assert-eq [{:path notes :kind :created}] (poll-watches))
+    (spit notes "second draft")
+    (assert-eq [{:path notes :kind :modified}] (poll-watches))
+    (delete-file notes)
+    (assert-eq [{:path notes :kind :deleted}] (poll-watches))
+    (assert-eq "created notes.txt\nmodified notes.txt\ndeleted notes.txt\n" (slurp log))
+
+    ;; Watches are recursive unless {:recursive false}
+    (create-dir (path/join dir "sub"))
+    (poll-watches)
+    (def shallow (watch dir (fn [event] nil) {:recursive false}))
+    (spit (path/join dir "sub/deep.txt") "x")
+    (assert-eq [{:path (path/join dir "sub/deep.txt") :kind :created}] (poll-watches))
+
+    ;; Unwatched paths stop reporting
+    (assert-eq true (unwatch id))
+    (assert-eq true (unwatch shallow))
+    (assert-eq false (unwatch id))
+    (spit (path/join dir "late.txt") "x")
+    (assert-eq [] (poll-watches))))
+
+(print "✓ watch and poll-watches")
+
+(print "Testing run-watches...")
+
+(with-temp [dir (temp-dir)]
+  (do
+    ;; A callback returning :stop removes its watch, which ends run-watches
+    (watch dir (fn [event] :stop))
+    (spit (path/join dir "trigger.txt") "go")
+    (assert-eq nil (run-watches {:interval 10 :timeout 5000}))
+    (assert-eq [] (poll-watches))
+
+    ;; Without changes run-watches returns after the timeout
+    (def quiet (watch dir (fn [event] nil)))
+    (assert-eq nil (run-watches {:interval 10 :timeout 50}))
+    (unwatch quiet)))
+
+(print "✓ run-watches")
+
+(print "Testing callback errors...")
+
+(with-temp [dir (temp-dir)
+            log (temp-file)]
+  (do
+    ;; Every event is still delivered when a callback fails; the error is
+    ;; raised afterwards
+    (def failing (watch dir (fn [event]
+                              (do (spit log (str (path/file-name (get event :path)) "\n") {:append true})
+                                  (undefined-fn)))))
+    (spit (path/join dir "a.txt") "a")
+    (spit (path/join dir "b.txt") "b")
+    (is (thrown-with-msg? #"Undefined symbol: undefined-fn" (poll-watches)))
+    (assert-eq "a.txt\nb.txt\n" (slurp log))
+    (assert-eq [] (poll-watches))
+    (unwatch failing)))
+
+(print "✓ callback errors")
+
+(print "Testing --watch...")
+
+;; Tests run from the repository root; CORTADO points at another build
+(def cortado (or (getenv "CORTADO") "target/debug/cortado"))
+
+;; A watched script that calls exit ends its own run, not the watch
+(with-temp [script (temp-file {:suffix ".lisp"})]
+  (do
+    (spit script "(println \"first run\") (exit 3)")
+    (def watcher (proc/start cortado "--watch" script script))
+    (def watch-out (get watcher :out))
+    (assert-eq "first run" (read-line watch-out))
+    (assert-eq true (str/starts-with? (read-line watch-out) "Watching"))
+    (spit script "(println \"second run after the change\") (exit 3)")
+    (assert-eq "" (read-line watch-out))
+    (assert-eq true (str/starts-with? (read-line watch-out) "--- Changed"))
+    (assert-eq "second run after the change" (read-line watch-out))
+    (assert-eq true (proc/alive? watcher))
+    (proc/kill watcher)
+    (proc/wait watcher)))
+
+(print "✓ --watch")
+
+(print "=== ALL WATCH TESTS PASSED ===")